@@ -0,0 +1,381 @@
+use std::fmt;
+
+use crate::integers::Integer;
+
+/**
+ * Format strings used by `print`, shared by both interpreters.
+ *
+ * A format string is printed as is, except for escape sequences (`\n`, `\t`, `\r`, `\\`, and `\~`)
+ * and placeholders, which are replaced by the values of the arguments:
+ *  - `~` is the next argument,
+ *  - `~{}` is also the next argument, but allows a specification after a colon, eg. `~{:>8}`,
+ *  - `~{2}` is the argument at index 2 (counting from 0),
+ *  - `~{x}` is the value of variable `x`; the parser turns these into indices and passes the
+ *    variables as extra arguments (see `resolve_names`).
+ *
+ * A specification is `[[fill]align][0][width][type]`, where `align` is one of `<` (left), `>`
+ * (right), or `^` (center), `fill` is the character to pad the value with if it is shorter than
+ * `width` (space by default), a `0` pads numbers with zeroes, and `type` is `x` or `X` for
+ * hexadecimal and `b` for binary integers.
+ */
+#[derive(PartialEq,Debug,Clone)]
+pub struct Format {
+    segments: Vec<Segment>,
+}
+
+#[derive(PartialEq,Debug,Clone)]
+pub enum Segment {
+    Text(String),
+    Placeholder { argument: Argument, specification: Specification },
+}
+
+#[derive(PartialEq,Debug,Clone)]
+pub enum Argument {
+    Next,
+    Index(usize),
+    Name(String),
+}
+
+#[derive(PartialEq,Debug,Copy,Clone)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(PartialEq,Debug,Copy,Clone)]
+pub enum Radix {
+    Decimal,
+    LowerHexadecimal,
+    UpperHexadecimal,
+    Binary,
+}
+
+#[derive(PartialEq,Debug,Clone)]
+pub struct Specification {
+    pub fill: char,
+    pub alignment: Option<Alignment>,
+    pub width: usize,
+    pub radix: Radix,
+}
+
+/**
+ * A value to fill a placeholder with. Integers are kept as numbers, so that they can be printed in
+ * a different radix. Everything else is printed the way the interpreter prints it.
+ */
+#[derive(PartialEq,Debug,Clone)]
+pub enum Value {
+    Integer(Integer),
+    Other(String),
+}
+
+impl Default for Specification {
+    fn default() -> Self {
+        Specification { fill: ' ', alignment: None, width: 0, radix: Radix::Decimal }
+    }
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Result<Format, String> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut characters = format.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            match character {
+                '\\' => match characters.next() {
+                    Some('n')  => text.push('\n'),
+                    Some('t')  => text.push('\t'),
+                    Some('r')  => text.push('\r'),
+                    Some('\\') => text.push('\\'),
+                    Some('~')  => text.push('~'),
+                    Some(c)    => return Err(format!("unknown escape sequence \\{} in {:?}", c, format)),
+                    None       => return Err(format!("unfinished escape sequence in {:?}", format)),
+                },
+                '~' => {
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::replace(&mut text, String::new())));
+                    }
+                    if characters.peek() == Some(&'{') {
+                        characters.next();
+                        let mut directive = String::new();
+                        loop {
+                            match characters.next() {
+                                Some('}') => break,
+                                Some(c) => directive.push(c),
+                                None => return Err(format!("unclosed placeholder in {:?}", format)),
+                            }
+                        }
+                        segments.push(Format::parse_directive(&directive)
+                            .map_err(|e| format!("{} in {:?}", e, format))?);
+                    } else {
+                        segments.push(Segment::Placeholder {
+                            argument: Argument::Next,
+                            specification: Specification::default(),
+                        });
+                    }
+                },
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Format { segments })
+    }
+
+    fn parse_directive(directive: &str) -> Result<Segment, String> {
+        let (argument, specification) = match directive.find(':') {
+            Some(colon) => (&directive[..colon], Some(&directive[colon + 1..])),
+            None => (directive, None),
+        };
+
+        let argument = if argument.is_empty() {
+            Argument::Next
+        } else if argument.chars().all(|c| c.is_ascii_digit()) {
+            Argument::Index(argument.parse().map_err(|_| format!("invalid index {}", argument))?)
+        } else if argument.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+               && !argument.starts_with(|c: char| c.is_ascii_digit()) {
+            Argument::Name(argument.to_string())
+        } else {
+            return Err(format!("invalid placeholder ~{{{}}}", directive))
+        };
+
+        let specification = match specification {
+            Some(specification) => Format::parse_specification(specification)
+                .map_err(|e| format!("{} in placeholder ~{{{}}}", e, directive))?,
+            None => Specification::default(),
+        };
+
+        Ok(Segment::Placeholder { argument, specification })
+    }
+
+    fn parse_specification(specification: &str) -> Result<Specification, String> {
+        let alignment = |c: char| match c {
+            '<' => Some(Alignment::Left),
+            '>' => Some(Alignment::Right),
+            '^' => Some(Alignment::Center),
+            _ => None,
+        };
+
+        let characters: Vec<char> = specification.chars().collect();
+        let mut result = Specification::default();
+        let mut position = 0;
+
+        match (characters.get(0).copied(), characters.get(1).copied().and_then(alignment)) {
+            (Some(fill), Some(align)) => { result.fill = fill; result.alignment = Some(align); position = 2 },
+            (Some(c), _) if alignment(c).is_some() => { result.alignment = alignment(c); position = 1 },
+            _ => (),
+        }
+
+        if characters.get(position) == Some(&'0') && result.alignment.is_none() {
+            result.fill = '0';
+            result.alignment = Some(Alignment::Right);
+            position += 1;
+        }
+
+        let width: String = characters[position..].iter().take_while(|c| c.is_ascii_digit()).collect();
+        position += width.len();
+        if !width.is_empty() {
+            result.width = width.parse().map_err(|_| format!("invalid width {}", width))?;
+        }
+
+        result.radix = match &characters[position..] {
+            []    => Radix::Decimal,
+            ['x'] => Radix::LowerHexadecimal,
+            ['X'] => Radix::UpperHexadecimal,
+            ['b'] => Radix::Binary,
+            rest  => return Err(format!("unknown format type {}", rest.iter().collect::<String>())),
+        };
+
+        Ok(result)
+    }
+
+    pub fn segments(&self) -> &Vec<Segment> {
+        &self.segments
+    }
+
+    /**
+     * The number of positional arguments the format string needs: enough for all the `~`
+     * placeholders and for the highest index.
+     */
+    pub fn expected_arguments(&self) -> usize {
+        let mut next = 0;
+        let mut highest = 0;
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Placeholder { argument: Argument::Next, specification: _ } => next += 1,
+                Segment::Placeholder { argument: Argument::Index(index), specification: _ } =>
+                    highest = highest.max(index + 1),
+                _ => (),
+            }
+        }
+        next.max(highest)
+    }
+
+    /**
+     * Names of the variables used by the format string, in the order they first appear.
+     */
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for segment in self.segments.iter() {
+            if let Segment::Placeholder { argument: Argument::Name(name), specification: _ } = segment {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        names
+    }
+
+    /**
+     * Replaces named placeholders with indices, so that the `n`-th name refers to the argument at
+     * `first + n`. This lets a compiler pass the values of variables as extra arguments.
+     */
+    pub fn resolve_names(&self, first: usize) -> Format {
+        let names = self.names();
+        let segments = self.segments.iter().map(|segment| match segment {
+            Segment::Placeholder { argument: Argument::Name(name), specification } =>
+                Segment::Placeholder {
+                    argument: Argument::Index(first + names.iter().position(|n| n == name).unwrap()),
+                    specification: specification.clone(),
+                },
+            segment => segment.clone(),
+        }).collect();
+        Format { segments }
+    }
+
+    /**
+     * Fills in the placeholders. Named placeholders have to be resolved beforehand.
+     */
+    pub fn render(&self, arguments: &[Value]) -> Result<String, String> {
+
+        let mut result = String::new();
+        let mut next = 0;
+        let mut used = vec![false; arguments.len()];
+
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Text(text) => result.push_str(text),
+                Segment::Placeholder { argument, specification } => {
+                    let value = match argument {
+                        Argument::Next => { next += 1; Format::argument(arguments, &mut used, next - 1)? },
+                        Argument::Index(index) => Format::argument(arguments, &mut used, *index)?,
+                        Argument::Name(name) =>
+                            return Err(format!("placeholder ~{{{}}} was not resolved", name)),
+                    };
+                    result.push_str(&specification.apply(&value)?);
+                }
+            }
+        }
+
+        if used.iter().any(|used| !used) {
+            return Err(format!("format uses {} of {} arguments", used.iter().filter(|u| **u).count(),
+                               arguments.len()))
+        }
+        Ok(result)
+    }
+
+    fn argument(arguments: &[Value], used: &mut Vec<bool>, index: usize) -> Result<Value, String> {
+        match arguments.get(index) {
+            Some(value) => { used[index] = true; Ok(value.clone()) },
+            None => Err(format!("not enough arguments: no argument at index {}", index)),
+        }
+    }
+}
+
+impl Specification {
+    pub fn apply(&self, value: &Value) -> Result<String, String> {
+        let string = match (value, self.radix) {
+            (Value::Integer(integer), radix) => {
+                let big = integer.to_big();
+                match radix {
+                    Radix::Decimal          => big.to_str_radix(10),
+                    Radix::LowerHexadecimal => big.to_str_radix(16),
+                    Radix::UpperHexadecimal => big.to_str_radix(16).to_uppercase(),
+                    Radix::Binary           => big.to_str_radix(2),
+                }
+            },
+            (Value::Other(string), Radix::Decimal) => string.clone(),
+            (Value::Other(string), _) => return Err(format!("cannot print {} in another radix, it is not an integer", string)),
+        };
+
+        let length = string.chars().count();
+        if length >= self.width {
+            return Ok(string)
+        }
+
+        let padding = self.width - length;
+        let fill = |n: usize| std::iter::repeat(self.fill).take(n).collect::<String>();
+        let alignment = self.alignment.unwrap_or(match value {
+            Value::Integer(_) => Alignment::Right,
+            Value::Other(_) => Alignment::Left,
+        });
+
+        Ok(match alignment {
+            // Zero padding goes between the sign and the digits.
+            Alignment::Right if self.fill == '0' && string.starts_with('-') =>
+                format!("-{}{}", fill(padding), &string[1..]),
+            Alignment::Right  => format!("{}{}", fill(padding), string),
+            Alignment::Left   => format!("{}{}", string, fill(padding)),
+            Alignment::Center => format!("{}{}{}", fill(padding / 2), string, fill(padding - padding / 2)),
+        })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Text(text) => {
+                    for c in text.chars() {
+                        match c {
+                            '\n' => write!(f, "\\n")?,
+                            '\t' => write!(f, "\\t")?,
+                            '\r' => write!(f, "\\r")?,
+                            '\\' => write!(f, "\\\\")?,
+                            '~'  => write!(f, "\\~")?,
+                            c    => write!(f, "{}", c)?,
+                        }
+                    }
+                },
+                Segment::Placeholder { argument, specification } => {
+                    let argument = match argument {
+                        Argument::Next => String::new(),
+                        Argument::Index(index) => index.to_string(),
+                        Argument::Name(name) => name.clone(),
+                    };
+                    if *specification == Specification::default() {
+                        if argument.is_empty() { write!(f, "~")? } else { write!(f, "~{{{}}}", argument)? }
+                    } else {
+                        write!(f, "~{{{}:{}}}", argument, specification)?
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Specification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(alignment) = self.alignment {
+            let alignment = match alignment {
+                Alignment::Left => '<',
+                Alignment::Right => '>',
+                Alignment::Center => '^',
+            };
+            write!(f, "{}{}", self.fill, alignment)?;
+        }
+        if self.width > 0 {
+            write!(f, "{}", self.width)?;
+        }
+        match self.radix {
+            Radix::Decimal => Ok(()),
+            Radix::LowerHexadecimal => write!(f, "x"),
+            Radix::UpperHexadecimal => write!(f, "X"),
+            Radix::Binary => write!(f, "b"),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::integers::Integer;
+
+/**
+ * Structural hashes of FML values, shared by both interpreters, so that a value hashes the same in
+ * either of them and from one run to the next.
+ *
+ * A value is hashed by feeding its parts to a `Hasher` in a fixed order:
+ *  - `null`, booleans, integers, floats, and strings are written with the `write_*` methods,
+ *  - an array is `start_array` with its length, followed by its elements in order,
+ *  - an object is `start_object`, followed by its own fields ordered by name (each as `write_name`
+ *    and the value), the names of its methods in order, and finally its parent,
+ *  - a map is `start_map`, followed by one `write_hash` per entry, in ascending order of hash,
+ *    where each entry is hashed by a fresh `Hasher` as its key followed by its value,
+ *  - a value that is already being hashed further up (a cycle) is written as `write_cycle`.
+ *
+ * Structurally equal values produce the same parts and therefore the same hash. Objects that
+ * define their own `==` are hashed structurally all the same.
+ */
+#[derive(PartialEq,Debug,Copy,Clone)]
+pub struct Hasher {
+    state: u64,
+}
+
+// 64-bit FNV-1a.
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[derive(PartialEq,Debug,Copy,Clone)]
+enum Tag {
+    Null = 0,
+    Boolean,
+    Integer,
+    Float,
+    String,
+    Array,
+    Object,
+    Map,
+    Name,
+    Hash,
+    Cycle,
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Hasher::new()
+    }
+}
+
+impl Hasher {
+    pub fn new() -> Self {
+        Hasher { state: OFFSET_BASIS }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(PRIME);
+        }
+    }
+
+    fn write_tag(&mut self, tag: Tag) {
+        self.write_bytes(&[tag as u8])
+    }
+
+    fn write_length(&mut self, length: usize) {
+        self.write_bytes(&(length as u64).to_le_bytes())
+    }
+
+    pub fn write_null(&mut self) {
+        self.write_tag(Tag::Null)
+    }
+
+    pub fn write_boolean(&mut self, value: bool) {
+        self.write_tag(Tag::Boolean);
+        self.write_bytes(&[value as u8])
+    }
+
+    // Integers are written in decimal, so that a value hashes the same whether it is stored as a
+    // small or a big integer.
+    pub fn write_integer(&mut self, value: &Integer) {
+        let digits = value.to_string();
+        self.write_tag(Tag::Integer);
+        self.write_length(digits.len());
+        self.write_bytes(digits.as_bytes())
+    }
+
+    pub fn write_float(&mut self, value: f64) {
+        self.write_tag(Tag::Float);
+        self.write_bytes(&value.to_bits().to_le_bytes())
+    }
+
+    pub fn write_string(&mut self, value: &str) {
+        self.write_tag(Tag::String);
+        self.write_length(value.len());
+        self.write_bytes(value.as_bytes())
+    }
+
+    pub fn start_array(&mut self, length: usize) {
+        self.write_tag(Tag::Array);
+        self.write_length(length)
+    }
+
+    pub fn start_object(&mut self, fields: usize, methods: usize) {
+        self.write_tag(Tag::Object);
+        self.write_length(fields);
+        self.write_length(methods)
+    }
+
+    pub fn start_map(&mut self, entries: usize) {
+        self.write_tag(Tag::Map);
+        self.write_length(entries)
+    }
+
+    pub fn write_name(&mut self, name: &str) {
+        self.write_tag(Tag::Name);
+        self.write_length(name.len());
+        self.write_bytes(name.as_bytes())
+    }
+
+    pub fn write_hash(&mut self, hash: u64) {
+        self.write_tag(Tag::Hash);
+        self.write_bytes(&hash.to_le_bytes())
+    }
+
+    pub fn write_cycle(&mut self) {
+        self.write_tag(Tag::Cycle)
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+
+    // The hash as an FML integer: non-negative and small enough for any integer semantics.
+    pub fn finish_as_integer(&self) -> i64 {
+        (self.state >> 33) as i64
+    }
+}
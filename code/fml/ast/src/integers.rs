@@ -0,0 +1,238 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use num_traits::{ToPrimitive, Zero};
+
+use crate::Operator;
+
+pub use num_bigint::BigInt;
+
+/**
+ * Defines what integer arithmetic does when its result does not fit into an integer.
+ *
+ * All semantics share the same literals (64 bit, checked when the literal is evaluated), the same
+ * comparisons, and the same error for division by zero. They differ in the range of values and in
+ * what happens on overflow.
+ */
+#[derive(PartialEq,Eq,Debug,Copy,Clone)]
+pub enum IntegerSemantics {
+    /**
+     * 32 bit two's complement integers. Overflowing results wrap around.
+     */
+    Wrapping32,
+
+    /**
+     * 64 bit integers. Overflow is an error.
+     */
+    Checked64,
+
+    /**
+     * 64 bit integers which are transparently promoted to arbitrary-precision integers when a
+     * result would overflow, and demoted back when a result fits into 64 bits again.
+     */
+    Promoting,
+}
+
+/**
+ * An integer value. A `Big` integer never fits into an `i64`, so every value has exactly one
+ * representation.
+ */
+#[derive(PartialEq,Eq,Debug,Clone,Hash)]
+pub enum Integer {
+    Small(i64),
+    Big(BigInt),
+}
+
+impl Default for IntegerSemantics {
+    fn default() -> Self {
+        IntegerSemantics::Wrapping32
+    }
+}
+
+impl IntegerSemantics {
+    pub fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "wrapping32" => Ok(IntegerSemantics::Wrapping32),
+            "checked64"  => Ok(IntegerSemantics::Checked64),
+            "promoting"  => Ok(IntegerSemantics::Promoting),
+            _ => Err(format!("Unknown integer semantics {}, expecting one of: wrapping32, \
+                              checked64, promoting", name)),
+        }
+    }
+
+    /**
+     * Checks whether an integer literal is representable under these semantics.
+     */
+    pub fn literal(&self, value: i64) -> Result<Integer, String> {
+        match self {
+            IntegerSemantics::Wrapping32 if value < i32::MIN as i64 || value > i32::MAX as i64 =>
+                Err(format!("integer literal {} does not fit into 32 bits", value)),
+            _ => Ok(Integer::Small(value)),
+        }
+    }
+
+    /**
+     * Performs an arithmetic operation (`+`, `-`, `*`, `/`, or `%`).
+     */
+    pub fn arithmetic(&self, operator: &Operator, left: &Integer, right: &Integer) -> Result<Integer, String> {
+        match operator {
+            Operator::Division | Operator::Module if right.is_zero() =>
+                return Err(format!("division by zero: {} {} {}", left, operator.to_str(), right)),
+            Operator::Addition | Operator::Subtraction | Operator::Multiplication
+            | Operator::Division | Operator::Module => (),
+            _ => return Err(format!("{} is not an arithmetic operator", operator.to_str())),
+        }
+
+        match self {
+            IntegerSemantics::Wrapping32 => {
+                let (l, r) = (left.wrap_to_i32(), right.wrap_to_i32());
+                let result = match operator {
+                    Operator::Addition       => l.wrapping_add(r),
+                    Operator::Subtraction    => l.wrapping_sub(r),
+                    Operator::Multiplication => l.wrapping_mul(r),
+                    Operator::Division       => l.wrapping_div(r),
+                    Operator::Module         => l.wrapping_rem(r),
+                    _ => unreachable!(),
+                };
+                Ok(Integer::Small(result as i64))
+            }
+
+            IntegerSemantics::Checked64 => {
+                let (l, r) = match (left, right) {
+                    (Integer::Small(l), Integer::Small(r)) => (*l, *r),
+                    _ => return Err(format!("integer {} or {} does not fit into 64 bits", left, right)),
+                };
+                checked_i64(operator, l, r)
+                    .map(Integer::Small)
+                    .ok_or_else(|| format!("integer overflow: {} {} {}", l, operator.to_str(), r))
+            }
+
+            IntegerSemantics::Promoting => {
+                if let (Integer::Small(l), Integer::Small(r)) = (left, right) {
+                    if let Some(result) = checked_i64(operator, *l, *r) {
+                        return Ok(Integer::Small(result))
+                    }
+                }
+                let (l, r) = (left.to_big(), right.to_big());
+                let result = match operator {
+                    Operator::Addition       => l + r,
+                    Operator::Subtraction    => l - r,
+                    Operator::Multiplication => l * r,
+                    Operator::Division       => l / r,
+                    Operator::Module         => l % r,
+                    _ => unreachable!(),
+                };
+                Ok(Integer::from_big(result))
+            }
+        }
+    }
+
+    /**
+     * Absolute value, computed as `0 - value` for negative values, so it overflows just like
+     * subtraction does.
+     */
+    pub fn absolute(&self, value: &Integer) -> Result<Integer, String> {
+        if value < &Integer::Small(0) {
+            self.arithmetic(&Operator::Subtraction, &Integer::Small(0), value)
+        } else {
+            Ok(value.clone())
+        }
+    }
+
+    /**
+     * Raises `base` to a non-negative `exponent` by repeated multiplication, so it overflows just
+     * like multiplication does.
+     */
+    pub fn power(&self, base: &Integer, exponent: &Integer) -> Result<Integer, String> {
+        let exponent = match exponent {
+            Integer::Small(n) if *n >= 0 => *n,
+            _ => return Err(format!("exponent {} must be a non-negative 64 bit integer", exponent)),
+        };
+
+        let mut result = Integer::Small(1);
+        let mut square = base.clone();
+        let mut remaining = exponent;
+        while remaining > 0 {
+            if remaining % 2 == 1 {
+                result = self.arithmetic(&Operator::Multiplication, &result, &square)?;
+            }
+            remaining /= 2;
+            if remaining > 0 {
+                square = self.arithmetic(&Operator::Multiplication, &square, &square)?;
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn checked_i64(operator: &Operator, l: i64, r: i64) -> Option<i64> {
+    match operator {
+        Operator::Addition       => l.checked_add(r),
+        Operator::Subtraction    => l.checked_sub(r),
+        Operator::Multiplication => l.checked_mul(r),
+        Operator::Division       => l.checked_div(r),
+        Operator::Module         => l.checked_rem(r),
+        _ => None,
+    }
+}
+
+impl Integer {
+    pub fn from_big(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(value) => Integer::Small(value),
+            None => Integer::Big(value),
+        }
+    }
+
+    pub fn to_big(&self) -> BigInt {
+        match self {
+            Integer::Small(value) => BigInt::from(*value),
+            Integer::Big(value) => value.clone(),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Integer::Small(value) => *value as f64,
+            Integer::Big(value) => value.to_f64().unwrap_or(std::f64::NAN),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Integer::Small(value) => *value == 0,
+            Integer::Big(value) => value.is_zero(),
+        }
+    }
+
+    fn wrap_to_i32(&self) -> i32 {
+        match self {
+            Integer::Small(value) => *value as i32,
+            Integer::Big(value) => (value % BigInt::from(1u64 << 32)).to_i64().unwrap() as i32,
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Integer::Small(l), Integer::Small(r)) => l.cmp(r),
+            (l, r) => l.to_big().cmp(&r.to_big()),
+        }
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integer::Small(value) => write!(f, "{}", value),
+            Integer::Big(value) => write!(f, "{}", value),
+        }
+    }
+}
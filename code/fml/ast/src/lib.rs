@@ -2,19 +2,28 @@ use std::fmt::Debug;
 use std::cmp::PartialEq;
 use serde::{Serialize, Deserialize};
 
+pub mod integers;
+pub mod format;
+pub mod hashing;
+
 pub trait Portable {
     fn to_string(&self) -> String;
 }
 
 #[derive(PartialEq,Debug,Serialize,Deserialize,Clone)]
 pub enum AST {
-    Number(i32),
+    Number(i64),
+    Float(f64),
     Boolean(bool),
+    String(String),
     Unit,
 
     VariableDefinition { name: Identifier, value: Box<AST> },
+    ConstantDefinition { name: Identifier, value: Box<AST> },
     ArrayDefinition { size: Box<AST>, value: Box<AST> },
     ObjectDefinition { extends: Option<Box<AST>>, members: Vec<Box<AST>> },
+    ClassDefinition { name: Identifier, parameters: Vec<Identifier>, extends: Option<Box<AST>>, members: Vec<Box<AST>> },
+    MapDefinition { entries: Vec<(Box<AST>, Box<AST>)> },
 
     VariableAccess { name: Identifier },
     FieldAccess { object: Box<AST>, field: Identifier },
@@ -29,14 +38,50 @@ pub enum AST {
 
     FunctionCall { function: Identifier, arguments: Vec<Box<AST>> },
     MethodCall { object: Box<AST>, method: Identifier, arguments: Vec<Box<AST>> },
+    SuperMethodCall { method: Identifier, arguments: Vec<Box<AST>> },
     OperatorCall { object: Box<AST>, operator: Operator, arguments: Vec<Box<AST>> },
+    Instantiation { class: Identifier, arguments: Vec<Box<AST>> },
     Print { format: String, arguments: Vec<Box<AST>> },
 
     Top (Vec<Box<AST>>),
     Block (Vec<Box<AST>>),
     Operation { operator: Operator, left: Box<AST>, right: Box<AST> },
     Loop { condition: Box<AST>, body: Box<AST> },
+    Iteration { variable: Identifier, iterable: Box<AST>, body: Box<AST> },
+    Range { start: Box<AST>, end: Box<AST> },
+    Break,
+    Continue,
     Conditional { condition: Box<AST>, consequent: Box<AST>, alternative: Box<AST> },
+    Match { value: Box<AST>, arms: Vec<(Pattern, Box<AST>)> },
+
+    Import { path: String, name: Identifier },
+}
+
+#[derive(PartialEq,Debug,Serialize,Deserialize,Clone)]
+pub enum Pattern {
+    Number(i64),
+    Boolean(bool),
+    String(String),
+    Unit,
+
+    Wildcard,
+    Binding(Identifier),
+    Array(Vec<Pattern>),
+    Object(Vec<(Identifier, Pattern)>),
+}
+
+impl Pattern {
+    // Names bound by this pattern, in the order they appear in it.
+    pub fn bindings(&self) -> Vec<Identifier> {
+        match self {
+            Pattern::Binding(name) => vec![name.clone()],
+            Pattern::Array(elements) =>
+                elements.iter().flat_map(|element| element.bindings()).collect(),
+            Pattern::Object(fields) =>
+                fields.iter().flat_map(|(_, pattern)| pattern.bindings()).collect(),
+            _ => vec![],
+        }
+    }
 }
 
 #[derive(PartialEq,Eq,Hash,Debug,Clone,Serialize,Deserialize)]
@@ -50,6 +95,10 @@ impl Identifier {
         self.0.to_string()
     }
     pub fn to_str(&self) -> &str { &self.0 }
+    // Name of the constructor function of a class; the space keeps it out of reach of source code.
+    pub fn to_constructor(&self) -> Identifier {
+        Identifier(format!("new {}", self.0))
+    }
 }
 
 #[derive(PartialEq,Debug,Copy,Clone,Serialize,Deserialize)]
@@ -61,6 +110,7 @@ pub enum Operator {
     Subtraction,
     Inequality,
     Equality,
+    Identity,
     Less,
     LessEqual,
     Greater,
@@ -79,6 +129,7 @@ impl Operator {
             Operator::Subtraction    => "-",
             Operator::Inequality     => "!=",
             Operator::Equality       => "==",
+            Operator::Identity       => "is",
             Operator::Less           => "<",
             Operator::LessEqual      => "<=",
             Operator::Greater        => ">",
@@ -1,4 +1,4 @@
-use crate::types::{ConstantPoolIndex, LocalFrameIndex, Arity};
+use crate::types::{ConstantPoolIndex, LocalFrameIndex, Arity, Size};
 use crate::serializable::Serializable;
 use crate::serializable;
 use std::io::{Write, Read};
@@ -17,7 +17,10 @@ pub enum OpCode {
      *
      * The [ProgramObject] retrieved from the [ConstantPool] is *guaranteed* to be one of:
      *  - [ProgramObject::Integer],
-     *  - [ProgramObject::Boolean], or
+     *  - [ProgramObject::Long],
+     *  - [ProgramObject::Float],
+     *  - [ProgramObject::Boolean],
+     *  - [ProgramObject::String], or
      *  - [ProgramObject::Null].
      *
      * Serialized as opcode `0x01`.
@@ -27,6 +30,9 @@ pub enum OpCode {
      * [ProgramObject]: ../objects/enum.ProgramObject.html
      * [ProgramObject::Boolean]: ../objects/enum.ProgramObject.html#variant.Boolean
      * [ProgramObject::Integer]: ../objects/enum.ProgramObject.html#variant.Integer
+     * [ProgramObject::Long]: ../objects/enum.ProgramObject.html#variant.Long
+     * [ProgramObject::Float]: ../objects/enum.ProgramObject.html#variant.Float
+     * [ProgramObject::String]: ../objects/enum.ProgramObject.html#variant.String
      * [ProgramObject::Null]: ../objects/enum.ProgramObject.html#variant.Null
      */
     Literal { index: /*Integer|Null|Boolean*/ ConstantPoolIndex },
@@ -110,6 +116,22 @@ pub enum OpCode {
      */
     Array,
 
+    /**
+     * ## Create a new map (runtime) object
+     *
+     * Pops `2 * entries` values from the `OperandStack`. The values are interpreted as key-value
+     * pairs, with the key of each pair pushed before its value. Creates a new map containing
+     * all these pairs and pushes it onto the `OperandStack`. If the same key occurs more than
+     * once, the pair pushed last wins.
+     *
+     * Keys must be integers, booleans, strings, or null.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x11`.
+     */
+    Map { entries: Size },
+
     /**
      * ## Push the value of an object's field member to stack
      *
@@ -160,6 +182,20 @@ pub enum OpCode {
      */
     CallMethod { name: ConstantPoolIndex, arguments: Arity },
 
+    /**
+     * ## Call a member method of the parent of the receiver
+     *
+     * Works like `CallMethod`, except that the method is looked up starting from the `parent` of
+     * the object that defines the method being run, rather than from the receiver. The method
+     * that is found is called with the given receiver, so passing `this` calls the method that the
+     * current one overrides, on the same object.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x12`.
+     */
+    CallSuperMethod { name: ConstantPoolIndex, arguments: Arity },
+
     /**
      * ## Call a global function
      *
@@ -247,6 +283,94 @@ pub enum OpCode {
      */
     Drop,
 
+    /**
+     * ## Test a value against a literal pattern
+     *
+     * Retrieves the constant at the given index, which must be an integer, a boolean, a string,
+     * or null. Pops a value from the `OperandStack` and pushes `true` if it is of the same type as
+     * the constant and equal to it, and `false` otherwise.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x13`.
+     */
+    MatchLiteral { index: ConstantPoolIndex },
+
+    /**
+     * ## Test a value against an array pattern
+     *
+     * Pops a value from the `OperandStack` and pushes `true` if it is an array with exactly
+     * `length` elements, and `false` otherwise.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x14`.
+     */
+    MatchArray { length: Size },
+
+    /**
+     * ## Test a value against a field of an object pattern
+     *
+     * Retrieves the string constant at the given index. Pops a value from the `OperandStack` and
+     * pushes `true` if it is a `RuntimeObject::Object` with its own field of that name, and
+     * `false` otherwise. Methods the object defines are not consulted.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x15`.
+     */
+    MatchField { name: ConstantPoolIndex },
+
+    /**
+     * ## Fail a match expression
+     *
+     * Pops the value that was matched from the `OperandStack` and stops the program with an error
+     * saying that no pattern matches it.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x16`.
+     */
+    NoMatch,
+
+    /**
+     * ## Mark the height of the operand stack
+     *
+     * Remembers how many values there are on the `OperandStack`, so that `DropToMark` can return
+     * to it. Marks are kept on a stack of their own. The compiler emits a mark at the start of a
+     * loop that contains `break` or `continue`.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x17`.
+     */
+    Mark,
+
+    /**
+     * ## Drop operands down to the mark
+     *
+     * Pops values from the `OperandStack` until there are as many as when the most recent mark was
+     * made. The mark is kept. Used by `break` and `continue` to drop the values an enclosing
+     * expression pushed before jumping out of it.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x18`.
+     */
+    DropToMark,
+
+    /**
+     * ## Forget the mark
+     *
+     * Removes the most recent mark. The compiler emits it at the end of a loop that starts with
+     * `Mark`.
+     *
+     * This is an extension to Feeny's bytecode.
+     *
+     * Serialized as opcode `0x19`.
+     */
+    Unmark,
+
     /**
      * ## Does nothing
      *
@@ -272,11 +396,14 @@ impl Serializable for OpCode {
             Print        { format,   arguments } => { format.serialize(sink);
                                                       arguments.serialize(sink)          },
             Array                                => {                                    },
+            Map          { entries             } => { entries.serialize(sink)            },
             Object       { class               } => { class.serialize(sink)              },
             GetSlot      { name                } => { name.serialize(sink)               },
             SetSlot      { name                } => { name.serialize(sink)               },
             CallMethod   { name,     arguments } => { name.serialize(sink);
                                                       arguments.serialize(sink)          },
+            CallSuperMethod { name,  arguments } => { name.serialize(sink);
+                                                      arguments.serialize(sink)          },
             CallFunction { name: function, arguments } => { function.serialize(sink);
                                                       arguments.serialize(sink)          },
             SetLocal     { index               } => { index.serialize(sink)              },
@@ -287,6 +414,13 @@ impl Serializable for OpCode {
             Jump         { label               } => { label.serialize(sink)              },
             Return                               => {                                    },
             Drop                                 => {                                    },
+            MatchLiteral { index               } => { index.serialize(sink)              },
+            MatchArray   { length              } => { length.serialize(sink)             },
+            MatchField   { name                } => { name.serialize(sink)               },
+            NoMatch                              => {                                    },
+            Mark                                 => {                                    },
+            DropToMark                           => {                                    },
+            Unmark                               => {                                    },
             Skip                                 => {                                    },
         };
     }
@@ -316,6 +450,16 @@ impl Serializable for OpCode {
             0x0E => Jump         { label:     ConstantPoolIndex::from_bytes(input)  },
             0x0F => Return,
             0x10 => Drop,
+            0x11 => Map          { entries:   Size::from_bytes(input)               },
+            0x12 => CallSuperMethod { name:   ConstantPoolIndex::from_bytes(input),
+                                   arguments: Arity::from_bytes(input)              },
+            0x13 => MatchLiteral { index:     ConstantPoolIndex::from_bytes(input)  },
+            0x14 => MatchArray   { length:    Size::from_bytes(input)               },
+            0x15 => MatchField   { name:      ConstantPoolIndex::from_bytes(input)  },
+            0x16 => NoMatch,
+            0x17 => Mark,
+            0x18 => DropToMark,
+            0x19 => Unmark,
             tag  => panic!("Cannot deserialize opcode: unknown tag {}", tag)
         }
     }
@@ -342,6 +486,15 @@ impl OpCode {
             Jump         { label: _                  } => 0x0E,
             Return                                     => 0x0F,
             Drop                                       => 0x10,
+            Map          { entries: _                } => 0x11,
+            CallSuperMethod { name: _,  arguments: _ } => 0x12,
+            MatchLiteral { index: _                  } => 0x13,
+            MatchArray   { length: _                 } => 0x14,
+            MatchField   { name: _                   } => 0x15,
+            NoMatch                                    => 0x16,
+            Mark                                       => 0x17,
+            DropToMark                                 => 0x18,
+            Unmark                                     => 0x19,
             Skip => 0xFF,
         }
     }
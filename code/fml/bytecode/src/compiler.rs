@@ -1,16 +1,26 @@
 use fml_ast;
 use crate::bytecode::OpCode;
-use fml_ast::{AST, Identifier, Operator};
+use fml_ast::{AST, Identifier, Operator, Pattern};
+use fml_ast::format::Format;
 use crate::program::Program;
 use crate::objects::ProgramObject;
 use crate::types::{LocalFrameIndex, ConstantPoolIndex, Arity, Size, AddressRange};
 use std::collections::{HashMap, HashSet};
 use crate::bytecode::OpCode::Literal;
 use std::ops::Deref;
+use fml_parser::modules::{ModuleLoader, Loaded};
+use fml_parser::checker::children;
 
 pub fn compile(ast: &AST) -> Program {
+    compile_with_modules(ast, ModuleLoader::new(vec!()))
+}
+
+pub fn compile_with_modules(ast: &AST, modules: ModuleLoader) -> Program {
     let mut program: Program = Program::empty();
     let mut bookkeeping: Bookkeeping = Bookkeeping::without_frame();
+    bookkeeping.modules = modules;
+    let ast = bookkeeping.modules.link(ast)
+        .unwrap_or_else(|e| panic!("Import error: {}", e));
     ast.compile_into(&mut program, &mut bookkeeping, true);
     program
 }
@@ -20,13 +30,22 @@ type Scope = usize;
 #[derive(PartialEq,Debug,Clone)]
 struct LocalFrame {
     locals: HashMap<(Scope, String), LocalFrameIndex>,
+    constants: HashSet<(Scope, String)>,
     scopes: Vec<Scope>,
     scope_sequence: Scope,
+    loops: Vec<LoopLabels>,
+}
+
+/** Labels of an enclosing loop that `break` and `continue` jump to. */
+#[derive(PartialEq,Debug,Clone,Copy)]
+struct LoopLabels {
+    end: ConstantPoolIndex,
+    condition: ConstantPoolIndex,
 }
 
 impl LocalFrame {
     fn new() -> Self {
-        LocalFrame { locals: HashMap::new(), scopes: vec!(0), scope_sequence: 0 }
+        LocalFrame { locals: HashMap::new(), constants: HashSet::new(), scopes: vec!(0), scope_sequence: 0, loops: vec!() }
     }
 
     #[allow(dead_code)]
@@ -37,7 +56,7 @@ impl LocalFrame {
             local_map.insert((0, local), LocalFrameIndex::from_usize(i));
         }
 
-        LocalFrame { locals: local_map, scopes: vec!(0), scope_sequence: 0 }
+        LocalFrame { locals: local_map, constants: HashSet::new(), scopes: vec!(0), scope_sequence: 0, loops: vec!() }
     }
 
     #[allow(dead_code)]
@@ -48,7 +67,7 @@ impl LocalFrame {
             local_map.insert((level, local), LocalFrameIndex::from_usize(i));
         }
 
-        LocalFrame { locals: local_map, scopes: vec!(0), scope_sequence: 0 }
+        LocalFrame { locals: local_map, constants: HashSet::new(), scopes: vec!(0), scope_sequence: 0, loops: vec!() }
     }
 
     fn current_scope(&self) -> Scope {
@@ -69,6 +88,23 @@ impl LocalFrame {
         Ok(index)
     }
 
+    fn register_new_constant(&mut self, id: &str) -> Result<LocalFrameIndex, String> {
+        let index = self.register_new_local(id)?;
+        self.constants.insert((self.current_scope(), id.to_string()));
+        Ok(index)
+    }
+
+    // Whether the innermost local visible under this name was defined with const.
+    fn is_constant(&self, id: &str) -> bool {
+        for scope in self.scopes.iter().rev() {
+            let key = (*scope, id.to_owned());
+            if self.locals.contains_key(&key) {
+                return self.constants.contains(&key);
+            }
+        }
+        false
+    }
+
     fn register_local(&mut self, id: &str) -> LocalFrameIndex {
         for scope in self.scopes.iter().rev() {
             let key = (*scope, id.to_owned());
@@ -112,6 +148,19 @@ impl LocalFrame {
         index
     }
 
+    fn enter_loop(&mut self, labels: LoopLabels) {
+        self.loops.push(labels);
+    }
+
+    fn leave_loop(&mut self) {
+        self.loops.pop()
+            .expect("Cannot leave loop: the loop stack is empty");
+    }
+
+    fn innermost_loop(&self) -> Option<LoopLabels> {
+        self.loops.last().copied()
+    }
+
     fn enter_scope(&mut self) {
         self.scope_sequence += 1;
         self.scopes.push(self.scope_sequence);
@@ -127,7 +176,9 @@ impl LocalFrame {
 pub struct Bookkeeping { // TODO rename
     frames: Vec<LocalFrame>,
     globals: HashSet<String>,
+    constants: HashSet<String>,
     top: LocalFrame,
+    modules: ModuleLoader,
 }
 
 // enum VariableIndex {
@@ -141,7 +192,9 @@ impl Bookkeeping {
         Bookkeeping {
             frames: vec!(LocalFrame::new()),
             globals: HashSet::new(),
+            constants: HashSet::new(),
             top: LocalFrame::new(),
+            modules: ModuleLoader::new(vec!()),
         }
     }
 
@@ -149,7 +202,9 @@ impl Bookkeeping {
         Bookkeeping {
             frames: vec!(),
             globals: HashSet::new(),
+            constants: HashSet::new(),
             top: LocalFrame::new(),
+            modules: ModuleLoader::new(vec!()),
         }
     }
 
@@ -158,7 +213,9 @@ impl Bookkeeping {
         Bookkeeping {
             frames: vec!(LocalFrame::from_locals(locals)),
             globals: globals.into_iter().collect(),
+            constants: HashSet::new(),
             top: LocalFrame::new(),
+            modules: ModuleLoader::new(vec!()),
         }
     }
 
@@ -167,7 +224,9 @@ impl Bookkeeping {
         Bookkeeping {
             frames: vec!(LocalFrame::from_locals(locals)),
             globals: HashSet::new(),
+            constants: HashSet::new(),
             top: LocalFrame::new(),
+            modules: ModuleLoader::new(vec!()),
         }
     }
 
@@ -176,7 +235,9 @@ impl Bookkeeping {
         Bookkeeping {
             frames: vec!(LocalFrame::from_locals_at(locals, level)),
             globals: HashSet::new(),
+            constants: HashSet::new(),
             top: LocalFrame::new(),
+            modules: ModuleLoader::new(vec!()),
         }
     }
 
@@ -185,7 +246,9 @@ impl Bookkeeping {
         Bookkeeping {
             frames: vec!(),
             globals: globals.into_iter().collect(),
+            constants: HashSet::new(),
             top: LocalFrame::new(),
+            modules: ModuleLoader::new(vec!()),
         }
     }
 
@@ -214,6 +277,30 @@ impl Bookkeeping {
         }
     }
 
+    fn enter_loop(&mut self, labels: LoopLabels) {
+        if self.frames.is_empty() {
+            self.top.enter_loop(labels)
+        } else {
+            self.frames.last_mut().unwrap().enter_loop(labels)
+        }
+    }
+
+    fn leave_loop(&mut self) {
+        if self.frames.is_empty() {
+            self.top.leave_loop()
+        } else {
+            self.frames.last_mut().unwrap().leave_loop()
+        }
+    }
+
+    // Functions and methods get frames of their own, so loops do not reach into their bodies.
+    fn innermost_loop(&self) -> Option<LoopLabels> {
+        match self.frames.last() {
+            None => self.top.innermost_loop(),
+            Some(frame) => frame.innermost_loop(),
+        }
+    }
+
     fn has_frame(&self) -> bool {
         !(self.frames.is_empty() && self.top.in_outermost_scope())
     }
@@ -222,6 +309,23 @@ impl Bookkeeping {
         self.globals.insert(id.to_string());
     }
 
+    fn register_constant_global(&mut self, id: &str) {
+        self.globals.insert(id.to_string());
+        self.constants.insert(id.to_string());
+    }
+
+    // Locals shadow globals, so a local defined with let hides a global constant of the same name.
+    fn is_constant(&self, id: &str) -> bool {
+        if self.has_local(id) {
+            match self.frames.last() {
+                None => self.top.is_constant(id),
+                Some(frame) => frame.is_constant(id),
+            }
+        } else {
+            self.constants.contains(id)
+        }
+    }
+
     fn has_local(&self, id: &str) -> bool {
         println!("How many frames? {:?}", self.frames.len());
         match self.frames.last() {
@@ -249,6 +353,14 @@ impl Bookkeeping {
         }
     }
 
+    fn register_new_constant(&mut self, id: &str) -> Result<LocalFrameIndex, String> {
+        if self.frames.is_empty() {
+            self.top.register_new_constant(id)
+        } else {
+            self.frames.last_mut().unwrap().register_new_constant(id)
+        }
+    }
+
     fn register_local(&mut self, id: &str) -> LocalFrameIndex {
         if self.frames.is_empty() {
             self.top.register_local(id)
@@ -302,7 +414,14 @@ impl Compiled for AST {
         println!("AST: {:?}", self);
         match self {
             AST::Number(value) => {
-                let constant = ProgramObject::Integer(*value);
+                let constant = ProgramObject::from_i64(*value);
+                let index = program.register_constant(constant);
+                program.emit_code(OpCode::Literal { index });
+                program.emit_conditionally(OpCode::Drop, !keep_result);
+            }
+
+            AST::Float(value) => {
+                let constant = ProgramObject::Float(*value);
                 let index = program.register_constant(constant);
                 program.emit_code(OpCode::Literal { index });
                 program.emit_conditionally(OpCode::Drop, !keep_result);
@@ -315,6 +434,13 @@ impl Compiled for AST {
                 program.emit_conditionally(OpCode::Drop, !keep_result);
             }
 
+            AST::String(value) => {
+                let constant = ProgramObject::from_str(value);
+                let index = program.register_constant(constant);
+                program.emit_code(OpCode::Literal { index });
+                program.emit_conditionally(OpCode::Drop, !keep_result);
+            }
+
             AST::Unit => {
                 let constant = ProgramObject::Null;
                 let index = program.register_constant(constant);
@@ -323,19 +449,12 @@ impl Compiled for AST {
             }
 
             AST::VariableDefinition { name: Identifier(name), value } => {
-                if environment.has_frame() {
-                    let index = environment.register_new_local(name)
-                        .expect(&format!("Cannot register new variable {}", &name))
-                        .clone();   // FIXME error if not new
-                    value.deref().compile_into(program, environment, true);    // FIXME scoping!!!
-                    program.emit_code(OpCode::SetLocal { index });
+                compile_variable_definition(name, false, value.deref(), program, environment);
+                program.emit_conditionally(OpCode::Drop, !keep_result);
+            }
 
-                } else {
-                    let index = program.register_constant(ProgramObject::from_str(name));
-                    environment.register_global(name);                  // TODO necessary?
-                    value.deref().compile_into(program, environment, true);
-                    program.emit_code(OpCode::SetGlobal { name: index });
-                }
+            AST::ConstantDefinition { name: Identifier(name), value } => {
+                compile_variable_definition(name, true, value.deref(), program, environment);
                 program.emit_conditionally(OpCode::Drop, !keep_result);
             }
 
@@ -353,6 +472,9 @@ impl Compiled for AST {
 
             AST::VariableMutation { name: Identifier(name), value } => {
                 println!("Has local? (mut) {:?} {}", name, environment.has_local(name));
+                if environment.is_constant(name) {
+                    panic!("Constant error: {} is constant and cannot be changed", name)
+                }
                 if environment.has_local(name) {
                     let index = environment.register_local(name).clone(); // FIXME error if does not exists
                     value.deref().compile_into(program, environment, true);    // FIXME scoping!!!
@@ -380,15 +502,24 @@ impl Compiled for AST {
             }
 
             AST::Loop { condition, body } => {
-                let (body_label_index, condition_label_index)
-                    = unpack!((_,_) from program.generate_new_label_names(vec!["loop_body", "loop_condition"]));
+                let (body_label_index, condition_label_index, end_label_index)
+                    = unpack!((_,_,_) from program.generate_new_label_names(vec!["loop_body", "loop_condition", "loop_end"]));
 
+                // A break or continue can happen in the middle of an expression, so the loop
+                // remembers where the operand stack was to drop back to it before jumping.
+                let escapes = may_break_or_continue(body);
+
+                program.emit_conditionally(OpCode::Mark, escapes);
                 program.emit_code(OpCode::Jump { label: condition_label_index });
                 program.emit_code(OpCode::Label { name: body_label_index });
+                environment.enter_loop(LoopLabels { end: end_label_index, condition: condition_label_index });
                 (**body).compile_into(program, environment, false);
+                environment.leave_loop();
                 program.emit_code(OpCode::Label { name: condition_label_index });
                 (**condition).compile_into(program, environment, true);
                 program.emit_code(OpCode::Branch { label: body_label_index });
+                program.emit_code(OpCode::Label { name: end_label_index });
+                program.emit_conditionally(OpCode::Unmark, escapes);
 
                 if keep_result {
                     let constant = ProgramObject::Null;
@@ -397,6 +528,162 @@ impl Compiled for AST {
                 }
             }
 
+            AST::Iteration { variable, iterable, body } => {
+                let names = program.generate_new_local_names(vec!["::i", "::end", "::array"]);
+                let (i, end, array) =
+                    (Identifier::from(&names[0]), Identifier::from(&names[1]), Identifier::from(&names[2]));
+
+                let (setup, element) = match iterable.deref() {
+                    // let ::i = eval START;
+                    // let ::end = eval END;
+                    AST::Range { start, end: stop } => {
+                        let setup = vec!(
+                            Box::new(AST::VariableDefinition { name: i.clone(), value: start.clone() }),
+                            Box::new(AST::VariableDefinition { name: end.clone(), value: stop.clone() }),
+                        );
+                        (setup, AST::VariableAccess { name: i.clone() })
+                    }
+
+                    // let ::array = eval ITERABLE;
+                    // let ::end = ::array.length();
+                    // let ::i = 0;
+                    _ => {
+                        let setup = vec!(
+                            Box::new(AST::VariableDefinition { name: array.clone(), value: iterable.clone() }),
+                            Box::new(AST::VariableDefinition {
+                                name: end.clone(),
+                                value: Box::new(AST::MethodCall {
+                                    object: Box::new(AST::VariableAccess { name: array.clone() }),
+                                    method: Identifier::from("length"),
+                                    arguments: vec!(),
+                                }),
+                            }),
+                            Box::new(AST::VariableDefinition { name: i.clone(), value: Box::new(AST::Number(0)) }),
+                        );
+                        let element = AST::ArrayAccess {
+                            array: Box::new(AST::VariableAccess { name: array }),
+                            index: Box::new(AST::VariableAccess { name: i.clone() }),
+                        };
+                        (setup, element)
+                    }
+                };
+
+                //   while ::i < ::end do
+                //   begin
+                //      let VARIABLE = ELEMENT;
+                //      ::i <- ::i + 1;
+                //      eval BODY;
+                //   end;
+                let loop_de_loop = AST::Loop {
+                    condition: Box::new(AST::Operation {
+                        operator: Operator::Less,
+                        left: Box::new(AST::VariableAccess { name: i.clone() }),
+                        right: Box::new(AST::VariableAccess { name: end }),
+                    }),
+                    body: Box::new(AST::Block(vec!(
+                        Box::new(AST::VariableDefinition { name: variable.clone(), value: Box::new(element) }),
+                        Box::new(AST::VariableMutation {
+                            name: i.clone(),
+                            value: Box::new(AST::Operation {
+                                operator: Operator::Addition,
+                                left: Box::new(AST::VariableAccess { name: i }),
+                                right: Box::new(AST::Number(1)),
+                            }),
+                        }),
+                        body.clone(),
+                    ))),
+                };
+
+                let mut iteration = setup;
+                iteration.push(Box::new(loop_de_loop));
+                AST::Block(iteration).compile_into(program, environment, keep_result);
+            }
+
+            AST::Break => {
+                let labels = environment.innermost_loop()
+                    .expect("Break error: break can only be used inside a loop");
+                program.emit_code(OpCode::DropToMark);
+                program.emit_code(OpCode::Jump { label: labels.end });
+            }
+
+            AST::Continue => {
+                let labels = environment.innermost_loop()
+                    .expect("Continue error: continue can only be used inside a loop");
+                program.emit_code(OpCode::DropToMark);
+                program.emit_code(OpCode::Jump { label: labels.condition });
+            }
+
+            AST::Match { value, arms } => {
+                let names = program.generate_new_local_names(vec!["::match"]);
+                let subject = Identifier::from(&names[0]);
+                let end_label_index = unpack!((_) from program.generate_new_label_names(vec!["match_end"]));
+
+                // let ::match = eval VALUE;
+                // for each arm: check each test of PATTERN, going to the next arm if one fails, then
+                //               begin let BINDING_1 = PATH_1; ...; eval BODY end
+                // no match ::match
+                environment.enter_scope();
+                AST::VariableDefinition { name: subject.clone(), value: value.clone() }
+                    .compile_into(program, environment, false);
+
+                for (pattern, body) in arms {
+                    let mut tests = Vec::new();
+                    let mut bindings = Vec::new();
+                    lower_pattern(pattern, AST::VariableAccess { name: subject.clone() },
+                                  &mut tests, &mut bindings);
+
+                    // Tests are checked in order, so a failed shape test guards the element and
+                    // field accesses that come after it.
+                    let next_label_index = unpack!((_) from program.generate_new_label_names(vec!["match_next"]));
+                    for (path, test) in tests {
+                        let passed_label_index =
+                            unpack!((_) from program.generate_new_label_names(vec!["match_passed"]));
+                        path.compile_into(program, environment, true);
+                        match test {
+                            PatternTest::Literal(constant) => {
+                                let index = program.register_constant(constant);
+                                program.emit_code(OpCode::MatchLiteral { index });
+                            }
+                            PatternTest::Array(length) =>
+                                program.emit_code(OpCode::MatchArray { length: Size::from_usize(length) }),
+                            PatternTest::Field(Identifier(name)) => {
+                                let name = program.register_constant(ProgramObject::from_str(&name));
+                                program.emit_code(OpCode::MatchField { name });
+                            }
+                        }
+                        program.emit_code(OpCode::Branch { label: passed_label_index });
+                        program.emit_code(OpCode::Jump { label: next_label_index });
+                        program.emit_code(OpCode::Label { name: passed_label_index });
+                    }
+
+                    let mut consequent: Vec<Box<AST>> = bindings.into_iter()
+                        .map(|(name, path)| Box::new(AST::VariableDefinition { name, value: Box::new(path) }))
+                        .collect();
+                    consequent.push(body.clone());
+                    AST::Block(consequent).compile_into(program, environment, keep_result);
+                    program.emit_code(OpCode::Jump { label: end_label_index });
+                    program.emit_code(OpCode::Label { name: next_label_index });
+                }
+
+                AST::VariableAccess { name: subject }.compile_into(program, environment, true);
+                program.emit_code(OpCode::NoMatch);
+                program.emit_code(OpCode::Label { name: end_label_index });
+                environment.leave_scope();
+            }
+
+            AST::Range { start: _, end: _ } => {
+                panic!("Range error: a range can only be iterated over by a for loop")
+            }
+
+            AST::MapDefinition { entries } => {
+                for (key, value) in entries {
+                    key.deref().compile_into(program, environment, true);
+                    value.deref().compile_into(program, environment, true);
+                }
+                program.emit_code(OpCode::Map { entries: Size::from_usize(entries.len()) });
+                program.emit_conditionally(OpCode::Drop, !keep_result);
+            }
+
             AST::ArrayDefinition { size, value } => {
                 match value.deref() {
                     AST::Boolean(_) | AST::Number(_) | AST::Unit |
@@ -580,6 +867,14 @@ impl Compiled for AST {
             }
 
             AST::Print { format, arguments } => {
+                let expected_arguments = Format::parse(format)
+                    .unwrap_or_else(|e| panic!("Print error: {}", e))
+                    .expected_arguments();
+                if expected_arguments != arguments.len() {
+                    panic!("Print error: format {:?} expects {} arguments, but {} were supplied",
+                           format, expected_arguments, arguments.len())
+                }
+
                 let format: ConstantPoolIndex =
                     program.register_constant(ProgramObject::String(format.to_string()));
 
@@ -668,6 +963,26 @@ impl Compiled for AST {
                 program.emit_conditionally(OpCode::Drop, !keep_result);
             }
 
+            // A class is a constructor function that builds an object from its members, so the class
+            // constant is registered once, when the constructor is compiled, and shared by all
+            // instances. The constructor's parameters are in scope of the parent and field
+            // initializers.
+            AST::ClassDefinition { name, parameters, extends, members } => {
+                let constructor = AST::FunctionDefinition {
+                    function: name.to_constructor(),
+                    parameters: parameters.clone(),
+                    body: Box::new(AST::ObjectDefinition { extends: extends.clone(),
+                                                           members: members.clone() }),
+                };
+                constructor.compile_into(program, environment, keep_result);
+            }
+
+            AST::Instantiation { class, arguments } => {
+                let call = AST::FunctionCall { function: class.to_constructor(),
+                                               arguments: arguments.clone() };
+                call.compile_into(program, environment, keep_result);
+            }
+
             AST::ObjectDefinition { extends, members } => {
 
                 match extends {
@@ -696,6 +1011,11 @@ impl Compiled for AST {
                         let index = program.register_constant(ProgramObject::from_str(name));
                         program.register_constant(ProgramObject::slot_from_index(index))
                     },
+                    AST::ConstantDefinition { name: Identifier(name), value } => {
+                        (*value).compile_into(program, environment, true);
+                        let index = program.register_constant(ProgramObject::from_str(name));
+                        program.register_constant(ProgramObject::ConstantSlot { name: index })
+                    },
                     _ => panic!("Object definition: cannot define a member from {:?}", m)
                 }).collect();
 
@@ -743,6 +1063,23 @@ impl Compiled for AST {
                 program.emit_conditionally(OpCode::Drop, !keep_result);
             }
 
+            // The overridden method is looked up from the parent of the object defining the current
+            // method, and runs with the same `this`.
+            AST::SuperMethodCall { method: Identifier(name), arguments } => {
+                if !environment.has_local("this") {
+                    panic!("Super call error: super.{} can only be called inside a method", name)
+                }
+                let index = program.register_constant(ProgramObject::from_str(name));
+                AST::VariableAccess { name: Identifier::from("this") }
+                    .compile_into(program, environment, true);
+                for argument in arguments.iter() {
+                    argument.compile_into(program, environment, true);
+                }
+                let arity = Arity::from_usize(arguments.len() + 1);
+                program.emit_code(OpCode::CallSuperMethod { name: index, arguments: arity });
+                program.emit_conditionally(OpCode::Drop, !keep_result);
+            }
+
             AST::OperatorCall { object, operator, arguments } => {
                 let index = program.register_constant(ProgramObject::from_str(operator.to_str()));
                 object.deref().compile_into(program, environment, true);
@@ -789,7 +1126,57 @@ impl Compiled for AST {
                 let function_index = program.register_constant(method);
                 program.set_entry(function_index);
             }
+
+            AST::Import { path, name: _ } => {
+                if environment.has_frame() {
+                    panic!("Import error: module {} can only be imported at the top level", path)
+                }
+
+                let loaded = environment.modules.load(path)
+                    .unwrap_or_else(|e| panic!("Import error: {}", e));
+
+                // A module's body is compiled in place the first time it is imported, its
+                // functions and variables become globals with mangled names.
+                if let Loaded::Module { prefix: _, body } = loaded {
+                    for child in body.iter() {
+                        child.deref().compile_into(program, environment, false)
+                    }
+                    environment.modules.finish();
+                }
+
+                let index = program.register_constant(ProgramObject::Null);
+                program.emit_conditionally(OpCode::Literal { index }, keep_result);
+            }
+        }
+    }
+}
+
+fn compile_variable_definition(name: &str,
+                               constant: bool,
+                               value: &AST,
+                               program: &mut Program,
+                               environment: &mut Bookkeeping) {
+    if environment.has_frame() {
+        let index = if constant {
+            environment.register_new_constant(name)
+        } else {
+            environment.register_new_local(name)
+        }.expect(&format!("Cannot register new variable {}", &name));   // FIXME error if not new
+        value.compile_into(program, environment, true);    // FIXME scoping!!!
+        program.emit_code(OpCode::SetLocal { index });
+
+    } else {
+        if environment.is_constant(name) {
+            panic!("Constant error: {} is constant and cannot be redefined", name)
+        }
+        let index = program.register_constant(ProgramObject::from_str(name));
+        if constant {
+            environment.register_constant_global(name);
+        } else {
+            environment.register_global(name);              // TODO necessary?
         }
+        value.compile_into(program, environment, true);
+        program.emit_code(OpCode::SetGlobal { name: index });
     }
 }
 
@@ -840,3 +1227,57 @@ fn compile_function_definition(name: &str,
 
     program.register_constant(method)
 }
+
+// Whether the body of a loop contains a break or continue. Functions and methods are skipped, since
+// loops do not reach into them.
+fn may_break_or_continue(ast: &AST) -> bool {
+    match ast {
+        AST::Break | AST::Continue => true,
+        AST::FunctionDefinition { .. } | AST::OperatorDefinition { .. } => false,
+        _ => children(ast).into_iter().any(may_break_or_continue),
+    }
+}
+
+// A check that a match arm makes of the value found at some path inside the matched value.
+enum PatternTest {
+    Literal(ProgramObject),
+    Array(usize),
+    Field(Identifier),
+}
+
+/**
+ * Translates a pattern into the tests a value at `path` has to pass to match it and the variables
+ * the pattern binds. The tests are dedicated instructions rather than calls, so that functions and
+ * methods defined by the program cannot change what a pattern matches. Array and object shapes are
+ * tested before their elements and fields are accessed.
+ */
+fn lower_pattern(pattern: &Pattern, path: AST, tests: &mut Vec<(AST, PatternTest)>,
+                 bindings: &mut Vec<(Identifier, AST)>) {
+    match pattern {
+        Pattern::Number(n) => tests.push((path, PatternTest::Literal(ProgramObject::from_i64(*n)))),
+        Pattern::Boolean(b) => tests.push((path, PatternTest::Literal(ProgramObject::Boolean(*b)))),
+        Pattern::String(s) => tests.push((path, PatternTest::Literal(ProgramObject::from_str(s)))),
+        Pattern::Unit => tests.push((path, PatternTest::Literal(ProgramObject::Null))),
+        Pattern::Wildcard => {}
+        Pattern::Binding(name) => bindings.push((name.clone(), path)),
+        Pattern::Array(elements) => {
+            tests.push((path.clone(), PatternTest::Array(elements.len())));
+            for (index, element) in elements.iter().enumerate() {
+                let element_path = AST::ArrayAccess {
+                    array: Box::new(path.clone()),
+                    index: Box::new(AST::Number(index as i64)),
+                };
+                lower_pattern(element, element_path, tests, bindings);
+            }
+        }
+        Pattern::Object(fields) => {
+            for (field, _) in fields {
+                tests.push((path.clone(), PatternTest::Field(field.clone())));
+            }
+            for (field, pattern) in fields {
+                let field_path = AST::FieldAccess { object: Box::new(path.clone()), field: field.clone() };
+                lower_pattern(pattern, field_path, tests, bindings);
+            }
+        }
+    }
+}
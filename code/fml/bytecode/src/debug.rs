@@ -131,6 +131,12 @@ impl UglyPrintWithContext for ProgramObject {
             ProgramObject::Integer(value) =>
                 write_string!(sink, first!(indent, prefix_first_line), "Int({})", value),
 
+            ProgramObject::Long(value) =>
+                write_string!(sink, first!(indent, prefix_first_line), "Long({})", value),
+
+            ProgramObject::Float(value) =>
+                write_string!(sink, first!(indent, prefix_first_line), "Float({:?})", value),
+
             ProgramObject::Boolean(value) =>
                 write_string!(sink, first!(indent, prefix_first_line), "Bool({})", value),
 
@@ -145,6 +151,12 @@ impl UglyPrintWithContext for ProgramObject {
                 write_string!(sink, 0, ")");
             },
 
+            ProgramObject::ConstantSlot {name} => {
+                write_string!(sink, first!(indent, prefix_first_line), "ConstantSlot(");
+                name.pretty_print_no_indent(sink);
+                write_string!(sink, 0, ")");
+            },
+
             ProgramObject::Class(slots) => {
                 write_string!(sink, first!(indent, prefix_first_line), "Class");
                 for slot in slots {
@@ -202,6 +214,10 @@ impl UglyPrint for OpCode {
             OpCode::Array => {
                 write_string!(sink, indent, "array");
             },
+            OpCode::Map { entries } => {
+                write_string!(sink, indent, "map ");
+                entries.pretty_print_no_indent(sink);
+            },
             OpCode::GetSlot { name } => {
                 write_string!(sink, indent, "get slot ");
                 name.pretty_print_no_indent(sink);
@@ -215,6 +231,11 @@ impl UglyPrint for OpCode {
                 name.pretty_print_no_indent(sink);
                 arguments.pretty_print_indent(sink, 1);
             },
+            OpCode::CallSuperMethod { name, arguments } => {
+                write_string!(sink, indent, "call super slot ");
+                name.pretty_print_no_indent(sink);
+                arguments.pretty_print_indent(sink, 1);
+            },
             OpCode::CallFunction { name, arguments } => {
                 write_string!(sink, indent, "call ");
                 name.pretty_print_no_indent(sink);
@@ -243,6 +264,30 @@ impl UglyPrint for OpCode {
             OpCode::Drop => {
                 write_string!(sink, indent, "drop");
             },
+            OpCode::MatchLiteral { index } => {
+                write_string!(sink, indent, "match lit ");
+                index.pretty_print_no_indent(sink);
+            },
+            OpCode::MatchArray { length } => {
+                write_string!(sink, indent, "match array ");
+                length.pretty_print_no_indent(sink);
+            },
+            OpCode::MatchField { name } => {
+                write_string!(sink, indent, "match field ");
+                name.pretty_print_no_indent(sink);
+            },
+            OpCode::NoMatch => {
+                write_string!(sink, indent, "no match");
+            },
+            OpCode::Mark => {
+                write_string!(sink, indent, "mark");
+            },
+            OpCode::DropToMark => {
+                write_string!(sink, indent, "drop to mark");
+            },
+            OpCode::Unmark => {
+                write_string!(sink, indent, "unmark");
+            },
             OpCode::Skip => {
                 write_string!(sink, indent, "nop");
             },
@@ -1,11 +1,19 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, BTreeMap, VecDeque};
+use std::rc::Rc;
 
-use crate::types::{Address, LocalFrameIndex, Arity};
-use crate::objects::{Pointer, Object, ProgramObject};
+use crate::types::{Address, LocalFrameIndex, Arity, ConstantPoolIndex};
+use crate::objects::{Pointer, Object, ProgramObject, MapKey, float_to_string};
 use crate::bytecode::OpCode;
 use crate::program::Program;
 use std::fmt::{Write, Error};
 use std::io::Write as IOWrite;
+use std::io::{BufRead, BufReader};
+use std::cmp::Ordering;
+use fml_ast::Operator;
+use fml_ast::integers::{IntegerSemantics, Integer};
+use fml_ast::format::{Format, Value};
+use fml_ast::hashing::Hasher;
+use fml_parser::checker::builtin_arity;
 
 pub struct Output {}
 
@@ -24,9 +32,22 @@ impl Write for Output {
     }
 }
 
-pub fn evaluate(program: &Program) {
+/**
+ * Evaluates the program. Returns the exit code if the program called `exit`.
+ */
+pub fn evaluate(program: &Program, integers: IntegerSemantics) -> Option<i32> {
+    evaluate_with_integers(program, integers, &mut Output::new())
+}
+
+pub fn evaluate_with_integers<W: Write>(program: &Program, integers: IntegerSemantics, output: &mut W) -> Option<i32> {
+    evaluate_with_input(program, integers, BufReader::new(std::io::stdin()), output)
+}
+
+pub fn evaluate_with_input<R: BufRead + 'static, W: Write>(program: &Program, integers: IntegerSemantics,
+                                                           input: R, output: &mut W) -> Option<i32> {
     let mut state = State::from(program);
-    let mut output = Output::new();
+    state.integers = integers;
+    state.input = Box::new(input);
 
     let (start_address, locals) = match program.get_constant(program.entry()) {
         Some(ProgramObject::Method { name:_, locals, arguments:_, code }) => (*code.start(), locals),
@@ -43,8 +64,10 @@ pub fn evaluate(program: &Program) {
     state.new_frame(None, slots);
     state.set_instruction_pointer(Some(start_address));
     while state.has_next_instruction_pointer() {
-        interpret(&mut state, &mut output, program);
+        interpret(&mut state, output, program);
     }
+
+    state.exit_code
 }
 
 /**
@@ -78,6 +101,7 @@ pub struct LocalFrame {
     slots: Vec<Pointer>, /* ProgramObject::Slot */
     return_address: Option<Address>, /* address */
     //parent_frame: u64, /* index to local frame stack */
+    host: Option<Pointer>, /* the object that defines the method running in the frame */
 }
 
 impl LocalFrame {
@@ -85,6 +109,7 @@ impl LocalFrame {
         LocalFrame {
             slots: vec!(),
             return_address: None,
+            host: None,
         }
     }
 
@@ -93,9 +118,24 @@ impl LocalFrame {
         LocalFrame {
             return_address,
             slots,
+            host: None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn from_method(host: Pointer, return_address: Option<Address>, slots: Vec<Pointer>) -> Self {
+        LocalFrame {
+            return_address,
+            slots,
+            host: Some(host),
         }
     }
 
+    // The object that defines the method running in this frame, if it is a method.
+    pub fn host(&self) -> Option<Pointer> {
+        self.host
+    }
+
     pub fn return_address(&self) -> &Option<Address> {
         &self.return_address
     }
@@ -228,7 +268,17 @@ impl Memory {
         match object {
             Object::Null => "null".to_string(),
             Object::Integer(n) => n.to_string(),
+            Object::BigInteger(n) => n.to_string(),
+            Object::Float(f) => float_to_string(*f),
             Object::Boolean(b) => b.to_string(),
+            Object::String(s) => s.clone(),
+            Object::Map(entries) => {
+                let entries: Vec<String> = entries.iter()
+                    .map(|(key, value)| format!("{}: {}", key.to_string(),
+                                                self.dereference_to_string(value)))
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            },
             Object::Array(elements) => {
                 let mut buffer = String::new();
                 buffer.push('[');
@@ -241,7 +291,7 @@ impl Memory {
                 buffer.push(']');
                 buffer
             },
-            Object::Object { parent, fields, methods:_ } => {
+            Object::Object { parent, fields, methods:_, constants:_ } => {
                 let mut buffer = String::from("object(");
 
                 buffer.push_str("..=");
@@ -271,6 +321,23 @@ pub struct State {
     pub globals: HashMap<String, Pointer>,
     pub functions: HashMap<String, ProgramObject>,
     pub memory: Memory,
+    pub integers: IntegerSemantics,
+    pub input: Box<dyn BufRead>,
+    pub exit_code: Option<i32>,
+    pub classes: HashMap<ConstantPoolIndex, Class>,
+    pub marks: Vec<usize>,
+}
+
+/**
+ * A class constant after its members were looked up and validated, kept by the state so that every
+ * instantiation of the same class after the first one skips straight to building the object. The
+ * methods are shared by all the instances rather than copied into each of them.
+ */
+#[derive(PartialEq,Debug,Clone)]
+pub struct Class {
+    pub slots: Vec<String>,
+    pub constants: HashSet<String>,
+    pub methods: Rc<HashMap<String, ProgramObject>>,
 }
 
 impl State {
@@ -340,6 +407,11 @@ impl State {
             globals,
             functions,
             memory,
+            integers: IntegerSemantics::default(),
+            input: Box::new(BufReader::new(std::io::stdin())),
+            exit_code: None,
+            classes: HashMap::new(),
+            marks: Vec::new(),
         }
     }
 
@@ -352,6 +424,11 @@ impl State {
             globals: HashMap::new(),
             functions: HashMap::new(),
             memory: Memory::new(),
+            integers: IntegerSemantics::default(),
+            input: Box::new(BufReader::new(std::io::stdin())),
+            exit_code: None,
+            classes: HashMap::new(),
+            marks: Vec::new(),
         }
     }
 
@@ -364,6 +441,11 @@ impl State {
             globals: HashMap::new(),
             functions: HashMap::new(),
             memory: Memory::new(),
+            integers: IntegerSemantics::default(),
+            input: Box::new(BufReader::new(std::io::stdin())),
+            exit_code: None,
+            classes: HashMap::new(),
+            marks: Vec::new(),
         }
     }
 
@@ -398,7 +480,12 @@ impl State {
     }
 
     pub fn new_frame(&mut self, return_address: Option<Address>, slots: Vec<Pointer>, ) {
-        self.frames.push(LocalFrame { slots, return_address });
+        self.frames.push(LocalFrame { slots, return_address, host: None });
+    }
+
+    // A frame for a method defined by `host`, whose receiver is the first slot.
+    pub fn new_method_frame(&mut self, return_address: Option<Address>, slots: Vec<Pointer>, host: Pointer) {
+        self.frames.push(LocalFrame { slots, return_address, host: Some(host) });
     }
 
     pub fn peek_operand(&mut self) -> Option<&Pointer> {
@@ -500,9 +587,13 @@ impl State {
         }
 
         let pass_by_value = object.as_ref().map_or(false, |e| match e {
-            Object::Object { parent:_, methods:_, fields:_ } => false,
+            Object::Object { parent:_, methods:_, fields:_, constants:_ } => false,
             Object::Array(_) => false,
+            Object::Map(_) => false,
+            Object::String(_) => true,
             Object::Integer(_) => true,
+            Object::BigInteger(_) => true,
+            Object::Float(_) => true,
             Object::Boolean(_) => true,
             Object::Null => true,
         });
@@ -548,8 +639,14 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
                 ProgramObject::Null => (),
                 ProgramObject::Boolean(_) => (),
                 ProgramObject::Integer(_) => (),
+                ProgramObject::Float(_) => (),
+                ProgramObject::String(_) => (),
+                ProgramObject::Long(value) => {
+                    state.integers.literal(*value)
+                        .unwrap_or_else(|e| panic!("Literal error: {}", e));
+                },
                 _ => panic!("Literal error: constant at index {:?} must be either Null, Integer, \
-                             or Boolean, but is {:?}", index, constant),
+                             Long, Float, Boolean, or String, but is {:?}", index, constant),
             }
 
             state.allocate_and_push_operand(Object::from_constant(constant));
@@ -618,90 +715,24 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
         }
 
         OpCode::Object { class: index } => {
-            let constant: &ProgramObject = program.get_constant(index)
-                .expect(&format!("Object error: no constant at index {:?}", index.value()));
-
-            let member_definitions: Vec<&ProgramObject> = match constant {
-                ProgramObject::Class(v) => v,
-                _ => panic!("Object error: constant at index {:?} must be a String, \
-                             but it is {:?}", index.value(), constant),
-            }.iter().map(| index | program.get_constant(index)
-                .expect(&format!("Object error: no constant at index {:?} for member_ object",
-                                 index.value()))).collect();
-
-            let (slots, methods): (Vec<&ProgramObject>, Vec<&ProgramObject>) =
-                member_definitions.iter().partition(|c| match c {
-                    ProgramObject::Method { code:_, locals:_, arguments:_, name:_ } => false,
-                    ProgramObject::Slot { name:_ } => true,
-                    member =>
-                        panic!("Object error: class members may be either Methods or Slots, \
-                                 but this member is {:?}", member),
-            }); // XXX this will work even if the member definitions are not sorted, which is
-                // contrary to the spec
-
-            let fields: HashMap<String, Pointer> = {
-                let mut map: HashMap<String, Pointer> = HashMap::new();
-                for slot in slots.into_iter().rev() {
-                    if let ProgramObject::Slot {name: index} = slot {
-                        let object = state.pop_operand()
-                            .expect("Object error: cannot pop operand (member) from empty operand \
-                                     stack");
-
-                        let constant: &ProgramObject = program.get_constant(index)
-                            .expect(&format!("Object error: no constant at index {:?}",
-                                             index.value()));
-
-                        let name: &str = match constant {
-                            ProgramObject::String(s) => s,
-                            _ => panic!("Object error: constant at index {:?} must be a String, \
-                                         but it is {:?}", index.value(), constant),
-                        };
-
-                        let result = map.insert(name.to_string(), object);
-                        if let Some(_) = result {
-                            panic!("Object error: member fields must have unique names, but \
-                                    {} is used by to name more than one field", name)
-                        }
-                    } else {
-                        unreachable!()
-                    }
-                }
-                map
+            let (slots, constants, methods) = {
+                let class = state.classes.entry(*index)
+                    .or_insert_with(|| resolve_class(program, index));
+                (class.slots.clone(), class.constants.clone(), class.methods.clone())
             };
 
-            let method_map: HashMap<String, ProgramObject> = {
-                let mut map: HashMap<String, ProgramObject> = HashMap::new();
-                for method in methods {
-                    match method {
-                        ProgramObject::Method { name: index, arguments:_, locals:_, code:_ } => {
-                            let constant: &ProgramObject = program.get_constant(index)
-                                .expect(&format!("Object error: no constant at index {:?}",
-                                                 index.value()));
-
-                            let name: &str = match constant {
-                                ProgramObject::String(s) => s,
-                                _ => panic!("Object error: constant at index {:?} must be a String, \
-                                             but it is {:?}", index.value(), constant),
-                            };
-                            let result = map.insert(name.to_string(), method.clone());
-
-                            match result {
-                                Some (other_method) =>
-                                    panic!("Object error: method {} has a non-unique name in \
-                                            object: {:?} v {:?}", name, method, other_method),
-                                None => ()
-                            }
-                        },
-                        _ => unreachable!(),
-                    }
-                }
-                map
-            };
+            let mut fields: HashMap<String, Pointer> = HashMap::new();
+            for name in slots.into_iter().rev() {
+                let object = state.pop_operand()
+                    .expect("Object error: cannot pop operand (member) from empty operand \
+                             stack");
+                fields.insert(name, object);
+            }
 
             let parent = state.pop_operand()
                 .expect("Object error: cannot pop operand (parent) from empty operand stack");
 
-            state.allocate_and_push_operand(Object::from(parent, fields, method_map));
+            state.allocate_and_push_operand(Object::with_constants(parent, fields, methods, constants));
             state.bump_instruction_pointer(program);
         }
 
@@ -741,6 +772,33 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
             state.bump_instruction_pointer(program);
         }
 
+        OpCode::Map { entries: size } => {
+            let mut pairs: Vec<(Pointer, Pointer)> = Vec::with_capacity(size.to_usize());
+            for index in 0..size.to_usize() {
+                let value = state.pop_operand()
+                    .expect(&format!("Map error: cannot pop value {} from empty operand stack",
+                                     index));
+                let key = state.pop_operand()
+                    .expect(&format!("Map error: cannot pop key {} from empty operand stack",
+                                     index));
+                pairs.push((key, value));
+            }
+
+            let mut entries: BTreeMap<MapKey, Pointer> = BTreeMap::new();
+            for (key_pointer, value) in pairs.into_iter().rev() {
+                let key_object = state.dereference(&key_pointer)
+                    .expect(&format!("Map error: pointer does not reference an object in memory \
+                                      {:?}", key_pointer));
+                let key = MapKey::from_object(key_object)
+                    .expect(&format!("Map error: object cannot be used as a map key {:?}",
+                                     key_object));
+                entries.insert(key, value);
+            }
+
+            state.allocate_and_push_operand(Object::Map(entries));
+            state.bump_instruction_pointer(program);
+        }
+
         OpCode::GetSlot { name: index } => {
             let constant: &ProgramObject = program.get_constant(index)
                 .expect(&format!("Get slot error: no constant to serve as label name at index {:?}",
@@ -754,12 +812,13 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
 
             let operand_pointer: Pointer = state.pop_operand()
                 .expect(&format!("Get slot error: cannot pop operand from empty operand stack"));
+            let operand_pointer = find_field_host(state, &operand_pointer, name).unwrap_or(operand_pointer);
 
             let operand = state.dereference(&operand_pointer)
                 .expect(&format!("Get slot error: no operand object at {:?}", operand_pointer));
 
             match operand {
-                Object::Object { parent:_, fields, methods:_ } => {
+                Object::Object { parent:_, fields, methods:_, constants:_ } => {
                     let slot: Pointer = fields.get(name)
                         .expect(&format!("Get slot error: no field {} in object", name))
                         .clone();
@@ -791,15 +850,19 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
             let host_pointer: Pointer = state.pop_operand().clone()
                 .expect(&format!("Set slot error: cannot pop operand (host) from empty operand \
                                   stack"));
+            let host_pointer = find_field_host(state, &host_pointer, name).unwrap_or(host_pointer);
 
             let host = state.dereference_mut(&host_pointer)
                 .expect(&format!("Set slot error: no operand object at {:?}", host_pointer));
 
             match host {
-                Object::Object { parent:_, fields, methods:_ } => {
+                Object::Object { parent:_, fields, methods:_, constants } => {
                     if !(fields.contains_key(name)) {
                         panic!("Set slot error: no field {} in object {:?}", name, host)
                     }
+                    if constants.contains(name) {
+                        panic!("Set slot error: field {} is constant and cannot be changed", name)
+                    }
 
                     fields.insert(name.to_string(), value.clone());
                     state.push_operand(value)
@@ -838,6 +901,29 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
                              {:?}", index, constant),
             };
 
+            // Identity cannot be redefined, and equality is structural unless the receiver (or one of
+            // its parents) defines its own.
+            if name == "is" || ((name == "==" || name == "!=")
+                                && compares_structurally(state, &object_pointer, name)) {
+                if arguments.len() != 1 {
+                    panic!("Call method error: {} takes 1 argument, but {} were supplied",
+                           name, arguments.len())
+                }
+                let result = match name {
+                    "is" => objects_equal(state, &object_pointer, &arguments[0]),
+                    "==" => structurally_equal(state, output, program, object_pointer, arguments[0],
+                                               &mut Vec::new()),
+                    _ => !structurally_equal(state, output, program, object_pointer, arguments[0],
+                                             &mut Vec::new()),
+                };
+                // A user-defined == may have called exit.
+                if state.has_next_instruction_pointer() {
+                    state.allocate_and_push_operand(Object::from_bool(result));
+                    state.bump_instruction_pointer(program);
+                }
+                return
+            }
+
             let object: &mut Object = state.dereference_mut(&object_pointer)
                 .expect(&format!("Call method error: no operand object at {:?}", object_pointer));
 
@@ -847,17 +933,65 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
             match object {
                 Object::Null =>
                     interpret_null_method(object_pointer, name, &Vec::from(arguments), state, program),
-                Object::Integer(_) =>
+                Object::Integer(_) | Object::BigInteger(_) =>
                     interpret_integer_method(object_pointer, name, &Vec::from(arguments), state, program),
+                Object::Float(_) =>
+                    interpret_float_method(object_pointer, name, &Vec::from(arguments), state, program),
                 Object::Boolean(_) =>
                     interpret_boolean_method(object_pointer, name, &Vec::from(arguments), state, program),
+                Object::String(_) =>
+                    interpret_string_method(object_pointer, name, &Vec::from(arguments), state, program),
                 Object::Array(_) =>
                     interpret_array_method(object_pointer, name, &Vec::from(arguments), *parameters, state, program),
-                Object::Object { parent:_, fields:_, methods:_ } =>
+                Object::Map(_) =>
+                    interpret_map_method(object_pointer, name, &Vec::from(arguments), state, program),
+                Object::Object { parent:_, fields:_, methods:_, constants:_ } =>
                     dispatch_object_method(object_pointer, name, &Vec::from(arguments), *parameters, state, program),
             };
         }
 
+        OpCode::CallSuperMethod { name: index, arguments: parameters } => {
+            if parameters.value() == 0 {
+                panic!("Call super method error: method must have at least one parameter (receiver)");
+            }
+
+            let mut arguments: VecDeque<Pointer> = VecDeque::with_capacity(parameters.value() as usize);
+            for index in 0..(parameters.to_usize() - 1) {
+                let element = state.pop_operand()
+                    .expect(&format!("Call super method error: cannot pop argument {} from empty \
+                                      operand stack", index));
+                arguments.push_front(element);
+            }
+
+            let receiver: Pointer = state.pop_operand()
+                .expect(&format!("Call super method error: cannot pop receiver from empty \
+                                  operand stack"));
+
+            let constant: &ProgramObject = program.get_constant(index)
+                .expect(&format!("Call super method error: no constant to serve as method name \
+                                  at index {:?}", index));
+
+            let name: &str = match constant {
+                ProgramObject::String(s) => s,
+                _ => panic!("Call super method error: constant at index {:?} must be a String, but \
+                             it is {:?}", index, constant),
+            };
+
+            // The lookup starts above the object that defines the running method, but the method
+            // that is found runs with the same receiver.
+            let host: Pointer = state.current_frame().and_then(|frame| frame.host())
+                .expect("Call super method error: super can only be called from a method");
+
+            let parent_pointer: Pointer = match state.dereference(&host) {
+                Some(Object::Object { parent, fields:_, methods:_, constants:_ }) => *parent,
+                Some(object) => panic!("Call super method error: method must be defined by an \
+                                        object, but it is {:?}", object),
+                None => panic!("Call super method error: no object at {:?}", host),
+            };
+
+            dispatch_method_from(receiver, parent_pointer, name, &Vec::from(arguments), *parameters, state, program);
+        }
+
         OpCode::CallFunction { name: index, arguments } => {
 
             let constant: &ProgramObject = program.get_constant(index)
@@ -870,10 +1004,14 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
                              object, but instead it is: {:?}", constant),
             };
 
-            let function: ProgramObject = {
-                state.get_function(name)
-                    .expect(&format!("Call function error: no such function {}", name))
-                    .clone()
+            // Functions defined by the program shadow the built-in ones.
+            let function: ProgramObject = match state.get_function(name) {
+                Some(function) => function.clone(),
+                None if builtin_arity(name).is_some() => {
+                    interpret_builtin_function(name, *arguments, state, program);
+                    return;
+                },
+                None => panic!("Call function error: no such function {}", name),
             };
 
             match function {
@@ -907,7 +1045,7 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
         }
 
         OpCode::Print { format: index, arguments } => {
-            let mut argument_values = {
+            let argument_values = {
                 let mut argument_values: Vec<Pointer> = Vec::new();
                 for index in 0..arguments.value() {
                     let element = state.pop_operand()
@@ -927,49 +1065,19 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
                             index, constant),
             };
 
-            let mut escape = false;
-            for character in format.chars() {
-                match (character, escape) {
-                    ('~', _) => {
-                        let string = &argument_values.pop()
-                            .map(|e| state.dereference_to_string(&e))
-                            .expect(&format!("Print error: Not enough arguments for format {}",
-                                             format));
-
-                        output.write_str(string)
-                            .expect("Print error: Could not write to output stream.")
-                    },
-                    ('\\', false) => {
-                        escape = true;
-                    }
-                    ('\\', true)  => {
-                        output.write_char('\\')
-                            .expect("Print error: Could not write to output stream.");
-                        escape = false;
-                    }
-                    ('n', true)  => {
-                        output.write_char('\n')
-                            .expect("Print error: Could not write to output stream.");
-                        escape = false;
-                    }
-                    ('t', true)  => {
-                        output.write_char('\t')
-                            .expect("Print error: Could not write to output stream.");
-                        escape = false;
-                    }
-                    (character, true)  => {
-                        panic!("Print error: Unknown escape sequence: \\{}", character)
-                    }
-                    (character, false) => {
-                        output.write_char(character)
-                            .expect("Print error: Could not write to output stream.")
-                    }
+            let values: Vec<Value> = argument_values.iter().rev().map(|pointer| {
+                match state.dereference(pointer).and_then(|object| object.to_integer()) {
+                    Some(integer) => Value::Integer(integer),
+                    None => Value::Other(state.dereference_to_string(pointer)),
                 }
-            }
+            }).collect();
 
-            if !argument_values.is_empty() {
-                panic!("Print error: Unused arguments for format {}", format)
-            }
+            let string = Format::parse(format)
+                .and_then(|format| format.render(&values))
+                .unwrap_or_else(|e| panic!("Print error: {}", e));
+
+            output.write_str(&string)
+                .expect("Print error: Could not write to output stream.");
 
             state.allocate_and_push_operand(Object::Null);
             state.bump_instruction_pointer(program);
@@ -1041,6 +1149,96 @@ pub fn interpret<Output>(state: &mut State, output: &mut Output, /*memory: &mut
             state.bump_instruction_pointer(program);
         },
 
+        OpCode::MatchLiteral { index } => {
+            let constant: &ProgramObject = program.get_constant(index)
+                .unwrap_or_else(|| panic!("Match literal error: no constant at index {:?}", index.value()));
+            let pointer = state.pop_operand()
+                .expect("Match literal error: cannot pop operand from empty operand stack");
+            let object = state.dereference(&pointer)
+                .unwrap_or_else(|| panic!("Match literal error: no operand object at {:?}", pointer));
+
+            // Literals only match values of their own type, so 1 does not match 1.0 or true.
+            let result = match (object, constant) {
+                (Object::Null, ProgramObject::Null) => true,
+                (Object::Boolean(a), ProgramObject::Boolean(b)) => a == b,
+                (Object::String(a), ProgramObject::String(b)) => a == b,
+                (Object::Integer(a), ProgramObject::Integer(b)) => *a == *b as i64,
+                (Object::Integer(a), ProgramObject::Long(b)) => a == b,
+                (_, ProgramObject::Null) | (_, ProgramObject::Boolean(_)) | (_, ProgramObject::String(_))
+                | (_, ProgramObject::Integer(_)) | (_, ProgramObject::Long(_)) => false,
+                _ => panic!("Match literal error: constant at index {:?} must be either Null, Integer, \
+                             Long, Boolean, or String, but is {:?}", index, constant),
+            };
+
+            state.allocate_and_push_operand(Object::from_bool(result));
+            state.bump_instruction_pointer(program);
+        },
+
+        OpCode::MatchArray { length } => {
+            let pointer = state.pop_operand()
+                .expect("Match array error: cannot pop operand from empty operand stack");
+            let object = state.dereference(&pointer)
+                .unwrap_or_else(|| panic!("Match array error: no operand object at {:?}", pointer));
+
+            let result = match object {
+                Object::Array(elements) => elements.len() == length.value() as usize,
+                _ => false,
+            };
+
+            state.allocate_and_push_operand(Object::from_bool(result));
+            state.bump_instruction_pointer(program);
+        },
+
+        OpCode::MatchField { name: index } => {
+            let name = match program.get_constant(index) {
+                Some(ProgramObject::String(name)) => name,
+                Some(constant) => panic!("Match field error: constant at index {:?} must be a String, \
+                                          but it is {:?}", index, constant),
+                None => panic!("Match field error: no constant at index {:?}", index.value()),
+            };
+            let pointer = state.pop_operand()
+                .expect("Match field error: cannot pop operand from empty operand stack");
+            let object = state.dereference(&pointer)
+                .unwrap_or_else(|| panic!("Match field error: no operand object at {:?}", pointer));
+
+            // Only the object's own fields count, not those of its parents.
+            let result = match object {
+                Object::Object { parent: _, fields, methods: _, constants: _ } => fields.contains_key(name),
+                _ => false,
+            };
+
+            state.allocate_and_push_operand(Object::from_bool(result));
+            state.bump_instruction_pointer(program);
+        },
+
+        OpCode::Mark => {
+            state.marks.push(state.operands.len());
+            state.bump_instruction_pointer(program);
+        },
+
+        OpCode::DropToMark => {
+            let height = *state.marks.last()
+                .expect("Drop to mark error: no mark was made");
+            if state.operands.len() < height {
+                panic!("Drop to mark error: operand stack has {} values, below the mark at {}",
+                       state.operands.len(), height)
+            }
+            state.operands.truncate(height);
+            state.bump_instruction_pointer(program);
+        },
+
+        OpCode::Unmark => {
+            state.marks.pop()
+                .expect("Unmark error: no mark was made");
+            state.bump_instruction_pointer(program);
+        },
+
+        OpCode::NoMatch => {
+            let pointer = state.pop_operand()
+                .expect("No match error: cannot pop operand from empty operand stack");
+            panic!("No pattern matches {}", state.dereference_to_string(&pointer))
+        },
+
         OpCode::Skip => {
             state.bump_instruction_pointer(program);
         }
@@ -1100,42 +1298,92 @@ pub fn interpret_null_method(pointer: Pointer, name: &str, arguments: &Vec<Point
 pub fn interpret_integer_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>,
                                 state: &mut State, program: &Program) {
 
+    let integers = state.integers;
     let (object, operand) = check_arguments_one!(pointer, arguments, name, state);
-    let result = match (object, name, operand) {
-        (Object::Integer(i), "+",   Object::Integer(j)) => Object::from_i32 (*i +  *j),
-        (Object::Integer(i), "-",   Object::Integer(j)) => Object::from_i32 (*i -  *j),
-        (Object::Integer(i), "*",   Object::Integer(j)) => Object::from_i32 (*i *  *j),
-        (Object::Integer(i), "/",   Object::Integer(j)) => Object::from_i32 (*i /  *j),
-        (Object::Integer(i), "%",   Object::Integer(j)) => Object::from_i32 (*i %  *j),
-        (Object::Integer(i), "<=",  Object::Integer(j)) => Object::from_bool(*i <= *j),
-        (Object::Integer(i), ">=",  Object::Integer(j)) => Object::from_bool(*i >= *j),
-        (Object::Integer(i), "<",   Object::Integer(j)) => Object::from_bool(*i <  *j),
-        (Object::Integer(i), ">",   Object::Integer(j)) => Object::from_bool(*i >  *j),
-        (Object::Integer(i), "==",  Object::Integer(j)) => Object::from_bool(*i == *j),
-        (Object::Integer(i), "!=",  Object::Integer(j)) => Object::from_bool(*i != *j),
-        (Object::Integer(_), "==",  _)                  => Object::from_bool(false),
-        (Object::Integer(_), "!=",  _)                  => Object::from_bool(true),
-
-        (Object::Integer(i), "add", Object::Integer(j)) => Object::from_i32 (*i +  *j),
-        (Object::Integer(i), "sub", Object::Integer(j)) => Object::from_i32 (*i -  *j),
-        (Object::Integer(i), "mul", Object::Integer(j)) => Object::from_i32 (*i *  *j),
-        (Object::Integer(i), "div", Object::Integer(j)) => Object::from_i32 (*i /  *j),
-        (Object::Integer(i), "mod", Object::Integer(j)) => Object::from_i32 (*i %  *j),
-        (Object::Integer(i), "le",  Object::Integer(j)) => Object::from_bool(*i <= *j),
-        (Object::Integer(i), "ge",  Object::Integer(j)) => Object::from_bool(*i >= *j),
-        (Object::Integer(i), "lt",  Object::Integer(j)) => Object::from_bool(*i <  *j),
-        (Object::Integer(i), "gt",  Object::Integer(j)) => Object::from_bool(*i >  *j),
-        (Object::Integer(i), "eq",  Object::Integer(j)) => Object::from_bool(*i == *j),
-        (Object::Integer(i), "neq", Object::Integer(j)) => Object::from_bool(*i != *j),
-        (Object::Integer(_), "eq",  _)                  => Object::from_bool(false),
-        (Object::Integer(_), "neq", _)                  => Object::from_bool(true),
+    let receiver = object.to_integer()
+        .expect(&format!("Call method error: object {:?} is not an integer", object));
+
+    let result = match (operand, operand.to_integer()) {
+        (Object::Float(j), _) => interpret_float_operation(receiver.to_f64(), name, *j),
+        (_, Some(argument))   => interpret_integer_operation(integers, &receiver, name, &argument),
+        (_, None) => match name {
+            "==" | "eq"  => Some(Object::from_bool(false)),
+            "!=" | "neq" => Some(Object::from_bool(true)),
+            _            => None,
+        },
+    }.expect(&format!("Call method error: object {:?} has no method {} for operand {:?}",
+                      object, name, operand));
+    push_result_and_finish!(result, state, program);
+}
 
-        _ => panic!("Call method error: object {:?} has no method {} for operand {:?}",
-                     object, name, operand),
+/**
+ * Arithmetic and comparison on integers. What happens when arithmetic overflows is up to the
+ * integer semantics the interpreter was started with.
+ */
+fn interpret_integer_operation(integers: IntegerSemantics, left: &Integer, name: &str,
+                               right: &Integer) -> Option<Object> {
+    let arithmetic = |operator: Operator| {
+        let result = integers.arithmetic(&operator, left, right)
+            .unwrap_or_else(|e| panic!("Call method error: {}", e));
+        Some(Object::from_integer(result))
     };
+    match name {
+        "+"  | "add" => arithmetic(Operator::Addition),
+        "-"  | "sub" => arithmetic(Operator::Subtraction),
+        "*"  | "mul" => arithmetic(Operator::Multiplication),
+        "/"  | "div" => arithmetic(Operator::Division),
+        "%"  | "mod" => arithmetic(Operator::Module),
+        "<=" | "le"  => Some(Object::from_bool(left <= right)),
+        ">=" | "ge"  => Some(Object::from_bool(left >= right)),
+        "<"  | "lt"  => Some(Object::from_bool(left <  right)),
+        ">"  | "gt"  => Some(Object::from_bool(left >  right)),
+        "==" | "eq"  => Some(Object::from_bool(left == right)),
+        "!=" | "neq" => Some(Object::from_bool(left != right)),
+        _            => None,
+    }
+}
+
+pub fn interpret_float_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>,
+                              state: &mut State, program: &Program) {
+
+    let (object, operand) = check_arguments_one!(pointer, arguments, name, state);
+    let result = match (object, operand) {
+        (Object::Float(x), Object::Float(y))   => interpret_float_operation(*x, name, *y),
+        (Object::Float(x), Object::Integer(j)) => interpret_float_operation(*x, name, *j as f64),
+        (Object::Float(x), Object::BigInteger(_)) =>
+            interpret_float_operation(*x, name, operand.to_integer().unwrap().to_f64()),
+        (Object::Float(_), _) => match name {
+            "==" | "eq"  => Some(Object::from_bool(false)),
+            "!=" | "neq" => Some(Object::from_bool(true)),
+            _            => None,
+        },
+        _ => None,
+    }.expect(&format!("Call method error: object {:?} has no method {} for operand {:?}",
+                      object, name, operand));
     push_result_and_finish!(result, state, program);
 }
 
+/**
+ * Arithmetic and comparison on floats. Mixed integer and float operations promote the integer to
+ * a float and end up here too.
+ */
+fn interpret_float_operation(x: f64, name: &str, y: f64) -> Option<Object> {
+    match name {
+        "+"  | "add" => Some(Object::from_f64 (x +  y)),
+        "-"  | "sub" => Some(Object::from_f64 (x -  y)),
+        "*"  | "mul" => Some(Object::from_f64 (x *  y)),
+        "/"  | "div" => Some(Object::from_f64 (x /  y)),
+        "%"  | "mod" => Some(Object::from_f64 (x %  y)),
+        "<=" | "le"  => Some(Object::from_bool(x <= y)),
+        ">=" | "ge"  => Some(Object::from_bool(x >= y)),
+        "<"  | "lt"  => Some(Object::from_bool(x <  y)),
+        ">"  | "gt"  => Some(Object::from_bool(x >  y)),
+        "==" | "eq"  => Some(Object::from_bool(x == y)),
+        "!=" | "neq" => Some(Object::from_bool(x != y)),
+        _            => None,
+    }
+}
+
 pub fn interpret_boolean_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>,
                                 state: &mut State, program: &Program) {
 
@@ -1161,6 +1409,10 @@ pub fn interpret_boolean_method(pointer: Pointer, name: &str, arguments: &Vec<Po
     push_result_and_finish!(result, state, program);
 }
 
+/**
+ * Arrays grow and shrink with `push`, `pop`, `insert`, and `remove`. Indices are never negative:
+ * a negative index is out of bounds just like one that is too large.
+ */
 pub fn interpret_array_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>,
                               arity: Arity, state: &mut State, program: &Program) {
 
@@ -1169,78 +1421,438 @@ pub fn interpret_array_method(pointer: Pointer, name: &str, arguments: &Vec<Poin
                 name, arity.value() - 1, arguments.len())
     }
 
-    // if name == "length" {
-    //     let object = state.dereference(&pointer);
-    //     match object {
-    //         Some(Object::Array(element_pointers)) => {
-    //             let length = element_pointers.len() as i32; // was originally converted from i32 to usize, so should be ok.
-    //             let length_object = Object::from_i32(length);
-    //             let pointer = state.allocate(length_object);
-    //             push_pointer_and_finish!(pointer, state, program);
-    //         },
-    //         None => panic!("Cannot dereference pointer: {:?}, no such object in memory"),
-    //         _ => panic!("Call method error: object {:?} has no method {} for zero operands",
-    //                     object, name),
-    //     }
-    // }
-
-    if name == "get" {
-        let (object, operand) = check_arguments_one!(pointer, arguments, name, state);
-        let result = match (object, operand) {
-            (Object::Array(element_pointers), Object::Integer(index)) => {
-                if (*index as usize) >= element_pointers.len() {
-                    panic!("Call method error: array index {} is out of bounds (should be < {})",
-                            index, element_pointers.len())
+    let expected_arguments = match name {
+        "length" | "pop"                                       => 0,
+        "get" | "push" | "remove" | "concat" | "contains" | "index_of" => 1,
+        "set" | "insert"                                       => 2,
+        _ => panic!("Call method error: array has no method {}", name),
+    };
+
+    if arguments.len() != expected_arguments {
+        panic!("Call method error: Array method {} takes {} argument, but {} were supplied",
+                name, expected_arguments, arguments.len())
+    }
+
+    let index: Option<i64> = match name {
+        "get" | "set" | "insert" | "remove" => match state.dereference(&arguments[0]) {
+            Some(Object::Integer(index)) => Some(*index),
+            Some(object) => panic!("Call method error: cannot index array with {:?}", object),
+            None => panic!("Call method error: no operand (1) object at {:?}", arguments[0]),
+        },
+        _ => None,
+    };
+
+    let found: Option<usize> = match name {
+        "contains" | "index_of" => {
+            let elements = array_elements(state, &pointer, name).clone();
+            elements.iter().position(|element| objects_equal(state, element, &arguments[0]))
+        },
+        _ => None,
+    };
+
+    let elements: &mut Vec<Pointer> = match state.dereference_mut(&pointer) {
+        Some(Object::Array(elements)) => elements,
+        object => panic!("Call method error: object {:?} is not an array", object),
+    };
+
+    let checked = |index: i64, length: usize| -> usize {
+        if index < 0 || index as usize >= length {
+            panic!("Call method error: array index {} is out of bounds (should be < {})",
+                   index, length)
+        }
+        index as usize
+    };
+
+    match (name, index) {
+        ("length", None) => {
+            let length = elements.len();
+            push_result_and_finish!(Object::from_i64(length as i64), state, program)
+        },
+        ("get", Some(index)) => {
+            let element = elements[checked(index, elements.len())];
+            push_pointer_and_finish!(element, state, program)
+        },
+        ("set", Some(index)) => {
+            let index = checked(index, elements.len());
+            elements[index] = arguments[1];
+            push_result_and_finish!(Object::Null, state, program)
+        },
+        ("push", None) => {
+            elements.push(arguments[0]);
+            push_result_and_finish!(Object::Null, state, program)
+        },
+        ("pop", None) => {
+            let element = elements.pop()
+                .expect("Call method error: cannot pop from an empty array");
+            push_pointer_and_finish!(element, state, program)
+        },
+        ("insert", Some(index)) => {
+            // Inserting at the index one past the last element appends.
+            let index = checked(index, elements.len() + 1);
+            elements.insert(index, arguments[1]);
+            push_result_and_finish!(Object::Null, state, program)
+        },
+        ("remove", Some(index)) => {
+            let element = elements.remove(checked(index, elements.len()));
+            push_pointer_and_finish!(element, state, program)
+        },
+        ("concat", None) => {
+            let mut concatenated = elements.clone();
+            concatenated.extend(array_elements(state, &arguments[0], name).iter());
+            push_result_and_finish!(Object::from_pointers(concatenated), state, program)
+        },
+        ("contains", None) => push_result_and_finish!(Object::from_bool(found.is_some()), state, program),
+        ("index_of", None) => {
+            let result = Object::from_i64(found.map_or(-1, |index| index as i64));
+            push_result_and_finish!(result, state, program)
+        },
+        _ => unreachable!(),
+    }
+}
+
+/**
+ * Integers, floats, booleans, null, and strings are equal if their values are. Arrays, maps, and
+ * objects are only equal to themselves.
+ */
+fn objects_equal(state: &State, left: &Pointer, right: &Pointer) -> bool {
+    if left == right {
+        return true
+    }
+    let (left, right) = (state.dereference(left).unwrap(), state.dereference(right).unwrap());
+    match (left, right) {
+        (Object::Null, Object::Null) => true,
+        (Object::Boolean(l), Object::Boolean(r)) => l == r,
+        (Object::Float(l), Object::Float(r)) => l == r,
+        (Object::String(l), Object::String(r)) => l == r,
+        _ => match (left.to_integer(), right.to_integer()) {
+            (Some(l), Some(r)) => l == r,
+            _ => false,
+        },
+    }
+}
+
+// Arrays and maps are compared structurally, and so are objects, unless the chain of parents does not
+// end in null or some object in it defines the operator.
+fn compares_structurally(state: &State, pointer: &Pointer, name: &str) -> bool {
+    let mut cursor = *pointer;
+    loop {
+        match state.dereference(&cursor)
+            .expect(&format!("Call method error: no object at {:?}", cursor)) {
+            Object::Object { parent, fields:_, methods, constants:_ } => {
+                if methods.contains_key(name) {
+                    return false
                 }
-                element_pointers.get(*index as usize)
-                    .expect("Call method error: no array element object at {:?}")
-            },
-            _ => panic!("Call method error: array {:?} has no method {} for operand {:?}",
-                         object, name, operand),
-        }.clone();
+                cursor = *parent;
+            }
+            Object::Array(_) | Object::Map(_) => return cursor == *pointer,
+            Object::Null => return cursor != *pointer,
+            _ => return false,
+        }
+    }
+}
+
+// The object in the chain of parents starting at `pointer` that has the field, like the AST
+// interpreter's `find_actual_host_object`. Fields are inherited just like methods.
+fn find_field_host(state: &State, pointer: &Pointer, name: &str) -> Option<Pointer> {
+    let mut cursor = *pointer;
+    while let Some(Object::Object { parent, fields, methods:_, constants:_ }) = state.dereference(&cursor) {
+        if fields.contains_key(name) {
+            return Some(cursor)
+        }
+        cursor = *parent;
+    }
+    None
+}
 
-        push_pointer_and_finish!(result, state, program);
+fn find_user_method(state: &State, pointer: &Pointer, name: &str) -> Option<(Pointer, ProgramObject)> {
+    let mut cursor = *pointer;
+    while let Some(Object::Object { parent, fields:_, methods, constants:_ }) = state.dereference(&cursor) {
+        if let Some(method) = methods.get(name) {
+            return Some((cursor, method.clone()))
+        }
+        cursor = *parent;
     }
+    None
+}
 
-    if name == "set" {
-        let operand_1_pointer: &Pointer = &arguments[0];
-        let operand_2_pointer: &Pointer = &arguments[1];
+/**
+ * Arrays are equal if they have equal elements, maps if they have the same keys with equal values,
+ * and objects if they have equal fields, the same methods, and equal parents. An object that
+ * defines `==` is compared by calling it. Pairs of values that are already being compared further
+ * up are considered equal, which makes comparing cyclic structures terminate.
+ */
+fn structurally_equal<W: Write>(state: &mut State, output: &mut W, program: &Program,
+                                left: Pointer, right: Pointer,
+                                visited: &mut Vec<(Pointer, Pointer)>) -> bool {
+    if let Some((host, method)) = find_user_method(state, &left, "==") {
+        let result = call_method_synchronously(state, output, program, method, left, host, vec!(right));
+        return match state.dereference(&result) {
+            Some(Object::Null) | Some(Object::Boolean(false)) | None => false,
+            Some(_) => true,
+        }
+    }
 
-        let index: usize = match state.dereference(operand_1_pointer) {
-            Some(Object::Integer(index)) => *index as usize,
-            Some(object) => panic!("Call method error: cannot index array with {:?}", object),
-            None => panic!("Call method error: no operand (1) object at {:?}", operand_1_pointer),
-        };
+    if left == right || visited.contains(&(left, right)) {
+        return true
+    }
+
+    visited.push((left, right));
+
+    let left_object = state.dereference(&left)
+        .expect(&format!("Call method error: no object at {:?}", left)).clone();
+    let right_object = state.dereference(&right)
+        .expect(&format!("Call method error: no object at {:?}", right)).clone();
 
-        let object : &mut Object = state.dereference_mut(&pointer).unwrap(); /* pre-checked elsewhere */
-        let result = match object {
-            Object::Array(element_pointers) => {
-                if index >= element_pointers.len() {
-                    panic!("Call method error: array index {} is out of bounds (should be < {})",
-                           index, element_pointers.len())
+    match (left_object, right_object) {
+        (Object::Array(left_elements), Object::Array(right_elements)) => {
+            if left_elements.len() != right_elements.len() {
+                return false
+            }
+            for (left, right) in left_elements.into_iter().zip(right_elements.into_iter()) {
+                if !structurally_equal(state, output, program, left, right, visited) {
+                    return false
                 }
-                element_pointers[index] = *operand_2_pointer;
-                Object::Null
-            },
-            _ => panic!("Call method error: object {:?} has no method {}", object, name),
-        };
+            }
+            true
+        }
+        (Object::Map(left_entries), Object::Map(right_entries)) => {
+            if left_entries.len() != right_entries.len() {
+                return false
+            }
+            for (key, left) in left_entries.into_iter() {
+                let equal = match right_entries.get(&key) {
+                    Some(right) => structurally_equal(state, output, program, left, *right, visited),
+                    None => false,
+                };
+                if !equal {
+                    return false
+                }
+            }
+            true
+        }
+        (Object::Object { parent: left_parent, fields: left_fields, methods: left_methods, constants:_ },
+         Object::Object { parent: right_parent, fields: right_fields, methods: right_methods, constants:_ }) => {
+            if left_methods != right_methods || left_fields.len() != right_fields.len() {
+                return false
+            }
+            for (name, left) in left_fields.into_iter() {
+                let equal = match right_fields.get(&name) {
+                    Some(right) => structurally_equal(state, output, program, left, *right, visited),
+                    None => false,
+                };
+                if !equal {
+                    return false
+                }
+            }
+            structurally_equal(state, output, program, left_parent, right_parent, visited)
+        }
+        _ => objects_equal(state, &left, &right),
+    }
+}
+
+// Runs a method to completion and returns its result, so that it can be called from inside an
+// instruction. The method returns to the current instruction, which then carries on.
+fn call_method_synchronously<W: Write>(state: &mut State, output: &mut W, program: &Program,
+                                       method: ProgramObject, receiver: Pointer, host: Pointer,
+                                       arguments: Vec<Pointer>) -> Pointer {
+    match method {
+        ProgramObject::Method { name: _, locals, arguments: arity, code } => {
+            if arguments.len() != arity.to_usize() - 1 {
+                panic!("Call method error: method takes {} arguments, but {} were supplied",
+                       arity.value() - 1, arguments.len())
+            }
+
+            let mut slots: Vec<Pointer> = Vec::with_capacity(1 + arity.to_usize() + locals.to_usize());
+            slots.push(receiver);
+            slots.extend(arguments);
+            for _ in 0..locals.to_usize() {
+                slots.push(state.allocate(Object::Null))
+            }
+
+            let depth = state.frames.len();
+            state.new_method_frame(*state.instruction_pointer(), slots, host);
+            state.set_instruction_pointer(Some(*code.start()));
+            while state.frames.len() > depth && state.has_next_instruction_pointer() {
+                interpret(state, output, program);
+            }
+
+            if state.has_next_instruction_pointer() {
+                state.pop_operand()
+                    .expect("Call method error: method did not leave a result on the operand stack")
+            } else {
+                state.allocate(Object::Null)
+            }
+        },
+        thing => panic!("Call method error: member should have type Method, but it is {:?}", thing),
+    }
+}
+
+// See `fml_ast::hashing` for the order in which the parts of a value are hashed.
+fn hash_object(state: &State, pointer: &Pointer, hasher: &mut Hasher, visiting: &mut Vec<Pointer>) {
+    if visiting.contains(pointer) {
+        hasher.write_cycle();
+        return
+    }
+
+    let object = state.dereference(pointer)
+        .expect(&format!("Call function error: no object at {:?}", pointer));
+
+    match object {
+        Object::Null => hasher.write_null(),
+        Object::Boolean(value) => hasher.write_boolean(*value),
+        Object::Integer(_) | Object::BigInteger(_) => hasher.write_integer(&object.to_integer().unwrap()),
+        Object::Float(value) => hasher.write_float(*value),
+        Object::String(value) => hasher.write_string(value),
+        Object::Array(elements) => {
+            visiting.push(*pointer);
+            hasher.start_array(elements.len());
+            for element in elements {
+                hash_object(state, element, hasher, visiting);
+            }
+            visiting.pop();
+        }
+        Object::Map(entries) => {
+            visiting.push(*pointer);
+            hasher.start_map(entries.len());
+            let mut hashes: Vec<u64> = entries.iter().map(|(key, value)| {
+                let mut entry = Hasher::new();
+                match key {
+                    MapKey::Null => entry.write_null(),
+                    MapKey::Boolean(value) => entry.write_boolean(*value),
+                    MapKey::Integer(value) => entry.write_integer(value),
+                    MapKey::String(value) => entry.write_string(value),
+                }
+                hash_object(state, value, &mut entry, visiting);
+                entry.finish()
+            }).collect();
+            hashes.sort();
+            for hash in hashes {
+                hasher.write_hash(hash);
+            }
+            visiting.pop();
+        }
+        Object::Object { parent, fields, methods, constants:_ } => {
+            visiting.push(*pointer);
+            hasher.start_object(fields.len(), methods.len());
+            let mut field_names: Vec<&String> = fields.keys().collect();
+            field_names.sort();
+            for name in field_names {
+                hasher.write_name(name);
+                hash_object(state, &fields[name], hasher, visiting);
+            }
+            let mut method_names: Vec<&String> = methods.keys().collect();
+            method_names.sort();
+            for name in method_names {
+                hasher.write_name(name);
+            }
+            hash_object(state, parent, hasher, visiting);
+            visiting.pop();
+        }
+    }
+}
+
+pub fn interpret_string_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>,
+                               state: &mut State, program: &Program) {
+
+    let (object, operand) = check_arguments_one!(pointer, arguments, name, state);
+    let result = match (object, name, operand) {
+        (Object::String(s), "eq",  Object::String(t)) => Object::from_bool(s == t),
+        (Object::String(s), "neq", Object::String(t)) => Object::from_bool(s != t),
+        (Object::String(_), "eq",  _)                 => Object::from_bool(false),
+        (Object::String(_), "neq", _)                 => Object::from_bool(true),
+
+        (Object::String(s), "==",  Object::String(t)) => Object::from_bool(s == t),
+        (Object::String(s), "!=",  Object::String(t)) => Object::from_bool(s != t),
+        (Object::String(_), "==",  _)                 => Object::from_bool(false),
+        (Object::String(_), "!=",  _)                 => Object::from_bool(true),
+
+        _ => panic!("Call method error: object {:?} has no method {} for operand {:?}",
+                    object, name, operand),
+    };
+    push_result_and_finish!(result, state, program);
+}
+
+/**
+ * Maps are mutated in place and hold pointers to their values, like arrays. Missing keys are not
+ * an error: `get` and `remove` return null for them.
+ */
+pub fn interpret_map_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>,
+                            state: &mut State, program: &Program) {
+
+    let expected_arguments = match name {
+        "get" | "has" | "remove" => 1,
+        "set"                    => 2,
+        "keys" | "size"          => 0,
+        _ => panic!("Call method error: map has no method {}", name),
+    };
 
-        push_result_and_finish!(result, state, program)
+    if arguments.len() != expected_arguments {
+        panic!("Call method error: Map method {} takes {} argument, but {} were supplied",
+                name, expected_arguments, arguments.len())
+    }
+
+    let key: Option<MapKey> = arguments.first().map(|key_pointer| {
+        let key_object = state.dereference(key_pointer)
+            .expect(&format!("Call method error: no operand object at {:?}", key_pointer));
+        MapKey::from_object(key_object)
+            .expect(&format!("Call method error: object cannot be used as a map key {:?}",
+                             key_object))
+    });
+
+    let entries: &mut BTreeMap<MapKey, Pointer> = match state.dereference_mut(&pointer) {
+        Some(Object::Map(entries)) => entries,
+        object => panic!("Call method error: object {:?} is not a map", object),
+    };
+
+    match (name, key) {
+        ("get", Some(key)) => match entries.get(&key) {
+            Some(value) => { let value = *value; push_pointer_and_finish!(value, state, program) },
+            None => push_result_and_finish!(Object::Null, state, program),
+        },
+        ("set", Some(key)) => {
+            entries.insert(key, arguments[1]);
+            push_result_and_finish!(Object::Null, state, program)
+        },
+        ("has", Some(key)) => {
+            let result = Object::from_bool(entries.contains_key(&key));
+            push_result_and_finish!(result, state, program)
+        },
+        ("remove", Some(key)) => match entries.remove(&key) {
+            Some(value) => push_pointer_and_finish!(value, state, program),
+            None => push_result_and_finish!(Object::Null, state, program),
+        },
+        ("size", None) => {
+            let result = Object::from_i64(entries.len() as i64);
+            push_result_and_finish!(result, state, program)
+        },
+        ("keys", None) => {
+            let keys: Vec<Object> = entries.keys().map(|key| key.to_object()).collect();
+            let elements: Vec<Pointer> = keys.into_iter().map(|key| state.allocate(key)).collect();
+            push_result_and_finish!(Object::from_pointers(elements), state, program)
+        },
+        _ => unreachable!(),
     }
 }
 
 fn dispatch_object_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>, arity: Arity,
                           state: &mut State, program: &Program) {
+    dispatch_method_from(pointer, pointer, name, arguments, arity, state, program)
+}
+
+// Looks the method up in the chain of parents starting at `start`, and calls it with `pointer` as
+// `this`. Built-in methods of a value at the end of the chain are called on that value.
+fn dispatch_method_from(pointer: Pointer, start: Pointer, name: &str, arguments: &Vec<Pointer>, arity: Arity,
+                        state: &mut State, program: &Program) {
 
     println!("Dispatch! {:?}.{}({:?})/{:?}", pointer, name, arguments,arity);
 
-    let mut cursor: Pointer = pointer;
+    let mut cursor: Pointer = start;
     loop {
         let object = state.dereference(&cursor)
             .expect("Call method error: no object at {:?}");
 
         let method: ProgramObject = match object {
-            Object::Object { parent, fields: _, methods } => {
+            Object::Object { parent, fields: _, methods, constants:_ } => {
                 if let Some(method) = methods.get(name) {
                     method.clone()
                 } else {
@@ -1248,6 +1860,10 @@ fn dispatch_object_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>
                     continue
                 }
             },
+            _ if cursor != pointer && is_reflection_method(name) => {
+                interpret_reflection_method(pointer, name, arguments, state, program);
+                break
+            },
             Object::Null => {
                 interpret_null_method(cursor, name, arguments, state, program);
                 break
@@ -1256,22 +1872,274 @@ fn dispatch_object_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>
                 interpret_boolean_method(cursor, name, arguments, state, program);
                 break
             },
-            Object::Integer(_) => {
+            Object::Integer(_) | Object::BigInteger(_) => {
                 interpret_integer_method(cursor, name, arguments, state, program);
                 break
             },
+            Object::Float(_) => {
+                interpret_float_method(cursor, name, arguments, state, program);
+                break
+            },
+            Object::String(_) => {
+                interpret_string_method(cursor, name, arguments, state, program);
+                break
+            },
             Object::Array(_) => {
                 interpret_array_method(cursor, name, arguments, arity, state, program);
                 break
             },
+            Object::Map(_) => {
+                interpret_map_method(cursor, name, arguments, state, program);
+                break
+            },
         };
 
-        interpret_object_method(method, cursor, name, arguments, state, program);
+        interpret_object_method(method, pointer, cursor, name, arguments, state, program);
         break
     }
 }
 
-fn interpret_object_method(method: ProgramObject, pointer: Pointer, name: &str,
+/**
+ * The standard library: functions that every program can call without defining them first.
+ */
+pub fn interpret_builtin_function(name: &str, arity: Arity, state: &mut State, program: &Program) {
+
+    let expected_arguments = builtin_arity(name)
+        .unwrap_or_else(|| panic!("Call function error: no such function {}", name));
+
+    if arity.to_usize() != expected_arguments {
+        panic!("Call function error: built-in function {} takes {} arguments, but {} were supplied",
+               name, expected_arguments, arity.value())
+    }
+
+    let mut arguments: VecDeque<Pointer> = VecDeque::with_capacity(expected_arguments);
+    for index in 0..expected_arguments {
+        let element = state.pop_operand()
+            .expect(&format!("Call function error: cannot pop argument {} from empty operand \
+                              stack", index));
+        arguments.push_front(element);
+    }
+
+    let integer_argument = |state: &State, index: usize| -> Integer {
+        let object = state.dereference(&arguments[index])
+            .expect(&format!("Call function error: no argument object at {:?}", arguments[index]));
+        object.to_integer()
+            .unwrap_or_else(|| panic!("Call function error: {} expects an integer, but got {:?}",
+                                      name, object))
+    };
+
+    let index_argument = |state: &State, index: usize| -> usize {
+        match integer_argument(state, index) {
+            Integer::Small(n) if n >= 0 => n as usize,
+            n => panic!("Call function error: {} expects a non-negative index, but got {}", name, n),
+        }
+    };
+
+    match name {
+        "length" => {
+            let length = array_elements(state, &arguments[0], name).len();
+            push_result_and_finish!(Object::from_i64(length as i64), state, program)
+        },
+        "hash" => {
+            let mut hasher = Hasher::new();
+            hash_object(state, &arguments[0], &mut hasher, &mut Vec::new());
+            push_result_and_finish!(Object::from_i64(hasher.finish_as_integer()), state, program)
+        },
+        "push" => {
+            let value = arguments[1];
+            array_elements(state, &arguments[0], name).push(value);
+            push_result_and_finish!(Object::Null, state, program)
+        },
+        "pop" => {
+            let value = array_elements(state, &arguments[0], name).pop()
+                .expect("Call function error: cannot pop from an empty array");
+            push_pointer_and_finish!(value, state, program)
+        },
+        "slice" => {
+            let (from, to) = (index_argument(state, 1), index_argument(state, 2));
+            let array = array_elements(state, &arguments[0], name);
+            if from > to || to > array.len() {
+                panic!("Call function error: slice {}..{} is out of bounds of array of length {}",
+                       from, to, array.len())
+            }
+            let slice = array[from..to].to_vec();
+            push_result_and_finish!(Object::from_pointers(slice), state, program)
+        },
+        "sort" => {
+            let mut sorted = array_elements(state, &arguments[0], name).clone();
+            sorted.sort_by(|left, right| compare_objects(state.dereference(left).unwrap(),
+                                                         state.dereference(right).unwrap()));
+            *array_elements(state, &arguments[0], name) = sorted;
+            push_result_and_finish!(Object::Null, state, program)
+        },
+        "abs" => {
+            let result = match state.dereference(&arguments[0]) {
+                Some(Object::Float(f)) => Object::from_f64(f.abs()),
+                _ => {
+                    let integer = integer_argument(state, 0);
+                    let result = state.integers.absolute(&integer)
+                        .unwrap_or_else(|e| panic!("Call function error: {}", e));
+                    Object::from_integer(result)
+                },
+            };
+            push_result_and_finish!(result, state, program)
+        },
+        "min" | "max" => {
+            let ordering = compare_objects(state.dereference(&arguments[0]).unwrap(),
+                                           state.dereference(&arguments[1]).unwrap());
+            let result = match (name, ordering) {
+                ("min", Ordering::Greater) | ("max", Ordering::Less) => arguments[1],
+                _ => arguments[0],
+            };
+            push_pointer_and_finish!(result, state, program)
+        },
+        "pow" => {
+            let (base, exponent) = (integer_argument(state, 0), integer_argument(state, 1));
+            let result = state.integers.power(&base, &exponent)
+                .unwrap_or_else(|e| panic!("Call function error: {}", e));
+            push_result_and_finish!(Object::from_integer(result), state, program)
+        },
+        "assert" => {
+            match state.dereference(&arguments[0]) {
+                Some(Object::Boolean(false)) | Some(Object::Null) =>
+                    panic!("Assertion error: assertion failed"),
+                _ => push_result_and_finish!(Object::Null, state, program),
+            }
+        },
+        "read_line" | "read_int" => {
+            let mut line = String::new();
+            let read = state.input.read_line(&mut line)
+                .unwrap_or_else(|e| panic!("Call function error: cannot read input: {}", e));
+            let line = line.trim_end_matches(|c| c == '\n' || c == '\r');
+            let result = match (name, read) {
+                (_, 0) => Object::Null,
+                ("read_line", _) => Object::String(line.to_string()),
+                (_, _) => {
+                    let integer = line.trim().parse::<i64>().ok()
+                        .and_then(|n| state.integers.literal(n).ok())
+                        .unwrap_or_else(|| panic!("Call function error: cannot read an integer \
+                                                   from {:?}", line));
+                    Object::from_integer(integer)
+                },
+            };
+            push_result_and_finish!(result, state, program)
+        },
+        "exit" => {
+            let code = match integer_argument(state, 0) {
+                Integer::Small(n) if n >= i32::MIN as i64 && n <= i32::MAX as i64 => n as i32,
+                n => panic!("Call function error: exit code {} does not fit into 32 bits", n),
+            };
+            state.exit_code = Some(code);
+            state.set_instruction_pointer(None);
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn array_elements<'a>(state: &'a mut State, pointer: &Pointer, name: &str) -> &'a mut Vec<Pointer> {
+    match state.dereference_mut(pointer) {
+        Some(Object::Array(elements)) => elements,
+        object => panic!("Call function error: {} expects an array, but got {:?}", name, object),
+    }
+}
+
+/**
+ * Orders numbers by value and strings alphabetically; other objects cannot be compared.
+ */
+fn compare_objects(left: &Object, right: &Object) -> Ordering {
+    match (left, right) {
+        (Object::String(l), Object::String(r)) => Some(l.cmp(r)),
+        (Object::Float(_), _) | (_, Object::Float(_)) => {
+            let as_f64 = |object: &Object| match object {
+                Object::Float(f) => Some(*f),
+                object => object.to_integer().map(|integer| integer.to_f64()),
+            };
+            match (as_f64(left), as_f64(right)) {
+                (Some(l), Some(r)) => l.partial_cmp(&r),
+                _ => None,
+            }
+        }
+        _ => match (left.to_integer(), right.to_integer()) {
+            (Some(l), Some(r)) => Some(l.cmp(&r)),
+            _ => None,
+        },
+    }.map_or_else(|| panic!("Call function error: cannot compare {:?} with {:?}", left, right),
+                  |ordering| ordering)
+}
+
+fn is_reflection_method(name: &str) -> bool {
+    match name {
+        "fields" | "methods" | "has_field" | "parent" | "get" | "set" => true,
+        _ => false,
+    }
+}
+
+// Reflective methods are understood by every object that does not define or inherit a method with the
+// same name, whatever its chain of parents ends in. They only look at the object's own members.
+pub fn interpret_reflection_method(pointer: Pointer, name: &str, arguments: &Vec<Pointer>,
+                                   state: &mut State, program: &Program) {
+
+    let expected_arguments = match name {
+        "fields" | "methods" | "parent" => 0,
+        "has_field" | "get"             => 1,
+        "set"                           => 2,
+        _ => panic!("Call method error: object has no method {}", name),
+    };
+
+    if arguments.len() != expected_arguments {
+        panic!("Call method error: Object method {} takes {} argument, but {} were supplied",
+                name, expected_arguments, arguments.len())
+    }
+
+    let field: Option<String> = arguments.first().map(|field_pointer| {
+        match state.dereference(field_pointer) {
+            Some(Object::String(field)) => field.clone(),
+            object => panic!("Call method error: field name must be a string, but it is {:?}",
+                             object),
+        }
+    });
+
+    let (parent, fields, methods) = match state.dereference_mut(&pointer) {
+        Some(Object::Object { parent, fields, methods, constants:_ }) => (*parent, fields, methods),
+        object => panic!("Call method error: object {:?} is not an object", object),
+    };
+
+    match (name, field) {
+        ("fields", None) => {
+            let mut names: Vec<String> = fields.keys().cloned().collect();
+            names.sort();
+            let elements: Vec<Pointer> =
+                names.into_iter().map(|name| state.allocate(Object::String(name))).collect();
+            push_result_and_finish!(Object::from_pointers(elements), state, program)
+        },
+        ("methods", None) => {
+            let mut names: Vec<String> = methods.keys().cloned().collect();
+            names.sort();
+            let elements: Vec<Pointer> =
+                names.into_iter().map(|name| state.allocate(Object::String(name))).collect();
+            push_result_and_finish!(Object::from_pointers(elements), state, program)
+        },
+        ("parent", None) => push_pointer_and_finish!(parent, state, program),
+        ("has_field", Some(field)) => {
+            let result = Object::from_bool(fields.contains_key(&field));
+            push_result_and_finish!(result, state, program)
+        },
+        ("get", Some(field)) => match fields.get(&field) {
+            Some(value) => { let value = *value; push_pointer_and_finish!(value, state, program) },
+            None => panic!("Call method error: no field {} in object", field),
+        },
+        ("set", Some(field)) => {
+            if !fields.contains_key(&field) {
+                panic!("Call method error: no field {} in object", field)
+            }
+            fields.insert(field, arguments[1]);
+            push_pointer_and_finish!(arguments[1], state, program)
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn interpret_object_method(method: ProgramObject, pointer: Pointer, host: Pointer, name: &str,
                            arguments: &Vec<Pointer>, state: &mut State, program: &Program) {
 
     match method {
@@ -1293,11 +2161,96 @@ fn interpret_object_method(method: ProgramObject, pointer: Pointer, name: &str,
             }
 
             state.bump_instruction_pointer(program);
-            state.new_frame(*state.instruction_pointer(), slots);
+            state.new_method_frame(*state.instruction_pointer(), slots, host);
             state.set_instruction_pointer(Some(*code.start()));
         },
 
         thing => panic!("Call method error: member {} in object definition should have type \
                          Method, but it is {:?}", name, thing),
     }
+}
+
+fn resolve_class(program: &Program, index: &ConstantPoolIndex) -> Class {
+    let constant: &ProgramObject = program.get_constant(index)
+        .expect(&format!("Object error: no constant at index {:?}", index.value()));
+
+    let member_definitions: Vec<&ProgramObject> = match constant {
+        ProgramObject::Class(v) => v,
+        _ => panic!("Object error: constant at index {:?} must be a String, \
+                     but it is {:?}", index.value(), constant),
+    }.iter().map(| index | program.get_constant(index)
+        .expect(&format!("Object error: no constant at index {:?} for member_ object",
+                         index.value()))).collect();
+
+    let (slots, methods): (Vec<&ProgramObject>, Vec<&ProgramObject>) =
+        member_definitions.iter().partition(|c| match c {
+            ProgramObject::Method { code:_, locals:_, arguments:_, name:_ } => false,
+            ProgramObject::Slot { name:_ } => true,
+            ProgramObject::ConstantSlot { name:_ } => true,
+            member =>
+                panic!("Object error: class members may be either Methods or Slots, \
+                         but this member is {:?}", member),
+    }); // XXX this will work even if the member definitions are not sorted, which is
+        // contrary to the spec
+
+    let mut constants: HashSet<String> = HashSet::new();
+    let slots: Vec<String> = {
+        let mut names: Vec<String> = Vec::new();
+        for slot in slots.into_iter() {
+            if let ProgramObject::Slot {name: index} | ProgramObject::ConstantSlot {name: index} = slot {
+                let constant: &ProgramObject = program.get_constant(index)
+                    .expect(&format!("Object error: no constant at index {:?}",
+                                     index.value()));
+
+                let name: &str = match constant {
+                    ProgramObject::String(s) => s,
+                    _ => panic!("Object error: constant at index {:?} must be a String, \
+                                 but it is {:?}", index.value(), constant),
+                };
+
+                if names.iter().any(|other| other == name) {
+                    panic!("Object error: member fields must have unique names, but \
+                            {} is used by to name more than one field", name)
+                }
+                if let ProgramObject::ConstantSlot {name:_} = slot {
+                    constants.insert(name.to_string());
+                }
+                names.push(name.to_string());
+            } else {
+                unreachable!()
+            }
+        }
+        names
+    };
+
+    let methods: HashMap<String, ProgramObject> = {
+        let mut map: HashMap<String, ProgramObject> = HashMap::new();
+        for method in methods {
+            match method {
+                ProgramObject::Method { name: index, arguments:_, locals:_, code:_ } => {
+                    let constant: &ProgramObject = program.get_constant(index)
+                        .expect(&format!("Object error: no constant at index {:?}",
+                                         index.value()));
+
+                    let name: &str = match constant {
+                        ProgramObject::String(s) => s,
+                        _ => panic!("Object error: constant at index {:?} must be a String, \
+                                     but it is {:?}", index.value(), constant),
+                    };
+                    let result = map.insert(name.to_string(), (*method).clone());
+
+                    match result {
+                        Some (other_method) =>
+                            panic!("Object error: method {} has a non-unique name in \
+                                    object: {:?} v {:?}", name, method, other_method),
+                        None => ()
+                    }
+                },
+                _ => unreachable!(),
+            }
+        }
+        map
+    };
+
+    Class { slots, constants, methods: Rc::new(methods) }
 }
\ No newline at end of file
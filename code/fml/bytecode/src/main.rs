@@ -17,7 +17,7 @@ mod bytecode_deserialization_tests {
     use std::io::Cursor;
     use crate::bytecode::OpCode;
     use crate::serializable::Serializable;
-    use crate::types::{ConstantPoolIndex, LocalFrameIndex, Arity};
+    use crate::types::{ConstantPoolIndex, LocalFrameIndex, Arity, Size};
 
     fn test(expected: OpCode, input: Vec<u8>) {
         assert_eq!(OpCode::from_bytes(&mut Cursor::new(input)), expected);
@@ -124,13 +124,67 @@ mod bytecode_deserialization_tests {
         let bytes = vec!(0x10);
         test(expected, bytes);
     }
+
+    #[test] fn map () {
+        let expected = OpCode::Map { entries: Size::new(2) };
+        let bytes = vec!(0x11, 0x02, 0x00);
+        test(expected, bytes);
+    }
+
+    #[test] fn call_super_method () {
+        let expected = OpCode::CallSuperMethod { name: ConstantPoolIndex::new(1), arguments: Arity::new(2) };
+        let bytes = vec!(0x12, 0x01, 0x00, 0x02);
+        test(expected, bytes);
+    }
+
+    #[test] fn match_literal () {
+        let expected = OpCode::MatchLiteral { index: ConstantPoolIndex::new(1) };
+        let bytes = vec!(0x13, 0x01, 0x00);
+        test(expected, bytes);
+    }
+
+    #[test] fn match_array () {
+        let expected = OpCode::MatchArray { length: Size::new(2) };
+        let bytes = vec!(0x14, 0x02, 0x00);
+        test(expected, bytes);
+    }
+
+    #[test] fn match_field () {
+        let expected = OpCode::MatchField { name: ConstantPoolIndex::new(1) };
+        let bytes = vec!(0x15, 0x01, 0x00);
+        test(expected, bytes);
+    }
+
+    #[test] fn no_match () {
+        let expected = OpCode::NoMatch;
+        let bytes = vec!(0x16);
+        test(expected, bytes);
+    }
+
+    #[test] fn mark () {
+        let expected = OpCode::Mark;
+        let bytes = vec!(0x17);
+        test(expected, bytes);
+    }
+
+    #[test] fn drop_to_mark () {
+        let expected = OpCode::DropToMark;
+        let bytes = vec!(0x18);
+        test(expected, bytes);
+    }
+
+    #[test] fn unmark () {
+        let expected = OpCode::Unmark;
+        let bytes = vec!(0x19);
+        test(expected, bytes);
+    }
 }
 
 #[cfg(test)]
 mod bytecode_serialization_tests {
     use crate::bytecode::OpCode;
     use crate::serializable::Serializable;
-    use crate::types::{ConstantPoolIndex, LocalFrameIndex, Arity};
+    use crate::types::{ConstantPoolIndex, LocalFrameIndex, Arity, Size};
 
     fn test (expected: Vec<u8>, object: OpCode) {
         let mut actual: Vec<u8> = Vec::new();
@@ -239,6 +293,60 @@ mod bytecode_serialization_tests {
         let object = OpCode::Drop;
         test(expected, object);
     }
+
+    #[test] fn map () {
+        let expected = vec!(0x11, 0x02, 0x00);
+        let object = OpCode::Map { entries: Size::new(2) };
+        test(expected, object);
+    }
+
+    #[test] fn call_super_method () {
+        let expected = vec!(0x12, 0x01, 0x00, 0x02);
+        let object = OpCode::CallSuperMethod { name: ConstantPoolIndex::new(1), arguments: Arity::new(2) };
+        test(expected, object);
+    }
+
+    #[test] fn match_literal () {
+        let expected = vec!(0x13, 0x01, 0x00);
+        let object = OpCode::MatchLiteral { index: ConstantPoolIndex::new(1) };
+        test(expected, object);
+    }
+
+    #[test] fn match_array () {
+        let expected = vec!(0x14, 0x02, 0x00);
+        let object = OpCode::MatchArray { length: Size::new(2) };
+        test(expected, object);
+    }
+
+    #[test] fn match_field () {
+        let expected = vec!(0x15, 0x01, 0x00);
+        let object = OpCode::MatchField { name: ConstantPoolIndex::new(1) };
+        test(expected, object);
+    }
+
+    #[test] fn no_match () {
+        let expected = vec!(0x16);
+        let object = OpCode::NoMatch;
+        test(expected, object);
+    }
+
+    #[test] fn mark () {
+        let expected = vec!(0x17);
+        let object = OpCode::Mark;
+        test(expected, object);
+    }
+
+    #[test] fn drop_to_mark () {
+        let expected = vec!(0x18);
+        let object = OpCode::DropToMark;
+        test(expected, object);
+    }
+
+    #[test] fn unmark () {
+        let expected = vec!(0x19);
+        let object = OpCode::Unmark;
+        test(expected, object);
+    }
 }
 
 #[cfg(test)]
@@ -280,6 +388,18 @@ mod program_object_serialization_tests {
         test(expected, object);
     }
 
+    #[test] fn float () {
+        let expected = vec!(0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x40);
+        let object = ProgramObject::Float(2.5);
+        test(expected, object);
+    }
+
+    #[test] fn long () {
+        let expected = vec!(0x08, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00);
+        let object = ProgramObject::Long(1 << 32);
+        test(expected, object);
+    }
+
     #[test] fn string () {
         let expected = vec!(0x02,
                             0x0C, 0x00, 0x00, 0x00,
@@ -294,6 +414,12 @@ mod program_object_serialization_tests {
         test(expected, object);
     }
 
+    #[test] fn constant_slot () {
+        let expected = vec!(0x09, 0x2A, 0x00);
+        let object = ProgramObject::ConstantSlot { name: ConstantPoolIndex::new(42) };
+        test(expected, object);
+    }
+
     #[test] fn class () {
         let expected = vec!(0x05,
                             0x02, 0x00,
@@ -370,6 +496,18 @@ mod program_object_deserialization_tests {
         test(expected, bytes);
     }
 
+    #[test] fn float () {
+        let expected = ProgramObject::Float(2.5);
+        let bytes = vec!(0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x40);
+        test(expected, bytes);
+    }
+
+    #[test] fn long () {
+        let expected = ProgramObject::Long(1 << 32);
+        let bytes = vec!(0x08, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00);
+        test(expected, bytes);
+    }
+
     #[test] fn string () {
         let expected = ProgramObject::String("Hello World\0".to_string());
         let bytes = vec!(0x02,
@@ -384,6 +522,12 @@ mod program_object_deserialization_tests {
         test(expected, bytes);
     }
 
+    #[test] fn constant_slot () {
+        let expected = ProgramObject::ConstantSlot { name: ConstantPoolIndex::new(42) };
+        let bytes = vec!(0x09, 0x2A, 0x00, );
+        test(expected, bytes);
+    }
+
     #[test] fn class () {
         let expected = ProgramObject::Class(vec!(ConstantPoolIndex::new(42),
                                                  ConstantPoolIndex::new(666)));
@@ -422,9 +566,10 @@ mod interpreter_test {
     use crate::bytecode::OpCode;
     use crate::types::{ConstantPoolIndex, Address, LocalFrameIndex, Arity, Size, AddressRange};
     use crate::program::{Program, Code};
-    use crate::objects::{ProgramObject, Pointer, Object};
+    use crate::objects::{ProgramObject, Pointer, Object, MapKey};
     use crate::interpreter::{State, interpret, LocalFrame, Memory};
-    use std::collections::HashMap;
+    use std::collections::{HashMap, BTreeMap};
+    use fml_ast::integers::{IntegerSemantics, Integer, BigInt};
 
     macro_rules! hashmap {
         ($key: expr, $value: expr) => {{
@@ -760,6 +905,27 @@ mod interpreter_test {
         assert_eq!(state.memory, Memory::from(vec!(Object::from_i32(42), Object::Null)), "test memory")
     }
 
+    #[test] fn print_float() {
+        let code = Code::from(vec!(
+            OpCode::Print { format: ConstantPoolIndex::new(0), arguments: Arity::new(2) },
+            OpCode::Skip,
+        ));
+
+        let constants: Vec<ProgramObject> = vec!(ProgramObject::String("~ ~".to_string()));
+        let globals: Vec<ConstantPoolIndex> = vec!();
+        let entry = ConstantPoolIndex::new(0);
+        let program = Program::new(code, constants, globals, entry);
+
+        let mut state = State::minimal();
+        state.allocate_and_push_operand(Object::from_f64(1.0));
+        state.allocate_and_push_operand(Object::from_f64(0.25));
+        let mut output: String = String::new();
+
+        interpret(&mut state, &mut output, &program);
+
+        assert_eq!(&output, "1.0 0.25", "test output");
+    }
+
     #[test] fn print_two() {
         let code = Code::from(vec!(
             OpCode::Print { format: ConstantPoolIndex::new(0), arguments: Arity::new(2) },
@@ -1314,7 +1480,7 @@ mod interpreter_test {
         assert_eq!(state.globals, HashMap::new(), "test globals");
         assert_eq!(state.instruction_pointer, Some(Address::from_usize(0)), "test instruction pointer");
         assert_eq!(state.frames, vec!(LocalFrame::empty(),
-                                      LocalFrame::from(Some(Address::from_usize(2)),
+                                      LocalFrame::from_method(Pointer::from(1), Some(Address::from_usize(2)),
                                                        vec!(Pointer::from(1)))), "test frames");
         assert_eq!(state.memory, Memory::from(vec!(Object::Null, receiver.clone())))
     }
@@ -1353,7 +1519,7 @@ mod interpreter_test {
         assert_eq!(state.globals, HashMap::new(), "test globals");
         assert_eq!(state.instruction_pointer, Some(Address::from_usize(0)), "test instruction pointer");
         assert_eq!(state.frames, vec!(LocalFrame::empty(),
-                                      LocalFrame::from(Some(Address::from_usize(2)),
+                                      LocalFrame::from_method(Pointer::from(1), Some(Address::from_usize(2)),
                                                        vec!(Pointer::from(1),
                                                             Pointer::from(2)))), "test frames");
         assert_eq!(state.memory, Memory::from(vec!(Object::Null,
@@ -1397,7 +1563,7 @@ mod interpreter_test {
         assert_eq!(state.globals, HashMap::new(), "test globals");
         assert_eq!(state.instruction_pointer, Some(Address::from_usize(0)), "test instruction pointer");
         assert_eq!(state.frames, vec!(LocalFrame::empty(),
-                                      LocalFrame::from(Some(Address::from_usize(2)),
+                                      LocalFrame::from_method(Pointer::from(1), Some(Address::from_usize(2)),
                                                        vec!(Pointer::from(1),
                                                             Pointer::from(2),
                                                             Pointer::from(3),
@@ -1410,6 +1576,11 @@ mod interpreter_test {
     }
 
     fn call_method(receiver: Object, argument: Object, operation: &str, result: Object) {
+        call_method_with_integers(IntegerSemantics::default(), receiver, argument, operation, result)
+    }
+
+    fn call_method_with_integers(integers: IntegerSemantics, receiver: Object, argument: Object,
+                                 operation: &str, result: Object) {
         let code = Code::from(vec!(
             OpCode::CallMethod { name: ConstantPoolIndex::new(0), arguments: Arity::new(1 + 1) },
             OpCode::Skip,
@@ -1421,6 +1592,7 @@ mod interpreter_test {
         let program = Program::new(code, constants, globals, entry);
 
         let mut state = State::minimal();
+        state.integers = integers;
         let mut output: String = String::new();
 
         state.set_instruction_pointer(Some(Address::from_usize(0)));
@@ -1456,6 +1628,14 @@ mod interpreter_test {
                     Object::from_bool(result));
     }
 
+    fn call_method_float(receiver: Object, argument: Object, operation: &str, result: f64) {
+        call_method(receiver, argument, operation, Object::from_f64(result));
+    }
+
+    fn call_method_float_cmp(receiver: Object, argument: Object, operation: &str, result: bool) {
+        call_method(receiver, argument, operation, Object::from_bool(result));
+    }
+
     fn call_method_boolean(receiver: bool, argument: bool, operation: &str, result: bool) {
         call_method(Object::from_bool(receiver),
                     Object::from_bool(argument),
@@ -1538,6 +1718,81 @@ mod interpreter_test {
         call_method_integer_cmp(5, 5, "ge", true);
     }
 
+    #[test] fn call_method_integer_wrapping32() {
+        call_method_with_integers(IntegerSemantics::Wrapping32,
+                                  Object::from_i32(i32::MAX), Object::from_i32(1), "+",
+                                  Object::from_i32(i32::MIN));
+        call_method_with_integers(IntegerSemantics::Wrapping32,
+                                  Object::from_i32(i32::MIN), Object::from_i32(-1), "/",
+                                  Object::from_i32(i32::MIN));
+        call_method_with_integers(IntegerSemantics::Wrapping32,
+                                  Object::from_i32(65536), Object::from_i32(65536), "mul",
+                                  Object::from_i32(0));
+    }
+
+    #[test] fn call_method_integer_checked64() {
+        call_method_with_integers(IntegerSemantics::Checked64,
+                                  Object::from_i32(i32::MAX), Object::from_i32(1), "+",
+                                  Object::from_i64(i32::MAX as i64 + 1));
+        call_method_with_integers(IntegerSemantics::Checked64,
+                                  Object::from_i64(i64::MIN), Object::from_i64(i64::MAX), "<",
+                                  Object::from_bool(true));
+    }
+
+    #[test] #[should_panic(expected = "integer overflow")] fn call_method_integer_checked64_overflow() {
+        call_method_with_integers(IntegerSemantics::Checked64,
+                                  Object::from_i64(i64::MAX), Object::from_i32(1), "+",
+                                  Object::Null);
+    }
+
+    #[test] #[should_panic(expected = "division by zero")] fn call_method_integer_division_by_zero() {
+        call_method_with_integers(IntegerSemantics::Wrapping32,
+                                  Object::from_i32(1), Object::from_i32(0), "/",
+                                  Object::Null);
+    }
+
+    #[test] fn call_method_integer_promoting() {
+        call_method_with_integers(IntegerSemantics::Promoting,
+                                  Object::from_i64(i64::MAX), Object::from_i32(1), "+",
+                                  Object::BigInteger(BigInt::from(i64::MAX) + 1));
+        call_method_with_integers(IntegerSemantics::Promoting,
+                                  Object::BigInteger(BigInt::from(i64::MAX) + 1), Object::from_i32(1), "-",
+                                  Object::from_i64(i64::MAX));
+        call_method_with_integers(IntegerSemantics::Promoting,
+                                  Object::BigInteger(BigInt::from(i64::MAX) * 4), Object::from_i64(i64::MAX), ">",
+                                  Object::from_bool(true));
+        call_method_with_integers(IntegerSemantics::Promoting,
+                                  Object::BigInteger(BigInt::from(i64::MAX) * 4), Object::from_f64(0.5), "*",
+                                  Object::from_f64(i64::MAX as f64 * 2.0));
+    }
+
+    #[test] fn call_method_float_arithmetic() {
+        call_method_float(Object::from_f64(2.5), Object::from_f64(0.5), "+",   3.0);
+        call_method_float(Object::from_f64(2.5), Object::from_f64(0.5), "-",   2.0);
+        call_method_float(Object::from_f64(2.5), Object::from_f64(0.5), "*",   1.25);
+        call_method_float(Object::from_f64(2.5), Object::from_f64(0.5), "/",   5.0);
+        call_method_float(Object::from_f64(2.5), Object::from_f64(1.0), "%",   0.5);
+        call_method_float(Object::from_f64(2.5), Object::from_f64(0.5), "add", 3.0);
+        call_method_float(Object::from_f64(2.5), Object::from_f64(0.5), "div", 5.0);
+    }
+
+    #[test] fn call_method_float_mixed_arithmetic() {
+        call_method_float(Object::from_f64(2.5), Object::from_i32(2),   "+", 4.5);
+        call_method_float(Object::from_i32(2),   Object::from_f64(2.5), "+", 4.5);
+        call_method_float(Object::from_i32(5),   Object::from_f64(2.0), "/", 2.5);
+        call_method_float(Object::from_f64(5.0), Object::from_i32(2),   "/", 2.5);
+    }
+
+    #[test] fn call_method_float_comparison() {
+        call_method_float_cmp(Object::from_f64(2.5), Object::from_f64(0.5), "<",  false);
+        call_method_float_cmp(Object::from_f64(2.5), Object::from_i32(3),   "<",  true);
+        call_method_float_cmp(Object::from_i32(3),   Object::from_f64(2.5), ">=", true);
+        call_method_float_cmp(Object::from_f64(2.0), Object::from_i32(2),   "==", true);
+        call_method_float_cmp(Object::from_i32(2),   Object::from_f64(2.0), "eq", true);
+        call_method_float_cmp(Object::from_f64(2.0), Object::from_bool(true), "==", false);
+        call_method_float_cmp(Object::from_f64(2.0), Object::Null,          "!=", true);
+    }
+
     #[test] fn call_method_boolean_conjunction() {
         call_method_boolean(true, false, "&",   false);
         call_method_boolean(true, true,  "&",   true);
@@ -1609,6 +1864,36 @@ mod interpreter_test {
                                                    Object::from_i32(1))), "test memory")
     }
 
+    #[test] fn call_method_array_length() {
+        let code = Code::from(vec!(
+            OpCode::CallMethod { name: ConstantPoolIndex::new(0), arguments: Arity::new(1) },
+            OpCode::Skip,
+        ));
+
+        let constants: Vec<ProgramObject> = vec!(ProgramObject::from_str("length"));
+        let globals: Vec<ConstantPoolIndex> = vec!();
+        let entry = ConstantPoolIndex::new(0);
+        let program = Program::new(code, constants, globals, entry);
+
+        let mut state = State::minimal();
+        let mut output: String = String::new();
+
+        state.set_instruction_pointer(Some(Address::from_usize(0)));
+        state.allocate(Object::from_i32(1));
+        state.allocate_and_push_operand(Object::from_pointers(vec!(Pointer::from(0),
+                                                                   Pointer::from(0))));
+
+        interpret(&mut state, &mut output, &program);
+
+        assert_eq!(&output, "", "test output");
+        assert_eq!(state.operands, vec!(Pointer::from(2)), "test operands");
+        assert_eq!(state.instruction_pointer, Some(Address::from_usize(1)), "test instruction pointer");
+        assert_eq!(state.memory, Memory::from(vec!(Object::from_i32(1),
+                                                   Object::from_pointers(vec!(Pointer::from(0),
+                                                                              Pointer::from(0))),
+                                                   Object::from_i32(2))), "test memory")
+    }
+
     // before: array(1,2,3)
     //         a.set(1, 42)
     // after:  array(1,42,3)
@@ -1660,6 +1945,122 @@ mod interpreter_test {
                                                      Pointer::from(2))), "test object state");
     }
 
+    // before: map(1 -> 42, "x" -> 43)
+    //         m.get(KEY)
+    fn call_method_map_get(key: Object, result: Option<Pointer>) {
+        let code = Code::from(vec!(
+            OpCode::CallMethod { name: ConstantPoolIndex::new(0), arguments: Arity::new(1 + 1) },
+            OpCode::Skip,
+        ));
+
+        let constants: Vec<ProgramObject> = vec!(ProgramObject::from_str("get"));
+        let globals: Vec<ConstantPoolIndex> = vec!();
+        let entry = ConstantPoolIndex::new(0);
+        let program = Program::new(code, constants, globals, entry);
+
+        let mut state = State::minimal();
+        let mut output: String = String::new();
+
+        let mut entries = BTreeMap::new();
+        entries.insert(MapKey::Integer(Integer::Small(1)), Pointer::from(0));
+        entries.insert(MapKey::String("x".to_string()), Pointer::from(1));
+
+        state.set_instruction_pointer(Some(Address::from_usize(0)));
+        state.allocate(Object::from_i32(42));
+        state.allocate(Object::from_i32(43));
+        state.allocate_and_push_operand(Object::Map(entries));
+        state.allocate_and_push_operand(key);
+
+        interpret(&mut state, &mut output, &program);
+
+        assert_eq!(&output, "", "test output");
+        assert_eq!(state.operands, vec!(result.unwrap_or(Pointer::from(4))), "test operands");
+        assert_eq!(state.instruction_pointer, Some(Address::from_usize(1)), "test instruction pointer");
+        if result.is_none() {
+            assert_eq!(state.dereference(&Pointer::from(4)), Some(&Object::Null), "test result");
+        }
+    }
+
+    #[test] fn call_method_map_get_integer() {
+        call_method_map_get(Object::from_i32(1), Some(Pointer::from(0)));
+    }
+
+    #[test] fn call_method_map_get_string() {
+        call_method_map_get(Object::String("x".to_string()), Some(Pointer::from(1)));
+    }
+
+    #[test] fn call_method_map_get_missing() {
+        call_method_map_get(Object::from_bool(true), None);
+    }
+
+    #[test] #[should_panic(expected = "cannot be used as a map key")]
+    fn call_method_map_get_array() {
+        call_method_map_get(Object::from_pointers(vec!()), None);
+    }
+
+    // before: map()
+    //         m.set("x", 42)
+    // after:  map("x" -> 42)
+    #[test] fn call_method_map_set() {
+        let code = Code::from(vec!(
+            OpCode::CallMethod { name: ConstantPoolIndex::new(0), arguments: Arity::new(3) },
+            OpCode::Skip,
+        ));
+
+        let constants: Vec<ProgramObject> = vec!(ProgramObject::from_str("set"));
+        let globals: Vec<ConstantPoolIndex> = vec!();
+        let entry = ConstantPoolIndex::new(0);
+        let program = Program::new(code, constants, globals, entry);
+
+        let mut state = State::minimal();
+        let mut output: String = String::new();
+
+        state.set_instruction_pointer(Some(Address::from_usize(0)));
+        state.allocate_and_push_operand(Object::Map(BTreeMap::new()));
+        state.allocate_and_push_operand(Object::String("x".to_string()));
+        state.allocate_and_push_operand(Object::from_i32(42));
+
+        interpret(&mut state, &mut output, &program);
+
+        let mut entries = BTreeMap::new();
+        entries.insert(MapKey::String("x".to_string()), Pointer::from(2));
+
+        assert_eq!(&output, "", "test output");
+        assert_eq!(state.operands, vec!(Pointer::from(3)), "test operands");    // returns null
+        assert_eq!(state.instruction_pointer, Some(Address::from_usize(1)), "test instruction pointer");
+        assert_eq!(state.memory, Memory::from(vec!(Object::Map(entries),
+                                                   Object::String("x".to_string()),
+                                                   Object::from_i32(42),
+                                                   Object::Null)), "test memory");
+    }
+
+    #[test] fn map_two() {
+        let code = Code::from(vec!(
+            OpCode::Map { entries: Size::new(2) },
+            OpCode::Skip,
+        ));
+
+        let program = Program::new(code, vec!(), vec!(), ConstantPoolIndex::new(0));
+
+        let mut state = State::minimal();
+        let mut output: String = String::new();
+
+        state.allocate_and_push_operand(Object::from_i32(1));
+        state.allocate_and_push_operand(Object::from_bool(true));
+        state.allocate_and_push_operand(Object::from_i32(1));
+        state.allocate_and_push_operand(Object::from_bool(false));
+
+        interpret(&mut state, &mut output, &program);
+
+        let mut entries = BTreeMap::new();
+        entries.insert(MapKey::Integer(Integer::Small(1)), Pointer::from(3));
+
+        assert_eq!(&output, "", "test output");
+        assert_eq!(state.operands, vec!(Pointer::from(4)), "test operands");
+        assert_eq!(state.instruction_pointer, Some(Address::from_usize(1)), "test instruction pointer");
+        assert_eq!(state.dereference(&Pointer::from(4)), Some(&Object::Map(entries)), "test map");
+    }
+
     #[test] fn call_method_null_equals() {
         call_method(Object::Null, Object::Null, "==", Object::from_bool(true));
         call_method(Object::Null, Object::from_i32(1), "==", Object::from_bool(false));
@@ -2251,6 +2652,34 @@ mod compiler_tests {
         assert_eq!(bookkeeping, expected_bookkeeping);
     }
 
+    #[test] fn float () {
+        let ast = AST::Float(0.5);
+
+        let mut program: Program = Program::empty();
+        let mut bookkeeping: Bookkeeping = Bookkeeping::with_frame();
+
+        ast.compile(&mut program, &mut bookkeeping);
+
+        let expected_bookkeeping: Bookkeeping = Bookkeeping::with_frame();
+
+        let expected_code = Code::from(vec!(
+            /* 0 */ OpCode::Literal { index: ConstantPoolIndex::new(0) }
+        ));
+
+        let expected_constants: Vec<ProgramObject> = vec!(
+            /* 0 */ ProgramObject::Float(0.5)
+        );
+
+        let expected_globals: Vec<ConstantPoolIndex> = vec!();
+        let expected_entry = ConstantPoolIndex::new(0);
+
+        let expected_program =
+            Program::new(expected_code, expected_constants, expected_globals, expected_entry);
+
+        assert_eq!(program, expected_program);
+        assert_eq!(bookkeeping, expected_bookkeeping);
+    }
+
     #[test] fn boolean () {
         let ast = AST::Boolean(true);
 
@@ -2307,6 +2736,81 @@ mod compiler_tests {
         assert_eq!(bookkeeping, expected_bookkeeping);
     }
 
+    #[test] #[should_panic(expected = "Range error")] fn range_outside_iteration () {
+        let ast = AST::Range { start: Box::new(AST::Number(0)), end: Box::new(AST::Number(1)) };
+
+        let mut program: Program = Program::empty();
+        let mut bookkeeping: Bookkeeping = Bookkeeping::with_frame();
+
+        ast.compile(&mut program, &mut bookkeeping);
+    }
+
+    #[test] fn string () {
+        let ast = AST::String("key".to_string());
+
+        let mut program: Program = Program::empty();
+        let mut bookkeeping: Bookkeeping = Bookkeeping::with_frame();
+
+        ast.compile(&mut program, &mut bookkeeping);
+
+        let expected_bookkeeping: Bookkeeping = Bookkeeping::with_frame();
+
+        let expected_code = Code::from(vec!(
+            /* 0 */ OpCode::Literal { index: ConstantPoolIndex::new(0) }
+        ));
+
+        let expected_constants: Vec<ProgramObject> = vec!(
+            /* 0 */ ProgramObject::from_str("key")
+        );
+
+        let expected_globals: Vec<ConstantPoolIndex> = vec!();
+        let expected_entry = ConstantPoolIndex::new(0);
+
+        let expected_program =
+            Program::new(expected_code, expected_constants, expected_globals, expected_entry);
+
+        assert_eq!(program, expected_program);
+        assert_eq!(bookkeeping, expected_bookkeeping);
+    }
+
+    #[test] fn map_definition () {
+        let ast = AST::MapDefinition { entries: vec!(
+            (Box::new(AST::Number(1)), Box::new(AST::Boolean(true))),
+            (Box::new(AST::String("key".to_string())), Box::new(AST::Unit)),
+        )};
+
+        let mut program: Program = Program::empty();
+        let mut bookkeeping: Bookkeeping = Bookkeeping::with_frame();
+
+        ast.compile(&mut program, &mut bookkeeping);
+
+        let expected_bookkeeping: Bookkeeping = Bookkeeping::with_frame();
+
+        let expected_code = Code::from(vec!(
+            /* 0 */ OpCode::Literal { index: ConstantPoolIndex::new(0) },
+            /* 1 */ OpCode::Literal { index: ConstantPoolIndex::new(1) },
+            /* 2 */ OpCode::Literal { index: ConstantPoolIndex::new(2) },
+            /* 3 */ OpCode::Literal { index: ConstantPoolIndex::new(3) },
+            /* 4 */ OpCode::Map { entries: Size::new(2) },
+        ));
+
+        let expected_constants: Vec<ProgramObject> = vec!(
+            /* 0 */ ProgramObject::Integer(1),
+            /* 1 */ ProgramObject::Boolean(true),
+            /* 2 */ ProgramObject::from_str("key"),
+            /* 3 */ ProgramObject::Null,
+        );
+
+        let expected_globals: Vec<ConstantPoolIndex> = vec!();
+        let expected_entry = ConstantPoolIndex::new(0);
+
+        let expected_program =
+            Program::new(expected_code, expected_constants, expected_globals, expected_entry);
+
+        assert_eq!(program, expected_program);
+        assert_eq!(bookkeeping, expected_bookkeeping);
+    }
+
     #[test] fn local_definition () {
         let ast = AST::VariableDefinition { name: Identifier::from("x"),
                                          value: Box::new(AST::Number(1)) };
@@ -2495,19 +2999,21 @@ mod compiler_tests {
         let expected_code = Code::from(vec!(
             /* 0 */ OpCode::Jump { label: ConstantPoolIndex::new(1) },
             /* 1 */ OpCode::Label { name: ConstantPoolIndex::new(0) },
-            /* 2 */ OpCode::Literal { index: ConstantPoolIndex::new(2) },
+            /* 2 */ OpCode::Literal { index: ConstantPoolIndex::new(3) },
             /* 3 */ OpCode::Drop,
             /* 4 */ OpCode::Label { name: ConstantPoolIndex::new(1) },
-            /* 5 */ OpCode::Literal { index: ConstantPoolIndex::new(3) },
+            /* 5 */ OpCode::Literal { index: ConstantPoolIndex::new(4) },
             /* 6 */ OpCode::Branch { label: ConstantPoolIndex::new(0) },
-            /* 7 */ OpCode::Literal { index: ConstantPoolIndex::new(2) },
+            /* 7 */ OpCode::Label { name: ConstantPoolIndex::new(2) },
+            /* 8 */ OpCode::Literal { index: ConstantPoolIndex::new(3) },
         ));
 
         let expected_constants: Vec<ProgramObject> = vec!(
             /* 0 */ ProgramObject::String("loop_body_0".to_string()),
             /* 1 */ ProgramObject::String("loop_condition_0".to_string()),
-            /* 2 */ ProgramObject::Null,
-            /* 3 */ ProgramObject::Boolean(false),
+            /* 2 */ ProgramObject::String("loop_end_0".to_string()),
+            /* 3 */ ProgramObject::Null,
+            /* 4 */ ProgramObject::Boolean(false),
         );
 
         let expected_globals: Vec<ConstantPoolIndex> = vec!();
@@ -2633,20 +3139,21 @@ mod compiler_tests {
             OpCode::Label { name: ConstantPoolIndex::new(3) },      // label loop_body_0:
             OpCode::GetLocal { index: LocalFrameIndex::new(1) },    // arr
             OpCode::GetLocal { index: LocalFrameIndex::new(2) },    // i
-            OpCode::CallFunction { name: ConstantPoolIndex::new(5), arguments: Arity::new(0) }, // call f() -> result on stack
-            OpCode::CallMethod { name: ConstantPoolIndex::new(6), arguments: Arity::new(3) },   // call arr.set(i, result of f())
+            OpCode::CallFunction { name: ConstantPoolIndex::new(6), arguments: Arity::new(0) }, // call f() -> result on stack
+            OpCode::CallMethod { name: ConstantPoolIndex::new(7), arguments: Arity::new(3) },   // call arr.set(i, result of f())
             OpCode::Drop,
             OpCode::GetLocal { index: LocalFrameIndex::new(2) },                         // i
-            OpCode::Literal { index: ConstantPoolIndex::new(8) },                        // 1
-            OpCode::CallMethod { name: ConstantPoolIndex::new(7), arguments: Arity::new(2) }, // i + 1
+            OpCode::Literal { index: ConstantPoolIndex::new(9) },                        // 1
+            OpCode::CallMethod { name: ConstantPoolIndex::new(8), arguments: Arity::new(2) }, // i + 1
             OpCode::SetLocal { index: LocalFrameIndex::new(2) },                         // i = i + 1
             OpCode::Drop,
 
             OpCode::Label { name: ConstantPoolIndex::new(4) },                           // label loop_condition_0:
             OpCode::GetLocal { index: LocalFrameIndex::new(2) },                         // i
             OpCode::GetLocal { index: LocalFrameIndex::new(0) },                         // size
-            OpCode::CallMethod { name: ConstantPoolIndex::new(9), arguments: Arity::new(2) }, // i < size
+            OpCode::CallMethod { name: ConstantPoolIndex::new(10), arguments: Arity::new(2) }, // i < size
             OpCode::Branch { label: ConstantPoolIndex::new(3) },                         // conditional jump to loop_body_0
+            OpCode::Label { name: ConstantPoolIndex::new(5) },                           // label loop_end_0:
             OpCode::GetLocal { index: LocalFrameIndex::new(1) },                         // arr
         ));
 
@@ -2656,11 +3163,12 @@ mod compiler_tests {
             /* 2 */ ProgramObject::Integer(0),
             /* 3 */ ProgramObject::from_str("loop_body_0"),
             /* 4 */ ProgramObject::from_str("loop_condition_0"),
-            /* 5 */ ProgramObject::from_str("f"),
-            /* 6 */ ProgramObject::from_str("set"),
-            /* 7 */ ProgramObject::from_str("+"),
-            /* 8 */ ProgramObject::from_i32(1),
-            /* 9 */ ProgramObject::from_str("<"),
+            /* 5 */ ProgramObject::from_str("loop_end_0"),
+            /* 6 */ ProgramObject::from_str("f"),
+            /* 7 */ ProgramObject::from_str("set"),
+            /* 8 */ ProgramObject::from_str("+"),
+            /* 9 */ ProgramObject::from_i32(1),
+            /* 10 */ ProgramObject::from_str("<"),
         );
 
         let expected_globals: Vec<ConstantPoolIndex> = vec!();
@@ -3492,18 +4000,58 @@ mod compiler_tests {
     }
 }
 
+#[cfg(test)]
+mod tests;
+
 fn main() {
     use std::env;
     use std::fs::File;
     use std::io::stdin;
     use std::io::Read;
-    use fml_ast::AST;
+    use std::path::Path;
+    use fml_parser::modules::ModuleLoader;
+    use fml_parser::serialization::ASTFormat;
+    use fml_parser::checker::check_or_exit;
+    use fml_ast::integers::IntegerSemantics;
 
     use crate::program::Program;
 
     println!("{:?}", env::args());
 
-    let files: Vec<String> = env::args().into_iter().map(|e| e.to_string()).collect();
+    let mut files: Vec<String> = env::args().into_iter().map(|e| e.to_string()).collect();
+
+    // `--ast FORMAT` reads a serialized AST (json, yaml, or sexpr) instead of source code.
+    let ast_format = match files.iter().position(|argument| argument == "--ast") {
+        Some(index) => {
+            let name = files.get(index + 1).cloned().expect("Expected a format after --ast");
+            files.drain(index..index + 2);
+            Some(ASTFormat::from_name(&name).unwrap_or_else(|| panic!("Unknown AST format: {}", name)))
+        },
+        None => None,
+    };
+
+    // `--feeny` parses the program as Feeny rather than FML; files ending in `.feeny` always are.
+    let feeny = match files.iter().position(|argument| argument == "--feeny") {
+        Some(index) => { files.remove(index); true },
+        None => false,
+    };
+
+    // `--integers SEMANTICS` selects what integer arithmetic does on overflow: wrapping32 (the
+    // default), checked64, or promoting.
+    let integers = match files.iter().position(|argument| argument == "--integers") {
+        Some(index) => {
+            let semantics = match files.get(index + 1) {
+                Some(name) => IntegerSemantics::from_str(name),
+                None => Err(String::from("Expected one of wrapping32, checked64, promoting after --integers")),
+            };
+            files.drain(index..files.len().min(index + 2));
+            semantics.unwrap_or_else(|e| {
+                eprintln!("Usage error: {}", e);
+                std::process::exit(2)
+            })
+        },
+        None => IntegerSemantics::default(),
+    };
 
     let input = match files.len() {
         0 => unreachable!(),
@@ -3527,11 +4075,22 @@ fn main() {
 
     println!("{}", input);
 
-    let ast: AST = fml_parser::parse(&input).expect("Parse error");
+    let syntax = match files.len() {
+        _ if feeny => fml_parser::Syntax::Feeny,
+        2 => fml_parser::Syntax::from_path(Path::new(files.last().unwrap())),
+        _ => fml_parser::Syntax::FML,
+    };
+    // The program is checked before it is compiled, and not run at all if there are errors.
+    let ast = check_or_exit(ast_format, syntax, &input);
 
     println!("{:?}", ast);
 
-    let program: Program = compiler::compile(&ast);
+    let mut modules = ModuleLoader::from_env();
+    if files.len() == 2 {
+        modules.set_main_file(Path::new(files.last().unwrap()));
+    }
+
+    let program: Program = compiler::compile_with_modules(&ast, modules);
 
     program.code().dump();
     println!("{:?}", program);
@@ -3540,5 +4099,7 @@ fn main() {
     program.pretty_print(&mut source);
     println!("{}", String::from_utf8(source).unwrap());
 
-    interpreter::evaluate(&program);
+    if let Some(code) = interpreter::evaluate(&program, integers) {
+        std::process::exit(code);
+    }
 }
@@ -3,8 +3,10 @@ use crate::bytecode::OpCode;
 use crate::serializable::{Serializable, SerializableWithContext};
 use crate::serializable;
 use std::io::{Read, Write};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::rc::Rc;
 use crate::program::Code;
+use fml_ast::integers::{Integer, BigInt};
 
 #[derive(PartialEq,Debug,Clone)]
 pub enum ProgramObject {
@@ -15,6 +17,23 @@ pub enum ProgramObject {
      */
     Integer(i32),
 
+    /**
+     * Represents a 64 bit integer that does not fit into 32 bits. Used by the `Literal`
+     * instruction. Whether the literal is accepted depends on the integer semantics the program is
+     * run with.
+     *
+     * Serialized with tag `0x08`.
+     */
+    Long(i64),
+
+    /**
+     * Represents a 64 bit floating-point number. Used by the `Literal` instruction.
+     *
+     * Serialized with tag `0x07`, followed by the 8 bytes of the IEEE 754 representation in
+     * little-endian order.
+     */
+    Float(f64),
+
     /**
      * Represents a boolean. Used by the `Literal` instruction.
      *
@@ -50,6 +69,17 @@ pub enum ProgramObject {
      */
     Slot { name: ConstantPoolIndex },
 
+    /**
+     * Represents a field member of an object declared with `const`. It is laid out like a
+     * `ProgramObject::Slot`, but the field cannot be changed by `SetSlot` once the object exists.
+     *
+     * Contains an index that refers to a `ProgramObject::String` object. The string object
+     * represents this slot's name.
+     *
+     * Serialized with tag `0x09`.
+     */
+    ConstantSlot { name: ConstantPoolIndex },
+
     /**
      * Represents one of two things:
      *   - a method member of an object, or
@@ -77,7 +107,8 @@ pub enum ProgramObject {
      *
      * It contains a vector containing indices to all the slots in the objects. Each index refers to
      * either:
-     *   - a `ProgramObject::Slot` object representing a member field, or
+     *   - a `ProgramObject::Slot` object representing a member field,
+     *   - a `ProgramObject::ConstantSlot` object representing a read-only member field, or
      *   - a `ProgramObject::Method` object representing a member method.
      *
      * Serialized with tag `0x05`.
@@ -96,6 +127,9 @@ impl ProgramObject {
             Slot {name:_}                                      => 0x04,
             Class(_)                                           => 0x05,
             Boolean(_)                                         => 0x06,
+            Float(_)                                           => 0x07,
+            Long(_)                                            => 0x08,
+            ConstantSlot {name:_}                              => 0x09,
         }
     }
 }
@@ -107,10 +141,13 @@ impl SerializableWithContext for ProgramObject {
         match &self {
             Null        => (),
             Integer(n)  => serializable::write_i32(sink, *n),
+            Float(f)    => serializable::write_f64(sink, *f),
+            Long(n)     => serializable::write_i64(sink, *n),
             Boolean(b)  => serializable::write_bool(sink, *b),
             String(s)   => serializable::write_utf8(sink, s),
             Class(v)    => ConstantPoolIndex::write_cpi_vector(sink, v),
             Slot {name} => name.serialize(sink),
+            ConstantSlot {name} => name.serialize(sink),
 
             Method {name, arguments, locals, code: range} => {
                 name.serialize(sink);
@@ -135,6 +172,9 @@ impl SerializableWithContext for ProgramObject {
             0x04 => ProgramObject::Slot { name: ConstantPoolIndex::from_bytes(input) },
             0x05 => ProgramObject::Class(ConstantPoolIndex::read_cpi_vector(input)),
             0x06 => ProgramObject::Boolean(serializable::read_bool(input)),
+            0x07 => ProgramObject::Float(serializable::read_f64(input)),
+            0x08 => ProgramObject::Long(serializable::read_i64(input)),
+            0x09 => ProgramObject::ConstantSlot { name: ConstantPoolIndex::from_bytes(input) },
             _    => panic!("Cannot deserialize value: unrecognized value tag: {}", tag)
         }
     }
@@ -165,6 +205,22 @@ impl ProgramObject {
         ProgramObject::Integer(n)
     }
 
+    #[allow(dead_code)]
+    pub fn from_f64(f: f64) -> Self {
+        ProgramObject::Float(f)
+    }
+
+    /**
+     * Integer constants that fit into 32 bits keep using the 32 bit representation.
+     */
+    pub fn from_i64(n: i64) -> Self {
+        if n >= i32::MIN as i64 && n <= i32::MAX as i64 {
+            ProgramObject::Integer(n as i32)
+        } else {
+            ProgramObject::Long(n)
+        }
+    }
+
     #[allow(dead_code)]
     pub fn from_usize(n: usize) -> Self {
         ProgramObject::Integer(n as i32)
@@ -203,32 +259,62 @@ impl Pointer {
 #[derive(PartialEq,Debug,Clone)]
 pub enum Object {
     Null,
-    Integer(i32),
+    Integer(i64),
+    BigInteger(BigInt),
+    Float(f64),
     Boolean(bool),
+    String(String),
     Array(Vec<Pointer>),
+    Map(BTreeMap<MapKey, Pointer>),
     Object {
         parent: Pointer,
         fields: HashMap<String, Pointer>,
-        methods: HashMap<String, ProgramObject>,
+        methods: Rc<HashMap<String, ProgramObject>>, /* shared by all instances of a class */
+        constants: HashSet<String>, /* names of fields declared with const */
     },
 }
 
 impl Object {
     pub fn from_pointers(v: Vec<Pointer>) -> Self { Object::Array(v)   }
-    pub fn from_i32(n :i32)               -> Self { Object::Integer(n) }
+    pub fn from_i32(n :i32)               -> Self { Object::Integer(n as i64) }
+    pub fn from_i64(n :i64)               -> Self { Object::Integer(n) }
+    pub fn from_f64(f: f64)               -> Self { Object::Float(f)   }
     pub fn from_bool(b: bool)             -> Self { Object::Boolean(b) }
 
     pub fn from_constant(constant: &ProgramObject) -> Self {
         match constant {
             ProgramObject::Null => Object::Null,
-            ProgramObject::Integer(value) => Object::Integer(*value),
+            ProgramObject::Integer(value) => Object::Integer(*value as i64),
+            ProgramObject::Long(value) => Object::Integer(*value),
+            ProgramObject::Float(value) => Object::Float(*value),
             ProgramObject::Boolean(value) => Object::Boolean(*value),
+            ProgramObject::String(value) => Object::String(value.clone()),
             _ => unimplemented!(),
         }
     }
 
+    pub fn from_integer(integer: Integer) -> Self {
+        match integer {
+            Integer::Small(n) => Object::Integer(n),
+            Integer::Big(n) => Object::BigInteger(n),
+        }
+    }
+
+    pub fn to_integer(&self) -> Option<Integer> {
+        match self {
+            Object::Integer(n) => Some(Integer::Small(*n)),
+            Object::BigInteger(n) => Some(Integer::Big(n.clone())),
+            _ => None,
+        }
+    }
+
     pub fn from(parent: Pointer, fields: HashMap<String, Pointer>, methods: HashMap<String, ProgramObject>) -> Self {
-        Object::Object { parent, fields, methods }
+        Object::Object { parent, fields, methods: Rc::new(methods), constants: HashSet::new() }
+    }
+
+    pub fn with_constants(parent: Pointer, fields: HashMap<String, Pointer>,
+                          methods: Rc<HashMap<String, ProgramObject>>, constants: HashSet<String>) -> Self {
+        Object::Object { parent, fields, methods, constants }
     }
 
     #[allow(dead_code)]
@@ -236,7 +322,10 @@ impl Object {
         match self {
             Object::Null => "null".to_string(),
             Object::Integer(n) => n.to_string(),
+            Object::BigInteger(n) => n.to_string(),
+            Object::Float(f) => float_to_string(*f),
             Object::Boolean(b) => b.to_string(),
+            Object::String(s) => s.clone(),
             Object::Array(elements) => {
                 let mut buffer = String::new();
                 buffer.push('[');
@@ -249,7 +338,13 @@ impl Object {
                 buffer.push(']');
                 buffer
             },
-            Object::Object { parent, fields, methods:_ } => {
+            Object::Map(entries) => {
+                let entries: Vec<String> = entries.iter()
+                    .map(|(key, value)| format!("{}: {}", key.to_string(), value.to_string()))
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            },
+            Object::Object { parent, fields, methods:_, constants:_ } => {
                 let mut buffer = String::from("object(");
 
                 buffer.push_str("..=");
@@ -270,4 +365,49 @@ impl Object {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Floats are always printed with a decimal point (or in exponent notation), so that `1.0` is
+ * distinguishable from `1` in the output.
+ */
+pub fn float_to_string(f: f64) -> String {
+    format!("{:?}", f)
+}
+
+/**
+ * Keys of map objects. Keys are stored by value, so only immutable objects can serve as keys. They
+ * are kept ordered, so that iterating over (and printing) a map is deterministic.
+ */
+#[derive(PartialEq,Eq,PartialOrd,Ord,Debug,Clone)]
+pub enum MapKey {
+    Null,
+    Boolean(bool),
+    Integer(Integer),
+    String(String),
+}
+
+impl MapKey {
+    pub fn from_object(object: &Object) -> Option<Self> {
+        match object {
+            Object::Null => Some(MapKey::Null),
+            Object::Boolean(b) => Some(MapKey::Boolean(*b)),
+            Object::Integer(_) | Object::BigInteger(_) => object.to_integer().map(MapKey::Integer),
+            Object::String(s) => Some(MapKey::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_object(&self) -> Object {
+        match self {
+            MapKey::Null => Object::Null,
+            MapKey::Boolean(b) => Object::Boolean(*b),
+            MapKey::Integer(n) => Object::from_integer(n.clone()),
+            MapKey::String(s) => Object::String(s.clone()),
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        self.to_object().to_string()
+    }
+}
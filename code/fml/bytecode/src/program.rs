@@ -314,6 +314,20 @@ impl Program {
             .collect()
     }
 
+    /**
+     * Generates names for compiler-introduced variables. They are numbered the same way as labels,
+     * so that nested desugarings at the top level (where they become globals) do not clash.
+     */
+    pub fn generate_new_local_names(&mut self, names: Vec<&str>) -> Vec<String> {
+        let names = names.into_iter()
+            .map(|name| self.labels.generate_label(name))
+            .map(|name| name.unwrap())
+            .collect();
+
+        self.labels.new_group();
+        names
+    }
+
     pub fn get_current_address(&self) -> Address {
         let size = self.code.opcodes.len();
         Address::from_usize(size - 1)
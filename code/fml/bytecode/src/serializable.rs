@@ -48,6 +48,18 @@ pub fn read_i32<R: Read>(reader: &mut R) -> i32 {
     i32::from_le_bytes(buf)
 }
 
+pub fn read_i64<R: Read>(reader: &mut R) -> i64 {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf).expect("Problem reading i64 from data stream");
+    i64::from_le_bytes(buf)
+}
+
+pub fn read_f64<R: Read>(reader: &mut R) -> f64 {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf).expect("Problem reading f64 from data stream");
+    f64::from_le_bytes(buf)
+}
+
 //pub fn read_u64<R: Read>(reader: &mut R) -> u64 {
 //    let mut buf = [0u8; 8];
 //    reader.read_exact(&mut buf).expect("Problem reading u64 from data stream");
@@ -121,6 +133,16 @@ pub fn write_i32<W: Write>(writer: &mut W, value: i32) -> () {
     writer.write(&buf).expect(&format!("Problem writing i32 to data sink: {}", value));
 }
 
+pub fn write_i64<W: Write>(writer: &mut W, value: i64) -> () {
+    let buf = value.to_le_bytes();
+    writer.write(&buf).expect(&format!("Problem writing i64 to data sink: {}", value));
+}
+
+pub fn write_f64<W: Write>(writer: &mut W, value: f64) -> () {
+    let buf = value.to_le_bytes();
+    writer.write(&buf).expect(&format!("Problem writing f64 to data sink: {}", value));
+}
+
 //pub fn write_u64<W: Write>(writer: &mut W, value: u64) -> () {
 //    let buf = value.to_le_bytes();
 //    writer.write(&buf).expect(&format!("Problem writing u64 to data sink: {}", value));
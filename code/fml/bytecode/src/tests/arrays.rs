@@ -0,0 +1,53 @@
+use super::run;
+
+#[test] fn push_and_pop() {
+    assert_eq!(run("let a = array(0, 0); \
+                    a.push(1); a.push(2); a.push(3); \
+                    let x = a.pop(); \
+                    print(\"~ ~ ~ ~\", x, a.length(), a[0], a[1])"),
+               "3 2 1 2");
+}
+
+#[test] fn insert_and_remove() {
+    assert_eq!(run("let a = array(2, 0); \
+                    a.insert(1, 5); a.insert(3, 7); a.insert(0, 9); \
+                    let x = a.remove(2); \
+                    for e in a do print(\"~ \", e); \
+                    print(\"~\", x)"),
+               "9 0 0 7 5");
+}
+
+#[test] fn concat() {
+    assert_eq!(run("let a = array(1, 1); let b = array(2, 2); \
+                    let c = a.concat(b); c.push(3); \
+                    print(\"~ ~ ~\", a.length(), b.length(), c.length())"),
+               "1 2 4");
+}
+
+#[test] fn contains_and_index_of() {
+    assert_eq!(run("let a = array(3, 0); a[1] <- \"x\"; a[2] <- 7; \
+                    print(\"~ ~ ~ ~\", a.contains(\"x\"), a.contains(8), a.index_of(7), a.index_of(null))"),
+               "true false 2 -1");
+}
+
+#[test] fn contains_object_by_identity() {
+    assert_eq!(run("let o = object begin end; let a = array(0, null); \
+                    a.push(o); print(\"~ ~\", a.contains(o), a.index_of(object begin end))"),
+               "true -1");
+}
+
+#[test] fn iteration_sees_growth() {
+    assert_eq!(run("let a = array(1, 1); a.push(2); for x in a do print(\"~\", x)"), "12");
+}
+
+#[test] #[should_panic(expected = "array index -1 is out of bounds")] fn negative_index() {
+    run("let a = array(2, 0); a[-1]");
+}
+
+#[test] #[should_panic(expected = "array index 3 is out of bounds")] fn insert_past_end() {
+    run("let a = array(2, 0); a.insert(3, 0)");
+}
+
+#[test] #[should_panic(expected = "cannot pop from an empty array")] fn pop_empty() {
+    run("array(0, 0).pop()");
+}
@@ -0,0 +1,66 @@
+use super::run;
+
+#[test] fn break_while() {
+    assert_eq!(run("let i = 0; \
+                    while true do begin \
+                        if i == 3 then break; \
+                        print(\"~\", i); \
+                        i <- i + 1; \
+                    end"),
+               "012");
+}
+
+#[test] fn continue_while() {
+    assert_eq!(run("let i = 0; \
+                    while i < 5 do begin \
+                        i <- i + 1; \
+                        if i % 2 == 0 then continue; \
+                        print(\"~\", i); \
+                    end"),
+               "135");
+}
+
+#[test] fn break_and_continue_for() {
+    assert_eq!(run("for i in 0..10 do begin \
+                        if i == 1 then continue; \
+                        if i == 4 then break; \
+                        print(\"~\", i); \
+                    end"),
+               "023");
+}
+
+#[test] fn break_innermost() {
+    assert_eq!(run("for i in 0..3 do for j in 0..3 do begin \
+                        if j == 1 then break; \
+                        print(\"~~ \", i, j); \
+                    end"),
+               "00 10 20 ");
+}
+
+#[test] fn break_in_function() {
+    assert_eq!(run("function f() -> begin \
+                        let i = 0; \
+                        while true do begin i <- i + 1; if i == 7 then break; end; \
+                        i \
+                    end; \
+                    print(\"~\", f())"),
+               "7");
+}
+
+#[test] fn break_inside_expression() {
+    assert_eq!(run("print(\"~ ~\", 1, while true do 2 + (if true then break else 0))"),
+               "1 null");
+}
+
+#[test] fn continue_inside_expression() {
+    assert_eq!(run("print(\"~ ~\", 7, for i in 0..3 do let x = 10 + (if i < 2 then continue else i))"),
+               "7 null");
+}
+
+#[test] #[should_panic(expected = "Break error")] fn break_outside_loop() {
+    run("break");
+}
+
+#[test] #[should_panic(expected = "Continue error")] fn continue_in_function_in_loop() {
+    run("while true do function f() -> continue");
+}
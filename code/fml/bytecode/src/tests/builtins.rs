@@ -0,0 +1,69 @@
+use super::{run, run_with_input};
+
+#[test] fn array_functions() {
+    assert_eq!(run("let a = array(2, 0); \
+                    push(a, 5); push(a, 3); \
+                    let x = pop(a); \
+                    let b = slice(a, 1, 3); \
+                    print(\"~ ~ ~ ~\", x, length(a), length(b), b[1])"),
+               "3 3 2 5");
+}
+
+#[test] fn sort_integers() {
+    assert_eq!(run("let a = array(4, 0); a[0] <- 3; a[1] <- -1; a[2] <- 10; \
+                    sort(a); \
+                    for x in a do print(\"~ \", x)"),
+               "-1 0 3 10 ");
+}
+
+#[test] fn sort_strings() {
+    assert_eq!(run("let a = array(3, \"b\"); a[1] <- \"c\"; a[2] <- \"a\"; \
+                    sort(a); \
+                    for x in a do print(\"~\", x)"),
+               "abc");
+}
+
+#[test] fn integer_functions() {
+    assert_eq!(run("print(\"~ ~ ~ ~ ~\", abs(-4), min(3, 2), max(3, 2), pow(2, 10), pow(7, 0))"),
+               "4 2 3 1024 1");
+}
+
+#[test] fn pow_wraps() {
+    assert_eq!(run("print(\"~\", pow(2, 32))"), "0");
+}
+
+#[test] fn user_function_shadows_builtin() {
+    assert_eq!(run("function abs(x) -> 42; print(\"~\", abs(-1))"), "42");
+}
+
+#[test] fn assert_passes() {
+    assert_eq!(run("assert(1 < 2); print(\"ok\")"), "ok");
+}
+
+#[test] #[should_panic(expected = "Assertion error")] fn assert_fails() {
+    run("assert(2 < 1)");
+}
+
+#[test] #[should_panic(expected = "cannot pop from an empty array")] fn pop_empty() {
+    run("pop(array(0, 0))");
+}
+
+#[test] #[should_panic(expected = "length expects an array")] fn length_of_integer() {
+    run("length(5)");
+}
+
+#[test] #[should_panic(expected = "takes 2 arguments")] fn wrong_arity() {
+    run("min(1)");
+}
+
+#[test] fn read_input() {
+    assert_eq!(run_with_input("let name = read_line(); let n = read_int(); \
+                               print(\"~ ~ ~\", name, n + 1, read_line())",
+                              "world\n 41 \n"),
+               ("world 42 null".to_string(), None));
+}
+
+#[test] fn exit_stops_program() {
+    assert_eq!(run_with_input("print(\"a\"); exit(3); print(\"b\")", ""),
+               ("a".to_string(), Some(3)));
+}
@@ -0,0 +1,87 @@
+use super::run;
+
+#[test] fn fields_from_parameters() {
+    assert_eq!(run("class Point(x, y) begin let x = x; let y = y + 1 end; \
+                    let p = new Point(1, 2); print(\"~ ~\", p.x, p.y)"), "1 3");
+}
+
+#[test] fn methods() {
+    assert_eq!(run("class Counter(start) begin \
+                        let count = start; \
+                        function tick() -> this.count <- this.count + 1 \
+                    end; \
+                    let c = new Counter(10); c.tick(); c.tick(); print(\"~\", c.count)"), "12");
+}
+
+#[test] fn instances_are_distinct() {
+    assert_eq!(run("class Box(v) begin let v = v end; \
+                    let a = new Box(1); let b = new Box(2); a.v <- 3; \
+                    print(\"~ ~\", a.v, b.v)"), "3 2");
+}
+
+#[test] fn instantiation_in_loop() {
+    assert_eq!(run("class Box(v) begin let v = v end; \
+                    let sum = 0; for i in 0..5 do sum <- sum + new Box(i).v; print(\"~\", sum)"), "10");
+}
+
+#[test] fn superclass_constructor() {
+    assert_eq!(run("class Rectangle(w, h) begin let w = w; let h = h; function area() -> this.w * this.h end; \
+                    class Square(side) extends Rectangle(side, side) begin let name = \"square\" end; \
+                    let s = new Square(3); print(\"~ ~\", s.name, s.area())"), "square 9");
+}
+
+#[test] fn overriding() {
+    assert_eq!(run("class Animal() begin function speak() -> \"...\"; function name() -> \"animal\" end; \
+                    class Dog() extends Animal begin function speak() -> \"woof\" end; \
+                    let d = new Dog(); print(\"~ ~ ~\", new Animal().speak(), d.speak(), d.name())"), "... woof animal");
+}
+
+#[test] fn class_and_function_of_same_name() {
+    assert_eq!(run("class Point(x) begin let x = x end; function Point(x) -> x * 2; \
+                    print(\"~ ~\", new Point(4).x, Point(4))"), "4 8");
+}
+
+#[test] #[should_panic(expected = "no such function new Missing")] fn unknown_class() {
+    run("new Missing()");
+}
+
+#[test] fn super_method() {
+    assert_eq!(run("class Animal() begin function speak() -> \"...\" end; \
+                    class Dog() extends Animal begin function speak() -> print(\"woof ~\", super.speak()) end; \
+                    new Dog().speak()"), "woof ...");
+}
+
+#[test] fn super_method_chain() {
+    assert_eq!(run("class A() begin function f(x) -> x end; \
+                    class B() extends A begin function f(x) -> super.f(x) * 10 end; \
+                    class C() extends B begin function f(x) -> super.f(x) + 1 end; \
+                    print(\"~\", new C().f(2))"), "21");
+}
+
+#[test] fn super_method_skips_parent_without_it() {
+    assert_eq!(run("class A() begin function f() -> 1 end; \
+                    class B() extends A begin end; \
+                    class C() extends B begin function f() -> super.f() + 1 end; \
+                    print(\"~\", new C().f())"), "2");
+}
+
+#[test] fn super_method_keeps_receiver() {
+    assert_eq!(run("class Base() begin let x = 1; function get() -> this.x end; \
+                    class Child() extends Base begin let x = 2; function get() -> super.get() + 10 end; \
+                    print(\"~\", new Child().get())"), "12");
+}
+
+#[test] fn super_method_on_prototype() {
+    assert_eq!(run("let p = object begin function +(x) -> 100 end; \
+                    let o = object extends p begin function +(x) -> super.+(x) + x end; \
+                    print(\"~\", o + 1)"), "101");
+}
+
+#[test] fn super_method_on_primitive_parent() {
+    assert_eq!(run("let o = object extends 5 begin function +(x) -> super.+(x) * 2 end; \
+                    print(\"~\", o + 1)"), "12");
+}
+
+#[test] #[should_panic(expected = "can only be called inside a method")] fn super_outside_method() {
+    run("function f() -> super.f(); f()");
+}
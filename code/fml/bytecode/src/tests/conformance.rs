@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::PathBuf;
+
+use super::run_ast;
+
+// Programs shared with the AST interpreter, each next to the output it should print.
+fn run_suite(suite: &str) {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../conformance").join(suite);
+    let mut sources: Vec<PathBuf> = fs::read_dir(&directory)
+        .expect("Cannot read conformance suite")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "fml"))
+        .collect();
+    sources.sort();
+    assert!(!sources.is_empty(), "Conformance suite {} is empty", suite);
+
+    let failures: Vec<String> = sources.iter().filter_map(|source| {
+        let program = fs::read_to_string(source).unwrap();
+        let expected = fs::read_to_string(source.with_extension("out")).unwrap_or_default();
+        let ast = fml_parser::parse(&program).expect("Parse error");
+        let output = run_ast(&ast);
+        if output.trim_end_matches('\n') == expected.trim_end_matches('\n') {
+            None
+        } else {
+            Some(format!("{}: expected {:?}, but got {:?}", source.display(), expected, output))
+        }
+    }).collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test] fn equality() {
+    run_suite("equality");
+}
+
+// Programs from `fml_parser::generator`, each printed from its seed.
+#[test] fn generated() {
+    run_suite("generated");
+}
+
+// Both interpreters print the same for every program generated from a range of seeds, which
+// goes well beyond the programs recorded in the suite.
+#[test] fn generated_differential() {
+    use fml_interpreter::environment::EnvironmentStack;
+    use fml_interpreter::heap::Memory;
+    use fml_interpreter::interpreter::evaluate;
+    use fml_interpreter::world::BufferedIO;
+
+    let failures: Vec<String> = (0..40).filter_map(|seed| {
+        let ast = fml_parser::generator::program(seed);
+
+        let bytecode = run_ast(&ast);
+
+        let mut tree = BufferedIO::new();
+        evaluate(&mut EnvironmentStack::new(), &mut Memory::new(), &mut tree, &ast);
+        let tree = tree.contents();
+
+        if bytecode.trim_end_matches('\n') == tree.trim_end_matches('\n') {
+            None
+        } else {
+            Some(format!("seed {}: bytecode printed {:?}, but the AST interpreter printed {:?}", seed, bytecode, tree))
+        }
+    }).collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
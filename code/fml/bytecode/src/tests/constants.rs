@@ -0,0 +1,42 @@
+use super::run;
+
+#[test] fn global_constant() {
+    assert_eq!(run("const x = 1; function f() -> x + 1; print(\"~ ~\", x, f())"), "1 2");
+}
+
+#[test] fn local_constant() {
+    assert_eq!(run("function f(y) -> begin const x = y * 2; x + 1 end; print(\"~\", f(3))"), "7");
+}
+
+#[test] fn local_variable_shadows_global_constant() {
+    assert_eq!(run("const x = 1; function f(x) -> begin x <- x + 1; x end; print(\"~ ~\", f(5), x)"), "6 1");
+}
+
+#[test] #[should_panic(expected = "Constant error")] fn global_constant_reassigned() {
+    run("const x = 1; x <- 2");
+}
+
+#[test] #[should_panic(expected = "Constant error")] fn global_constant_reassigned_in_function() {
+    run("const x = 1; function f() -> x <- 2; f()");
+}
+
+#[test] #[should_panic(expected = "Constant error")] fn global_constant_redefined() {
+    run("const x = 1; let x = 2");
+}
+
+#[test] #[should_panic(expected = "Constant error")] fn local_constant_reassigned() {
+    run("function f() -> begin const x = 1; x <- 2 end; f()");
+}
+
+#[test] fn constant_field() {
+    assert_eq!(run("let o = object begin const x = 1; let y = 2 end; o.y <- 3; print(\"~ ~\", o.x, o.y)"),
+               "1 3");
+}
+
+#[test] #[should_panic(expected = "is constant")] fn constant_field_reassigned() {
+    run("let o = object begin const x = 1 end; o.x <- 2");
+}
+
+#[test] #[should_panic(expected = "is constant")] fn constant_field_reassigned_in_method() {
+    run("class Point(x) begin const x = x; function move() -> this.x <- this.x + 1 end; new Point(1).move()");
+}
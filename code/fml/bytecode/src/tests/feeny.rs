@@ -0,0 +1,35 @@
+use std::fs;
+use std::path::PathBuf;
+
+use super::run_ast;
+
+// Runs one of the original Feeny test programs and compares what it prints against the output
+// recorded in the comment block at the end of the program.
+fn run_program(name: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../feeny/tests").join(name).with_extension("feeny");
+    let program = fs::read_to_string(&path).expect("Cannot read Feeny program");
+
+    let expected: Vec<&str> = program.lines()
+        .skip_while(|line| !line.to_uppercase().contains("OUTPUT"))
+        .skip(2)
+        .map(|line| line.trim_start_matches(';').trim_end())
+        .collect();
+    assert!(!expected.is_empty(), "Feeny program {} records no output", name);
+
+    let ast = fml_parser::feeny::parse(&program).expect("Parse error");
+    let output = run_ast(&ast);
+    let output: Vec<&str> = output.lines().map(|line| line.trim_end()).collect();
+
+    assert_eq!(output.join("\n").trim(), expected.join("\n").trim());
+}
+
+#[test] fn bsearch() { run_program("bsearch") }
+#[test] fn cplx() { run_program("cplx") }
+#[test] fn fibonacci() { run_program("fibonacci") }
+#[test] fn hanoi() { run_program("hanoi") }
+#[test] fn lists() { run_program("lists") }
+#[test] fn vector() { run_program("vector") }
+#[test] fn inheritance() { run_program("inheritance") }
+
+// Not run: `stack` records `Null` where FML prints `null`, and the sudoku solvers take too long.
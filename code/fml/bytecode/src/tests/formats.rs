@@ -0,0 +1,53 @@
+use crate::compiler::compile;
+
+use super::run;
+
+#[test] fn escapes() {
+    assert_eq!(run("print(\"a\\~b\\\\c\\td\\n\")"), "a~b\\c\td\n");
+}
+
+#[test] fn positional() {
+    assert_eq!(run("print(\"~{1} ~{0} ~{1}\", 1, 2)"), "2 1 2");
+}
+
+#[test] fn named() {
+    assert_eq!(run("let x = 1; let name = \"y\"; print(\"~{name}=~, ~{x}\", 2)"), "y=2, 1");
+}
+
+#[test] fn named_local() {
+    assert_eq!(run("function f(n) -> print(\"n=~{n}\"); f(5)"), "n=5");
+}
+
+#[test] fn width_and_alignment() {
+    assert_eq!(run("print(\"[~{:>5}][~{:<5}][~{:^5}][~{:-^6}][~{:05}][~{:05}]\", 1, \"ab\", \"c\", true, 42, -42)"),
+               "[    1][ab   ][  c  ][-true-][00042][-0042]");
+}
+
+#[test] fn radix() {
+    assert_eq!(run("print(\"~{:x} ~{:X} ~{:b} ~{:08b} ~{:x}\", 255, 255, 5, 5, -255)"),
+               "ff FF 101 00000101 -ff");
+}
+
+#[test] fn width_too_small() {
+    assert_eq!(run("print(\"~{:2}\", 12345)"), "12345");
+}
+
+#[test] #[should_panic(expected = "expects 2 arguments, but 1 were supplied")] fn too_few_arguments() {
+    compile(&fml_parser::parse("print(\"~ ~\", 1)").unwrap());
+}
+
+#[test] #[should_panic(expected = "expects 1 arguments, but 2 were supplied")] fn too_many_arguments() {
+    compile(&fml_parser::parse("print(\"~\", 1, 2)").unwrap());
+}
+
+#[test] #[should_panic(expected = "expects 3 arguments, but 1 were supplied")] fn index_out_of_range() {
+    compile(&fml_parser::parse("print(\"~{2}\", 1)").unwrap());
+}
+
+#[test] #[should_panic(expected = "unknown format type")] fn unknown_type() {
+    compile(&fml_parser::parse("print(\"~{:q}\", 1)").unwrap());
+}
+
+#[test] #[should_panic(expected = "not an integer")] fn radix_of_string() {
+    run("print(\"~{:x}\", \"a\")");
+}
@@ -0,0 +1,26 @@
+use fml_ast::integers::IntegerSemantics;
+
+use super::run_with_integers;
+
+const FACTORIAL: &str = "function factorial(n) -> if n < 2 then 1 else n * factorial(n - 1); \
+                         print(\"~\", factorial(25))";
+
+#[test] fn factorial_wrapping32() {
+    assert_eq!(run_with_integers(IntegerSemantics::Wrapping32, FACTORIAL), "2076180480");
+}
+
+#[test] #[should_panic(expected = "integer overflow")] fn factorial_checked64() {
+    run_with_integers(IntegerSemantics::Checked64, FACTORIAL);
+}
+
+#[test] fn factorial_promoting() {
+    assert_eq!(run_with_integers(IntegerSemantics::Promoting, FACTORIAL), "15511210043330985984000000");
+}
+
+#[test] fn long_literal() {
+    assert_eq!(run_with_integers(IntegerSemantics::Checked64, "print(\"~\", 4294967296 + 1)"), "4294967297");
+}
+
+#[test] #[should_panic(expected = "does not fit into 32 bits")] fn long_literal_wrapping32() {
+    run_with_integers(IntegerSemantics::Wrapping32, "4294967296");
+}
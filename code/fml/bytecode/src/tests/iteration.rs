@@ -0,0 +1,34 @@
+use super::run;
+
+#[test] fn range() {
+    assert_eq!(run("for i in 0..5 do print(\"~\", i)"), "01234");
+}
+
+#[test] fn empty_range() {
+    assert_eq!(run("for i in 5..0 do print(\"~\", i)"), "");
+}
+
+#[test] fn array() {
+    assert_eq!(run("let a = array(3, 7); a[1] <- 8; for x in a do print(\"~ \", x)"),
+               "7 8 7 ");
+}
+
+#[test] fn nested_at_top_level() {
+    assert_eq!(run("for i in 0..2 do for j in 0..2 do print(\"~~ \", i, j)"),
+               "00 01 10 11 ");
+}
+
+#[test] fn in_function() {
+    assert_eq!(run("function sum(a) -> begin \
+                        let s = 0; \
+                        for x in a do s <- s + x; \
+                        for i in 1..3 do s <- s + i; \
+                        s \
+                    end; \
+                    print(\"~\", sum(array(4, 10)))"),
+               "43");
+}
+
+#[test] fn result_is_null() {
+    assert_eq!(run("print(\"~\", for i in 0..1 do i)"), "null");
+}
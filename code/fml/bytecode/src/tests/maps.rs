@@ -0,0 +1,36 @@
+use super::run;
+
+#[test] fn literal() {
+    assert_eq!(run("print(\"~\", map(2 -> true, \"a\" -> 1, null -> 3))"),
+               "{null: 3, 2: true, a: 1}");
+}
+
+#[test] fn empty() {
+    assert_eq!(run("let m = map(); print(\"~ ~\", m, m.size())"), "{} 0");
+}
+
+#[test] fn get_set_has_remove() {
+    assert_eq!(run("let m = map(1 -> 2); \
+                    m.set(\"k\", 3); \
+                    print(\"~ ~ ~ \", m.get(\"k\"), m.get(7), m.has(1)); \
+                    print(\"~ ~ ~\", m.remove(1), m.has(1), m.size())"),
+               "3 null true 2 false 1");
+}
+
+#[test] fn indexing() {
+    assert_eq!(run("let m = map(); m[true] <- 42; print(\"~ ~\", m[true], m[false])"),
+               "42 null");
+}
+
+#[test] fn keys() {
+    assert_eq!(run("let m = map(\"b\" -> 1, \"a\" -> 2); print(\"~\", m.keys().get(0))"),
+               "a");
+}
+
+#[test] fn duplicate_keys() {
+    assert_eq!(run("print(\"~\", map(1 -> 1, 1 -> 2))"), "{1: 2}");
+}
+
+#[test] #[should_panic(expected = "cannot be used as a map key")] fn array_key() {
+    run("map(array(1, 0) -> 1)");
+}
@@ -0,0 +1,88 @@
+use super::run;
+
+#[test] fn match_literals() {
+    assert_eq!(run("function describe(x) -> match x with \
+                        0 -> \"zero\"; \
+                        true -> \"yes\"; \
+                        \"hi\" -> \"greeting\"; \
+                        null -> \"nothing\"; \
+                        _ -> \"other\" \
+                    end; \
+                    print(\"~ ~ ~ ~ ~\", describe(0), describe(true), describe(\"hi\"), \
+                                         describe(null), describe(7))"),
+               "zero yes greeting nothing other");
+}
+
+#[test] fn match_binding() {
+    assert_eq!(run("print(\"~\", match 5 with 0 -> 0; n -> n + 1 end)"), "6");
+}
+
+#[test] fn match_array() {
+    assert_eq!(run("function f(a) -> match a with \
+                        [] -> 0; \
+                        [x] -> x; \
+                        [0, y] -> y; \
+                        [x, y] -> x + y; \
+                        _ -> 100 \
+                    end; \
+                    let a = array(2, 0); a[1] <- 4; \
+                    let b = array(2, 3); \
+                    print(\"~ ~ ~ ~ ~\", f(array(0, 0)), f(array(1, 9)), f(a), f(b), f(array(3, 0)))"),
+               "0 9 4 6 100");
+}
+
+#[test] fn match_object() {
+    assert_eq!(run("function f(o) -> match o with \
+                        object(kind = \"point\", x, y) -> x * y; \
+                        object(x) -> x; \
+                        _ -> 0 \
+                    end; \
+                    let p = object begin let kind = \"point\"; let x = 2; let y = 3; end; \
+                    let q = object begin let x = 5; end; \
+                    print(\"~ ~ ~\", f(p), f(q), f(1))"),
+               "6 5 0");
+}
+
+#[test] fn match_nested() {
+    assert_eq!(run("let o = object begin let items = array(2, 1); end; \
+                    print(\"~\", match o with object(items = [a, b]) -> a + b end)"),
+               "2");
+}
+
+#[test] fn match_first_arm_wins() {
+    assert_eq!(run("print(\"~\", match 1 with x -> \"first\"; 1 -> \"second\" end)"), "first");
+}
+
+#[test] fn match_literal_compares_types() {
+    assert_eq!(run("function f(x) -> match x with 1 -> \"one\"; true -> \"yes\"; _ -> \"other\" end; \
+                    print(\"~ ~ ~\", f(1), f(1.0), f(\"true\"))"),
+               "one other other");
+}
+
+#[test] fn match_shapes_of_other_values() {
+    assert_eq!(run("function f(x) -> match x with [a] -> \"array\"; object(a) -> \"object\"; _ -> \"other\" end; \
+                    let p = object begin let a = 1; end; \
+                    let o = object extends p begin end; \
+                    print(\"~ ~ ~ ~\", f(array(1, 0)), f(p), f(o), f(\"a\"))"),
+               "array object other other");
+}
+
+#[test] fn match_ignores_user_length() {
+    assert_eq!(run("function length(x) -> 2; \
+                    print(\"~\", match array(1, 5) with [a, b] -> a; _ -> 0 end)"),
+               "0");
+}
+
+#[test] fn match_ignores_user_has_field() {
+    assert_eq!(run("let o = object begin let x = 1; function has_field(name) -> true; end; \
+                    print(\"~\", match o with object(y) -> y; object(x) -> x end)"),
+               "1");
+}
+
+#[test] #[should_panic(expected = "No pattern matches 3")] fn match_ignores_user_assert() {
+    run("function assert(x) -> null; match 3 with 4 -> 1 end");
+}
+
+#[test] #[should_panic(expected = "No pattern matches 1")] fn match_no_arm() {
+    run("match 1 with 2 -> null end");
+}
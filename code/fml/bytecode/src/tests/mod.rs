@@ -0,0 +1,59 @@
+// Programs compiled from source and run by the bytecode interpreter, grouped by the part of the
+// language they exercise. Every group runs its programs with the helpers below, which return what
+// the program printed.
+
+mod integers;
+mod maps;
+mod iteration;
+mod break_continue;
+mod matching;
+mod reflection;
+mod builtins;
+mod arrays;
+mod formats;
+mod classes;
+mod conformance;
+mod feeny;
+mod constants;
+mod modules;
+
+use fml_ast::AST;
+use fml_ast::integers::IntegerSemantics;
+use fml_parser::modules::ModuleLoader;
+
+use crate::compiler::compile_with_modules;
+use crate::interpreter::evaluate_with_input;
+
+// Runs the program with the given integer semantics and input, returning its output and the code
+// it exited with, if it called exit.
+fn execute(ast: &AST, modules: ModuleLoader, integers: IntegerSemantics, input: &'static str)
+           -> (String, Option<i32>) {
+    let program = compile_with_modules(ast, modules);
+    let mut output = String::new();
+    let exit_code = evaluate_with_input(&program, integers, input.as_bytes(), &mut output);
+    (output, exit_code)
+}
+
+fn parse(source: &str) -> AST {
+    fml_parser::parse(source).expect("Parse error")
+}
+
+pub fn run(source: &str) -> String {
+    run_ast(&parse(source))
+}
+
+pub fn run_ast(ast: &AST) -> String {
+    execute(ast, ModuleLoader::new(vec!()), IntegerSemantics::default(), "").0
+}
+
+pub fn run_with_integers(integers: IntegerSemantics, source: &str) -> String {
+    execute(&parse(source), ModuleLoader::new(vec!()), integers, "").0
+}
+
+pub fn run_with_input(source: &str, input: &'static str) -> (String, Option<i32>) {
+    execute(&parse(source), ModuleLoader::new(vec!()), IntegerSemantics::default(), input)
+}
+
+pub fn run_with_modules(modules: ModuleLoader, source: &str) -> String {
+    execute(&parse(source), modules, IntegerSemantics::default(), "").0
+}
@@ -0,0 +1,48 @@
+use fml_parser::modules::{ModuleLoader, ModuleDirectory};
+
+use super::run_with_modules;
+
+fn run(directory: &ModuleDirectory, source: &str) -> String {
+    run_with_modules(ModuleLoader::new(vec!(directory.path())), source)
+}
+
+#[test] fn import_function() {
+    let directory = ModuleDirectory::new("function", vec!(
+        ("util.fml", "function max(a, b) -> if a > b then a else b")));
+    assert_eq!(run(&directory, "import \"util.fml\"; print(\"~\\n\", util.max(2, 5))"), "5\n");
+}
+
+#[test] fn import_with_alias() {
+    let directory = ModuleDirectory::new("alias", vec!(
+        ("util.fml", "let counter = 40; function bump() -> counter <- counter + 1")));
+    assert_eq!(run(&directory, "import \"util.fml\" as u; u.bump(); u.bump(); print(\"~\\n\", u.counter)"), "42\n");
+}
+
+#[test] fn import_class() {
+    let directory = ModuleDirectory::new("class", vec!(
+        ("shapes.fml", "class Square(side) begin let side = side; function area() -> this.side * this.side end")));
+    assert_eq!(run(&directory, "import \"shapes.fml\" as s; print(\"~\", new s.Square(3).area())"), "9");
+}
+
+#[test] fn import_does_not_clash_with_main() {
+    let directory = ModuleDirectory::new("clash", vec!(
+        ("util.fml", "let x = 1; function f() -> x")));
+    assert_eq!(run(&directory, "import \"util.fml\"; let x = 2; function f() -> x; print(\"~ ~\", f(), util.f())"), "2 1");
+}
+
+#[test] fn import_evaluated_once() {
+    let directory = ModuleDirectory::new("once", vec!(
+        ("a.fml", "import \"c.fml\""), ("b.fml", "import \"c.fml\""), ("c.fml", "print(\"c\")")));
+    assert_eq!(run(&directory, "import \"a.fml\"; import \"b.fml\"; import \"c.fml\""), "c");
+}
+
+#[test] #[should_panic(expected = "Import cycle")] fn import_cycle() {
+    let directory = ModuleDirectory::new("cycle", vec!(
+        ("a.fml", "import \"b.fml\""), ("b.fml", "import \"a.fml\"")));
+    run(&directory, "import \"a.fml\"");
+}
+
+#[test] #[should_panic(expected = "only be imported at the top level")] fn import_in_function() {
+    let directory = ModuleDirectory::new("nested", vec!(("util.fml", "null")));
+    run(&directory, "function f() -> begin import \"util.fml\" end; f()");
+}
@@ -0,0 +1,52 @@
+use super::run;
+
+#[test] fn fields_and_methods() {
+    assert_eq!(run("let o = object begin let y = 2; let x = 1; function f() -> 0; function g(a) -> a; end; \
+                    let fs = o.fields(); let ms = o.methods(); \
+                    print(\"~~ ~~\", fs[0], fs[1], ms[0], ms[1])"),
+               "xy fg");
+}
+
+#[test] fn has_field() {
+    assert_eq!(run("let o = object begin let x = 1; end; \
+                    print(\"~ ~\", o.has_field(\"x\"), o.has_field(\"y\"))"),
+               "true false");
+}
+
+#[test] fn get_and_set() {
+    assert_eq!(run("let o = object begin let x = 1; end; \
+                    o.set(\"x\", o.get(\"x\") + 41); \
+                    print(\"~\", o.x)"),
+               "42");
+}
+
+#[test] fn parent() {
+    assert_eq!(run("let p = object begin let x = 1; end; \
+                    let o = object extends p begin let y = 2; end; \
+                    print(\"~ ~ ~\", o.parent().x, o.fields()[0], p.parent())"),
+               "1 y null");
+}
+
+#[test] fn reflection_on_object_extending_value() {
+    assert_eq!(run("let o = object extends array(2, 0) begin let x = 1; end; \
+                    print(\"~ ~ ~\", o.has_field(\"x\"), o.fields()[0], o.get(\"x\"))"),
+               "true x 1");
+}
+
+#[test] fn generic_serializer() {
+    assert_eq!(run("function show(o) -> begin \
+                        for field in o.fields() do print(\"~=~;\", field, o.get(field)); \
+                    end; \
+                    show(object begin let a = 1; let b = true; end)"),
+               "a=1;b=true;");
+}
+
+#[test] fn user_method_takes_precedence() {
+    assert_eq!(run("let o = object begin let x = 1; function get(name) -> 7; end; \
+                    print(\"~\", o.get(\"x\"))"),
+               "7");
+}
+
+#[test] #[should_panic(expected = "no field z")] fn get_missing_field() {
+    run("let o = object begin let x = 1; end; o.get(\"z\")");
+}
@@ -5,7 +5,7 @@ use std::io::{Read, Write};
 #[derive(PartialEq,Debug,Copy,Clone)] pub struct Arity(u8);
 #[derive(PartialEq,Debug,Copy,Clone)] pub struct Size(u16);
 #[derive(PartialEq,Debug,Copy,Clone)] pub struct Address(u32);
-#[derive(PartialEq,Debug,Copy,Clone)] pub struct ConstantPoolIndex(u16);
+#[derive(PartialEq,Eq,Hash,Debug,Copy,Clone)] pub struct ConstantPoolIndex(u16);
 #[derive(PartialEq,Debug,Copy,Clone)] pub struct LocalFrameIndex(u16);
 #[derive(PartialEq,Debug,Copy,Clone)] pub struct AddressRange { start: Address, length: usize }
 
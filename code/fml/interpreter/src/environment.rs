@@ -1,14 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error;
 use std::fmt;
 
 use crate::heap::{Reference, FunctionReference};
+use fml_parser::modules::ModuleLoader;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum EnvironmentError {
     Undefined (String),
     AlreadyDefined (String),
     NotFound (String),
+    Constant (String),
     LastEnvironment,
     Impossible,
 }
@@ -16,6 +18,7 @@ pub enum EnvironmentError {
 macro_rules! undefined_error {($name:expr) => { Err(EnvironmentError::Undefined($name)) }}
 macro_rules! already_defined_error {($name:expr) => { Err(EnvironmentError::AlreadyDefined($name)) }}
 macro_rules! not_found_error {($name:expr) => { Err(EnvironmentError::NotFound($name.to_string())) }}
+macro_rules! constant_error {($name:expr) => { Err(EnvironmentError::Constant($name)) }}
 //macro_rules! last_environment_error {() => { Err(EnvironmentError::LastEnvironment) }}
 //macro_rules! impossible_error {() => { Err(EnvironmentError::Impossible) }}
 macro_rules! all_good{() => { Ok(()) }}
@@ -29,6 +32,8 @@ impl fmt::Display for EnvironmentError {
                 write!(f, "Attempt to define a binding that has already been defined {}", binding),
             EnvironmentError::NotFound(binding) =>
                 write!(f, "Attempt to look up a binding that not been defined {}", binding),
+            EnvironmentError::Constant(binding) =>
+                write!(f, "Attempt to redefine a binding that was defined as a constant {}", binding),
             EnvironmentError::Impossible =>
                 write!(f, "This should never happen"),
             EnvironmentError::LastEnvironment =>
@@ -41,21 +46,31 @@ impl error::Error for EnvironmentError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> { None }
 }
 
+// Raised by break and continue, and cleared by the innermost enclosing loop. While an escape is
+// pending, blocks stop evaluating their remaining expressions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Escape {
+    Break,
+    Continue,
+}
+
 #[derive(Debug)]
 struct Frame {
     id: usize,
     parent: Option<usize>,
+    in_loop: bool,
     bindings: HashMap<String, Reference>,
+    constants: HashSet<String>,
     functions: HashMap<String, FunctionReference>,
 }
 
 impl Frame {
     fn top() -> Frame {
-        Frame { id: 0, bindings: HashMap::new(), functions: HashMap::new(), parent: None }
+        Frame { id: 0, bindings: HashMap::new(), constants: HashSet::new(), functions: HashMap::new(), parent: None, in_loop: false }
     }
 
-    fn new(id: usize, parent: usize) -> Frame {
-        Frame { id, bindings: HashMap::new(), functions: HashMap::new(), parent: Some(parent) }
+    fn new(id: usize, parent: usize, in_loop: bool) -> Frame {
+        Frame { id, bindings: HashMap::new(), constants: HashSet::new(), functions: HashMap::new(), parent: Some(parent), in_loop }
     }
 
     fn contains_binding(&self, name: &str) -> bool {
@@ -97,6 +112,13 @@ impl Frame {
         all_good!()
     }
 
+    fn register_constant(&mut self, name: String, object: Reference) -> Result<(), EnvironmentError> {
+        self.register_binding(name.clone(), object)?;
+        self.constants.insert(name);
+
+        all_good!()
+    }
+
     fn register_function(&mut self, name: String, object: FunctionReference) -> Result<(), EnvironmentError> {
         if self.contains_function(&name) { return already_defined_error!(name) }
 
@@ -108,6 +130,7 @@ impl Frame {
 
     fn change_binding(&mut self, name: String, object: Reference) -> Result<(), EnvironmentError> {
         if !self.contains_binding(&name) { return undefined_error!(name) }
+        if self.constants.contains(&name) { return constant_error!(name) }
 
         let result = self.bindings.insert(name, object);
         assert!(result.is_some());
@@ -132,11 +155,41 @@ enum LookupFunctionResult<'a> {
 pub struct EnvironmentStack {
     id_sequence: usize,
     frames: Vec<Frame>,
+    modules: ModuleLoader,
+    escape: Option<Escape>,
 }
 
 impl EnvironmentStack {
     pub fn new() -> EnvironmentStack {
-        EnvironmentStack { id_sequence: 1, frames: vec!(Frame::top()) }
+        EnvironmentStack::with_modules(ModuleLoader::new(vec!()))
+    }
+
+    pub fn with_modules(modules: ModuleLoader) -> EnvironmentStack {
+        EnvironmentStack { id_sequence: 1, frames: vec!(Frame::top()), modules, escape: None }
+    }
+
+    pub fn modules_mut(&mut self) -> &mut ModuleLoader {
+        &mut self.modules
+    }
+
+    pub fn in_top_frame(&self) -> bool {
+        self.frames.len() == 1
+    }
+
+    pub fn in_loop(&self) -> bool {
+        self.frames.last().expect("Invalid stack: empty").in_loop
+    }
+
+    pub fn signal_escape(&mut self, escape: Escape) {
+        self.escape = Some(escape)
+    }
+
+    pub fn is_escaping(&self) -> bool {
+        self.escape.is_some()
+    }
+
+    pub fn take_escape(&mut self) -> Option<Escape> {
+        self.escape.take()
     }
 
     pub fn next_id(&mut self) -> usize {
@@ -190,24 +243,46 @@ impl EnvironmentStack {
         self.frames.last_mut().unwrap().register_binding(name, object)
     }
 
+    pub fn register_constant(&mut self, name: String, object: Reference) -> Result<(), EnvironmentError>  {
+        self.frames.last_mut().unwrap().register_constant(name, object)
+    }
+
     pub fn register_function(&mut self, name: String, object: FunctionReference) -> Result<(), EnvironmentError>  {
         self.frames.last_mut().unwrap().register_function(name, object)
     }
 
+    // Changes the binding in the innermost frame that defines it, like `lookup_binding` finds it.
     pub fn change_binding(&mut self, name: String, object: Reference) -> Result<(), EnvironmentError>  {
-        self.frames.last_mut().unwrap().change_binding(name, object)
+        let mut cursor = self.frames.last().expect("Invalid stack: empty").id;
+        loop {
+            let frame = self.frames.get(cursor)
+                .expect(&format!("Invalid stack frame: {}", cursor));
+            match frame.lookup_binding(&name) {
+                LookupResult::Found(_) => break,
+                LookupResult::KeepLooking(id) => cursor = id,
+                LookupResult::NotFound => return undefined_error!(name),
+            }
+        }
+        self.frames.get_mut(cursor).unwrap().change_binding(name, object)
     }
 
     pub fn add_soft_frame(&mut self) {
+        let parent = self.frames.last().expect("Invalid stack: empty");
+        let (parent, in_loop) = (parent.id, parent.in_loop);
+        let id = self.next_id();
+        self.frames.push(Frame::new(id, parent, in_loop));
+    }
+
+    pub fn add_loop_frame(&mut self) {
         let parent = self.frames.last().expect("Invalid stack: empty").id;
         let id = self.next_id();
-        self.frames.push(Frame::new(id, parent));
+        self.frames.push(Frame::new(id, parent, true));
     }
 
     pub fn add_hard_frame(&mut self) {
         let parent = self.frames.first().expect("Invalid stack: empty").id;
         let id = self.next_id();
-        self.frames.push(Frame::new(id, parent));
+        self.frames.push(Frame::new(id, parent, false));
     }
 
     pub fn remove_frame(&mut self) {
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, BTreeMap};
 use fml_ast::AST;
+use fml_ast::integers::{IntegerSemantics, Integer, BigInt};
 
 // https://dev.to/deciduously/rust-your-own-lisp-50an
 // https://github.com/kenpratt/rusty_scheme
@@ -9,26 +10,48 @@ pub enum Instance {
     Object {
         extends: Option<Reference>,
         fields: HashMap<String, Reference>,
-        methods: HashMap<String, FunctionReference>
+        methods: HashMap<String, FunctionReference>,
+        constants: HashSet<String>,
     },
     Array {
         size: usize,
         values: Vec<Reference>,
     },
+    BigInteger(BigInt),
+    String(String),
+    Map(BTreeMap<MapKey, Reference>),
 }
 
 impl Instance {
     pub fn empty() -> Instance {
-        Instance::Object {extends: None, fields: HashMap::new(), methods: HashMap::new()}
+        Instance::Object {extends: None, fields: HashMap::new(), methods: HashMap::new(), constants: HashSet::new()}
     }
     pub fn object(extends: Option<Reference>,
                   fields: HashMap<String, Reference>,
                   methods: HashMap<String, FunctionReference>) -> Instance {
-        Instance::Object {extends, fields, methods}
+        Instance::Object {extends, fields, methods, constants: HashSet::new()}
+    }
+    pub fn object_with_constants(extends: Option<Reference>,
+                                 fields: HashMap<String, Reference>,
+                                 methods: HashMap<String, FunctionReference>,
+                                 constants: HashSet<String>) -> Instance {
+        Instance::Object {extends, fields, methods, constants}
     }
     pub fn array(elements: Vec<Reference>) -> Instance {
         Instance::Array {size: elements.len(), values: elements}
     }
+    pub fn map(entries: BTreeMap<MapKey, Reference>) -> Instance {
+        Instance::Map(entries)
+    }
+}
+
+// Map keys are stored by value, and ordered so that printing a map is deterministic.
+#[derive(Clone, Debug, PartialOrd, Ord, PartialEq, Eq)]
+pub enum MapKey {
+    Unit,
+    Boolean(bool),
+    Integer(Integer),
+    String(String),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -57,13 +80,24 @@ pub struct StringReference(u64);
 pub enum Reference {
     Unit,
     Object(u64),
-    Integer(i32),
+    Integer(i64),
+    BigInteger(u64), // integers that do not fit into 64 bits live in memory
+    Float(u64), // bit pattern of an f64, so references stay Eq and Hash
     Boolean(bool),
-    Array {reference: u64, size: usize}
+    String(u64),
+    Array {reference: u64, size: usize},
+    Map(u64),
+}
+
+impl Reference {
+    pub fn from_f64(value: f64) -> Reference {
+        Reference::Float(value.to_bits())
+    }
 }
 
 #[derive(Debug)]
 pub struct Memory {
+    integers: IntegerSemantics,
     sequence: ReferenceSequence,
     objects: HashMap<Reference, Instance>,
     functions: HashMap<FunctionReference, Function>,
@@ -88,17 +122,94 @@ impl ReferenceSequence {
         self.0 += 1;
         Reference::Array {reference: n, size}
     }
+    fn next_big_integer(&mut self) -> Reference {
+        let n = self.0;
+        self.0 += 1;
+        Reference::BigInteger(n)
+    }
+    fn next_string(&mut self) -> Reference {
+        let n = self.0;
+        self.0 += 1;
+        Reference::String(n)
+    }
+    fn next_map(&mut self) -> Reference {
+        let n = self.0;
+        self.0 += 1;
+        Reference::Map(n)
+    }
 }
 
 impl Memory {
     pub fn new() -> Memory {
+        Memory::with_integer_semantics(IntegerSemantics::default())
+    }
+
+    pub fn with_integer_semantics(integers: IntegerSemantics) -> Memory {
         Memory {
+            integers,
             sequence: ReferenceSequence(0),
             objects: HashMap::new(),
             functions: HashMap::new(),
         }
     }
 
+    pub fn integers(&self) -> IntegerSemantics {
+        self.integers
+    }
+
+    pub fn put_integer(&mut self, integer: Integer) -> Reference {
+        match integer {
+            Integer::Small(n) => Reference::Integer(n),
+            Integer::Big(n) => self.put_object(Instance::BigInteger(n)),
+        }
+    }
+
+    pub fn get_integer(&self, reference: &Reference) -> Option<Integer> {
+        match reference {
+            Reference::Integer(n) => Some(Integer::Small(*n)),
+            Reference::BigInteger(_) => match self.get_object(reference) {
+                Some(Instance::BigInteger(n)) => Some(Integer::Big(n.clone())),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn get_f64(&self, reference: &Reference) -> Option<f64> {
+        match reference {
+            Reference::Float(bits) => Some(f64::from_bits(*bits)),
+            _ => self.get_integer(reference).map(|integer| integer.to_f64()),
+        }
+    }
+
+    pub fn get_string(&self, reference: &Reference) -> Option<&str> {
+        match self.get_object(reference) {
+            Some(Instance::String(string)) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn get_map_key(&self, reference: &Reference) -> Option<MapKey> {
+        match reference {
+            Reference::Unit => Some(MapKey::Unit),
+            Reference::Boolean(b) => Some(MapKey::Boolean(*b)),
+            Reference::Integer(_) | Reference::BigInteger(_) =>
+                self.get_integer(reference).map(MapKey::Integer),
+            Reference::String(_) =>
+                self.get_string(reference).map(|string| MapKey::String(string.to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn put_map_key(&mut self, key: MapKey) -> Reference {
+        match key {
+            MapKey::Unit => Reference::Unit,
+            MapKey::Boolean(b) => Reference::Boolean(b),
+            MapKey::Integer(integer) => self.put_integer(integer),
+            MapKey::String(string) => self.put_object(Instance::String(string)),
+        }
+    }
+
     pub fn contains_object(&self, reference: &Reference) -> bool {
         self.objects.contains_key(reference)
     }
@@ -132,11 +243,26 @@ impl Memory {
                 self.objects.insert(reference, object);
                 reference
             },
-            Instance::Object {extends:_, fields:_, methods:_} => {
+            Instance::Object {extends:_, fields:_, methods:_, constants:_} => {
                 let reference = self.sequence.next_object();
                 self.objects.insert(reference, object);
                 reference
             },
+            Instance::BigInteger(_) => {
+                let reference = self.sequence.next_big_integer();
+                self.objects.insert(reference, object);
+                reference
+            },
+            Instance::String(_) => {
+                let reference = self.sequence.next_string();
+                self.objects.insert(reference, object);
+                reference
+            },
+            Instance::Map(_) => {
+                let reference = self.sequence.next_map();
+                self.objects.insert(reference, object);
+                reference
+            },
         }
     }
 
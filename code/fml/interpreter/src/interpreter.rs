@@ -1,10 +1,20 @@
 use fml_ast::AST;
 use fml_ast::Identifier;
+use fml_ast::Pattern;
 use crate::world::World;
-use crate::environment::EnvironmentStack;
-use crate::heap::{Memory, Function, Reference, Instance, FunctionReference};
+use crate::environment::{EnvironmentStack, Escape};
+use crate::heap::{Memory, Function, Reference, Instance, FunctionReference, MapKey};
+use fml_parser::modules::Loaded;
+use fml_parser::checker::builtin_arity;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::cmp::Ordering;
+use fml_ast::integers::Integer;
+use fml_ast::format::{Format, Value};
+use fml_ast::hashing::Hasher;
+
+// Binding of the object that defines the running method; it cannot be named in FML code.
+const HOST: &str = "::host";
 
 //macro_rules! extract_identifier_token {
 //    ($ast:expr) => {
@@ -25,13 +35,12 @@ macro_rules! construct_function_definition {
     }
 }
 
+// Negative indices are out of bounds, just like in the bytecode backend.
 macro_rules! extract_array_offset {
     ($array_instance:expr, $index_value:expr) => {
         match $array_instance {
             Instance::Array {size, values:_} if $index_value >= 0 && ($index_value as usize) < *size =>
                 $index_value as usize,
-            Instance::Array {size, values} if $index_value <  0 && ($index_value.abs() as usize) < *size =>
-                values.len() - 1 + $index_value.abs() as usize,
             Instance::Array {size:_, values:_} =>
                 panic!("Array index out of bounds: {}", $index_value),
             _ => panic!("Attempt to index a non-array object"),
@@ -47,7 +56,7 @@ macro_rules! find_actual_host_object {
                 let object_instance: &Instance =
                     $memory.get_object(&cursor).expect("Could not find object instance");
                 match object_instance {
-                    Instance::Object { extends, fields, methods:_} => {
+                    Instance::Object { extends, fields, methods:_, constants:_} => {
                         if fields.contains_key(&$field_name) {
                             break;
                         }
@@ -73,7 +82,7 @@ macro_rules! find_actual_host_object_for_method {
                 let object_instance: &Instance =
                     $memory.get_object(&cursor).expect("Could not find object instance");
                 match object_instance {
-                    Instance::Object { extends, fields:_, methods} => {
+                    Instance::Object { extends, fields:_, methods, constants:_} => {
                         if methods.contains_key(&$method_name) {
                             break;
                         }
@@ -109,6 +118,25 @@ pub fn hard_evaluate (stack: &mut EnvironmentStack, memory: &mut Memory, world:
     value
 }
 
+// Evaluates one pass of a loop body. Returns false if the body broke out of the loop.
+pub fn loop_evaluate (stack: &mut EnvironmentStack, memory: &mut Memory, world: &mut impl World, bindings: Vec<(String, Reference)>, expression: &AST) -> bool {
+    stack.add_loop_frame();
+    bindings.into_iter().for_each(|binding| {
+        let (name, object) = binding;
+        stack.register_binding(name, object).expect("Cannot register binding for loop variable");
+    });
+    let _ = evaluate(stack, memory, world, expression);
+    stack.remove_frame();
+    stack.take_escape() != Some(Escape::Break)
+}
+
+pub fn evaluate_with_modules (stack: &mut EnvironmentStack, memory: &mut Memory,
+                              world: &mut impl World, program: &AST) -> Reference {
+    let program = stack.modules_mut().link(program)
+        .unwrap_or_else(|e| panic!("Import error: {}", e));
+    evaluate(stack, memory, world, &program)
+}
+
 pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
                  world: &mut impl World, expression: &AST) -> Reference {
 
@@ -121,30 +149,54 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
 
     match expression {
 
-        AST::LocalDefinition {local: Identifier(local), value} => {
+        AST::VariableDefinition {name: Identifier(local), value} => {
             let reference = soft_evaluate(stack, memory, world, &*value);
             stack.register_binding(local.to_string(), reference).expect("Cannot register binding");
             Reference::Unit
         },
 
-        AST::LocalMutation {local: Identifier(local), value} => {
+        AST::ConstantDefinition {name: Identifier(name), value} => {
+            let reference = soft_evaluate(stack, memory, world, &*value);
+            stack.register_constant(name.to_string(), reference).expect("Cannot register constant");
+            Reference::Unit
+        },
+
+        AST::VariableMutation {name: Identifier(local), value} => {
             let reference = soft_evaluate(stack, memory, world, &*value);
             stack.change_binding(local.to_string(), reference).expect("Cannot modify binding");
             Reference::Unit
         }
 
-        AST::LocalAccess {local: Identifier(local)} => {
+        AST::VariableAccess {name: Identifier(local)} => {
             *stack.lookup_binding(&local).expect("Cannot resolve identifier")
         },
 
-        AST::Number(n) => Reference::Integer(*n),
+        AST::Number(n) => {
+            let integer = memory.integers().literal(*n)
+                .unwrap_or_else(|e| panic!("Literal error: {}", e));
+            memory.put_integer(integer)
+        },
+        AST::Float(f) => Reference::from_f64(*f),
         AST::Boolean(b) => Reference::Boolean(*b),
+        AST::String(string) => memory.put_object(Instance::String(string.to_string())),
         AST::Unit => Reference::Unit,
 
+        // Top-level definitions stay in the frame the program is run in.
+        AST::Top(expressions) => {
+            let mut reference = Reference::Unit;
+            for expression in expressions {
+                reference = evaluate(stack, memory, world, &*expression);
+            }
+            reference
+        },
+
         AST::Block(expressions) => {
             let mut reference = Reference::Unit;
             for expression in expressions {
-                reference = evaluate(stack, memory, world, &*expression)
+                reference = evaluate(stack, memory, world, &*expression);
+                if stack.is_escaping() {
+                    return Reference::Unit
+                }
             }
             reference
         },
@@ -162,6 +214,26 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
             soft_evaluate(stack, memory, world, &*next_expression)
         },
 
+        AST::Match { value, arms } => {
+            let value_reference = soft_evaluate(stack, memory, world, &*value);
+
+            for (pattern, body) in arms {
+                let mut bindings: Vec<(String, Reference)> = Vec::new();
+                if match_pattern(memory, pattern, value_reference, &mut bindings) {
+                    stack.add_soft_frame();
+                    bindings.into_iter().for_each(|binding| {
+                        let (name, object) = binding;
+                        stack.register_binding(name, object).expect("Cannot register binding for pattern variable");
+                    });
+                    let reference = evaluate(stack, memory, world, &*body);
+                    stack.remove_frame();
+                    return reference
+                }
+            }
+
+            panic!("No pattern matches {}", evaluate_to_string(memory, value_reference))
+        }
+
         AST::FunctionDefinition { function: Identifier(function), body, parameters } => {
             let function_definition = construct_function_definition!(function, parameters, body);
             let function_reference = memory.put_function(function_definition);
@@ -169,17 +241,101 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
             Reference::Unit
         }
 
+        // A class is a constructor function that builds an object from the class members.
+        AST::ClassDefinition { name, parameters, extends, members } => {
+            let constructor = AST::FunctionDefinition {
+                function: name.to_constructor(),
+                parameters: parameters.clone(),
+                body: Box::new(AST::ObjectDefinition { extends: extends.clone(), members: members.clone() }),
+            };
+            evaluate(stack, memory, world, &constructor)
+        }
+
+        AST::Instantiation { class, arguments } => {
+            let call = AST::FunctionCall { function: class.to_constructor(), arguments: arguments.clone() };
+            evaluate(stack, memory, world, &call)
+        }
+
         AST::Loop { condition, body } => {
-            let condition_reference = soft_evaluate(stack, memory, world, &*condition);
+            while evaluate_to_boolean(soft_evaluate(stack, memory, world, &*condition)) {
+                if !loop_evaluate(stack, memory, world, vec!(), &*body) {
+                    break
+                }
+            }
 
-            while evaluate_to_boolean(condition_reference) {
-                let _ = soft_evaluate(stack, memory, world, &*body);
+            Reference::Unit
+        }
+
+        AST::Break => {
+            if !stack.in_loop() {
+                panic!("Break can only occur inside a loop")
             }
+            stack.signal_escape(Escape::Break);
+            Reference::Unit
+        }
 
+        AST::Continue => {
+            if !stack.in_loop() {
+                panic!("Continue can only occur inside a loop")
+            }
+            stack.signal_escape(Escape::Continue);
             Reference::Unit
         }
 
-        AST::FunctionApplication {function: Identifier(function), arguments} => {
+        AST::Iteration { variable: Identifier(variable), iterable, body } => {
+            // Array elements are looked up as the loop goes, so the body sees its own writes to
+            // the array, just like in the bytecode backend.
+            let (array_reference, start, end) = match &**iterable {
+                AST::Range { start, end } => {
+                    let start_reference = soft_evaluate(stack, memory, world, &*start);
+                    let end_reference = soft_evaluate(stack, memory, world, &*end);
+                    match (start_reference, end_reference) {
+                        (Reference::Integer(start), Reference::Integer(end)) => (None, start, end),
+                        _ => panic!("Cannot iterate over range from {:?} to {:?}",
+                                    start_reference, end_reference),
+                    }
+                }
+                _ => match soft_evaluate(stack, memory, world, &*iterable) {
+                    // The size in the reference is stale if the array grew since it was created.
+                    Reference::Array {reference, size} => match memory.get_object(&Reference::Array {reference, size}) {
+                        Some(Instance::Array {size:_, values}) =>
+                            (Some(Reference::Array {reference, size}), 0, values.len() as i64),
+                        _ => panic!("Could not find array instance"),
+                    },
+                    reference => panic!("Cannot iterate over non-array object {:?}", reference),
+                }
+            };
+
+            for index in start..end {
+                let element = match array_reference {
+                    Some(array_reference) => match memory.get_object(&array_reference) {
+                        Some(Instance::Array {size:_, values}) => values[index as usize],
+                        _ => panic!("Could not find array instance"),
+                    },
+                    None => Reference::Integer(index),
+                };
+
+                if !loop_evaluate(stack, memory, world, vec!((variable.to_string(), element)), &*body) {
+                    break
+                }
+            }
+
+            Reference::Unit
+        }
+
+        AST::Range {start:_, end:_} => {
+            panic!("Ranges can only be iterated over by for loops")
+        }
+
+        AST::FunctionCall {function: Identifier(function), arguments} => {
+            // Functions defined by the program shadow the built-in ones.
+            if stack.lookup_function(&function).is_err() && builtin_arity(function).is_some() {
+                let argument_references: Vec<Reference> = arguments.iter()
+                    .map(|argument| soft_evaluate(stack, memory, world, &*argument))
+                    .collect();
+                return evaluate_builtin_function(memory, world, &function, argument_references)
+            }
+
             let function_reference = stack.lookup_function(&function)
                 .expect(&format!("Function {} not found on stack", function));
 
@@ -220,14 +376,34 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
             memory.put_object(Instance::array(elements))
         }
 
+        AST::MapDefinition {entries} => {
+            let mut map: BTreeMap<MapKey, Reference> = BTreeMap::new();
+            for (key, value) in entries.iter() {
+                let key_reference = soft_evaluate(stack, memory, world, &*key);
+                let value_reference = soft_evaluate(stack, memory, world, &*value);
+                let key = memory.get_map_key(&key_reference)
+                    .expect(&format!("Cannot use {:?} as a map key", key_reference));
+                map.insert(key, value_reference);
+            }
+            memory.put_object(Instance::map(map))
+        }
+
         AST::ArrayAccess {array, index} => {
             let index_reference = soft_evaluate(stack, memory, world, &*index);
+            let array_reference = soft_evaluate(stack, memory, world, &*array);
+            if let Reference::Map(_) = array_reference {
+                return evaluate_map_method(memory, array_reference, "get", vec!(index_reference))
+            }
+            // Indexing an object calls its get method, like indexing an array does.
+            if let Reference::Object(_) = array_reference {
+                return evaluate_method_call(stack, memory, world, array_reference, "get".to_string(), vec!(index_reference))
+            }
+
             let index_value = match index_reference {
                 Reference::Integer(n) => n,
                 _ => panic!("Cannot convert {:?} to integer", index_reference),
             };
 
-            let array_reference = soft_evaluate(stack, memory, world, &*array);
             let array_instance: &Instance =
                 memory.get_object(&array_reference).expect("Could not find array instance");
 
@@ -244,20 +420,23 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
         }
 
         AST::ArrayMutation {array, index, value} => {
-            let (array, index) = match &**array {
-                AST::ArrayAccess {array, index} => (array, index),
-                _ => panic!("Cannot mutate non-array object"),
-            };
-
             let index_reference = soft_evaluate(stack, memory, world, &*index);
+            let value_reference = soft_evaluate(stack, memory, world, &*value);
+            let array_reference = soft_evaluate(stack, memory, world, &*array);
+
+            if let Reference::Map(_) = array_reference {
+                evaluate_map_method(memory, array_reference, "set", vec!(index_reference, value_reference));
+                return Reference::Unit
+            }
+            if let Reference::Object(_) = array_reference {
+                return evaluate_method_call(stack, memory, world, array_reference, "set".to_string(),
+                                            vec!(index_reference, value_reference))
+            }
+
             let index_value = match index_reference {
                 Reference::Integer(n) => n,
                 _ => panic!("Cannot convert {:?} to integer", index_reference),
             };
-
-            let value_reference = soft_evaluate(stack, memory, world, &*value);
-
-            let array_reference = soft_evaluate(stack, memory, world, &*array);
             let array_instance: &Instance =
                 memory.get_object(&array_reference).expect("Could not find array instance");
 
@@ -268,7 +447,7 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
 
             match array_instance_mut {
                 Instance::Array {size:_, values} => {
-                    values.insert(offset, value_reference)
+                    values[offset] = value_reference
                 }
                 _ => panic!("Attempt to index a non-array object")
             };
@@ -284,12 +463,18 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
 
             let mut fields: HashMap<String, Reference> = HashMap::new();
             let mut methods: HashMap<String, FunctionReference> = HashMap::new();
+            let mut constants: HashSet<String> = HashSet::new();
             for member in members.iter() {
                 match &**member {
-                    AST::LocalDefinition {local: Identifier(local), value} => {
+                    AST::VariableDefinition {name: Identifier(local), value} => {
                         let definition_reference = soft_evaluate(stack, memory, world, &*value);
                         fields.insert(local.to_string(), definition_reference);
                     },
+                    AST::ConstantDefinition {name: Identifier(name), value} => {
+                        let definition_reference = soft_evaluate(stack, memory, world, &*value);
+                        fields.insert(name.to_string(), definition_reference);
+                        constants.insert(name.to_string());
+                    },
                     AST::FunctionDefinition {function: Identifier(function), parameters, body} => {
                         let function_definition = construct_function_definition!(function, parameters, body);
                         let function_reference = memory.put_function(function_definition);
@@ -305,7 +490,7 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
                 }
             }
 
-            let object_instance = Instance::object(super_object_reference, fields, methods);
+            let object_instance = Instance::object_with_constants(super_object_reference, fields, methods, constants);
             memory.put_object(object_instance)
         },
 
@@ -316,17 +501,12 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
             let actual_instance = memory.get_object(&actual_reference).expect("Could not find object instance");
 
             match actual_instance {
-                Instance::Object { extends:_, fields, methods:_ } => *fields.get(&**field).unwrap(),
+                Instance::Object { extends:_, fields, methods:_, constants:_ } => *fields.get(&**field).unwrap(),
                 _ => panic!("Fatal inconsistency in instance store.")
             }
         }
 
-        AST::FieldMutation {field_path, value} => {
-            let (object, field) = match &**field_path {
-                AST::FieldAccess {object, field: Identifier(field)} => (object, field),
-                _ => panic!("Cannot mutate non-array object"),
-            };
-
+        AST::FieldMutation {object, field: Identifier(field), value} => {
             let value_reference = soft_evaluate(stack, memory, world, &**value);
 
             let object_reference = soft_evaluate(stack, memory, world, &*object);
@@ -335,7 +515,10 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
             let actual_instance = memory.get_object_mut(&actual_reference).expect("Could not find object instance");
 
             match actual_instance {
-                Instance::Object { extends:_, fields, methods: _ } => {
+                Instance::Object { extends:_, fields, methods: _, constants } => {
+                    if constants.contains(&**field) {
+                        panic!("Field {} is constant and cannot be changed", field)
+                    }
                     fields.insert(field.to_string(), value_reference);
                 },
                 _ => panic!("Fatal inconsistency in instance store.")
@@ -344,13 +527,17 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
             Reference::Unit
         },
 
-        AST::MethodCall {method_path, arguments} => {
-            let (object, method_name) = match &**method_path {
-                AST::FieldAccess {object, field: Identifier(field)} => (object, field.to_string()),
-                AST::OperatorAccess {object, operator} => (object, operator.to_string()),
-                _ => panic!("Cannot call method on a non-object"),
-            };
+        AST::MethodCall {object, method: Identifier(method), arguments} => {
+            let object_reference = soft_evaluate(stack, memory, world, &*object);
+            let argument_references: Vec<Reference> = arguments.iter().map(|expression| {
+                soft_evaluate(stack, memory, world, &(**expression).clone())
+            }).collect();
+
+            evaluate_method_call(stack, memory, world, object_reference, method.to_string(), argument_references)
+        }
 
+        AST::OperatorCall {object, operator, arguments} => {
+            let method_name = operator.to_string();
             let object_reference = soft_evaluate(stack, memory, world, &*object);
             let argument_references: Vec<Reference> = arguments.iter().map(|expression| {
                 soft_evaluate(stack, memory, world, &(**expression).clone())
@@ -359,6 +546,26 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
             evaluate_method_call(stack, memory, world, object_reference, method_name, argument_references)
         }
 
+        AST::SuperMethodCall {method: Identifier(method_name), arguments} => {
+            let method_name = method_name.to_string();
+            let object_reference = *stack.lookup_binding("this")
+                .expect(&format!("Super method {} can only be called inside a method", method_name));
+            let host_reference = *stack.lookup_binding(HOST)
+                .expect(&format!("Super method {} can only be called inside a method", method_name));
+
+            let parent_reference = match memory.get_object(&host_reference) {
+                Some(Instance::Object {extends: Some(parent), fields:_, methods:_, constants:_}) => *parent,
+                _ => panic!("Cannot call super method {}: object has no parent", method_name),
+            };
+
+            let argument_references: Vec<Reference> = arguments.iter().map(|expression| {
+                soft_evaluate(stack, memory, world, &(**expression).clone())
+            }).collect();
+
+            let actual_reference = find_actual_host_object_for_method!(memory, parent_reference, method_name);
+            invoke_method(stack, memory, world, actual_reference, object_reference, method_name, argument_references)
+        }
+
         AST::Operation {operator, left, right} => {
             use fml_ast::Operator::*;
 
@@ -366,20 +573,33 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
             let right_reference = soft_evaluate(stack, memory, world, &**right);
 
             let result = match (left_reference, operator, right_reference) {
-                (left_reference, Equality, right_reference) => Reference::Boolean(left_reference == right_reference),
-                (left_reference, Inequality, right_reference) => Reference::Boolean(left_reference != right_reference),
-
-                (Reference::Integer(left_value), Multiplication, Reference::Integer(right_value)) => Reference::Integer(left_value * right_value),
-                (Reference::Integer(left_value), Division, Reference::Integer(right_value)) => Reference::Integer(left_value / right_value),
-                (Reference::Integer(left_value), Module, Reference::Integer(right_value)) => Reference::Integer(left_value % right_value),
-                (Reference::Integer(left_value), Addition, Reference::Integer(right_value)) => Reference::Integer(left_value + right_value),
-                (Reference::Integer(left_value), Subtraction, Reference::Integer(right_value)) => Reference::Integer(left_value - right_value),
-                //(Reference::Integer(left_value), Inequality, Reference::Integer(right_value)) => Reference::Boolean(left_value != right_value),
-                //(Reference::Integer(left_value), Equality, Reference::Integer(right_value)) => Reference::Boolean(left_value == right_value),
-                (Reference::Integer(left_value), Less, Reference::Integer(right_value)) => Reference::Boolean(left_value < right_value),
-                (Reference::Integer(left_value), LessEqual, Reference::Integer(right_value)) => Reference::Boolean(left_value <= right_value),
-                (Reference::Integer(left_value), Greater, Reference::Integer(right_value)) => Reference::Boolean(left_value > right_value),
-                (Reference::Integer(left_value), GreaterEqual, Reference::Integer(right_value)) => Reference::Boolean(left_value >= right_value),
+                // Identity cannot be redefined.
+                (left_reference, Identity, right_reference) =>
+                    Reference::Boolean(references_identical(memory, left_reference, right_reference)),
+
+                (Reference::Float(_), _, _) | (_, _, Reference::Float(_))
+                    if memory.get_f64(&left_reference).is_some() && memory.get_f64(&right_reference).is_some() =>
+                    evaluate_float_operation(memory.get_f64(&left_reference).unwrap(), operator,
+                                             memory.get_f64(&right_reference).unwrap()),
+
+                (Reference::Integer(_), _, _) | (Reference::BigInteger(_), _, _)
+                    if memory.get_integer(&right_reference).is_some() =>
+                    evaluate_integer_operation(memory, left_reference, operator, right_reference),
+
+                (Reference::String(_), Equality, Reference::String(_)) =>
+                    Reference::Boolean(memory.get_string(&left_reference) == memory.get_string(&right_reference)),
+                (Reference::String(_), Inequality, Reference::String(_)) =>
+                    Reference::Boolean(memory.get_string(&left_reference) != memory.get_string(&right_reference)),
+
+                // Equality is structural unless the object defines its own.
+                (Reference::Object(_), Equality, _) | (Reference::Object(_), Inequality, _)
+                    if understands_method(memory, left_reference, operator.to_str()) =>
+                    evaluate_method_call(stack, memory, world, left_reference, operator.to_string(), vec!(right_reference)),
+                (left_reference, Equality, right_reference) =>
+                    Reference::Boolean(structurally_equal(stack, memory, world, left_reference, right_reference, &mut Vec::new())),
+                (left_reference, Inequality, right_reference) =>
+                    Reference::Boolean(!structurally_equal(stack, memory, world, left_reference, right_reference, &mut Vec::new())),
+
 
                 //(Reference::Boolean(left_value), Inequality, Reference::Boolean(right_value)) => Reference::Boolean(left_value != right_value),
                 //(Reference::Boolean(left_value), Equality, Reference::Boolean(right_value)) => Reference::Boolean(left_value == right_value),
@@ -400,58 +620,47 @@ pub fn evaluate (stack: &mut EnvironmentStack, memory: &mut Memory,
         },
 
         AST::Print {format, arguments} => {
-            let format_string = match &**format {
-                AST::String(string) => string,
-                _ => panic!("Format string must be a string, but it is this: {:?}", format),
-            };
+            let format_string = format;
 
-            if arguments.is_empty() {
-                world.output(format_string.to_string());
-            } else {
-                let mut values: Vec<String> = arguments.iter().map(|argument| {
-                    evaluate_to_string(soft_evaluate(stack, memory, world, &*argument))
-                }).rev().collect();
-
-                let mut escape = false;
-                let mut result = String::new();
-
-                for character in format_string.chars() {
-                    match character {
-                        '~' if !escape => {
-                            match values.pop() {
-                                Some(value) => result.push_str(&value),
-                                None => panic!("Cannot fill placeholder in {}", format_string),
-                            }
-                        },
-                        '\\' if !escape => { escape = true;                         },
-                        '\\' if  escape => { escape = false; result.push('\\')      },
-                        'n'  if  escape => { escape = false; result.push('\n')      },
-                        't'  if  escape => { escape = false; result.push('\t')      },
-                        'r'  if  escape => { escape = false; result.push('\r')      },
-                        _ =>               { escape = false; result.push(character) },
-                    }
+            let values: Vec<Value> = arguments.iter().map(|argument| {
+                let reference = soft_evaluate(stack, memory, world, &*argument);
+                match memory.get_integer(&reference) {
+                    Some(integer) => Value::Integer(integer),
+                    None => Value::Other(evaluate_to_string(memory, reference)),
                 }
+            }).collect();
 
-                if !values.is_empty() {
-                    panic!("Too many arguments for format string {}", format_string)
-                }
+            let result = Format::parse(format_string)
+                .and_then(|format| format.render(&values))
+                .unwrap_or_else(|e| panic!("Cannot print {}: {}", format_string, e));
 
-                world.output(result);
-            }
+            world.output(result);
 
             Reference::Unit
         },
 
-        AST::String(_) => {
-            panic!("String literals cannot occur outside of print.")
+        AST::OperatorDefinition { operator:_, parameters:_, body:_} => {
+            panic!("Operators can only be defined within bodies of objects")
         },
 
-        AST::OperatorAccess {object:_, operator:_} => {
-            panic!("Operator access is not allowed, we don't have first class functions and junk")
-        },
+        AST::Import { path, name:_ } => {
+            if !stack.in_top_frame() {
+                panic!("Import error: module {} can only be imported at the top level", path)
+            }
 
-        AST::OperatorDefinition { operator:_, parameters:_, body:_} => {
-            panic!("Operators can only be defined within bodies of objects")
+            let loaded = stack.modules_mut().load(path)
+                .unwrap_or_else(|e| panic!("Import error: {}", e));
+
+            // Module members were renamed by the loader, so they are registered in the top frame
+            // alongside the program's own definitions without clashing.
+            if let Loaded::Module { prefix:_, body } = loaded {
+                for expression in body.iter() {
+                    evaluate(stack, memory, world, &*expression);
+                }
+                stack.modules_mut().finish();
+            }
+
+            Reference::Unit
         },
     }
 }
@@ -460,9 +669,30 @@ fn evaluate_method_call(stack: &mut EnvironmentStack, memory: &mut Memory,
                         world: &mut impl World, object_reference: Reference,
                         method_name: String, arguments: Vec<Reference>) -> Reference {
 
+    if let Reference::Map(_) = object_reference {
+        return evaluate_map_method(memory, object_reference, &method_name, arguments)
+    }
+
+    if let Reference::Array {reference:_, size:_} = object_reference {
+        return evaluate_array_method(memory, object_reference, &method_name, arguments)
+    }
+
+    if is_reflection_method(&method_name) && !understands_method(memory, object_reference, &method_name) {
+        return evaluate_reflection_method(memory, object_reference, &method_name, arguments)
+    }
+
     let actual_reference = find_actual_host_object_for_method!(memory, object_reference, method_name);
-    let function_reference = match memory.get_object(&actual_reference) {
-        Some(Instance::Object{extends:_, methods, fields:_}) => methods.get(&method_name).unwrap(),
+    invoke_method(stack, memory, world, actual_reference, object_reference, method_name, arguments)
+}
+
+// The object that defines the method is bound alongside `this`, so that super calls inside the
+// method can start looking for the overridden method from its parent.
+fn invoke_method(stack: &mut EnvironmentStack, memory: &mut Memory, world: &mut impl World,
+                 host_reference: Reference, object_reference: Reference,
+                 method_name: String, arguments: Vec<Reference>) -> Reference {
+
+    let function_reference = match memory.get_object(&host_reference) {
+        Some(Instance::Object{extends:_, methods, fields:_, constants:_}) => methods.get(&method_name).unwrap(),
         Some(instance) => panic!("Invalid instance type {:?}.", instance),
         None => panic!("Fatal inconsistency in instance store."),
     };
@@ -480,28 +710,591 @@ fn evaluate_method_call(stack: &mut EnvironmentStack, memory: &mut Memory,
             bindings.push((parameter.to_string(), *reference))
         }
         bindings.push(("this".to_string(), object_reference));
+        bindings.push((HOST.to_string(), host_reference));
         bindings
     };
 
     hard_evaluate(stack, memory, world, bindings, &*function_definition.body)
 }
 
+fn is_reflection_method(method_name: &str) -> bool {
+    match method_name {
+        "fields" | "methods" | "has_field" | "parent" | "get" | "set" => true,
+        _ => false,
+    }
+}
+
+fn understands_method(memory: &Memory, object_reference: Reference, method_name: &str) -> bool {
+    let mut cursor = Some(object_reference);
+    while let Some(reference) = cursor {
+        match memory.get_object(&reference) {
+            Some(Instance::Object { extends, fields:_, methods, constants:_ }) => {
+                if methods.contains_key(method_name) {
+                    return true
+                }
+                cursor = *extends;
+            },
+            _ => return false,
+        }
+    }
+    false
+}
+
+// Reflective methods only look at the object's own members, so generic code walks up the
+// inheritance chain with parent().
+fn evaluate_reflection_method(memory: &mut Memory, object_reference: Reference,
+                              method_name: &str, arguments: Vec<Reference>) -> Reference {
+
+    let expected_arguments = match method_name {
+        "fields" | "methods" | "parent" => 0,
+        "has_field" | "get" => 1,
+        "set" => 2,
+        _ => unreachable!(),
+    };
+
+    if arguments.len() != expected_arguments {
+        panic!("Object method {} takes {} arguments, but {} were supplied",
+               method_name, expected_arguments, arguments.len())
+    }
+
+    let field: Option<String> = arguments.first().map(|field_reference| {
+        memory.get_string(field_reference)
+            .expect(&format!("Field name must be a string, not {:?}", field_reference))
+            .to_string()
+    });
+
+    let (extends, fields, methods) = match memory.get_object_mut(&object_reference) {
+        Some(Instance::Object { extends, fields, methods, constants:_ }) => (*extends, fields, methods),
+        _ => panic!("Cannot find method {} in {:?}", method_name, object_reference),
+    };
+
+    match (method_name, field) {
+        ("fields", None) | ("methods", None) => {
+            let mut names: Vec<String> = if method_name == "fields" {
+                fields.keys().cloned().collect()
+            } else {
+                methods.keys().cloned().collect()
+            };
+            names.sort();
+            let elements: Vec<Reference> = names.into_iter()
+                .map(|name| memory.put_object(Instance::String(name)))
+                .collect();
+            memory.put_object(Instance::array(elements))
+        },
+        ("parent", None) => extends.unwrap_or(Reference::Unit),
+        ("has_field", Some(field)) => Reference::Boolean(fields.contains_key(&field)),
+        ("get", Some(field)) => *fields.get(&field)
+            .expect(&format!("Cannot find field {} in object {:?}", field, object_reference)),
+        ("set", Some(field)) => {
+            if !fields.contains_key(&field) {
+                panic!("Cannot find field {} in object {:?}", field, object_reference)
+            }
+            fields.insert(field, arguments[1]);
+            arguments[1]
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn evaluate_array_method(memory: &mut Memory, array_reference: Reference,
+                         method_name: &str, arguments: Vec<Reference>) -> Reference {
+
+    let expected_arguments = match method_name {
+        "length" | "pop" => 0,
+        "get" | "push" | "remove" | "concat" | "contains" | "index_of" => 1,
+        "set" | "insert" => 2,
+        _ => panic!("Cannot find method {} in array {:?}", method_name, array_reference),
+    };
+
+    if arguments.len() != expected_arguments {
+        panic!("Array method {} takes {} arguments, but {} were supplied",
+               method_name, expected_arguments, arguments.len())
+    }
+
+    let index = match method_name {
+        "get" | "set" | "insert" | "remove" => match arguments[0] {
+            Reference::Integer(index) => Some(index),
+            reference => panic!("Cannot index array with {:?}", reference),
+        },
+        _ => None,
+    };
+
+    let found = match method_name {
+        "contains" | "index_of" => array_values(memory, &array_reference).clone().into_iter()
+            .position(|element| references_equal(memory, element, arguments[0])),
+        _ => None,
+    };
+
+    let checked = |index: i64, length: usize| {
+        if index < 0 || index as usize >= length {
+            panic!("Array index out of bounds: {}", index)
+        }
+        index as usize
+    };
+
+    let values = array_values(memory, &array_reference);
+    let result = match (method_name, index) {
+        ("length", None) => Reference::Integer(values.len() as i64),
+        ("get", Some(index)) => values[checked(index, values.len())],
+        ("set", Some(index)) => { let index = checked(index, values.len()); values[index] = arguments[1]; Reference::Unit },
+        ("push", None) => { values.push(arguments[0]); Reference::Unit },
+        ("pop", None) => values.pop().expect("Cannot pop from an empty array"),
+        // Inserting at the index one past the last element appends.
+        ("insert", Some(index)) => { let index = checked(index, values.len() + 1); values.insert(index, arguments[1]); Reference::Unit },
+        ("remove", Some(index)) => { let index = checked(index, values.len()); values.remove(index) },
+        ("concat", None) => {
+            let mut concatenated = values.clone();
+            concatenated.extend(array_values(memory, &arguments[0]).iter());
+            memory.put_object(Instance::array(concatenated))
+        },
+        ("contains", None) => Reference::Boolean(found.is_some()),
+        ("index_of", None) => Reference::Integer(found.map_or(-1, |index| index as i64)),
+        _ => unreachable!(),
+    };
+
+    resize_array(memory, &array_reference);
+    result
+}
+
+// Integers, floats, booleans, unit, and strings are equal if their values are. Arrays, maps, and
+// objects are only equal to themselves.
+fn references_equal(memory: &Memory, left: Reference, right: Reference) -> bool {
+    match (left, right) {
+        (Reference::String(_), Reference::String(_)) => memory.get_string(&left) == memory.get_string(&right),
+        (Reference::Integer(_), _) | (Reference::BigInteger(_), _) =>
+            memory.get_integer(&right).map_or(false, |integer| memory.get_integer(&left) == Some(integer)),
+        _ => left == right,
+    }
+}
+
+fn references_identical(memory: &Memory, left: Reference, right: Reference) -> bool {
+    match (left, right) {
+        (Reference::Array {reference: left, size:_}, Reference::Array {reference: right, size:_}) => left == right,
+        _ => references_equal(memory, left, right),
+    }
+}
+
+// Arrays are equal if they have equal elements, maps if they have the same keys with equal values,
+// and objects if they have equal fields, the same methods, and equal parents. An object that
+// defines == is compared by calling it. Pairs that are already being compared further up are
+// considered equal, so comparing cyclic structures terminates.
+fn structurally_equal(stack: &mut EnvironmentStack, memory: &mut Memory, world: &mut impl World,
+                      left: Reference, right: Reference, visited: &mut Vec<(Reference, Reference)>) -> bool {
+    if let Reference::Object(_) = left {
+        if understands_method(memory, left, "==") {
+            let result = evaluate_method_call(stack, memory, world, left, "==".to_string(), vec!(right));
+            return evaluate_to_boolean(result)
+        }
+    }
+
+    if references_identical(memory, left, right) || visited.contains(&(left, right)) {
+        return true
+    }
+
+    visited.push((left, right));
+
+    match (left, right) {
+        (Reference::Array {reference:_, size:_}, Reference::Array {reference:_, size:_}) => {
+            let left_values = array_values(memory, &left).clone();
+            let right_values = array_values(memory, &right).clone();
+            left_values.len() == right_values.len()
+                && left_values.into_iter().zip(right_values.into_iter())
+                    .all(|(left, right)| structurally_equal(stack, memory, world, left, right, visited))
+        },
+        (Reference::Map(_), Reference::Map(_)) => {
+            let (left_entries, right_entries) = match (memory.get_object(&left), memory.get_object(&right)) {
+                (Some(Instance::Map(left_entries)), Some(Instance::Map(right_entries))) =>
+                    (left_entries.clone(), right_entries.clone()),
+                _ => panic!("Fatal inconsistency in instance store."),
+            };
+            left_entries.len() == right_entries.len()
+                && left_entries.into_iter().all(|(key, left)| match right_entries.get(&key) {
+                    Some(right) => structurally_equal(stack, memory, world, left, *right, visited),
+                    None => false,
+                })
+        },
+        (Reference::Object(_), Reference::Object(_)) => {
+            let (left_parent, left_fields, left_methods, right_parent, right_fields, right_methods) =
+                match (memory.get_object(&left), memory.get_object(&right)) {
+                    (Some(Instance::Object {extends: left_parent, fields: left_fields, methods: left_methods, constants:_}),
+                     Some(Instance::Object {extends: right_parent, fields: right_fields, methods: right_methods, constants:_})) =>
+                        (*left_parent, left_fields.clone(), left_methods.clone(),
+                         *right_parent, right_fields.clone(), right_methods.clone()),
+                    _ => panic!("Fatal inconsistency in instance store."),
+                };
+
+            let same_methods = left_methods.len() == right_methods.len()
+                && left_methods.iter().all(|(name, left)| match right_methods.get(name) {
+                    Some(right) => memory.get_function(left) == memory.get_function(right),
+                    None => false,
+                });
+
+            same_methods
+                && left_fields.len() == right_fields.len()
+                && left_fields.into_iter().all(|(name, left)| match right_fields.get(&name) {
+                    Some(right) => structurally_equal(stack, memory, world, left, *right, visited),
+                    None => false,
+                })
+                && match (left_parent, right_parent) {
+                    (Some(left), Some(right)) => structurally_equal(stack, memory, world, left, right, visited),
+                    (None, None) => true,
+                    _ => false,
+                }
+        },
+        (Reference::String(_), Reference::String(_)) => memory.get_string(&left) == memory.get_string(&right),
+        _ => references_equal(memory, left, right),
+    }
+}
+
+// See fml_ast::hashing for the order in which the parts of a value are hashed.
+fn hash_reference(memory: &Memory, reference: Reference, hasher: &mut Hasher, visiting: &mut Vec<Reference>) {
+    if visiting.iter().any(|other| references_identical(memory, *other, reference)) {
+        hasher.write_cycle();
+        return
+    }
+
+    match reference {
+        Reference::Unit => hasher.write_null(),
+        Reference::Boolean(value) => hasher.write_boolean(value),
+        Reference::Integer(_) | Reference::BigInteger(_) => hasher.write_integer(&memory.get_integer(&reference).unwrap()),
+        Reference::Float(_) => hasher.write_float(memory.get_f64(&reference).unwrap()),
+        Reference::String(_) => hasher.write_string(memory.get_string(&reference).unwrap()),
+        Reference::Array {reference:_, size:_} | Reference::Map(_) | Reference::Object(_) => {
+            visiting.push(reference);
+            match memory.get_object(&reference) {
+                Some(Instance::Array {size:_, values}) => {
+                    hasher.start_array(values.len());
+                    for value in values {
+                        hash_reference(memory, *value, hasher, visiting);
+                    }
+                },
+                Some(Instance::Map(entries)) => {
+                    hasher.start_map(entries.len());
+                    let mut hashes: Vec<u64> = entries.iter().map(|(key, value)| {
+                        let mut entry = Hasher::new();
+                        match key {
+                            MapKey::Unit => entry.write_null(),
+                            MapKey::Boolean(value) => entry.write_boolean(*value),
+                            MapKey::Integer(value) => entry.write_integer(value),
+                            MapKey::String(value) => entry.write_string(value),
+                        }
+                        hash_reference(memory, *value, &mut entry, visiting);
+                        entry.finish()
+                    }).collect();
+                    hashes.sort();
+                    for hash in hashes {
+                        hasher.write_hash(hash);
+                    }
+                },
+                Some(Instance::Object {extends, fields, methods, constants:_}) => {
+                    hasher.start_object(fields.len(), methods.len());
+                    let mut field_names: Vec<&String> = fields.keys().collect();
+                    field_names.sort();
+                    for name in field_names {
+                        hasher.write_name(name);
+                        hash_reference(memory, fields[name], hasher, visiting);
+                    }
+                    let mut method_names: Vec<&String> = methods.keys().collect();
+                    method_names.sort();
+                    for name in method_names {
+                        hasher.write_name(name);
+                    }
+                    match extends {
+                        Some(parent) => hash_reference(memory, *parent, hasher, visiting),
+                        None => hasher.write_null(),
+                    }
+                },
+                _ => panic!("Fatal inconsistency in instance store."),
+            }
+            visiting.pop();
+        },
+    }
+}
+
+fn evaluate_map_method(memory: &mut Memory, map_reference: Reference,
+                       method_name: &str, arguments: Vec<Reference>) -> Reference {
+
+    let expected_arguments = match method_name {
+        "get" | "has" | "remove" => 1,
+        "set" => 2,
+        "keys" | "size" => 0,
+        _ => panic!("Cannot find method {} in map {:?}", method_name, map_reference),
+    };
+
+    if arguments.len() != expected_arguments {
+        panic!("Map method {} takes {} arguments, but {} were supplied",
+               method_name, expected_arguments, arguments.len())
+    }
+
+    let key = arguments.first().map(|key_reference| {
+        memory.get_map_key(key_reference)
+            .expect(&format!("Cannot use {:?} as a map key", key_reference))
+    });
+
+    let map = match memory.get_object_mut(&map_reference) {
+        Some(Instance::Map(map)) => map,
+        _ => panic!("Fatal inconsistency in instance store."),
+    };
+
+    match (method_name, key) {
+        ("get", Some(key)) => *map.get(&key).unwrap_or(&Reference::Unit),
+        ("set", Some(key)) => { map.insert(key, arguments[1]); Reference::Unit },
+        ("has", Some(key)) => Reference::Boolean(map.contains_key(&key)),
+        ("remove", Some(key)) => map.remove(&key).unwrap_or(Reference::Unit),
+        ("size", None) => Reference::Integer(map.len() as i64),
+        ("keys", None) => {
+            let keys: Vec<MapKey> = map.keys().cloned().collect();
+            let elements: Vec<Reference> = keys.into_iter().map(|key| memory.put_map_key(key)).collect();
+            memory.put_object(Instance::array(elements))
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn evaluate_integer_operation(memory: &mut Memory, left_reference: Reference,
+                              operator: &fml_ast::Operator, right_reference: Reference) -> Reference {
+    use fml_ast::Operator::*;
+    let left = memory.get_integer(&left_reference).expect("Integer not found in memory");
+    let right = memory.get_integer(&right_reference).expect("Integer not found in memory");
+    match operator {
+        Multiplication | Division | Module | Addition | Subtraction => {
+            let result = memory.integers().arithmetic(operator, &left, &right)
+                .unwrap_or_else(|e| panic!("Arithmetic error: {}", e));
+            memory.put_integer(result)
+        },
+        Equality => Reference::Boolean(left == right),
+        Inequality => Reference::Boolean(left != right),
+        Less => Reference::Boolean(left < right),
+        LessEqual => Reference::Boolean(left <= right),
+        Greater => Reference::Boolean(left > right),
+        GreaterEqual => Reference::Boolean(left >= right),
+        _ => panic!("Operator {} is not implemented for operands {} and {}",
+                    operator.to_string(), left, right),
+    }
+}
+
+fn evaluate_float_operation(left: f64, operator: &fml_ast::Operator, right: f64) -> Reference {
+    use fml_ast::Operator::*;
+    match operator {
+        Multiplication => Reference::from_f64(left * right),
+        Division => Reference::from_f64(left / right),
+        Module => Reference::from_f64(left % right),
+        Addition => Reference::from_f64(left + right),
+        Subtraction => Reference::from_f64(left - right),
+        Equality => Reference::Boolean(left == right),
+        Inequality => Reference::Boolean(left != right),
+        Less => Reference::Boolean(left < right),
+        LessEqual => Reference::Boolean(left <= right),
+        Greater => Reference::Boolean(left > right),
+        GreaterEqual => Reference::Boolean(left >= right),
+        _ => panic!("Operator {} is not implemented for operands {} and {}",
+                    operator.to_string(), left, right),
+    }
+}
+
+// Object patterns only look at the object's own fields, not the ones it inherits, just like in the
+// bytecode backend.
+fn match_pattern(memory: &Memory, pattern: &Pattern, reference: Reference,
+                 bindings: &mut Vec<(String, Reference)>) -> bool {
+    match (pattern, reference) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Binding(Identifier(name)), reference) => {
+            bindings.push((name.to_string(), reference));
+            true
+        }
+        (Pattern::Number(n), Reference::Integer(m)) => *n == m,
+        (Pattern::Boolean(a), Reference::Boolean(b)) => *a == b,
+        (Pattern::String(a), Reference::String(_)) => memory.get_string(&reference) == Some(a.as_str()),
+        (Pattern::Unit, Reference::Unit) => true,
+        (Pattern::Array(patterns), Reference::Array {reference:_, size:_}) => {
+            let values = match memory.get_object(&reference) {
+                Some(Instance::Array {size:_, values}) => values.clone(),
+                _ => panic!("Could not find array instance"),
+            };
+            values.len() == patterns.len() && patterns.iter().zip(values.into_iter())
+                .all(|(pattern, value)| match_pattern(memory, pattern, value, bindings))
+        }
+        (Pattern::Object(patterns), Reference::Object(_)) => {
+            let fields = match memory.get_object(&reference) {
+                Some(Instance::Object {extends:_, fields, methods:_, constants:_}) => fields.clone(),
+                _ => panic!("Could not find object instance"),
+            };
+            patterns.iter().all(|(Identifier(field), pattern)| match fields.get(field) {
+                Some(value) => match_pattern(memory, pattern, *value, bindings),
+                None => false,
+            })
+        }
+        _ => false,
+    }
+}
+
+// The standard library: functions that every program can call without defining them first.
+fn evaluate_builtin_function(memory: &mut Memory, world: &mut impl World, function_name: &str,
+                             arguments: Vec<Reference>) -> Reference {
+
+    let expected_arguments = builtin_arity(function_name).unwrap();
+
+    if arguments.len() != expected_arguments {
+        panic!("Built-in function {} takes {} arguments, but {} were supplied",
+               function_name, expected_arguments, arguments.len())
+    }
+
+    let integer_argument = |memory: &Memory, index: usize| {
+        memory.get_integer(&arguments[index])
+            .expect(&format!("Built-in function {} expects an integer, not {:?}", function_name, arguments[index]))
+    };
+
+    let index_argument = |memory: &Memory, index: usize| {
+        match integer_argument(memory, index) {
+            Integer::Small(n) if n >= 0 => n as usize,
+            n => panic!("Built-in function {} expects a non-negative index, not {}", function_name, n),
+        }
+    };
+
+    match function_name {
+        "length" => Reference::Integer(array_values(memory, &arguments[0]).len() as i64),
+        "hash" => {
+            let mut hasher = Hasher::new();
+            hash_reference(memory, arguments[0], &mut hasher, &mut Vec::new());
+            Reference::Integer(hasher.finish_as_integer())
+        },
+        "push" => {
+            let values = array_values(memory, &arguments[0]);
+            values.push(arguments[1]);
+            resize_array(memory, &arguments[0]);
+            Reference::Unit
+        },
+        "pop" => {
+            let value = array_values(memory, &arguments[0]).pop()
+                .expect("Cannot pop from an empty array");
+            resize_array(memory, &arguments[0]);
+            value
+        },
+        "slice" => {
+            let (from, to) = (index_argument(memory, 1), index_argument(memory, 2));
+            let values = array_values(memory, &arguments[0]);
+            if from > to || to > values.len() {
+                panic!("Slice {}..{} is out of bounds of array of length {}", from, to, values.len())
+            }
+            let slice = values[from..to].to_vec();
+            memory.put_object(Instance::array(slice))
+        },
+        "sort" => {
+            let mut sorted = array_values(memory, &arguments[0]).clone();
+            sorted.sort_by(|left, right| compare_references(memory, *left, *right));
+            *array_values(memory, &arguments[0]) = sorted;
+            Reference::Unit
+        },
+        "abs" => match arguments[0] {
+            Reference::Float(_) => Reference::from_f64(memory.get_f64(&arguments[0]).unwrap().abs()),
+            _ => {
+                let integer = integer_argument(memory, 0);
+                let result = memory.integers().absolute(&integer)
+                    .unwrap_or_else(|e| panic!("Built-in function error: {}", e));
+                memory.put_integer(result)
+            },
+        },
+        "min" | "max" => match (function_name, compare_references(memory, arguments[0], arguments[1])) {
+            ("min", Ordering::Greater) | ("max", Ordering::Less) => arguments[1],
+            _ => arguments[0],
+        },
+        "pow" => {
+            let (base, exponent) = (integer_argument(memory, 0), integer_argument(memory, 1));
+            let result = memory.integers().power(&base, &exponent)
+                .unwrap_or_else(|e| panic!("Built-in function error: {}", e));
+            memory.put_integer(result)
+        },
+        // Only false and null fail, as in the bytecode interpreter.
+        "assert" => match arguments[0] {
+            Reference::Boolean(false) | Reference::Unit => panic!("Assertion failed"),
+            _ => Reference::Unit,
+        },
+        "read_line" | "read_int" => {
+            match (function_name, world.read_line()) {
+                (_, None) => Reference::Unit,
+                ("read_line", Some(line)) => memory.put_object(Instance::String(line)),
+                (_, Some(line)) => {
+                    let integer = line.trim().parse::<i64>().ok()
+                        .and_then(|n| memory.integers().literal(n).ok())
+                        .unwrap_or_else(|| panic!("Cannot read an integer from {:?}", line));
+                    memory.put_integer(integer)
+                },
+            }
+        },
+        "exit" => match integer_argument(memory, 0) {
+            Integer::Small(n) if n >= i32::MIN as i64 && n <= i32::MAX as i64 => world.exit(n as i32),
+            n => panic!("Exit code {} does not fit into 32 bits", n),
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn array_values<'a>(memory: &'a mut Memory, reference: &Reference) -> &'a mut Vec<Reference> {
+    match memory.get_object_mut(reference) {
+        Some(Instance::Array {size:_, values}) => values,
+        _ => panic!("Expected an array, not {:?}", reference),
+    }
+}
+
+fn resize_array(memory: &mut Memory, reference: &Reference) {
+    if let Some(Instance::Array {size, values}) = memory.get_object_mut(reference) {
+        *size = values.len();
+    }
+}
+
+// Orders numbers by value and strings alphabetically; other values cannot be compared.
+fn compare_references(memory: &Memory, left: Reference, right: Reference) -> Ordering {
+    let ordering = match (left, right) {
+        (Reference::String(_), Reference::String(_)) =>
+            Some(memory.get_string(&left).cmp(&memory.get_string(&right))),
+        (Reference::Float(_), _) | (_, Reference::Float(_)) =>
+            match (memory.get_f64(&left), memory.get_f64(&right)) {
+                (Some(l), Some(r)) => l.partial_cmp(&r),
+                _ => None,
+            },
+        _ => match (memory.get_integer(&left), memory.get_integer(&right)) {
+            (Some(l), Some(r)) => Some(l.cmp(&r)),
+            _ => None,
+        },
+    };
+    ordering.unwrap_or_else(|| panic!("Cannot compare {:?} with {:?}", left, right))
+}
+
+// Only false and null are false, like in the bytecode interpreter.
 fn evaluate_to_boolean(reference: Reference) -> bool {
     match reference {
         Reference::Boolean(b) => b,
         Reference::Unit => false,
-        Reference::Object(_) => true,
-        Reference::Integer(n) => n == 0,
-        Reference::Array {reference: _, size: _} => true,
+        _ => true,
     }
 }
 
-fn evaluate_to_string(reference: Reference) -> String {
+fn evaluate_to_string(memory: &Memory, reference: Reference) -> String {
     match reference {
         Reference::Boolean(b) => format!("{}", b),
         Reference::Unit => "null".to_string(),
         Reference::Object(reference) => format!("<ref:{}>", reference),
         Reference::Integer(n) => format!("{}", n),
+        Reference::BigInteger(_) => format!("{}", memory.get_integer(&reference)
+            .expect("Integer not found in memory")),
+        Reference::Float(bits) => format!("{:?}", f64::from_bits(bits)),
+        Reference::String(_) => memory.get_string(&reference)
+            .expect("String not found in memory").to_string(),
         Reference::Array {reference, size} => format!("<ref:{}, size:{}>", reference, size),
+        Reference::Map(_) => match memory.get_object(&reference) {
+            Some(Instance::Map(map)) => {
+                let entries: Vec<String> = map.iter().map(|(key, value)| {
+                    let key = match key {
+                        MapKey::Unit => "null".to_string(),
+                        MapKey::Boolean(b) => format!("{}", b),
+                        MapKey::Integer(n) => format!("{}", n),
+                        MapKey::String(string) => string.to_string(),
+                    };
+                    format!("{}: {}", key, evaluate_to_string(memory, *value))
+                }).collect();
+                format!("{{{}}}", entries.join(", "))
+            },
+            _ => panic!("Map not found in memory"),
+        },
     }
 }
\ No newline at end of file
@@ -0,0 +1,1374 @@
+pub mod environment;
+pub mod heap;
+pub mod interpreter;
+pub mod world;
+
+#[macro_use]
+extern crate fml_ast;
+extern crate fml_parser;
+extern crate regex;
+
+#[cfg(test)]
+mod memory_tests {
+    use crate::heap::Memory;
+    use crate::heap::Instance;
+    use crate::heap::Function;
+    use fml_ast::Identifier;
+
+    #[test] fn instance () {
+        let mut memory = Memory::new();
+        let object = Instance::empty();
+        let reference = memory.put_object(object);
+        assert!(memory.get_object(&reference).is_some())
+    }
+
+    #[test] fn function () {
+        let mut memory = Memory::new();
+        let function_body = fml_ast::AST::VariableAccess{name: Identifier::from("x")};
+        let object = Function::new(
+            "f".to_string(),
+            vec!("x".to_string()),
+            Box::new(function_body));
+        let reference = memory.put_function(object);
+        assert!(memory.get_function(&reference).is_some())
+    }
+}
+
+#[cfg(test)]
+mod environment_tests {
+    use crate::environment::EnvironmentStack;
+    use crate::heap::Reference;
+    use crate::heap::FunctionReference;
+
+    #[test]
+    fn basic () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(0)).is_ok());
+        assert_eq!(gamma.lookup_binding("x"),
+                   Ok(&Reference::Object(0)));
+    }
+
+    #[test]
+    fn basic_function () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(0)).is_ok());
+        assert_eq!(gamma.lookup_function("f"),
+                   Ok(&FunctionReference::Function(0)));
+    }
+
+    #[test]
+    fn soft_frame () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(0)).is_ok());
+
+        gamma.add_soft_frame();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(1)).is_ok());
+
+        gamma.add_soft_frame();
+        assert_eq!(gamma.lookup_binding("x"),
+                   Ok(&Reference::Object(1)));
+    }
+
+    #[test]
+    fn hard_frame () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(0)).is_ok());
+
+        gamma.add_soft_frame();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(1)).is_ok());
+
+        gamma.add_hard_frame();
+        assert_eq!(gamma.lookup_binding("x"),
+                   Ok(&Reference::Object(0)));
+    }
+
+    #[test]
+    fn hard_frame_exclusion () {
+        let mut gamma = EnvironmentStack::new();
+
+        gamma.add_soft_frame();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(0)).is_ok());
+
+        gamma.add_hard_frame();
+        assert!(gamma.lookup_binding("x").is_err());
+    }
+
+    #[test]
+    fn soft_frame_function () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(0)).is_ok());
+
+        gamma.add_soft_frame();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(1)).is_ok());
+
+        gamma.add_soft_frame();
+        assert_eq!(gamma.lookup_function("f"),
+                   Ok(&FunctionReference::Function(1)));
+    }
+
+    #[test]
+    fn hard_frame_function () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(0)).is_ok());
+
+        gamma.add_soft_frame();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(1)).is_ok());
+
+        gamma.add_hard_frame();
+        assert_eq!(gamma.lookup_function("f"),
+                   Ok(&FunctionReference::Function(0)));
+    }
+
+    #[test]
+    fn hard_frame_function_exclusion () {
+        let mut gamma = EnvironmentStack::new();
+
+        gamma.add_soft_frame();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(0)).is_ok());
+
+        gamma.add_hard_frame();
+        assert!(gamma.lookup_function("f").is_err());
+    }
+
+    #[test]
+    fn shadowing () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(0)).is_ok());
+
+        gamma.add_soft_frame();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(1)).is_ok());
+        assert_eq!(gamma.lookup_binding("x"),
+                   Ok(&Reference::Object(1)));
+
+        gamma.remove_frame();
+        assert_eq!(gamma.lookup_binding("x"),
+                   Ok(&Reference::Object(0)));
+    }
+
+    #[test]
+    fn shadowing_function () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(0)).is_ok());
+
+        gamma.add_soft_frame();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(1)).is_ok());
+        assert_eq!(gamma.lookup_function("f"),
+                   Ok(&FunctionReference::Function(1)));
+
+        gamma.remove_frame();
+        assert_eq!(gamma.lookup_function("f"),
+                   Ok(&FunctionReference::Function(0)));
+    }
+
+    #[test]
+    fn undefined_lookup () {
+        let gamma = EnvironmentStack::new();
+        assert!(gamma.lookup_binding("x").is_err());
+    }
+
+    #[test]
+    fn undefined_function () {
+        let gamma = EnvironmentStack::new();
+        assert!(gamma.lookup_binding("f").is_err());
+    }
+
+    #[test]
+    fn define_redefine_error () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(0)).is_ok());
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(1)).is_err());
+    }
+
+    #[test]
+    fn function_redefine_error () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(0)).is_ok());
+        assert!(gamma.register_function("f".to_string(),
+                                        FunctionReference::Function(1)).is_err());
+    }
+
+    #[test]
+    fn redefine () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Object(0)).is_ok());
+        assert!(gamma.change_binding("x".to_string(),
+                                     Reference::Object(1)).is_ok());
+        assert_eq!(gamma.lookup_binding("x"),
+                   Ok(&Reference::Object(1)));
+    }
+
+    #[test]
+    fn redefine_undefined_error () {
+        let mut gamma = EnvironmentStack::new();
+        assert!(gamma.change_binding("x".to_string(),
+                                     Reference::Object(1)).is_err());
+    }
+}
+
+#[cfg(test)]
+mod interpreter_tests {
+    use crate::heap::Memory;
+    use crate::heap::Instance;
+    use crate::heap::Function;
+    use crate::heap::Reference;
+    use crate::heap::FunctionReference;
+    use crate::heap::MapKey;
+    use crate::environment::EnvironmentStack;
+    use crate::world::BufferedIO;
+    use crate::interpreter::evaluate;
+    use fml_ast::AST;
+    use std::collections::{HashMap, HashSet, BTreeMap};
+    use fml_ast::integers::{IntegerSemantics, Integer};
+
+    // A program of one expression is that expression, so that it can stand for a function body.
+    fn parse(source: &str) -> AST {
+        match fml_parser::parse(source).unwrap_or_else(|error| panic!("Cannot parse {}: {}", source, error)) {
+            AST::Top(mut expressions) if expressions.len() == 1 => *expressions.pop().unwrap(),
+            ast => ast,
+        }
+    }
+
+    macro_rules! make_function {
+        ($name:expr, $body:expr, $( $parameter:expr ),*) => {
+            Function::new($name.to_string(), {
+                let mut parameters: Vec<String> = Vec::new();
+                $(
+                    parameters.push($parameter.to_string());
+                )*
+                parameters
+            }, Box::new($body))
+        };
+        ($name:expr, $body:expr) => {
+            Function::new($name.to_string(), Vec::new(), Box::new($body))
+        }
+    }
+
+    macro_rules! make_array {
+        ($size:expr, $value:expr) => {
+            Instance::Array {
+                size: $size,
+                values: {
+                    let mut values = Vec::new();
+                    for _ in 0..$size {
+                        values.push($value.clone())
+                    }
+                    values
+                }
+            }
+        }
+    }
+
+    // let x = 1
+    #[test]
+    fn define_local () {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let x = 1");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+        assert_eq!(gamma.lookup_binding("x"), Ok(&Reference::Integer(1)))
+    }
+
+    // x = 1
+    #[test]
+    fn redefine_local() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        assert!(gamma.register_binding("x".to_string(), Reference::Integer(0)).is_ok());
+
+        let ast = parse("x <- 1");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+        assert_eq!(gamma.lookup_binding("x"), Ok(&Reference::Integer(1)))
+    }
+
+    // x
+    #[test]
+    fn identifier_local_lookup() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        assert!(gamma.register_binding("x".to_string(),
+                                       Reference::Integer(1)).is_ok());
+
+        let ast = parse("x");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1))
+    }
+
+    // 42
+    #[test]
+    fn number() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("42");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(42))
+    }
+
+    // null
+    #[test]
+    fn unit() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("null");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit)
+    }
+
+    // true
+    #[test]
+    fn boolean() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("true");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast),
+                   Reference::Boolean(true))
+    }
+
+    // 2147483647 + 1
+    #[test]
+    fn integer_wrapping32() {
+        let mut memory = Memory::with_integer_semantics(IntegerSemantics::Wrapping32);
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("2147483647 + 1");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(-2147483648))
+    }
+
+    // 9223372036854775807 + 1
+    #[test]
+    #[should_panic(expected = "integer overflow")]
+    fn integer_checked64() {
+        let mut memory = Memory::with_integer_semantics(IntegerSemantics::Checked64);
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("9223372036854775807 + 1");
+
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // print("~", 9223372036854775807 * 4 / 2)
+    #[test]
+    fn integer_promoting() {
+        let mut memory = Memory::with_integer_semantics(IntegerSemantics::Promoting);
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("print(\"~\", 9223372036854775807 * 4 / 2)");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+
+        let expected_output = BufferedIO::from(vec!("18446744073709551614"));
+        assert_eq!(expected_output, world);
+    }
+
+    // 2.5
+    #[test]
+    fn float() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("2.5");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::from_f64(2.5))
+    }
+
+    // 1.5 * 2
+    #[test]
+    fn float_mixed_arithmetic() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("1.5 * 2");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::from_f64(3.0))
+    }
+
+    // 2 < 2.5
+    #[test]
+    fn float_mixed_comparison() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("2 < 2.5");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true))
+    }
+
+    // begin 1; 2; 3; end
+    #[test]
+    fn block() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("begin 1; 2; 3; end");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(3))
+    }
+
+    // if true then 1 else 2
+    #[test]
+    fn conditional_consequent() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("if true then 1 else 2");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1))
+    }
+
+    // if false then 1 else 2
+    #[test]
+    fn conditional_alternative() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("if false then 1 else 2");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(2))
+    }
+
+    // let s = 0; for i in 1..4 do s <- s + i; s
+    #[test]
+    fn iteration_over_range() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let s = 0; for i in 1..4 do s <- s + i; s");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(6))
+    }
+
+    // let s = 0; for x in array(3, 2) do s <- s + x; s
+    #[test]
+    fn iteration_over_array() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let s = 0; for x in array(3, 2) do s <- s + x; s");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(6))
+    }
+
+    // let i = 0; while true do begin i <- i + 1; if i == 5 then break end; i
+    #[test]
+    fn loop_break() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let i = 0; while true do begin i <- i + 1; if i == 5 then break end; i");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(5))
+    }
+
+    // let s = 0; for i in 0..5 do begin if i == 2 then continue; s <- s + i end; s
+    #[test]
+    fn iteration_continue() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let s = 0; for i in 0..5 do begin if i == 2 then continue; s <- s + i end; s");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(8))
+    }
+
+    // break
+    #[test]
+    #[should_panic(expected = "inside a loop")]
+    fn break_outside_loop() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("break");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // match x with ... end
+    #[test]
+    fn match_literal_and_binding() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("match 5 with 0 -> 0; \"five\" -> 1; n -> n + 1 end");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(6))
+    }
+
+    #[test]
+    fn match_array_and_object() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let o = object begin let items = array(2, 3); end; \
+                         match o with [x] -> x; object(items = [a, b]) -> a * b; _ -> 0 end");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(9))
+    }
+
+    #[test]
+    fn match_literal_compares_types() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("match 1.0 with 1 -> \"one\"; true -> \"yes\"; _ -> null end");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit)
+    }
+
+    #[test]
+    #[should_panic(expected = "No pattern matches 1")]
+    fn match_no_arm() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("match 1 with 2 -> null end");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // function f(x) x
+    #[test]
+    fn function_definition() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("function f(x) <- x");
+
+        println!("{:?}", ast);
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+
+        let reference = gamma.lookup_function("f").unwrap();
+        assert!(memory.contains_function(reference));
+        let function = memory.get_function(reference).unwrap();
+
+        let expected = make_function!("f", parse("x"), "x");
+        assert_eq!(function, &expected);
+    }
+
+    // f(1)
+    #[test]
+    fn function_call() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let function = make_function!("f", parse("x"), "x");
+        let reference = memory.put_function(function);
+        assert!(gamma.register_function("f".to_string(), reference).is_ok());
+
+        let ast = parse("f(1)");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1));
+    }
+
+    // array(10, 1)
+    #[test]
+    fn array_definition() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("array(10, 1)");
+
+        let expected_reference = Reference::Array {reference: 0, size: 10};
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), expected_reference);
+
+        assert!(memory.contains_object(&expected_reference));
+        assert_eq!(memory.get_object(&expected_reference),
+                   Some(&make_array!(10, Reference::Integer(1))));
+    }
+
+    // a[1]
+    #[test]
+    fn array_access() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let expected_reference = memory.put_object(make_array!(10, Reference::Integer(1)));
+        assert!(gamma.register_binding("a".to_string(), expected_reference).is_ok());
+
+        let ast = parse("a[1]");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1));
+    }
+
+    // a[-1]
+    #[test]
+    #[should_panic(expected = "Array index out of bounds: -1")]
+    fn array_access_negative_index() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let a = array(3, 0); a[-1]");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // a.push(x); a.pop(); a.insert(i, x); a.remove(i); a.concat(b); a.contains(x); a.index_of(x)
+    #[test]
+    fn array_methods() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let a = array(0, 0); a.push(1); a.push(2); a.push(3); a.pop()");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(3));
+
+        let ast = parse("a.insert(0, 7); a.insert(3, 8); a.remove(1)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1));
+
+        let ast = parse("a.concat(array(2, 9)).length()");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(5));
+
+        let ast = parse("a.contains(8) & a.index_of(2) == 1 & a.index_of(5) == -1");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+
+        let ast = parse("let s = 0; for x in a do s <- s + x; s");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(17));
+    }
+
+    // map(1 -> true, "a" -> 2)
+    #[test]
+    fn map_definition() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("map(1 -> true, \"a\" -> 2)");
+
+        // The string key is allocated before the map.
+        let expected_reference = Reference::Map(1);
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), expected_reference);
+
+        let mut expected_map = BTreeMap::new();
+        expected_map.insert(MapKey::Integer(Integer::Small(1)), Reference::Boolean(true));
+        expected_map.insert(MapKey::String("a".to_string()), Reference::Integer(2));
+        assert_eq!(memory.get_object(&expected_reference), Some(&Instance::map(expected_map)));
+    }
+
+    // m["a"] <- 1; m["a"]
+    #[test]
+    fn map_access() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let map_reference = memory.put_object(Instance::map(BTreeMap::new()));
+        assert!(gamma.register_binding("m".to_string(), map_reference).is_ok());
+
+        let ast = parse("m[\"a\"] <- 1; m[\"a\"]");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1));
+    }
+
+    // m.set(null, 1); m.has(null); m.remove(null); m.size()
+    #[test]
+    fn map_methods() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let map_reference = memory.put_object(Instance::map(BTreeMap::new()));
+        assert!(gamma.register_binding("m".to_string(), map_reference).is_ok());
+
+        let ast = parse("m.set(null, 1); m.has(null)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+
+        let ast = parse("m.remove(null)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1));
+
+        let ast = parse("m.size()");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(0));
+
+        let ast = parse("m.get(false)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+    }
+
+    // map(array(1, 0) -> 1)
+    #[test]
+    #[should_panic(expected = "as a map key")]
+    fn map_array_key() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("map(array(1, 0) -> 1)");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // o.fields(); o.has_field("x"); o.get("x"); o.set("x", 5); o.parent()
+    #[test]
+    fn object_reflection() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let p = object begin let z = 0; end; \
+                         let o = object extends p begin let y = 2; let x = 1; function f() -> 0; end");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+        let ast = parse("o.fields()[0] == \"x\" & o.methods()[0] == \"f\"");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+
+        let ast = parse("o.has_field(\"z\")");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(false));
+
+        let ast = parse("o.set(\"x\", 5); o.get(\"x\")");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(5));
+
+        let ast = parse("o.parent().has_field(\"z\")");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+
+        let ast = parse("p.parent()");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+    }
+
+    #[test]
+    fn reflection_on_object_extending_value() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let o = object extends array(2, 0) begin let x = 1; end; \
+                         o.has_field(\"x\") & o.fields()[0] == \"x\" & o.get(\"x\") == 1");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+    }
+
+    // push(a, 5); pop(a); length(a); slice(a, 0, 2); sort(a)
+    #[test]
+    fn builtin_array_functions() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let a = array(2, 4); push(a, 1); push(a, 9); pop(a)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(9));
+
+        let ast = parse("sort(a); let s = 0; for x in slice(a, 0, 2) do s <- s * 10 + x; s");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(14));
+
+        let ast = parse("length(a)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(3));
+    }
+
+    // length(5)
+    #[test]
+    #[should_panic(expected = "Expected an array")]
+    fn builtin_length_of_integer() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("length(5)");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // abs(-4) + min(3, 2) + max(3, 2) + pow(2, 10)
+    #[test]
+    fn builtin_integer_functions() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("abs(-4) + min(3, 2) + max(3, 2) + pow(2, 10)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1033));
+    }
+
+    // assert(1); assert(0); assert("")
+    #[test]
+    fn builtin_assert_fails_only_on_false_and_null() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("assert(1); assert(0); assert(\"\"); assert(true)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+    }
+
+    // assert(null)
+    #[test]
+    #[should_panic(expected = "Assertion failed")]
+    fn builtin_assert_null() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("assert(null)");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // read_int() + read_int(); read_line()
+    #[test]
+    fn builtin_input_functions() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::with_input(vec!("4", " 38 ", "hello"));
+
+        let ast = parse("let sum = read_int() + read_int(); print(\"~ ~ ~\", sum, read_line(), read_line())");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+        assert_eq!(world.contents(), "42 hello null");
+    }
+
+    // print("before"); exit(3); print("after")
+    #[test]
+    fn builtin_exit() {
+        use crate::world::Exited;
+
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("print(\"before\"); exit(3); print(\"after\")");
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            evaluate(&mut gamma, &mut memory, &mut world, &ast);
+        }));
+        assert_eq!(result.unwrap_err().downcast_ref::<Exited>(), Some(&Exited(3)));
+        assert_eq!(world.exit_code(), Some(3));
+        assert_eq!(world.contents(), "before");
+    }
+
+    // function abs(x) -> 42; abs(-1)
+    #[test]
+    fn builtin_shadowed_by_user_function() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("function abs(x) -> 42; abs(-1)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(42));
+    }
+
+    // "a" == "a"
+    #[test]
+    fn string_equality() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("\"a\" == \"a\"");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true))
+    }
+
+    // object begin end
+    #[test]
+    fn empty_object_definition() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("object begin end");
+
+        let expected_reference = Reference::Object(0);
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), expected_reference);
+
+        assert!(memory.contains_object(&expected_reference));
+        assert_eq!(memory.get_object(&expected_reference),
+                   Some(&Instance::empty()));
+    }
+
+    // object begin let x = 1; function add(x) <- (this.x) + x; end
+    #[test]
+    fn object_definition() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("object begin let x = 1; function add(x) <- (this.x) + x; end");
+
+        let expected_reference = Reference::Object(1);
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), expected_reference);
+
+        let method = make_function!("add", parse("(this.x) + x"), "x");
+
+        assert!(memory.contains_function(&FunctionReference::Function(0)));
+        assert_eq!(memory.get_function(&FunctionReference::Function(0)), Some(&method));
+
+        let mut fields = HashMap::new();
+        fields.insert("x".to_string(), Reference::Integer(1));
+
+        let mut methods = HashMap::new();
+        methods.insert("add".to_string(), FunctionReference::Function(0));
+
+        assert!(memory.contains_object(&expected_reference));
+        assert_eq!(memory.get_object(&expected_reference),
+                Some(&Instance::Object{extends: None, fields, methods, constants: HashSet::new()}));
+    }
+
+    // obj.x
+    #[test]
+    fn field_access() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let mut fields = HashMap::new();
+        fields.insert("x".to_string(), Reference::Integer(42));
+
+        let object_instance = Instance::Object{extends: None, fields, methods: HashMap::new(), constants: HashSet::new()};
+        let object_reference = memory.put_object(object_instance);
+        assert!(gamma.register_binding("obj".to_string(), object_reference).is_ok());
+
+        let ast = parse("obj.x");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(42));
+    }
+
+    // obj.get()
+    #[test]
+    fn method_getter_call() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let mut fields = HashMap::new();
+        fields.insert("x".to_string(), Reference::Integer(42));
+
+        let mut methods = HashMap::new();
+        let method_instance = make_function!("get", parse("(this.x)"));
+        let method_reference = memory.put_function(method_instance);
+        methods.insert("get".to_string(), method_reference);
+
+        let object_instance = Instance::Object{extends: None, fields, methods, constants: HashSet::new()};
+        let object_reference = memory.put_object(object_instance);
+        assert!(gamma.register_binding("obj".to_string(), object_reference).is_ok());
+
+        let ast = parse("obj.get()");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(42));
+    }
+
+    // fortytwo + 1
+    #[test]
+    fn operator_call() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let mut fields = HashMap::new();
+        fields.insert("value".to_string(), Reference::Integer(42));
+
+        let mut methods = HashMap::new();
+        let method_instance = make_function!("+", parse("(this.value) + x"), "x");
+        let method_reference = memory.put_function(method_instance);
+        methods.insert("+".to_string(), method_reference);
+
+        let object_instance = Instance::Object{extends: None, fields, methods, constants: HashSet::new()};
+        let object_reference = memory.put_object(object_instance);
+        assert!(gamma.register_binding("fortytwo".to_string(), object_reference).is_ok());
+
+        let ast = parse("fortytwo + 1");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(43));
+    }
+
+    #[test]
+    fn object_equality() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let mut fields = HashMap::new();
+        fields.insert("value".to_string(), Reference::Integer(42));
+
+        let mut methods = HashMap::new();
+        let method_instance = make_function!("+", parse("(this.value) + x"), "x");
+        let method_reference = memory.put_function(method_instance);
+        methods.insert("+".to_string(), method_reference);
+
+        let object_instance = Instance::Object{extends: None, fields, methods, constants: HashSet::new()};
+        let object_reference = memory.put_object(object_instance);
+        assert!(gamma.register_binding("fortytwo".to_string(), object_reference).is_ok());
+
+        let ast = parse("fortytwo == fortytwo");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+    }
+
+    #[test]
+    fn object_inequality() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let mut fields = HashMap::new();
+        fields.insert("value".to_string(), Reference::Integer(42));
+
+        let mut methods = HashMap::new();
+        let method_instance = make_function!("+", parse("(this.value) + x"), "x");
+        let method_reference = memory.put_function(method_instance);
+        methods.insert("+".to_string(), method_reference);
+
+        let object_instance = Instance::Object{extends: None, fields, methods, constants: HashSet::new()};
+        let object_reference = memory.put_object(object_instance);
+        assert!(gamma.register_binding("fortytwo".to_string(), object_reference).is_ok());
+
+        let ast = parse("fortytwo != fortytwo");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(false));
+    }
+
+    #[test]
+    fn print_simple() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("print(\"a\")");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+
+        let expected_output = BufferedIO::from(vec!("a"));
+        assert_eq!(expected_output, world);
+    }
+
+    #[test]
+    fn print_complicated() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("print(\"~.~~.~\", 1, 2, 3, 4)");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+
+        let expected_output = BufferedIO::from(vec!("1.23.4"));
+        assert_eq!(expected_output, world);
+    }
+
+    #[test]
+    fn print_float() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("print(\"~ ~\", 1.0, 0.25)");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+
+        let expected_output = BufferedIO::from(vec!("1.0 0.25"));
+        assert_eq!(expected_output, world);
+    }
+
+    #[test]
+    fn print_escape() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("print(\"~\\~~\", 1, 2)");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+
+        let expected_output = BufferedIO::from(vec!("1~2"));
+        assert_eq!(expected_output, world);
+    }
+
+    #[test]
+    fn print_directives() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let x = 255; print(\"~{1}~{0}|~{:>4}|~{:*<4}|~{x:x}|~{x:08b}\", 1, \"a\")");
+
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+
+        let expected_output = BufferedIO::from(vec!("a1|   1|a***|ff|11111111"));
+        assert_eq!(expected_output, world);
+    }
+
+    // class Point(x, y) begin let x = x; let y = y; function sum() -> this.x + this.y end; new Point(1, 2).sum()
+    #[test]
+    fn class_instantiation() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("class Point(x, y) begin let x = x; let y = y; function sum() -> this.x + this.y end");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Unit);
+
+        let ast = parse("new Point(1, 2).sum()");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(3));
+
+        let ast = parse("let a = new Point(1, 2); let b = new Point(1, 2); a.x <- 5; b.x");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(1));
+    }
+
+    // class Square(side) extends Rectangle(side, side) begin end; new Square(3).area()
+    #[test]
+    fn class_with_superclass() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("class Rectangle(w, h) begin let w = w; let h = h; function area() -> this.w * this.h end; \
+                         class Square(side) extends Rectangle(side, side) begin function sides() -> 4 end");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+        let ast = parse("let s = new Square(3); s.area() + s.sides()");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(13));
+    }
+
+    // class C() extends B begin function f(x) -> super.f(x) + 1 end; new C().f(2)
+    #[test]
+    fn super_method_call() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("class A() begin function f(x) -> x end; \
+                         class B() extends A begin function f(x) -> super.f(x) * 10 end; \
+                         class C() extends B begin function f(x) -> super.f(x) + 1 end");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+        let ast = parse("new C().f(2)");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(21));
+    }
+
+    // object extends p begin function g() -> super.g() + this.x end
+    #[test]
+    fn super_method_call_keeps_receiver() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let p = object begin let x = 1; function g() -> 10 end; \
+                         let o = object extends p begin let x = 2; function g() -> super.g() + this.x end");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+        let ast = parse("o.g()");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(12));
+    }
+
+    // class Child() extends Base begin let x = 2; function get() -> super.get() + 10 end
+    #[test]
+    fn super_method_call_sees_overridden_field() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("class Base() begin let x = 1; function get() -> this.x end; \
+                         class Child() extends Base begin let x = 2; function get() -> super.get() + 10 end");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+        let ast = parse("new Child().get()");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(12));
+    }
+
+    // const x = 1; x + 1
+    #[test]
+    fn constant_definition() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("const x = 1; x + 1");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Integer(2));
+    }
+
+    // const x = 1; x <- 2
+    #[test]
+    #[should_panic(expected = "Constant")]
+    fn constant_cannot_be_changed() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("const x = 1; x <- 2");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // let o = object begin const x = 1 end; o.x <- 2
+    #[test]
+    #[should_panic(expected = "is constant")]
+    fn constant_field_cannot_be_changed() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let o = object begin const x = 1; let y = 2 end; o.y <- 3; o.x <- 2");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+    }
+
+    // array(2, 1) == array(2, 1) & (array(2, 1) is array(2, 1)) == false
+    #[test]
+    fn structural_equality_and_identity() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let a = array(2, 1); let b = array(2, 1); a[1] <- 2; b[1] <- 2; \
+                         let o = object begin let x = a end; let p = object begin let x = b end");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+        let ast = parse("a == b & o == p & a is a & (a is b) == false & (o is p) == false & 1 is 1");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+    }
+
+    // object begin function ==(other) -> true end == 1
+    #[test]
+    fn user_defined_equality_overrides_structural() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let o = object begin function ==(other) -> true end; \
+                         let a = array(1, o); let b = array(1, 1)");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+        let ast = parse("o == 1 & a == b & (o is 1) == false");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+    }
+
+    // hash(object begin let x = array(1, 1) end) == hash(object begin let x = array(1, 1) end)
+    #[test]
+    fn hash_of_equal_values() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("hash(object begin let x = array(1, 1) end) == hash(object begin let x = array(1, 1) end) \
+                         & hash(array(1, 1)) != hash(array(1, 2))");
+        assert_eq!(evaluate(&mut gamma, &mut memory, &mut world, &ast), Reference::Boolean(true));
+    }
+
+    // The shared conformance programs print the same output as under the bytecode interpreter.
+    #[test]
+    fn equality_conformance() {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../conformance/equality");
+        let mut sources: Vec<std::path::PathBuf> = std::fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |extension| extension == "fml"))
+            .collect();
+        sources.sort();
+
+        for source in sources {
+            let mut memory = Memory::new();
+            let mut gamma = EnvironmentStack::new();
+            let mut world = BufferedIO::new();
+
+            let ast = parse(&std::fs::read_to_string(&source).unwrap());
+            evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+            let expected = std::fs::read_to_string(source.with_extension("out")).unwrap();
+            let expected_output = BufferedIO::from(vec!(expected.trim_end_matches('\n')));
+            assert_eq!(world, expected_output, "{}", source.display());
+        }
+    }
+
+    // Programs from `fml_parser::generator` print the same output as under the bytecode
+    // interpreter. They print more than once, so the output is compared as one string.
+    #[test]
+    fn generated_conformance() {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../conformance/generated");
+        let mut sources: Vec<std::path::PathBuf> = std::fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |extension| extension == "fml"))
+            .collect();
+        sources.sort();
+
+        for source in sources {
+            let mut memory = Memory::new();
+            let mut gamma = EnvironmentStack::new();
+            let mut world = BufferedIO::new();
+
+            let ast = parse(&std::fs::read_to_string(&source).unwrap());
+            evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+            let expected = std::fs::read_to_string(source.with_extension("out")).unwrap();
+            assert_eq!(world.contents().trim_end_matches('\n'), expected.trim_end_matches('\n'), "{}", source.display());
+        }
+    }
+
+    // if 0 then 1 else 2; a[0] <- 5; o[0]
+    #[test]
+    fn feeny_semantics() {
+        let mut memory = Memory::new();
+        let mut gamma = EnvironmentStack::new();
+        let mut world = BufferedIO::new();
+
+        let ast = parse("let a = array(2, 0); a[0] <- 5; \
+                         let o = object begin function get(i) -> i + 1; function set(i, x) -> x * 2 end; \
+                         print(\"~ ~ ~ ~ ~\", if 0 then 1 else 2, a[0], a[1], o[1], o[1] <- 4)");
+        evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+        assert_eq!(world, BufferedIO::from(vec!("1 5 0 2 8")))
+    }
+
+    // The original Feeny test programs print the output recorded in the comment block at their end,
+    // like under the bytecode interpreter.
+    #[test]
+    fn feeny_programs() {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../feeny/tests");
+        for name in ["bsearch", "cplx", "fibonacci", "hanoi", "lists", "vector", "inheritance"] {
+            let program = std::fs::read_to_string(directory.join(name).with_extension("feeny")).unwrap();
+            let expected: Vec<&str> = program.lines()
+                .skip_while(|line| !line.to_uppercase().contains("OUTPUT"))
+                .skip(2)
+                .map(|line| line.trim_start_matches(';').trim_end())
+                .collect();
+
+            let mut memory = Memory::new();
+            let mut gamma = EnvironmentStack::new();
+            let mut world = BufferedIO::new();
+
+            let ast = fml_parser::feeny::parse(&program).unwrap_or_else(|error| panic!("Cannot parse {}: {:?}", name, error));
+            evaluate(&mut gamma, &mut memory, &mut world, &ast);
+
+            let contents = world.contents();
+            let output: Vec<&str> = contents.lines().map(|line| line.trim_end()).collect();
+            assert_eq!(output.join("\n").trim(), expected.join("\n").trim(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+
+/**
+ * Everything a program does outside of the interpreter goes through the world, so that tests can
+ * see what a program printed, give it input, and catch it exiting.
+ */
+pub trait World {
+    fn output(&mut self, string: String);
+
+    // The next line of input, without its line ending, or `None` at the end of the input.
+    fn read_line(&mut self) -> Option<String>;
+
+    // Ends the program with an exit code. Nothing is evaluated after it.
+    fn exit(&mut self, code: i32) -> !;
+}
+
+/**
+ * What a `BufferedIO` world unwinds with when the program exits, carrying the exit code.
+ */
+#[derive(PartialEq,Eq,Debug,Clone,Copy)]
+pub struct Exited(pub i32);
+
+/**
+ * A world that keeps everything the program prints, one string per `print`, and reads its input
+ * from a list of lines. Exiting unwinds with `Exited`, after recording the exit code.
+ */
+#[derive(PartialEq,Eq,Debug,Clone,Default)]
+pub struct BufferedIO {
+    output: Vec<String>,
+    input: VecDeque<String>,
+    exit_code: Option<i32>,
+}
+
+impl BufferedIO {
+    pub fn new() -> Self {
+        BufferedIO { output: Vec::new(), input: VecDeque::new(), exit_code: None }
+    }
+
+    pub fn from(output: Vec<&str>) -> Self {
+        BufferedIO { output: output.into_iter().map(|string| string.to_string()).collect(), ..BufferedIO::new() }
+    }
+
+    pub fn with_input(input: Vec<&str>) -> Self {
+        BufferedIO { input: input.into_iter().map(|line| line.to_string()).collect(), ..BufferedIO::new() }
+    }
+
+    // Everything printed so far, as one string.
+    pub fn contents(&self) -> String {
+        self.output.concat()
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+}
+
+impl World for BufferedIO {
+    fn output(&mut self, string: String) {
+        self.output.push(string)
+    }
+
+    fn read_line(&mut self) -> Option<String> {
+        self.input.pop_front()
+    }
+
+    fn exit(&mut self, code: i32) -> ! {
+        self.exit_code = Some(code);
+        std::panic::panic_any(Exited(code))
+    }
+}
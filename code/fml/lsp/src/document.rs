@@ -0,0 +1,260 @@
+use fml_ast::{AST, Identifier};
+use fml_parser::Syntax;
+use fml_parser::diagnostics::Diagnostic;
+use fml_parser::checker::{Reference, resolve_source};
+
+/**
+ * An open source file, along with what the server knows about it.
+ *
+ * The AST carries no source locations, so names are found by scanning the text: every identifier
+ * is recorded with its offsets, and the ones that follow `function`, `let`, `const`, `class`, or
+ * `for` (or `defn`, `method`, and `var` in Feeny), as well as the parameters of functions, are
+ * marked as definitions. Uses of names are resolved by the checker, which follows the scopes of
+ * the program, so a name only resolves while the text parses. Names of fields and methods, which
+ * follow a `.`, are never resolved.
+ *
+ * The AST is kept from the last version of the text that parsed, so that completion keeps working
+ * while the text is being edited. Text that parses is also checked, and its diagnostics are those
+ * of the checker.
+ */
+pub struct Document {
+    pub text: String,
+    pub syntax: Syntax,
+    pub ast: Option<AST>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub names: Vec<Name>,
+    pub references: Vec<Reference>,
+}
+
+#[derive(PartialEq,Debug,Copy,Clone)]
+pub enum Kind {
+    Function,
+    Class,
+    Variable,
+    Parameter,
+}
+
+#[derive(PartialEq,Debug,Clone)]
+pub struct Name {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    pub definition: Option<Kind>,
+    pub parameters: Vec<String>,
+}
+
+impl Document {
+    pub fn new(uri: &str, text: String) -> Self {
+        let mut document = Document {
+            text: String::new(),
+            syntax: Syntax::from_path(std::path::Path::new(uri)),
+            ast: None,
+            diagnostics: vec!(),
+            names: vec!(),
+            references: vec!(),
+        };
+        document.update(text);
+        document
+    }
+
+    pub fn update(&mut self, text: String) {
+        match resolve_source(self.syntax, &text) {
+            Ok((ast, diagnostics, references)) => {
+                self.ast = Some(ast);
+                self.diagnostics = diagnostics;
+                self.references = references;
+            }
+            Err(diagnostics) => { self.diagnostics = diagnostics; self.references = vec!() }
+        }
+        self.names = scan(&text, self.syntax);
+        self.text = text;
+    }
+
+    pub fn name_at(&self, offset: usize) -> Option<usize> {
+        self.names.iter().position(|name| name.start <= offset && offset <= name.end)
+    }
+
+    pub fn definition_of(&self, index: usize) -> Option<usize> {
+        let name = &self.names[index];
+        if name.definition.is_some() {
+            return Some(index)
+        }
+        let reference = self.references.iter().find(|reference| reference.name == (name.start, name.end))?;
+        self.names.iter().position(|candidate| (candidate.start, candidate.end) == reference.definition)
+    }
+
+    pub fn references_to(&self, definition: usize) -> Vec<usize> {
+        (0..self.names.len()).filter(|i| self.definition_of(*i) == Some(definition)).collect()
+    }
+
+    // The first definition of the given name and kind, used to locate the symbols of the AST.
+    pub fn find_definition(&self, name: &str, kind: Kind) -> Option<&Name> {
+        self.names.iter().find(|candidate| candidate.name == name && candidate.definition == Some(kind))
+    }
+
+    // Line and character of an offset, counted from 0, in UTF-16 code units as LSP requires.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset.min(self.text.len())];
+        let line = before.matches('\n').count();
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        (line, before[line_start..].encode_utf16().count())
+    }
+
+    pub fn offset(&self, line: usize, character: usize) -> usize {
+        let line_start = self.text.split_inclusive('\n').take(line).map(|line| line.len()).sum::<usize>();
+        let mut units = 0;
+        for (index, c) in self.text[line_start..].char_indices() {
+            if units >= character || c == '\n' {
+                return line_start + index
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+}
+
+// Methods defined in the objects and classes of the program, with their parameters.
+pub fn methods(ast: &AST) -> Vec<(Identifier, Vec<Identifier>)> {
+    let mut methods = Vec::new();
+    collect_methods(ast, &mut methods);
+    methods
+}
+
+fn collect_methods(ast: &AST, methods: &mut Vec<(Identifier, Vec<Identifier>)>) {
+    match ast {
+        AST::ObjectDefinition { members, .. } | AST::ClassDefinition { members, .. } => {
+            for member in members {
+                if let AST::FunctionDefinition { function, parameters, .. } = member.as_ref() {
+                    if !methods.iter().any(|(name, _)| name == function) {
+                        methods.push((function.clone(), parameters.clone()))
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+    for child in children(ast) {
+        collect_methods(child, methods)
+    }
+}
+
+fn children(ast: &AST) -> Vec<&AST> {
+    match ast {
+        AST::Number(_) | AST::Float(_) | AST::Boolean(_) | AST::String(_) | AST::Unit
+        | AST::VariableAccess { .. } | AST::Break | AST::Continue | AST::Import { .. } => vec!(),
+
+        AST::VariableDefinition { value, .. } | AST::ConstantDefinition { value, .. }
+        | AST::VariableMutation { value, .. } => vec!(value),
+        AST::FieldAccess { object, .. } => vec!(object),
+        AST::FunctionDefinition { body, .. } | AST::OperatorDefinition { body, .. } => vec!(body),
+
+        AST::ArrayDefinition { size: first, value: second }
+        | AST::ArrayAccess { array: first, index: second }
+        | AST::FieldMutation { object: first, value: second, .. }
+        | AST::Operation { left: first, right: second, .. }
+        | AST::Loop { condition: first, body: second }
+        | AST::Iteration { iterable: first, body: second, .. }
+        | AST::Range { start: first, end: second } => vec!(first, second),
+
+        AST::ArrayMutation { array, index, value } => vec!(array, index, value),
+        AST::Conditional { condition, consequent, alternative } => vec!(condition, consequent, alternative),
+
+        AST::ObjectDefinition { extends, members } | AST::ClassDefinition { extends, members, .. } =>
+            extends.iter().chain(members.iter()).map(|child| child.as_ref()).collect(),
+        AST::MapDefinition { entries } =>
+            entries.iter().flat_map(|(key, value)| vec!(key.as_ref(), value.as_ref())).collect(),
+
+        AST::MethodCall { object, arguments, .. } | AST::OperatorCall { object, arguments, .. } =>
+            std::iter::once(object).chain(arguments.iter()).map(|child| child.as_ref()).collect(),
+        AST::FunctionCall { arguments, .. } | AST::SuperMethodCall { arguments, .. }
+        | AST::Instantiation { arguments, .. } | AST::Print { arguments, .. }
+        | AST::Top(arguments) | AST::Block(arguments) =>
+            arguments.iter().map(|child| child.as_ref()).collect(),
+
+        AST::Match { value, arms } =>
+            std::iter::once(value).chain(arms.iter().map(|(_, arm)| arm)).map(|child| child.as_ref()).collect(),
+    }
+}
+
+const FML_KEYWORDS: &[&str] = &[
+    "begin", "end", "if", "then", "else", "let", "const", "null", "print", "object", "extends",
+    "class", "new", "super", "is", "while", "do", "for", "in", "break", "continue", "match", "with",
+    "function", "array", "map", "true", "false", "this", "import", "as",
+];
+
+const FEENY_KEYWORDS: &[&str] = &[
+    "defn", "method", "object", "var", "if", "else", "while", "printf", "null",
+];
+
+// The kind of name defined by the identifier following a keyword.
+fn defines(keyword: &str, syntax: Syntax) -> Option<Kind> {
+    match (syntax, keyword) {
+        (Syntax::FML, "function") | (Syntax::Feeny, "defn") | (Syntax::Feeny, "method") => Some(Kind::Function),
+        (Syntax::FML, "class") => Some(Kind::Class),
+        (Syntax::FML, "let") | (Syntax::FML, "const") | (Syntax::FML, "for")
+        | (Syntax::Feeny, "var") => Some(Kind::Variable),
+        _ => None,
+    }
+}
+
+pub fn scan(text: &str, syntax: Syntax) -> Vec<Name> {
+    let keywords = match syntax { Syntax::FML => FML_KEYWORDS, Syntax::Feeny => FEENY_KEYWORDS };
+    let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_'
+        || (syntax == Syntax::Feeny && (c == '-' || c == '?' || c == '!'));
+
+    let mut names: Vec<Name> = Vec::new();
+    let mut defining: Option<Kind> = None;
+    // The function or class whose parameters come next, and whether they are being read.
+    let mut parameters_of: Option<Option<usize>> = None;
+    let mut in_parameters = false;
+
+    let mut position = 0;
+    while let Some(c) = text[position..].chars().next() {
+        let start = position;
+        let rest = &text[position..];
+        if c.is_whitespace() {
+            position += c.len_utf8();
+        } else if (syntax == Syntax::FML && rest.starts_with("//")) || (syntax == Syntax::Feeny && c == ';') {
+            position += rest.find('\n').unwrap_or(rest.len());
+        } else if syntax == Syntax::FML && rest.starts_with("/*") {
+            position += rest[2..].find("*/").map_or(rest.len(), |end| end + 4);
+        } else if c == '"' {
+            let mut escaped = false;
+            let length = rest[1..].char_indices()
+                .find(|(_, c)| { let end = *c == '"' && !escaped; escaped = *c == '\\' && !escaped; end })
+                .map_or(rest.len(), |(index, _)| index + 2);
+            position += length;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let length = rest.find(|c: char| !is_identifier(c)).unwrap_or(rest.len());
+            position += length;
+            let word = &text[start..position];
+            if keywords.contains(&word) {
+                defining = defines(word, syntax);
+                if matches!(defining, Some(Kind::Function) | Some(Kind::Class)) {
+                    parameters_of = Some(None);
+                }
+                continue
+            }
+
+            let definition = if in_parameters { Some(Kind::Parameter) } else { defining.take() };
+            if in_parameters {
+                if let Some(Some(function)) = parameters_of {
+                    let parameter = word.to_string();
+                    names[function].parameters.push(parameter);
+                }
+            } else if parameters_of == Some(None) && definition.is_some() {
+                parameters_of = Some(Some(names.len()));
+            }
+            names.push(Name { name: word.to_string(), start, end: position, definition, parameters: vec!() });
+        } else {
+            position += c.len_utf8();
+            defining = None;
+            match c {
+                '(' if parameters_of.is_some() && !in_parameters => in_parameters = true,
+                ')' if in_parameters => { in_parameters = false; parameters_of = None }
+                _ => (),
+            }
+        }
+    }
+    names
+}
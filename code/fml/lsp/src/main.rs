@@ -0,0 +1,180 @@
+pub mod protocol;
+pub mod document;
+pub mod server;
+
+extern crate fml_ast;
+extern crate fml_parser;
+extern crate serde_json;
+
+use std::io::{stdin, stdout, BufRead, BufReader, Write};
+
+use serde_json::Value;
+
+use crate::protocol::{read_message, write_message, error_response, PARSE_ERROR};
+use crate::server::Server;
+
+/**
+ * A language server for FML (and Feeny, for files ending in `.feeny`), speaking JSON-RPC over
+ * standard input and output. Editors start it as `fml_lsp` with no arguments.
+ */
+fn main() {
+    serve(&mut BufReader::new(stdin()), &mut stdout());
+}
+
+// Handles messages until the input ends or the editor asks the server to exit. A message that
+// cannot be read is answered with an error, and the server goes on to the next one, unless the
+// input itself failed.
+fn serve(input: &mut impl BufRead, output: &mut impl Write) {
+    let mut server = Server::new();
+    loop {
+        let message = match read_message(input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(error) if error.kind() == std::io::ErrorKind::InvalidData => {
+                let reply = error_response(&Value::Null, PARSE_ERROR, &error.to_string());
+                write_message(output, &reply).expect("Error writing message");
+                continue
+            }
+            Err(error) => panic!("Error reading message: {}", error),
+        };
+        for reply in server.handle(&message) {
+            write_message(output, &reply).expect("Error writing message");
+        }
+        if server.exited {
+            break
+        }
+    }
+}
+
+#[cfg(test)]
+mod server_tests {
+    use serde_json::{json, Value};
+    use crate::server::Server;
+
+    const URI: &str = "file:///test.fml";
+
+    fn open(server: &mut Server, text: &str) -> Vec<Value> {
+        server.handle(&json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen",
+                               "params": { "textDocument": { "uri": URI, "languageId": "fml",
+                                                             "version": 1, "text": text } } }))
+    }
+
+    fn request(server: &mut Server, method: &str, line: usize, character: usize) -> Value {
+        let mut messages = server.handle(&json!({
+            "jsonrpc": "2.0", "id": 1, "method": method,
+            "params": { "textDocument": { "uri": URI }, "position": { "line": line, "character": character },
+                        "context": { "includeDeclaration": true } } }));
+        assert_eq!(messages.len(), 1);
+        messages.remove(0)["result"].clone()
+    }
+
+    fn range(start_line: usize, start: usize, end_line: usize, end: usize) -> Value {
+        json!({ "start": { "line": start_line, "character": start }, "end": { "line": end_line, "character": end } })
+    }
+
+    #[test] fn initialize() {
+        let mut server = Server::new();
+        let capabilities = &request(&mut server, "initialize", 0, 0)["capabilities"];
+        assert_eq!(capabilities["definitionProvider"], json!(true));
+        assert_eq!(capabilities["completionProvider"]["triggerCharacters"], json!(["."]));
+    }
+
+    #[test] fn diagnostics_on_open_and_change() {
+        let mut server = Server::new();
+        let messages = open(&mut server, "let x = 1;\nlet y = ;");
+        assert_eq!(messages[0]["method"], json!("textDocument/publishDiagnostics"));
+        let diagnostics = &messages[0]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[0]["range"], range(1, 8, 1, 9));
+        assert_eq!(diagnostics[0]["message"], json!("unexpected `;`, expected an expression"));
+
+        let messages = server.handle(&json!({ "jsonrpc": "2.0", "method": "textDocument/didChange",
+            "params": { "textDocument": { "uri": URI, "version": 2 },
+                        "contentChanges": [{ "text": "let x = 1;\nlet y = 2;" }] } }));
+        assert_eq!(messages[0]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test] fn checker_diagnostics() {
+        let mut server = Server::new();
+        let messages = open(&mut server, "function f(a) -> a + b;\nf(1, 2)");
+        let diagnostics = &messages[0]["params"]["diagnostics"];
+        assert_eq!(diagnostics[0]["range"], range(0, 21, 0, 22));
+        assert_eq!(diagnostics[0]["severity"], json!(2));
+        assert_eq!(diagnostics[1]["message"], json!("function `f` takes 1 argument, but 2 were given"));
+        assert_eq!(diagnostics[1]["severity"], json!(1));
+    }
+
+    #[test] fn definition() {
+        let mut server = Server::new();
+        open(&mut server, "function f(x) -> x + 1;\nlet y = 1;\nf(y)");
+        assert_eq!(request(&mut server, "textDocument/definition", 2, 0)["range"], range(0, 9, 0, 10));
+        assert_eq!(request(&mut server, "textDocument/definition", 2, 2)["range"], range(1, 4, 1, 5));
+        assert_eq!(request(&mut server, "textDocument/definition", 0, 17)["range"], range(0, 11, 0, 12));
+    }
+
+    #[test] fn shadowing_resolves_to_nearest_definition() {
+        let mut server = Server::new();
+        open(&mut server, "let x = 1;\nlet x = x + 1;\nx");
+        assert_eq!(request(&mut server, "textDocument/definition", 2, 0)["range"], range(1, 4, 1, 5));
+    }
+
+    #[test] fn references() {
+        let mut server = Server::new();
+        open(&mut server, "function f() -> 1;\nf();\nlet g = f() // f\n");
+        let references = request(&mut server, "textDocument/references", 0, 9);
+        let ranges: Vec<Value> = references.as_array().unwrap().iter().map(|reference| reference["range"].clone()).collect();
+        assert_eq!(ranges, vec!(range(0, 9, 0, 10), range(1, 0, 1, 1), range(2, 8, 2, 9)));
+    }
+
+    #[test] fn hover_shows_parameters() {
+        let mut server = Server::new();
+        open(&mut server, "function add(a, b) -> a + b;\nadd(1, 2)");
+        let hover = request(&mut server, "textDocument/hover", 1, 1);
+        assert_eq!(hover["contents"]["value"], json!("```fml\nfunction add(a, b)\n```"));
+    }
+
+    #[test] fn document_symbols() {
+        let mut server = Server::new();
+        open(&mut server, "function f() -> 1;\nlet o = object begin function m(x) -> x end;\nclass C() begin end;\nlet z = 1");
+        let symbols = request(&mut server, "textDocument/documentSymbol", 0, 0);
+        let names: Vec<(Value, Value)> = symbols.as_array().unwrap().iter()
+            .map(|symbol| (symbol["name"].clone(), symbol["kind"].clone())).collect();
+        assert_eq!(names, vec!((json!("f"), json!(12)), (json!("o"), json!(19)), (json!("C"), json!(5))));
+        assert_eq!(symbols[1]["children"][0]["name"], json!("m"));
+    }
+
+    #[test] fn completion_of_methods_while_editing() {
+        let mut server = Server::new();
+        open(&mut server, "let o = object begin function push(x) -> x; function pop() -> null end;\no");
+        server.handle(&json!({ "jsonrpc": "2.0", "method": "textDocument/didChange",
+            "params": { "textDocument": { "uri": URI, "version": 2 },
+                        "contentChanges": [{ "text": "let o = object begin function push(x) -> x; function pop() -> null end;\no." }] } }));
+        let labels: Vec<Value> = request(&mut server, "textDocument/completion", 1, 2).as_array().unwrap()
+            .iter().map(|item| item["label"].clone()).collect();
+        assert_eq!(labels, vec!(json!("push"), json!("pop")));
+    }
+
+    #[test] fn definition_follows_scopes() {
+        let mut server = Server::new();
+        open(&mut server, "function f(x) -> x;\nfunction g() -> x;\nlet x = 1;\nlet x = x + 1;\nlet o = object begin let v = 1 end;\nlet v = 2;\no.v");
+        assert_eq!(request(&mut server, "textDocument/definition", 1, 16)["range"], range(2, 4, 2, 5));
+        assert_eq!(request(&mut server, "textDocument/definition", 3, 8)["range"], range(2, 4, 2, 5));
+        assert_eq!(request(&mut server, "textDocument/definition", 6, 2), Value::Null);
+    }
+
+    #[test] fn malformed_messages_are_answered() {
+        let shutdown = json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }).to_string();
+        let input = format!("Content-Length: 5\r\n\r\n{{oops\r\nContent-Length: {}\r\n\r\n{}", shutdown.len(), shutdown);
+        let mut output = Vec::new();
+        crate::serve(&mut input.as_bytes(), &mut output);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("\"code\":-32700"), "{}", output);
+        assert!(output.contains("\"result\":null"), "{}", output);
+    }
+
+    #[test] fn unknown_method() {
+        let mut server = Server::new();
+        let messages = server.handle(&json!({ "jsonrpc": "2.0", "id": 7, "method": "workspace/symbol", "params": {} }));
+        assert_eq!(messages[0]["error"]["code"], json!(-32601));
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use serde_json::{json, Value};
+
+use crate::document::{Document, Kind, Name, methods};
+use crate::protocol::{response, error_response, notification, METHOD_NOT_FOUND, INVALID_PARAMS};
+use fml_ast::AST;
+
+// Kinds of symbols and completion items, as numbered by LSP.
+const SYMBOL_CLASS: u64 = 5;
+const SYMBOL_FUNCTION: u64 = 12;
+const SYMBOL_OBJECT: u64 = 19;
+const SYMBOL_METHOD: u64 = 6;
+const COMPLETION_METHOD: u64 = 2;
+const SEVERITY_ERROR: u64 = 1;
+const SEVERITY_WARNING: u64 = 2;
+const SYNC_FULL: u64 = 1;
+
+/**
+ * Handles the messages of a single editor session. Each message produces the messages to send
+ * back: the response to a request, and the diagnostics of documents that were opened or changed.
+ */
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    pub exited: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server { documents: HashMap::new(), exited: false }
+    }
+
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = match message.get("id") {
+            Some(id) => id,
+            None => return self.handle_notification(method, params),
+        };
+
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": SYNC_FULL,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                },
+                "serverInfo": { "name": "fml-lsp" },
+            })),
+            "shutdown" => Some(Value::Null),
+            "textDocument/definition" => self.at_position(params).map(|(document, index)| {
+                document.definition_of(index)
+                    .map_or(Value::Null, |definition| location(document, params, &document.names[definition]))
+            }),
+            "textDocument/references" => self.at_position(params).map(|(document, index)| {
+                let references = document.definition_of(index)
+                    .map_or(vec!(), |definition| document.references_to(definition));
+                Value::Array(references.into_iter()
+                    .map(|reference| location(document, params, &document.names[reference])).collect())
+            }),
+            "textDocument/hover" => self.at_position(params).map(|(document, index)| {
+                match document.definition_of(index) {
+                    Some(definition) => hover(document, &document.names[index], &document.names[definition]),
+                    None => Value::Null,
+                }
+            }),
+            "textDocument/documentSymbol" => self.document(params).map(document_symbols),
+            "textDocument/completion" => self.document(params).map(completions),
+            _ => return vec!(error_response(id, METHOD_NOT_FOUND, &format!("Unknown method: {}", method))),
+        };
+
+        match result {
+            Some(result) => vec!(response(id, result)),
+            None => vec!(error_response(id, INVALID_PARAMS, "Unknown document or position")),
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("").to_string();
+                self.documents.insert(uri.clone(), Document::new(&uri, text));
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didChange" => {
+                // Only full synchronization is offered, so the last change holds the whole text.
+                let text = params["contentChanges"].as_array().and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                match (self.documents.get_mut(&uri), text) {
+                    (Some(document), Some(text)) => document.update(text.to_string()),
+                    _ => return vec!(),
+                }
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec!(notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": [] })))
+            }
+            "exit" => { self.exited = true; vec!() }
+            _ => vec!(),
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Vec<Value> {
+        let document = &self.documents[uri];
+        let diagnostics: Vec<Value> = document.diagnostics.iter().map(|diagnostic| json!({
+            "range": range(document, diagnostic.start, diagnostic.end),
+            "severity": if diagnostic.is_error() { SEVERITY_ERROR } else { SEVERITY_WARNING },
+            "source": "fml",
+            "message": diagnostic.describe(),
+        })).collect();
+        vec!(notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": diagnostics })))
+    }
+
+    fn document(&self, params: &Value) -> Option<&Document> {
+        params["textDocument"]["uri"].as_str().and_then(|uri| self.documents.get(uri))
+    }
+
+    fn at_position(&self, params: &Value) -> Option<(&Document, usize)> {
+        let document = self.document(params)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        document.name_at(document.offset(line, character)).map(|index| (document, index))
+    }
+}
+
+fn range(document: &Document, start: usize, end: usize) -> Value {
+    let (start_line, start_character) = document.position(start);
+    let (end_line, end_character) = document.position(end);
+    json!({
+        "start": { "line": start_line, "character": start_character },
+        "end": { "line": end_line, "character": end_character },
+    })
+}
+
+fn location(document: &Document, params: &Value, name: &Name) -> Value {
+    json!({ "uri": params["textDocument"]["uri"], "range": range(document, name.start, name.end) })
+}
+
+fn signature(keyword: &str, name: &str, parameters: &[String]) -> String {
+    format!("{} {}({})", keyword, name, parameters.join(", "))
+}
+
+fn hover(document: &Document, name: &Name, definition: &Name) -> Value {
+    let text = match definition.definition {
+        Some(Kind::Function) => signature("function", &definition.name, &definition.parameters),
+        Some(Kind::Class) => signature("class", &definition.name, &definition.parameters),
+        Some(Kind::Variable) => format!("let {}", definition.name),
+        Some(Kind::Parameter) | None => format!("parameter {}", definition.name),
+    };
+    json!({
+        "contents": { "kind": "markdown", "value": format!("```fml\n{}\n```", text) },
+        "range": range(document, name.start, name.end),
+    })
+}
+
+fn symbol(document: &Document, name: &str, kind: Kind, symbol_kind: u64, children: Vec<Value>) -> Option<Value> {
+    document.find_definition(name, kind).map(|definition| {
+        let range = range(document, definition.start, definition.end);
+        json!({ "name": name, "kind": symbol_kind, "range": range, "selectionRange": range,
+                "children": children })
+    })
+}
+
+// Top-level functions, and objects and classes along with their methods.
+fn document_symbols(document: &Document) -> Value {
+    let top = match &document.ast {
+        Some(AST::Top(top)) => top,
+        _ => return json!([]),
+    };
+    let member_symbols = |members: &Vec<Box<AST>>| -> Vec<Value> {
+        members.iter().filter_map(|member| match member.as_ref() {
+            AST::FunctionDefinition { function, .. } =>
+                symbol(document, function.to_str(), Kind::Function, SYMBOL_METHOD, vec!()),
+            _ => None,
+        }).collect()
+    };
+
+    Value::Array(top.iter().filter_map(|definition| match definition.as_ref() {
+        AST::FunctionDefinition { function, .. } =>
+            symbol(document, function.to_str(), Kind::Function, SYMBOL_FUNCTION, vec!()),
+        AST::ClassDefinition { name, members, .. } =>
+            symbol(document, name.to_str(), Kind::Class, SYMBOL_CLASS, member_symbols(members)),
+        AST::VariableDefinition { name, value } | AST::ConstantDefinition { name, value } => match value.as_ref() {
+            AST::ObjectDefinition { members, .. } =>
+                symbol(document, name.to_str(), Kind::Variable, SYMBOL_OBJECT, member_symbols(members)),
+            _ => None,
+        },
+        _ => None,
+    }).collect())
+}
+
+// Methods of every object and class in the document, offered wherever a method can be called.
+fn completions(document: &Document) -> Value {
+    let methods = document.ast.as_ref().map_or(vec!(), methods);
+    Value::Array(methods.into_iter().map(|(name, parameters)| {
+        let parameters: Vec<String> = parameters.iter().map(|parameter| parameter.to_string()).collect();
+        json!({ "label": name.to_str(), "kind": COMPLETION_METHOD,
+                "detail": signature("function", name.to_str(), &parameters) })
+    }).collect())
+}
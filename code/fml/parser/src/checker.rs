@@ -0,0 +1,610 @@
+use std::collections::{HashMap, HashSet};
+
+use fml_ast::{AST, Identifier, Pattern};
+
+use crate::Syntax;
+use crate::diagnostics::Diagnostic;
+use crate::serialization::ASTFormat;
+use crate::lexer::Lexer;
+use crate::feeny::lexer::{Lexer as FeenyLexer, Tok};
+
+/**
+ * Functions that every program can call without defining them, with the number of arguments they
+ * take. Both interpreters provide the same ones.
+ */
+pub const BUILTINS: &[(&str, usize)] = &[
+    ("read_line", 0), ("read_int", 0),
+    ("length", 1), ("pop", 1), ("sort", 1), ("abs", 1), ("assert", 1), ("exit", 1), ("hash", 1),
+    ("push", 2), ("min", 2), ("max", 2), ("pow", 2),
+    ("slice", 3),
+];
+
+/**
+ * The number of arguments a builtin function takes, or `None` if there is no builtin by that name.
+ */
+pub fn builtin_arity(name: &str) -> Option<usize> {
+    BUILTINS.iter().find(|(builtin, _)| *builtin == name).map(|(_, arity)| *arity)
+}
+
+/**
+ * Checks a program before it is run, for mistakes that would otherwise only show up when (and if)
+ * the offending code is executed.
+ *
+ * Names are resolved the way the compiler resolves them. Functions and classes are global no
+ * matter where they are defined, and so are variables defined at the top level outside of any
+ * block. Every function and method body has its own locals, and every block, loop, and match arm
+ * opens a new scope among them. Methods are the functions defined as members of objects and
+ * classes, and only they have `this`.
+ *
+ * Errors are calls to undefined functions or classes, calls with the wrong number of arguments,
+ * repeated parameter names, `this` and `super` outside of a method, and a local defined twice in
+ * the same scope. Uses of variables that are not defined anywhere are warnings, since the code
+ * using them may never run.
+ *
+ * Without the source, every diagnostic is at offset 0. With it, a diagnostic is placed at the name
+ * it is about.
+ */
+pub fn check(ast: &AST) -> Vec<Diagnostic> {
+    Checker::new(ast, Locator::empty()).run(ast).0
+}
+
+/**
+ * Parses the source in the given syntax and checks the program. Fails with the syntax errors if
+ * the source does not parse.
+ */
+pub fn check_source(syntax: Syntax, input: &str) -> Result<(AST, Vec<Diagnostic>), Vec<Diagnostic>> {
+    resolve_source(syntax, input).map(|(ast, diagnostics, _)| (ast, diagnostics))
+}
+
+/**
+ * Reads the program the way both interpreters do before running it: as a serialized AST if a
+ * format is given, otherwise as source in the given syntax. Diagnostics are printed to stderr, and
+ * the process exits if any of them is an error, so a program that is returned is fit to run.
+ */
+pub fn check_or_exit(ast_format: Option<ASTFormat>, syntax: Syntax, input: &str) -> AST {
+    let (ast, diagnostics) = match ast_format {
+        Some(format) => {
+            let ast = format.deserialize(input).expect("AST error");
+            let diagnostics = check(&ast);
+            (ast, diagnostics)
+        },
+        None => check_source(syntax, input).unwrap_or_else(|diagnostics| {
+            let rendered: Vec<String> = diagnostics.iter().map(|diagnostic| diagnostic.render(input)).collect();
+            panic!("Parse error: {}", rendered.join("\n"))
+        }),
+    };
+    for diagnostic in diagnostics.iter() {
+        match ast_format {
+            Some(_) => eprintln!("{:?}: {}", diagnostic.severity, diagnostic.describe()),
+            None => eprintln!("{}", diagnostic.render(input)),
+        }
+    }
+    if diagnostics.iter().any(|diagnostic| diagnostic.is_error()) {
+        std::process::exit(1)
+    }
+    ast
+}
+
+/**
+ * A use of a variable, function, or class in the source, and the definition it refers to, both as
+ * offsets of the name.
+ */
+#[derive(PartialEq,Eq,Debug,Copy,Clone)]
+pub struct Reference {
+    pub name: (usize, usize),
+    pub definition: (usize, usize),
+}
+
+/**
+ * Like `check_source`, but also resolves the names used in the program, the same way the checker
+ * does. A local refers to its definition in the innermost enclosing scope, a variable used outside
+ * of a function to the global definition that precedes it, and one used inside a function to the
+ * first global definition. A variable is not visible in the value it is defined with. Names of
+ * fields and methods are not resolved, since they depend on the object.
+ */
+pub fn resolve_source(syntax: Syntax, input: &str)
+                      -> Result<(AST, Vec<Diagnostic>, Vec<Reference>), Vec<Diagnostic>> {
+    let (ast, locator) = parse_and_locate(syntax, input)?;
+    let (diagnostics, references) = Checker::new(&ast, locator).run(&ast);
+    Ok((ast, diagnostics, references))
+}
+
+pub(crate) fn parse_and_locate(syntax: Syntax, input: &str) -> Result<(AST, Locator<'_>), Vec<Diagnostic>> {
+    match syntax {
+        Syntax::FML => {
+            let (ast, trivia) = crate::parse_with_trivia(input)?;
+            let tokens = Lexer::new(input).map(|token| (token.start, token.end)).collect();
+            Ok((ast, Locator::new(input, tokens, trivia.statements)))
+        }
+        Syntax::Feeny => {
+            let ast = crate::feeny::parse_with_diagnostics(input)?;
+            let tokens = FeenyLexer::new(input).filter_map(|token| match token {
+                Ok((_, Tok::Newline, _)) | Ok((_, Tok::Indent, _)) | Ok((_, Tok::Dedent, _)) | Err(_) => None,
+                Ok((start, _, end)) => Some((start, end)),
+            }).collect();
+            Ok((ast, Locator::new(input, tokens, vec!())))
+        }
+    }
+}
+
+// Finds the tokens of the source as the AST is walked. The walk meets names, keywords, and
+// operators in the order in which they are written, so each one is looked for after the previous
+// one found, within the statement being checked. Names that are not in the source, like the
+// variables that named placeholders of `print` expand into, are placed at the whole statement.
+pub(crate) struct Locator<'a> {
+    tokens: Vec<(usize, usize, &'a str)>,
+    next_token: usize,
+    statements: Vec<(usize, usize)>,
+    next_statement: usize,
+    enclosing: Vec<(usize, usize)>,
+    length: usize,
+}
+
+impl<'a> Locator<'a> {
+    fn new(source: &'a str, tokens: Vec<(usize, usize)>, statements: Vec<(usize, usize)>) -> Self {
+        let tokens = tokens.into_iter().map(|(start, end)| (start, end, &source[start..end])).collect();
+        let mut statements = statements;
+        statements.sort();
+        Locator { tokens, next_token: 0, statements, next_statement: 0, enclosing: vec!(), length: source.len() }
+    }
+
+    fn empty() -> Self {
+        Locator::new("", vec!(), vec!())
+    }
+
+    pub(crate) fn enter_statement(&mut self) {
+        let span = self.statements.get(self.next_statement).cloned().unwrap_or((0, self.length));
+        self.next_statement += 1;
+        while self.tokens.get(self.next_token).is_some_and(|(start, _, _)| *start < span.0) {
+            self.next_token += 1;
+        }
+        self.enclosing.push(span);
+    }
+
+    pub(crate) fn leave_statement(&mut self) {
+        self.enclosing.pop();
+    }
+
+    pub(crate) fn locate(&mut self, text: &str) -> (usize, usize) {
+        self.find(text).unwrap_or_else(|| self.statement())
+    }
+
+    // The statement being checked.
+    pub(crate) fn statement(&self) -> (usize, usize) {
+        self.enclosing.last().cloned().unwrap_or((0, self.length))
+    }
+
+    // The token with the given text, if it is in the source.
+    pub(crate) fn find(&mut self, text: &str) -> Option<(usize, usize)> {
+        let statement = self.statement();
+        let found = self.tokens[self.next_token..].iter()
+            .take_while(|(start, _, _)| *start < statement.1)
+            .position(|(_, _, token)| *token == text)?;
+        let (start, end, _) = self.tokens[self.next_token + found];
+        self.next_token += found + 1;
+        Some((start, end))
+    }
+}
+
+struct Frame {
+    // The locals of each scope, with where they are defined.
+    scopes: Vec<HashMap<String, Option<(usize, usize)>>>,
+    method: bool,
+}
+
+// What a name refers to, once all the definitions are known.
+enum Target {
+    Definition((usize, usize)),
+    // A global variable, with the definition that precedes the use, if it is outside of functions.
+    Global(String, Option<(usize, usize)>),
+    Function(String),
+}
+
+struct Checker<'a> {
+    // Known functions and constructors (`new C`), with the number of their parameters, or `None`
+    // if they are defined more than once with different numbers.
+    functions: HashMap<String, Option<usize>>,
+    globals: HashSet<String>,
+    constants: HashSet<String>,
+    frames: Vec<Frame>,
+    locator: Locator<'a>,
+    diagnostics: Vec<Diagnostic>,
+    // Where the global variables, functions, and classes are defined, in order.
+    global_definitions: HashMap<String, Vec<(usize, usize)>>,
+    function_definitions: HashMap<String, (usize, usize)>,
+    references: Vec<((usize, usize), Target)>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(ast: &AST, locator: Locator<'a>) -> Self {
+        let mut checker = Checker {
+            functions: HashMap::new(),
+            globals: HashSet::new(),
+            constants: HashSet::new(),
+            frames: vec!(Frame { scopes: vec!(HashMap::new()), method: false }),
+            locator,
+            diagnostics: vec!(),
+            global_definitions: HashMap::new(),
+            function_definitions: HashMap::new(),
+            references: vec!(),
+        };
+        checker.collect_definitions(ast, true);
+        checker
+    }
+
+    fn run(mut self, ast: &AST) -> (Vec<Diagnostic>, Vec<Reference>) {
+        self.check(ast);
+        let references = self.references.iter().filter_map(|(name, target)| {
+            let definition = match target {
+                Target::Definition(definition) => Some(*definition),
+                Target::Global(_, Some(definition)) => Some(*definition),
+                Target::Global(global, None) =>
+                    self.global_definitions.get(global).and_then(|definitions| definitions.first().cloned()),
+                Target::Function(function) => self.function_definitions.get(function).cloned(),
+            };
+            definition.map(|definition| Reference { name: *name, definition })
+        }).collect();
+        (self.diagnostics, references)
+    }
+
+    // Functions and classes anywhere in the program, and variables of the global scope.
+    fn collect_definitions(&mut self, ast: &AST, global: bool) {
+        match ast {
+            AST::FunctionDefinition { function, parameters, body } => {
+                self.define_function(function.to_str(), parameters.len());
+                self.collect_definitions(body, false);
+                return
+            }
+            AST::ClassDefinition { name, parameters, extends, members } => {
+                self.define_function(name.to_constructor().to_str(), parameters.len());
+                extends.iter().chain(members.iter()).for_each(|child| self.collect_members(child));
+                return
+            }
+            AST::ObjectDefinition { extends, members } => {
+                extends.iter().chain(members.iter()).for_each(|child| self.collect_members(child));
+                return
+            }
+            AST::VariableDefinition { name, .. } | AST::ConstantDefinition { name, .. } | AST::Import { name, .. }
+                if global => { self.globals.insert(name.to_string()); }
+            _ => (),
+        }
+        let opens_scope = matches!(ast, AST::Block(_) | AST::Iteration { .. } | AST::Match { .. }
+                                        | AST::OperatorDefinition { .. });
+        for child in children(ast) {
+            self.collect_definitions(child, global && !opens_scope);
+        }
+    }
+
+    // Methods are not functions, but the code in them may define some.
+    fn collect_members(&mut self, member: &AST) {
+        match member {
+            AST::FunctionDefinition { body, .. } | AST::OperatorDefinition { body, .. } =>
+                self.collect_definitions(body, false),
+            other => self.collect_definitions(other, false),
+        }
+    }
+
+    fn define_function(&mut self, name: &str, arity: usize) {
+        let known = self.functions.entry(name.to_string()).or_insert(Some(arity));
+        if *known != Some(arity) {
+            *known = None;
+        }
+    }
+
+    fn error(&mut self, (start, end): (usize, usize), message: String) {
+        self.diagnostics.push(Diagnostic::new(start, end, &message));
+    }
+
+    fn warning(&mut self, (start, end): (usize, usize), message: String) {
+        self.diagnostics.push(Diagnostic::warning(start, end, &message));
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn in_global_scope(&self) -> bool {
+        self.frames.len() == 1 && self.frames[0].scopes.len() == 1
+    }
+
+    fn enter_scope(&mut self) {
+        self.frame().scopes.push(HashMap::new());
+    }
+
+    fn leave_scope(&mut self) {
+        self.frame().scopes.pop();
+    }
+
+    fn enter_frame(&mut self, method: bool) {
+        self.frames.push(Frame { scopes: vec!(HashMap::new()), method });
+    }
+
+    fn leave_frame(&mut self) {
+        self.frames.pop();
+    }
+
+    // Looks up a variable, and remembers what the use at the given location refers to. Fails if the
+    // variable is not defined.
+    fn resolve(&mut self, name: &str, location: Option<(usize, usize)>) -> bool {
+        let frame = self.frames.last().unwrap();
+        let target = match frame.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(definition) => definition.map(Target::Definition),
+            None if self.globals.contains(name) => {
+                let preceding = match self.frames.len() {
+                    1 => self.global_definitions.get(name).and_then(|definitions| definitions.last().cloned()),
+                    _ => None,
+                };
+                Some(Target::Global(name.to_string(), preceding))
+            }
+            None => return false,
+        };
+        if let (Some(location), Some(target)) = (location, target) {
+            self.references.push((location, target));
+        }
+        true
+    }
+
+    fn define_variable(&mut self, name: &Identifier, location: Option<(usize, usize)>, constant: bool) {
+        let name = name.to_string();
+        let at = location.unwrap_or_else(|| self.locator.statement());
+        if self.in_global_scope() {
+            if self.constants.contains(&name) {
+                self.error(at, format!("constant `{}` cannot be redefined", name));
+            }
+            if let Some(location) = location {
+                self.global_definitions.entry(name.clone()).or_default().push(location);
+            }
+            if constant {
+                self.constants.insert(name);
+            }
+        } else if self.frame().scopes.last_mut().unwrap().insert(name.clone(), location).is_some() {
+            self.error(at, format!("`{}` is already defined in this scope", name));
+        }
+    }
+
+    fn locate_function(&mut self, name: &str, location: Option<(usize, usize)>) {
+        if let Some(location) = location {
+            self.function_definitions.entry(name.to_string()).or_insert(location);
+        }
+    }
+
+    fn define_parameters(&mut self, parameters: &[Identifier]) {
+        for parameter in parameters {
+            let location = self.locator.find(parameter.to_str());
+            if self.frame().scopes.last_mut().unwrap().insert(parameter.to_string(), location).is_some() {
+                let at = location.unwrap_or_else(|| self.locator.statement());
+                self.error(at, format!("parameter `{}` is repeated", parameter.to_str()));
+            }
+        }
+    }
+
+    fn check_call(&mut self, name: &str, described: &str, location: (usize, usize), arguments: usize) {
+        let arity = match self.functions.get(name) {
+            Some(arity) => *arity,
+            None => match builtin_arity(name) {
+                Some(arity) => Some(arity),
+                None => return self.error(location, format!("{} is not defined", described)),
+            }
+        };
+        match arity {
+            Some(arity) if arity != arguments => self.error(location, format!(
+                "{} takes {} argument{}, but {} {} given", described, arity, if arity == 1 { "" } else { "s" },
+                arguments, if arguments == 1 { "was" } else { "were" })),
+            _ => (),
+        }
+    }
+
+    fn check_sequence(&mut self, statements: &[Box<AST>]) {
+        for statement in statements {
+            self.locator.enter_statement();
+            self.check(statement);
+            self.locator.leave_statement();
+        }
+    }
+
+    fn check_members(&mut self, members: &[Box<AST>]) {
+        for member in members {
+            self.locator.enter_statement();
+            match member.as_ref() {
+                AST::FunctionDefinition { function, parameters, body } => {
+                    self.locator.locate(function.to_str());
+                    self.check_function(parameters, body, true);
+                }
+                AST::OperatorDefinition { parameters, body, .. } =>
+                    self.check_function(parameters, body, true),
+                AST::VariableDefinition { name, value } | AST::ConstantDefinition { name, value } => {
+                    self.locator.locate(name.to_str());
+                    self.check(value);
+                }
+                other => self.check(other),
+            }
+            self.locator.leave_statement();
+        }
+    }
+
+    fn check_function(&mut self, parameters: &[Identifier], body: &AST, method: bool) {
+        self.enter_frame(method);
+        self.define_parameters(parameters);
+        self.check(body);
+        self.leave_frame();
+    }
+
+    fn check_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Binding(name) => {
+                let location = self.locator.find(name.to_str());
+                self.define_variable(name, location, false);
+            }
+            Pattern::Array(elements) => elements.iter().for_each(|element| self.check_pattern(element)),
+            Pattern::Object(fields) => for (field, pattern) in fields {
+                match pattern {
+                    Pattern::Binding(name) if name == field => (),
+                    _ => { self.locator.locate(field.to_str()); }
+                }
+                self.check_pattern(pattern);
+            },
+            _ => (),
+        }
+    }
+
+    fn check(&mut self, ast: &AST) {
+        match ast {
+            AST::Top(statements) => self.check_sequence(statements),
+            AST::Block(statements) => {
+                self.enter_scope();
+                self.check_sequence(statements);
+                self.leave_scope();
+            }
+
+            AST::VariableDefinition { name, value } | AST::ConstantDefinition { name, value } => {
+                let location = self.locator.find(name.to_str());
+                self.check(value);
+                self.define_variable(name, location, matches!(ast, AST::ConstantDefinition { .. }));
+            }
+            AST::FunctionDefinition { function, parameters, body } => {
+                let location = self.locator.find(function.to_str());
+                self.locate_function(function.to_str(), location);
+                self.check_function(parameters, body, false);
+            }
+            AST::OperatorDefinition { parameters, body, .. } => self.check_function(parameters, body, false),
+            AST::ClassDefinition { name, parameters, extends, members } => {
+                let location = self.locator.find(name.to_str());
+                self.locate_function(name.to_constructor().to_str(), location);
+                // The parent and the fields are evaluated by the constructor.
+                self.enter_frame(false);
+                self.define_parameters(parameters);
+                if let Some(parent) = extends { self.check(parent) }
+                self.check_members(members);
+                self.leave_frame();
+            }
+            AST::ObjectDefinition { extends, members } => {
+                if let Some(parent) = extends { self.check(parent) }
+                self.check_members(members);
+            }
+
+            AST::VariableAccess { name } if name.to_str() == "this" => {
+                let location = self.locator.locate("this");
+                if !self.frames.last().unwrap().method {
+                    self.error(location, "`this` can only be used inside a method".to_string());
+                }
+            }
+            AST::VariableAccess { name } => {
+                let location = self.locator.find(name.to_str());
+                if !self.resolve(name.to_str(), location) {
+                    let at = location.unwrap_or_else(|| self.locator.statement());
+                    self.warning(at, format!("`{}` is not defined", name.to_str()));
+                }
+            }
+            AST::VariableMutation { name, value } => {
+                let location = self.locator.find(name.to_str());
+                if !self.resolve(name.to_str(), location) {
+                    let at = location.unwrap_or_else(|| self.locator.statement());
+                    self.warning(at, format!("`{}` is not defined", name.to_str()));
+                }
+                self.check(value);
+            }
+            AST::FieldAccess { object, field } => {
+                self.check(object);
+                self.locator.locate(field.to_str());
+            }
+            AST::FieldMutation { object, field, value } => {
+                self.check(object);
+                self.locator.locate(field.to_str());
+                self.check(value);
+            }
+
+            AST::FunctionCall { function, arguments } => {
+                let found = self.locator.find(function.to_str());
+                found.iter().for_each(|name| self.references.push((*name, Target::Function(function.to_string()))));
+                let location = found.unwrap_or_else(|| self.locator.statement());
+                self.check_call(function.to_str(), &format!("function `{}`", function.to_str()), location, arguments.len());
+                arguments.iter().for_each(|argument| self.check(argument));
+            }
+            // The classes of imported modules are not visible to the checker, only the module's alias.
+            AST::Instantiation { class, arguments } if class.to_str().contains('.') => {
+                let (alias, name) = class.to_str().split_once('.').unwrap();
+                self.check(&AST::VariableAccess { name: Identifier::from(alias) });
+                self.locator.locate(name);
+                arguments.iter().for_each(|argument| self.check(argument));
+            }
+            AST::Instantiation { class, arguments } => {
+                let found = self.locator.find(class.to_str());
+                found.iter().for_each(|name| self.references.push((*name, Target::Function(class.to_constructor().to_string()))));
+                let location = found.unwrap_or_else(|| self.locator.statement());
+                self.check_call(class.to_constructor().to_str(), &format!("class `{}`", class.to_str()), location, arguments.len());
+                arguments.iter().for_each(|argument| self.check(argument));
+            }
+            AST::MethodCall { object, method, arguments } => {
+                self.check(object);
+                self.locator.locate(method.to_str());
+                arguments.iter().for_each(|argument| self.check(argument));
+            }
+            AST::SuperMethodCall { method, arguments } => {
+                let location = self.locator.locate(method.to_str());
+                if !self.frames.last().unwrap().method {
+                    self.error(location, format!("`super.{}` can only be called inside a method", method.to_str()));
+                }
+                arguments.iter().for_each(|argument| self.check(argument));
+            }
+
+            AST::Iteration { variable, iterable, body } => {
+                let location = self.locator.find(variable.to_str());
+                self.check(iterable);
+                self.enter_scope();
+                self.define_variable(variable, location, false);
+                self.check(body);
+                self.leave_scope();
+            }
+            AST::Match { value, arms } => {
+                self.check(value);
+                for (pattern, body) in arms {
+                    self.enter_scope();
+                    self.check_pattern(pattern);
+                    self.check(body);
+                    self.leave_scope();
+                }
+            }
+
+            other => children(other).into_iter().for_each(|child| self.check(child)),
+        }
+    }
+}
+
+// The expressions directly inside another, in the order in which they are written.
+pub fn children(ast: &AST) -> Vec<&AST> {
+    match ast {
+        AST::Number(_) | AST::Float(_) | AST::Boolean(_) | AST::String(_) | AST::Unit
+        | AST::VariableAccess { .. } | AST::Break | AST::Continue | AST::Import { .. } => vec!(),
+
+        AST::VariableDefinition { value, .. } | AST::ConstantDefinition { value, .. }
+        | AST::VariableMutation { value, .. } => vec!(value),
+        AST::FieldAccess { object, .. } => vec!(object),
+        AST::FunctionDefinition { body, .. } | AST::OperatorDefinition { body, .. } => vec!(body),
+
+        AST::ArrayDefinition { size: first, value: second }
+        | AST::ArrayAccess { array: first, index: second }
+        | AST::FieldMutation { object: first, value: second, .. }
+        | AST::Operation { left: first, right: second, .. }
+        | AST::Loop { condition: first, body: second }
+        | AST::Iteration { iterable: first, body: second, .. }
+        | AST::Range { start: first, end: second } => vec!(first, second),
+
+        AST::ArrayMutation { array, index, value } => vec!(array, index, value),
+        AST::Conditional { condition, consequent, alternative } => vec!(condition, consequent, alternative),
+
+        AST::ObjectDefinition { extends, members } | AST::ClassDefinition { extends, members, .. } =>
+            extends.iter().chain(members.iter()).map(|child| child.as_ref()).collect(),
+        AST::MapDefinition { entries } =>
+            entries.iter().flat_map(|(key, value)| vec!(key.as_ref(), value.as_ref())).collect(),
+
+        AST::MethodCall { object, arguments, .. } | AST::OperatorCall { object, arguments, .. } =>
+            std::iter::once(object).chain(arguments.iter()).map(|child| child.as_ref()).collect(),
+        AST::FunctionCall { arguments, .. } | AST::SuperMethodCall { arguments, .. }
+        | AST::Instantiation { arguments, .. } | AST::Print { arguments, .. }
+        | AST::Top(arguments) | AST::Block(arguments) =>
+            arguments.iter().map(|child| child.as_ref()).collect(),
+
+        AST::Match { value, arms } =>
+            std::iter::once(value).chain(arms.iter().map(|(_, arm)| arm)).map(|child| child.as_ref()).collect(),
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+use lalrpop_util::{ParseError, ErrorRecovery};
+use lalrpop_util::lexer::Token;
+
+/**
+ * A syntax error found while parsing, or a problem found by the checker, located by the byte
+ * offsets of the offending input.
+ *
+ * The parser recovers from errors at `;`, `end`, and between top-level expressions, so a single
+ * parse can produce several diagnostics. Use `render` with the parsed source to turn one into a
+ * message with a line and column, the expected tokens, and a snippet of the source with a caret.
+ */
+#[derive(PartialEq,Debug,Clone)]
+pub struct Diagnostic {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+    pub expected: Vec<String>,
+    pub severity: Severity,
+}
+
+/**
+ * Errors stop a program from being run, warnings are only reported.
+ */
+#[derive(PartialEq,Debug,Copy,Clone)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Diagnostic {
+    pub fn new(start: usize, end: usize, message: &str) -> Self {
+        Diagnostic { start, end, message: message.to_string(), expected: vec!(), severity: Severity::Error }
+    }
+
+    pub fn warning(start: usize, end: usize, message: &str) -> Self {
+        Diagnostic { severity: Severity::Warning, ..Diagnostic::new(start, end, message) }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    // Line and column of the start of the error, both counted from 1.
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        (line, column)
+    }
+
+    // The message followed by the expected tokens, without a location.
+    pub fn describe(&self) -> String {
+        match self.expected.len() {
+            0 => self.message.clone(),
+            1 => format!("{}, expected {}", self.message, self.expected[0]),
+            n => format!("{}, expected {} or {}", self.message,
+                         self.expected[..n - 1].join(", "), self.expected[n - 1]),
+        }
+    }
+
+    pub fn render(&self, source: &str) -> String {
+        let (line, column) = self.location(source);
+        let text = source.lines().nth(line - 1).unwrap_or("");
+
+        let message = match self.severity {
+            Severity::Error => format!("{}:{}: {}", line, column, self.describe()),
+            Severity::Warning => format!("{}:{}: warning: {}", line, column, self.describe()),
+        };
+
+        let width = source[self.start.min(source.len())..self.end.min(source.len())]
+            .lines().next().map_or(0, |token| token.chars().count()).max(1);
+        let padding: String = text.chars().take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+
+        format!("{}\n    {}\n    {}{}", message, text, padding, "^".repeat(width))
+    }
+}
+
+impl<'input> From<ParseError<usize, Token<'input>, &'static str>> for Diagnostic {
+    fn from(error: ParseError<usize, Token<'input>, &'static str>) -> Self {
+        match error {
+            ParseError::InvalidToken { location } =>
+                Diagnostic::new(location, location + 1, "unrecognized character"),
+            ParseError::UnrecognizedEOF { location, expected } =>
+                Diagnostic { expected: describe_expected(expected),
+                             ..Diagnostic::new(location, location, "unexpected end of input") },
+            ParseError::UnrecognizedToken { token: (start, Token(_, text), end), expected } =>
+                Diagnostic { expected: describe_expected(expected),
+                             ..Diagnostic::new(start, end, &format!("unexpected `{}`", text)) },
+            ParseError::ExtraToken { token: (start, Token(_, text), end) } =>
+                Diagnostic::new(start, end, &format!("unexpected `{}` after the end of the program", text)),
+            ParseError::User { error } =>
+                Diagnostic::new(0, 0, error),
+        }
+    }
+}
+
+impl<'input> From<ErrorRecovery<usize, Token<'input>, &'static str>> for Diagnostic {
+    fn from(recovery: ErrorRecovery<usize, Token<'input>, &'static str>) -> Self {
+        Diagnostic::from(recovery.error)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// Tokens as the grammar names them, and as they are written in the source.
+const TOKENS: &[(&str, &str)] = &[
+    ("SEMICOLON", "`;`"), ("BEGIN", "`begin`"), ("END", "`end`"), ("OR", "`|`"), ("AND", "`&`"),
+    ("EQUAL", "`==`"), ("UNEQUAL", "`!=`"), ("GREATER", "`>`"), ("LESS", "`<`"),
+    ("GREATER_EQUAL", "`>=`"), ("LESS_EQUAL", "`<=`"), ("PLUS", "`+`"), ("MINUS", "`-`"),
+    ("DIVIDE", "`/`"), ("MULTIPLY", "`*`"), ("MODULE", "`%`"), ("IF", "`if`"), ("THEN", "`then`"),
+    ("ELSE", "`else`"), ("LPAREN", "`(`"), ("RPAREN", "`)`"), ("LET", "`let`"),
+    ("CONST", "`const`"), ("BE", "`=`"), ("UNIT", "`null`"), ("LARROW", "`<-`"),
+    ("RARROW", "`->`"), ("PRINT", "`print`"), ("OBJECT", "`object`"), ("EXTENDS", "`extends`"),
+    ("CLASS", "`class`"), ("NEW", "`new`"), ("SUPER", "`super`"), ("IS", "`is`"), ("DOT", "`.`"),
+    ("LBRACKET", "`[`"), ("RBRACKET", "`]`"), ("WHILE", "`while`"), ("DO", "`do`"),
+    ("FOR", "`for`"), ("IN", "`in`"), ("DOTDOT", "`..`"), ("BREAK", "`break`"),
+    ("CONTINUE", "`continue`"), ("MATCH", "`match`"), ("WITH", "`with`"),
+    ("FUNCTION", "`function`"), ("ARRAY", "`array`"), ("MAP", "`map`"), ("COMMA", "`,`"),
+    ("TRUE", "`true`"), ("FALSE", "`false`"), ("THIS", "`this`"), ("IMPORT", "`import`"),
+    ("AS", "`as`"), ("IDENTIFIER", "an identifier"), ("NUMBER", "a number"),
+    ("FLOAT", "a float"), ("STRING_LITERAL", "a string"),
+];
+
+// Tokens that can start an expression. When the parser expects any of them, they are reported
+// together as "an expression" rather than listed one by one.
+const EXPRESSION_STARTS: &[&str] = &[
+    "ARRAY", "BEGIN", "BREAK", "CLASS", "CONST", "CONTINUE", "FALSE", "FLOAT", "FOR", "FUNCTION",
+    "IDENTIFIER", "IF", "IMPORT", "LET", "LPAREN", "MAP", "MATCH", "NEW", "NUMBER", "OBJECT",
+    "PRINT", "STRING_LITERAL", "SUPER", "THIS", "TRUE", "UNIT", "WHILE",
+];
+
+fn describe_expected(expected: Vec<String>) -> Vec<String> {
+    let names: Vec<&str> = expected.iter().map(|token| token.trim_matches('"')).collect();
+    let expects_expression = ["IDENTIFIER", "NUMBER", "LPAREN", "IF"].iter()
+        .all(|token| names.contains(token));
+
+    let mut descriptions: Vec<String> = Vec::new();
+    for name in names {
+        let description = if expects_expression && EXPRESSION_STARTS.contains(&name) {
+            "an expression".to_string()
+        } else {
+            TOKENS.iter().find(|(token, _)| *token == name)
+                .map_or(name.to_string(), |(_, description)| description.to_string())
+        };
+        if !descriptions.contains(&description) {
+            descriptions.push(description);
+        }
+    }
+    descriptions
+}
@@ -0,0 +1,249 @@
+use fml_ast::{AST, Identifier, Pattern};
+
+/**
+ * Draws an AST as a Graphviz graph, to be laid out by `dot`.
+ *
+ * Every node of the AST is a box labeled with its variant, along with the names, operators, and
+ * strings it holds. Its subtrees hang off it by edges labeled with the fields they are in, like
+ * `condition` or `consequent`, and numbered when a field holds several, like `arguments[1]`.
+ */
+pub fn dot(ast: &AST) -> String {
+    let mut lines = vec!("digraph AST {".to_string(), "  node [shape=box, fontname=monospace];".to_string());
+    let mut count = 0;
+    dot_node(&node(ast), &mut count, &mut lines);
+    lines.push("}".to_string());
+    lines.join("\n") + "\n"
+}
+
+/**
+ * Shows an AST as a self-contained HTML page with a tree that expands and collapses node by node.
+ * Nodes are labeled as in `dot`, and each one is preceded by the field it is in.
+ */
+pub fn html(ast: &AST) -> String {
+    let mut body = String::new();
+    html_node(None, &node(ast), 1, &mut body);
+    format!("{}{}{}", HTML_HEAD, body, HTML_TAIL)
+}
+
+const HTML_HEAD: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>AST</title>
+<style>
+  body { font-family: monospace; }
+  ul { list-style: none; margin: 0; padding-left: 1.5em; }
+  summary { cursor: pointer; }
+  .field { color: #777; }
+  .variant { font-weight: bold; }
+  .detail { color: #05a; }
+</style>
+</head>
+<body>
+<ul>
+";
+
+const HTML_TAIL: &str = "</ul>
+</body>
+</html>
+";
+
+// A node of the AST as it is drawn: its variant, the values it holds, and its labeled subtrees.
+struct Node {
+    variant: &'static str,
+    details: Vec<String>,
+    children: Vec<(String, Node)>,
+}
+
+impl Node {
+    fn new(variant: &'static str) -> Self {
+        Node { variant, details: vec!(), children: vec!() }
+    }
+
+    fn detail(mut self, detail: String) -> Self {
+        self.details.push(detail);
+        self
+    }
+
+    fn child(mut self, field: &str, ast: &AST) -> Self {
+        self.children.push((field.to_string(), node(ast)));
+        self
+    }
+
+    fn children(mut self, field: &str, asts: &[Box<AST>]) -> Self {
+        for (index, ast) in asts.iter().enumerate() {
+            self.children.push((format!("{}[{}]", field, index), node(ast)));
+        }
+        self
+    }
+}
+
+fn names(identifiers: &[Identifier]) -> String {
+    format!("({})", identifiers.iter().map(|identifier| identifier.to_str()).collect::<Vec<_>>().join(", "))
+}
+
+fn quoted(string: &str) -> String {
+    format!("\"{}\"", string)
+}
+
+fn node(ast: &AST) -> Node {
+    match ast {
+        AST::Number(n) => Node::new("Number").detail(n.to_string()),
+        AST::Float(f) => Node::new("Float").detail(format!("{:?}", f)),
+        AST::Boolean(b) => Node::new("Boolean").detail(b.to_string()),
+        AST::String(string) => Node::new("String").detail(quoted(string)),
+        AST::Unit => Node::new("Unit"),
+
+        AST::VariableDefinition { name, value } =>
+            Node::new("VariableDefinition").detail(name.to_string()).child("value", value),
+        AST::ConstantDefinition { name, value } =>
+            Node::new("ConstantDefinition").detail(name.to_string()).child("value", value),
+        AST::ArrayDefinition { size, value } =>
+            Node::new("ArrayDefinition").child("size", size).child("value", value),
+        AST::ObjectDefinition { extends, members } => {
+            let object = Node::new("ObjectDefinition");
+            let object = match extends { Some(parent) => object.child("extends", parent), None => object };
+            object.children("members", members)
+        }
+        AST::ClassDefinition { name, parameters, extends, members } => {
+            let class = Node::new("ClassDefinition").detail(name.to_string()).detail(names(parameters));
+            let class = match extends { Some(parent) => class.child("extends", parent), None => class };
+            class.children("members", members)
+        }
+        AST::MapDefinition { entries } => {
+            let mut map = Node::new("MapDefinition");
+            for (index, (key, value)) in entries.iter().enumerate() {
+                map = map.child(&format!("keys[{}]", index), key).child(&format!("values[{}]", index), value);
+            }
+            map
+        }
+
+        AST::VariableAccess { name } => Node::new("VariableAccess").detail(name.to_string()),
+        AST::FieldAccess { object, field } =>
+            Node::new("FieldAccess").detail(field.to_string()).child("object", object),
+        AST::ArrayAccess { array, index } =>
+            Node::new("ArrayAccess").child("array", array).child("index", index),
+
+        AST::VariableMutation { name, value } =>
+            Node::new("VariableMutation").detail(name.to_string()).child("value", value),
+        AST::FieldMutation { object, field, value } =>
+            Node::new("FieldMutation").detail(field.to_string()).child("object", object).child("value", value),
+        AST::ArrayMutation { array, index, value } =>
+            Node::new("ArrayMutation").child("array", array).child("index", index).child("value", value),
+
+        AST::FunctionDefinition { function, parameters, body } =>
+            Node::new("FunctionDefinition").detail(function.to_string()).detail(names(parameters)).child("body", body),
+        AST::OperatorDefinition { operator, parameters, body } =>
+            Node::new("OperatorDefinition").detail(operator.to_str().to_string()).detail(names(parameters)).child("body", body),
+
+        AST::FunctionCall { function, arguments } =>
+            Node::new("FunctionCall").detail(function.to_string()).children("arguments", arguments),
+        AST::MethodCall { object, method, arguments } =>
+            Node::new("MethodCall").detail(method.to_string()).child("object", object).children("arguments", arguments),
+        AST::SuperMethodCall { method, arguments } =>
+            Node::new("SuperMethodCall").detail(method.to_string()).children("arguments", arguments),
+        AST::OperatorCall { object, operator, arguments } =>
+            Node::new("OperatorCall").detail(operator.to_str().to_string()).child("object", object).children("arguments", arguments),
+        AST::Instantiation { class, arguments } =>
+            Node::new("Instantiation").detail(class.to_string()).children("arguments", arguments),
+        AST::Print { format, arguments } =>
+            Node::new("Print").detail(quoted(format)).children("arguments", arguments),
+
+        AST::Top(statements) => Node::new("Top").children("statements", statements),
+        AST::Block(statements) => Node::new("Block").children("statements", statements),
+        AST::Operation { operator, left, right } =>
+            Node::new("Operation").detail(operator.to_str().to_string()).child("left", left).child("right", right),
+        AST::Loop { condition, body } =>
+            Node::new("Loop").child("condition", condition).child("body", body),
+        AST::Iteration { variable, iterable, body } =>
+            Node::new("Iteration").detail(variable.to_string()).child("iterable", iterable).child("body", body),
+        AST::Range { start, end } => Node::new("Range").child("start", start).child("end", end),
+        AST::Break => Node::new("Break"),
+        AST::Continue => Node::new("Continue"),
+        AST::Conditional { condition, consequent, alternative } =>
+            Node::new("Conditional")
+                .child("condition", condition).child("consequent", consequent).child("alternative", alternative),
+        AST::Match { value, arms } => {
+            let mut node = Node::new("Match").child("value", value);
+            for (index, (pattern, body)) in arms.iter().enumerate() {
+                node.children.push((format!("patterns[{}]", index), pattern_node(pattern)));
+                node = node.child(&format!("bodies[{}]", index), body);
+            }
+            node
+        }
+
+        AST::Import { path, name } => Node::new("Import").detail(quoted(path)).detail(name.to_string()),
+    }
+}
+
+fn pattern_node(pattern: &Pattern) -> Node {
+    match pattern {
+        Pattern::Number(n) => Node::new("NumberPattern").detail(n.to_string()),
+        Pattern::Boolean(b) => Node::new("BooleanPattern").detail(b.to_string()),
+        Pattern::String(string) => Node::new("StringPattern").detail(quoted(string)),
+        Pattern::Unit => Node::new("UnitPattern"),
+        Pattern::Wildcard => Node::new("WildcardPattern"),
+        Pattern::Binding(name) => Node::new("BindingPattern").detail(name.to_string()),
+        Pattern::Array(elements) => {
+            let mut node = Node::new("ArrayPattern");
+            for (index, element) in elements.iter().enumerate() {
+                node.children.push((format!("elements[{}]", index), pattern_node(element)));
+            }
+            node
+        }
+        Pattern::Object(fields) => {
+            let mut node = Node::new("ObjectPattern");
+            for (field, pattern) in fields {
+                node.children.push((field.to_string(), pattern_node(pattern)));
+            }
+            node
+        }
+    }
+}
+
+fn escape_dot(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Writes out the node and its subtrees, numbering nodes in the order they are visited. Returns the
+// number of the node.
+fn dot_node(node: &Node, count: &mut usize, lines: &mut Vec<String>) -> usize {
+    let id = *count;
+    *count += 1;
+    let label: Vec<String> = std::iter::once(node.variant.to_string())
+        .chain(node.details.iter().cloned())
+        .map(|line| escape_dot(&line))
+        .collect();
+    lines.push(format!("  n{} [label=\"{}\"];", id, label.join("\\n")));
+    for (field, child) in node.children.iter() {
+        let child_id = dot_node(child, count, lines);
+        lines.push(format!("  n{} -> n{} [label=\"{}\"];", id, child_id, escape_dot(field)));
+    }
+    id
+}
+
+fn escape_html(string: &str) -> String {
+    string.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn html_node(field: Option<&str>, node: &Node, depth: usize, html: &mut String) {
+    let indent = "  ".repeat(depth);
+    let mut label = String::new();
+    if let Some(field) = field {
+        label.push_str(&format!("<span class=\"field\">{}:</span> ", escape_html(field)));
+    }
+    label.push_str(&format!("<span class=\"variant\">{}</span>", node.variant));
+    for detail in node.details.iter() {
+        label.push_str(&format!(" <span class=\"detail\">{}</span>", escape_html(detail)));
+    }
+
+    if node.children.is_empty() {
+        html.push_str(&format!("{}<li>{}</li>\n", indent, label));
+        return
+    }
+    html.push_str(&format!("{}<li><details open><summary>{}</summary>\n{}<ul>\n", indent, label, indent));
+    for (field, child) in node.children.iter() {
+        html_node(Some(field), child, depth + 1, html);
+    }
+    html.push_str(&format!("{}</ul></details></li>\n", indent));
+}
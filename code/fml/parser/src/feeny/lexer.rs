@@ -0,0 +1,322 @@
+use std::fmt;
+use std::collections::VecDeque;
+
+/**
+ * Tokens of the original, indentation-sensitive Feeny syntax (see `feeny.ebnf` in the repository
+ * root).
+ *
+ * Layout is turned into tokens the way Python does it: every line that holds a statement ends
+ * with `Newline`, a line indented deeper than the one before it starts with `Indent`, and a line
+ * indented less starts with one `Dedent` for every level it closes. Inside parentheses and
+ * brackets line breaks and indentation are ignored, so argument lists can span several lines.
+ */
+#[derive(Clone,Debug,PartialEq)]
+pub enum Tok<'input> {
+    Newline,
+    Indent,
+    Dedent,
+
+    Defn,
+    Method,
+    Object,
+    Var,
+    If,
+    Else,
+    While,
+    Printf,
+    Null,
+
+    Identifier(&'input str),
+    Number(&'input str),
+    String(&'input str),
+
+    Colon,
+    Comma,
+    Dot,
+    Assign,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Module,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    Unequal,
+}
+
+impl<'input> fmt::Display for Tok<'input> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Tok::Newline => "end of line",
+            Tok::Indent => "indentation",
+            Tok::Dedent => "end of indented block",
+            Tok::Defn => "defn",
+            Tok::Method => "method",
+            Tok::Object => "object",
+            Tok::Var => "var",
+            Tok::If => "if",
+            Tok::Else => "else",
+            Tok::While => "while",
+            Tok::Printf => "printf",
+            Tok::Null => "null",
+            Tok::Identifier(text) | Tok::Number(text) | Tok::String(text) => text,
+            Tok::Colon => ":",
+            Tok::Comma => ",",
+            Tok::Dot => ".",
+            Tok::Assign => "=",
+            Tok::LParen => "(",
+            Tok::RParen => ")",
+            Tok::LBracket => "[",
+            Tok::RBracket => "]",
+            Tok::Plus => "+",
+            Tok::Minus => "-",
+            Tok::Multiply => "*",
+            Tok::Divide => "/",
+            Tok::Module => "%",
+            Tok::Less => "<",
+            Tok::LessEqual => "<=",
+            Tok::Greater => ">",
+            Tok::GreaterEqual => ">=",
+            Tok::Equal => "==",
+            Tok::Unequal => "!=",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct SyntaxError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+impl SyntaxError {
+    pub fn new(start: usize, end: usize, message: &str) -> Self {
+        SyntaxError { start, end, message: message.to_string() }
+    }
+}
+
+pub type Spanned<'input> = Result<(usize, Tok<'input>, usize), SyntaxError>;
+
+pub struct Lexer<'input> {
+    input: &'input str,
+    position: usize,
+    indentation: Vec<usize>,
+    nesting: usize,
+    at_line_start: bool,
+    line_has_tokens: bool,
+    after_operand: bool,
+    pending: VecDeque<(usize, Tok<'input>, usize)>,
+    finished: bool,
+}
+
+impl<'input> Lexer<'input> {
+    pub fn new(input: &'input str) -> Self {
+        Lexer {
+            input,
+            position: 0,
+            indentation: vec!(0),
+            nesting: 0,
+            at_line_start: true,
+            line_has_tokens: false,
+            after_operand: false,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.input[self.position..].chars().nth(1)
+    }
+
+    fn skip_while(&mut self, predicate: impl Fn(char) -> bool) -> usize {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if !predicate(c) { break }
+            self.position += c.len_utf8();
+        }
+        self.position - start
+    }
+
+    fn emit(&mut self, start: usize, token: Tok<'input>, end: usize) {
+        // After a token that ends an operand, a `-` is subtraction rather than the sign of a
+        // number literal.
+        self.after_operand = matches!(token, Tok::Identifier(_) | Tok::Number(_) | Tok::String(_)
+                                             | Tok::Null | Tok::RParen | Tok::RBracket);
+        self.line_has_tokens = true;
+        self.pending.push_back((start, token, end))
+    }
+
+    fn end_line(&mut self, start: usize, end: usize) {
+        if self.line_has_tokens {
+            self.pending.push_back((start, Tok::Newline, end));
+            self.line_has_tokens = false;
+            self.after_operand = false;
+        }
+    }
+
+    fn indent(&mut self) -> Result<(), SyntaxError> {
+        let start = self.position;
+        let width = self.skip_while(|c| c == ' ' || c == '\t');
+
+        // Blank lines and lines holding only a comment do not affect the layout.
+        match self.peek() {
+            None => { self.at_line_start = false; return Ok(()) }
+            Some('\n') | Some('\r') | Some(';') => {
+                self.skip_while(|c| c != '\n');
+                self.position += self.peek().map_or(0, |c| c.len_utf8());
+                return Ok(())
+            }
+            Some(_) => self.at_line_start = false,
+        }
+
+        let current = *self.indentation.last().unwrap();
+        if width > current {
+            self.indentation.push(width);
+            self.pending.push_back((self.position, Tok::Indent, self.position));
+        }
+        while width < *self.indentation.last().unwrap() {
+            self.indentation.pop();
+            self.pending.push_back((self.position, Tok::Dedent, self.position));
+        }
+        if width != *self.indentation.last().unwrap() {
+            return Err(SyntaxError::new(start, self.position,
+                                        "indentation does not match any enclosing block"))
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) {
+        self.end_line(self.position, self.position);
+        while self.indentation.len() > 1 {
+            self.indentation.pop();
+            self.pending.push_back((self.position, Tok::Dedent, self.position));
+        }
+        self.finished = true;
+    }
+
+    fn token(&mut self) -> Result<(), SyntaxError> {
+        if self.at_line_start && self.nesting == 0 {
+            return self.indent()
+        }
+
+        let start = self.position;
+        let c = match self.peek() {
+            None => { self.finish(); return Ok(()) }
+            Some(c) => c,
+        };
+
+        match c {
+            '\n' => {
+                self.position += 1;
+                if self.nesting == 0 {
+                    self.end_line(start, self.position);
+                    self.at_line_start = true;
+                }
+            }
+            ' ' | '\t' | '\r' => { self.skip_while(|c| c == ' ' || c == '\t' || c == '\r'); }
+            ';' => { self.skip_while(|c| c != '\n'); }
+
+            '"' => {
+                self.position += 1;
+                loop {
+                    match self.peek() {
+                        None | Some('\n') =>
+                            return Err(SyntaxError::new(start, self.position, "unterminated string")),
+                        Some('\\') => self.position += 1 + self.peek_second().map_or(0, |c| c.len_utf8()),
+                        Some('"') => { self.position += 1; break }
+                        Some(c) => self.position += c.len_utf8(),
+                    }
+                }
+                let text = &self.input[start + 1..self.position - 1];
+                self.emit(start, Tok::String(text), self.position)
+            }
+
+            c if c.is_ascii_digit()
+                 || (c == '-' && matches!(self.peek_second(), Some(c) if c.is_ascii_digit()) && !self.after_operand) => {
+                self.position += 1;
+                self.skip_while(|c| c.is_ascii_digit());
+                let text = &self.input[start..self.position];
+                self.emit(start, Tok::Number(text), self.position)
+            }
+
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                self.skip_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '?' || c == '!');
+                let text = &self.input[start..self.position];
+                let token = match text {
+                    "defn" => Tok::Defn,
+                    "method" => Tok::Method,
+                    "object" => Tok::Object,
+                    "var" => Tok::Var,
+                    "if" => Tok::If,
+                    "else" => Tok::Else,
+                    "while" => Tok::While,
+                    "printf" => Tok::Printf,
+                    "null" => Tok::Null,
+                    identifier => Tok::Identifier(identifier),
+                };
+                self.emit(start, token, self.position)
+            }
+
+            _ => {
+                let (token, length) = match (c, self.peek_second()) {
+                    ('<', Some('=')) => (Tok::LessEqual, 2),
+                    ('>', Some('=')) => (Tok::GreaterEqual, 2),
+                    ('=', Some('=')) => (Tok::Equal, 2),
+                    ('!', Some('=')) => (Tok::Unequal, 2),
+                    ('<', _) => (Tok::Less, 1),
+                    ('>', _) => (Tok::Greater, 1),
+                    ('=', _) => (Tok::Assign, 1),
+                    ('+', _) => (Tok::Plus, 1),
+                    ('-', _) => (Tok::Minus, 1),
+                    ('*', _) => (Tok::Multiply, 1),
+                    ('/', _) => (Tok::Divide, 1),
+                    ('%', _) => (Tok::Module, 1),
+                    (':', _) => (Tok::Colon, 1),
+                    (',', _) => (Tok::Comma, 1),
+                    ('.', _) => (Tok::Dot, 1),
+                    ('(', _) => { self.nesting += 1; (Tok::LParen, 1) }
+                    ('[', _) => { self.nesting += 1; (Tok::LBracket, 1) }
+                    (')', _) => { self.nesting = self.nesting.saturating_sub(1); (Tok::RParen, 1) }
+                    (']', _) => { self.nesting = self.nesting.saturating_sub(1); (Tok::RBracket, 1) }
+                    _ => return Err(SyntaxError::new(start, start + c.len_utf8(), "unrecognized character")),
+                };
+                self.position += length;
+                self.emit(start, token, self.position)
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Spanned<'input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(Ok(token))
+            }
+            if self.finished {
+                return None
+            }
+            if let Err(error) = self.token() {
+                self.finished = true;
+                return Some(Err(error))
+            }
+        }
+    }
+}
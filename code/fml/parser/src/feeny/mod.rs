@@ -0,0 +1,138 @@
+/**
+ * A front-end for the original, indentation-sensitive Feeny syntax (see `feeny.ebnf` in the
+ * repository root), producing the same AST as the FML parser, so that Feeny programs run on
+ * either interpreter.
+ *
+ * Feeny has no operator methods: `a + b` calls the method `add` of `a`. Methods named after the
+ * operators (`add`, `sub`, `mul`, `div`, `mod`, `lt`, `gt`, `le`, `ge`, and `eq`) are therefore
+ * translated into operator definitions, and calls to them into operator calls.
+ */
+pub mod lexer;
+
+lalrpop_mod!(pub grammar, "/feeny/grammar.rs"); // synthesized by LALRPOP
+
+use lalrpop_util::ParseError;
+use fml_ast::{AST, Operator, Identifier};
+
+use crate::diagnostics::Diagnostic;
+use lexer::{Lexer, Tok, SyntaxError};
+
+pub fn parse(input: &str) -> Result<AST, String> {
+    parse_with_diagnostics(input).map_err(|diagnostics| {
+        diagnostics.iter().map(|diagnostic| diagnostic.render(input)).collect::<Vec<String>>().join("\n")
+    })
+}
+
+/**
+ * Parses the input, stopping at the first syntax error.
+ */
+pub fn parse_with_diagnostics(input: &str) -> Result<AST, Vec<Diagnostic>> {
+    grammar::ProgramParser::new().parse(Lexer::new(input))
+        .map_err(|error| vec!(Diagnostic::from(error)))
+}
+
+pub fn operator_method(name: &str) -> Option<Operator> {
+    match name {
+        "add" => Some(Operator::Addition),
+        "sub" => Some(Operator::Subtraction),
+        "mul" => Some(Operator::Multiplication),
+        "div" => Some(Operator::Division),
+        "mod" => Some(Operator::Module),
+        "lt" => Some(Operator::Less),
+        "gt" => Some(Operator::Greater),
+        "le" => Some(Operator::LessEqual),
+        "ge" => Some(Operator::GreaterEqual),
+        "eq" => Some(Operator::Equality),
+        _ => None,
+    }
+}
+
+// Indexing with a single index is an array access, otherwise it calls `get` with all the indices.
+pub fn indexing(array: AST, indices: Vec<AST>) -> Result<AST, &'static str> {
+    let mut indices = indices;
+    match indices.len() {
+        0 => Err("indexing requires at least one index"),
+        1 => Ok(AST::ArrayAccess { array: Box::new(array), index: Box::new(indices.pop().unwrap()) }),
+        _ => Ok(AST::MethodCall { object: Box::new(array), method: Identifier::from("get"),
+                                  arguments: put_into_boxes!(indices) }),
+    }
+}
+
+// The left-hand side of an assignment is parsed as an expression and turned into a mutation here.
+pub fn assignment(target: AST, value: AST) -> Result<AST, &'static str> {
+    match target {
+        AST::VariableAccess { name } =>
+            Ok(AST::VariableMutation { name, value: Box::new(value) }),
+        AST::FieldAccess { object, field } =>
+            Ok(AST::FieldMutation { object, field, value: Box::new(value) }),
+        AST::ArrayAccess { array, index } =>
+            Ok(AST::ArrayMutation { array, index, value: Box::new(value) }),
+        AST::MethodCall { object, method, mut arguments } if method.to_str() == "get" => {
+            arguments.push(Box::new(value));
+            Ok(AST::MethodCall { object, method: Identifier::from("set"), arguments })
+        }
+        _ => Err("only variables, fields, and array elements can be assigned to"),
+    }
+}
+
+impl<'input> From<ParseError<usize, Tok<'input>, SyntaxError>> for Diagnostic {
+    fn from(error: ParseError<usize, Tok<'input>, SyntaxError>) -> Self {
+        match error {
+            ParseError::InvalidToken { location } =>
+                Diagnostic::new(location, location + 1, "unrecognized character"),
+            ParseError::UnrecognizedEOF { location, expected } =>
+                Diagnostic { expected: describe_expected(expected),
+                             ..Diagnostic::new(location, location, "unexpected end of input") },
+            ParseError::UnrecognizedToken { token: (start, token, end), expected } =>
+                Diagnostic { expected: describe_expected(expected),
+                             ..Diagnostic::new(start, end, &format!("unexpected {}", describe_token(&token))) },
+            ParseError::ExtraToken { token: (start, token, end) } =>
+                Diagnostic::new(start, end, &format!("unexpected {} after the end of the program",
+                                                     describe_token(&token))),
+            ParseError::User { error } =>
+                Diagnostic::new(error.start, error.end, &error.message),
+        }
+    }
+}
+
+fn describe_token(token: &Tok) -> String {
+    match token {
+        Tok::Newline | Tok::Indent | Tok::Dedent => token.to_string(),
+        _ => format!("`{}`", token),
+    }
+}
+
+// Tokens as the grammar names them, and as they are written in the source.
+const TOKENS: &[(&str, &str)] = &[
+    ("NEWLINE", "end of line"), ("INDENT", "indentation"), ("DEDENT", "end of indented block"),
+    ("DEFN", "`defn`"), ("METHOD", "`method`"), ("OBJECT", "`object`"), ("VAR", "`var`"),
+    ("IF", "`if`"), ("ELSE", "`else`"), ("WHILE", "`while`"), ("PRINTF", "`printf`"),
+    ("NULL", "`null`"), ("IDENTIFIER", "an identifier"), ("NUMBER", "a number"),
+    ("STRING", "a string"), ("COLON", "`:`"), ("COMMA", "`,`"), ("DOT", "`.`"), ("ASSIGN", "`=`"),
+    ("LPAREN", "`(`"), ("RPAREN", "`)`"), ("LBRACKET", "`[`"), ("RBRACKET", "`]`"),
+    ("PLUS", "`+`"), ("MINUS", "`-`"), ("MULTIPLY", "`*`"), ("DIVIDE", "`/`"), ("MODULE", "`%`"),
+    ("LESS", "`<`"), ("LESS_EQUAL", "`<=`"), ("GREATER", "`>`"), ("GREATER_EQUAL", "`>=`"),
+    ("EQUAL", "`==`"), ("UNEQUAL", "`!=`"),
+];
+
+// Tokens that can start an expression, reported together as "an expression".
+const EXPRESSION_STARTS: &[&str] = &["IDENTIFIER", "NUMBER", "NULL", "LPAREN", "PRINTF"];
+
+fn describe_expected(expected: Vec<String>) -> Vec<String> {
+    let names: Vec<&str> = expected.iter().map(|token| token.trim_matches('"')).collect();
+    let expects_expression = EXPRESSION_STARTS.iter().all(|token| names.contains(token));
+
+    let mut descriptions: Vec<String> = Vec::new();
+    for name in names {
+        let description = if expects_expression && EXPRESSION_STARTS.contains(&name) {
+            "an expression".to_string()
+        } else {
+            TOKENS.iter().find(|(token, _)| *token == name)
+                .map_or(name.to_string(), |(_, description)| description.to_string())
+        };
+        if !descriptions.contains(&description) {
+            descriptions.push(description);
+        }
+    }
+    descriptions
+}
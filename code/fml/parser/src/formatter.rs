@@ -0,0 +1,453 @@
+use fml_ast::{AST, Identifier, Operator, Pattern};
+
+use crate::diagnostics::Diagnostic;
+use crate::trivia::{Trivia, Comment};
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::parse_with_trivia;
+
+/**
+ * Reformats FML source canonically, keeping its comments.
+ *
+ * The program is printed back from its AST: every statement of a block or member of an object is
+ * on its own line, indented by two spaces per level of `begin`/`end`, operators are surrounded by
+ * spaces, and argument lists that would not fit on a line are broken into one argument per line.
+ * Parentheses are only kept where the structure of the expression needs them. Formatting never
+ * changes the AST of the program. A statement with a comment inside one of its expressions, where
+ * printing it again would move the comment, is kept as it was written.
+ */
+pub fn format(input: &str) -> Result<String, Vec<Diagnostic>> {
+    let (ast, trivia) = parse_with_trivia(input)?;
+    let tokens = Lexer::with_trivia(input).filter(|token| token.kind != TokenKind::Whitespace).collect();
+    let mut formatter = Formatter { source: input, tokens, trivia, statement: 0, comment: 0, enclosing: vec!(), previous: 0 };
+    Ok(formatter.program(&ast))
+}
+
+/**
+ * Prints an AST as FML source, laid out as by `format`. Parsing the printed source gives back the
+ * same AST.
+ */
+pub fn print(ast: &AST) -> String {
+    let trivia = Trivia { comments: vec!(), statements: vec!() };
+    let mut formatter = Formatter { source: "", tokens: vec!(), trivia, statement: 0, comment: 0, enclosing: vec!(), previous: 0 };
+    formatter.program(ast)
+}
+
+const INDENT: &str = "  ";
+const MAX_WIDTH: usize = 100;
+
+// Precedence levels, from the loosest: anything, `|`, `&`, comparisons, `+ -`, `* / %`, field
+// access, and expressions that never need parentheses.
+const ANY: u8 = 0;
+const FIELD: u8 = 6;
+const ACCESSIBLE: u8 = 7;
+
+fn operator_precedence(operator: &Operator) -> u8 {
+    match operator {
+        Operator::Disjunction => 1,
+        Operator::Conjunction => 2,
+        Operator::Equality | Operator::Inequality | Operator::Identity | Operator::Less
+        | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual => 3,
+        Operator::Addition | Operator::Subtraction => 4,
+        Operator::Multiplication | Operator::Division | Operator::Module => 5,
+    }
+}
+
+fn precedence(ast: &AST) -> u8 {
+    match ast {
+        AST::Operation { operator, .. } => operator_precedence(operator),
+        AST::FieldAccess { .. } => FIELD,
+        AST::Number(_) | AST::Float(_) | AST::Boolean(_) | AST::String(_) | AST::Unit
+        | AST::VariableAccess { .. } | AST::ArrayDefinition { .. } | AST::MapDefinition { .. }
+        | AST::ArrayAccess { .. } | AST::FunctionCall { .. } | AST::MethodCall { .. }
+        | AST::SuperMethodCall { .. } | AST::OperatorCall { .. } | AST::Instantiation { .. }
+        | AST::Block(_) | AST::Match { .. } => ACCESSIBLE,
+        _ => ANY,
+    }
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    // The tokens of the source, comments included.
+    tokens: Vec<Token<'a>>,
+    trivia: Trivia,
+    // The next statement location and comment to be used.
+    statement: usize,
+    comment: usize,
+    // Ends of the statements being printed, innermost last, and of the last statement printed.
+    enclosing: Vec<usize>,
+    previous: usize,
+}
+
+impl<'a> Formatter<'a> {
+    fn program(&mut self, ast: &AST) -> String {
+        let statements: Vec<&AST> = match ast {
+            AST::Top(statements) if statements.as_slice() == [Box::new(AST::Unit)]
+                                    && self.trivia.statements.is_empty() => vec!(),
+            AST::Top(statements) => statements.iter().map(|statement| statement.as_ref()).collect(),
+            other => vec!(other),
+        };
+        let mut output = self.sequence(&statements, 0, ";");
+        let rest = self.comments_before(self.source.len(), 0);
+        if !rest.is_empty() {
+            if !output.is_empty() { output.push('\n') }
+            output.push_str(&rest);
+        }
+        output.push('\n');
+        output
+    }
+
+    fn comments_before(&mut self, offset: usize, indent: usize) -> String {
+        let mut lines = Vec::new();
+        while let Some(comment) = self.trivia.comments.get(self.comment) {
+            if comment.start >= offset { break }
+            lines.push(format!("{}{}", INDENT.repeat(indent), comment.text));
+            self.comment += 1;
+        }
+        lines.join("\n")
+    }
+
+    // Comments that start on the line where a statement ends.
+    fn trailing_comments(&mut self, end: usize) -> String {
+        let mut trailing = String::new();
+        while let Some(Comment { start, text, .. }) = self.trivia.comments.get(self.comment) {
+            if *start < end || self.source[end..*start].contains('\n') { break }
+            trailing.push(' ');
+            trailing.push_str(text);
+            self.comment += 1;
+        }
+        trailing
+    }
+
+    // Statements, each on its own line at the given indentation, with their comments.
+    fn sequence(&mut self, statements: &[&AST], indent: usize, separator: &str) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut previous_end: Option<usize> = None;
+        for (index, statement) in statements.iter().enumerate() {
+            let (start, end) = self.trivia.statements.get(self.statement).cloned()
+                .unwrap_or((self.source.len(), self.source.len()));
+            self.statement += 1;
+
+            // A blank line between two statements is kept.
+            if let Some(previous_end) = previous_end {
+                if previous_end <= start && self.source[previous_end..start].contains("\n\n") {
+                    lines.push(String::new());
+                }
+            }
+            let comments = self.comments_before(start, indent);
+            if !comments.is_empty() { lines.push(comments) }
+
+            let text = if self.has_stray_comments(start, end) {
+                self.verbatim(start, end)
+            } else {
+                self.enclosing.push(end);
+                let text = self.expression(statement, indent, ANY, false);
+                self.enclosing.pop();
+                text
+            };
+            let mut line = format!("{}{}", INDENT.repeat(indent), text);
+            if index + 1 < statements.len() { line.push_str(separator) }
+            line.push_str(&self.trailing_comments(end));
+            lines.push(line);
+            previous_end = Some(end);
+            self.previous = end;
+        }
+        lines.join("\n")
+    }
+
+    // Whether a comment inside the statement at `start..end` would be moved by printing it: every
+    // comment that is not inside one of the statements nested in it has to be right before or after
+    // one of them, or follow the last statement of a block right before its `end`.
+    fn has_stray_comments(&self, start: usize, end: usize) -> bool {
+        let nested: Vec<(usize, usize)> = self.trivia.statements.iter().skip(self.statement)
+            .take_while(|(nested_start, _)| *nested_start < end).cloned().collect();
+        let code: Vec<&Token> = self.tokens.iter()
+            .filter(|token| token.kind != TokenKind::Comment && start <= token.start && token.end <= end)
+            .collect();
+
+        self.trivia.comments.iter()
+            .filter(|comment| start < comment.start && comment.start < end)
+            .filter(|comment| !nested.iter().any(|(nested_start, nested_end)|
+                *nested_start <= comment.start && comment.start < *nested_end))
+            .any(|comment| {
+                let before: Vec<&&Token> = code.iter().filter(|token| token.end <= comment.start).collect();
+                let after: Vec<&&Token> = code.iter().filter(|token| token.start >= comment.end).collect();
+
+                // The end of the nested statement the comment follows, with at most a `;` between them.
+                let follows = match before.as_slice() {
+                    [.., statement, separator] if separator.text == ";" => Some(statement.end),
+                    [.., statement] => Some(statement.end),
+                    [] => None,
+                }.filter(|position| nested.iter().any(|(_, nested_end)| nested_end == position));
+
+                let trailing = follows.is_some_and(|position| !self.source[position..comment.start].contains('\n'));
+                let leading = matches!(before.last(), Some(token) if token.text == ";" || token.text == "begin")
+                    && matches!(after.first(), Some(token) if nested.iter().any(|(nested_start, _)| *nested_start == token.start));
+                let closing = follows.is_some()
+                    && matches!(after.first(), Some(token) if token.kind == TokenKind::Keyword && token.text == "end");
+                !(trailing || leading || closing)
+            })
+    }
+
+    // The statement as it was written, with the statements and comments inside it.
+    fn verbatim(&mut self, start: usize, end: usize) -> String {
+        while matches!(self.trivia.statements.get(self.statement), Some((nested_start, _)) if *nested_start < end) {
+            self.statement += 1;
+        }
+        while matches!(self.trivia.comments.get(self.comment), Some(comment) if comment.start < end) {
+            self.comment += 1;
+        }
+        self.source[start..end].to_string()
+    }
+
+    // A `begin`/`end` delimited block of statements, with comments left before its `end`.
+    fn block(&mut self, opening: &str, statements: &[&AST], indent: usize) -> String {
+        let body = self.sequence(statements, indent + 1, ";");
+        let enclosing = self.enclosing.last().cloned().unwrap_or(self.source.len());
+        // The `end` of a block with statements is the first one after the last of them.
+        let end = self.tokens.iter()
+            .filter(|_| !statements.is_empty())
+            .find(|token| token.start >= self.previous && token.kind == TokenKind::Keyword && token.text == "end")
+            .map_or(enclosing, |token| token.start);
+        let rest = self.comments_before(end, indent + 1);
+        let mut lines = vec!(opening.to_string());
+        if !body.is_empty() { lines.push(body) }
+        if !rest.is_empty() { lines.push(rest) }
+        lines.push(format!("{}end", INDENT.repeat(indent)));
+        lines.join("\n")
+    }
+
+    fn members(&mut self, opening: &str, members: &[Box<AST>], indent: usize) -> String {
+        let members: Vec<&AST> = members.iter().map(|member| member.as_ref()).collect();
+        self.block(opening, &members, indent)
+    }
+
+    // An argument list on one line, or one argument per line if it does not fit.
+    fn arguments(&mut self, prefix: &str, arguments: &[&AST], indent: usize) -> String {
+        let rendered: Vec<String> = arguments.iter()
+            .map(|argument| self.expression(argument, indent + 1, ANY, false)).collect();
+        let flat = rendered.join(", ");
+        let width = INDENT.len() * indent + prefix.len() + flat.len() + 2;
+        if rendered.iter().any(|argument| argument.contains('\n')) || (width > MAX_WIDTH && rendered.len() > 1) {
+            let separator = format!(",\n{}", INDENT.repeat(indent + 1));
+            format!("{}(\n{}{}\n{})", prefix, INDENT.repeat(indent + 1), rendered.join(&separator), INDENT.repeat(indent))
+        } else {
+            format!("{}({})", prefix, flat)
+        }
+    }
+
+    fn boxed_arguments(&mut self, prefix: &str, arguments: &[Box<AST>], indent: usize) -> String {
+        let arguments: Vec<&AST> = arguments.iter().map(|argument| argument.as_ref()).collect();
+        self.arguments(prefix, &arguments, indent)
+    }
+
+    // An expression in a position that requires at least the given precedence, parenthesized if
+    // it binds more loosely. A closed expression cannot end with an `if` without an `else`.
+    fn expression(&mut self, ast: &AST, indent: usize, minimum: u8, closed: bool) -> String {
+        if precedence(ast) < minimum {
+            return format!("({})", self.expression(ast, indent, ANY, false))
+        }
+
+        match ast {
+            AST::Number(n) => n.to_string(),
+            AST::Float(f) => float(*f),
+            AST::Boolean(b) => b.to_string(),
+            AST::String(s) => format!("\"{}\"", s),
+            AST::Unit => "null".to_string(),
+
+            AST::VariableDefinition { name, value } =>
+                format!("let {} = {}", name.to_str(), self.expression(value, indent, ANY, closed)),
+            AST::ConstantDefinition { name, value } =>
+                format!("const {} = {}", name.to_str(), self.expression(value, indent, ANY, closed)),
+            AST::ArrayDefinition { size, value } => {
+                let arguments = [size.as_ref(), value.as_ref()];
+                self.arguments("array", &arguments, indent)
+            }
+            AST::ObjectDefinition { extends, members } => {
+                let opening = match extends {
+                    Some(parent) => format!("object extends {} begin", self.expression(parent, indent, ANY, false)),
+                    None => "object begin".to_string(),
+                };
+                self.members(&opening, members, indent)
+            }
+            AST::ClassDefinition { name, parameters, extends, members } => {
+                let mut opening = format!("class {}({})", name.to_str(), names(parameters));
+                match extends.as_deref() {
+                    Some(AST::Instantiation { class, arguments }) if arguments.is_empty() =>
+                        opening.push_str(&format!(" extends {}", class.to_str())),
+                    Some(AST::Instantiation { class, arguments }) =>
+                        opening.push_str(&format!(" extends {}", self.boxed_arguments(class.to_str(), arguments, indent))),
+                    _ => (),
+                }
+                opening.push_str(" begin");
+                self.members(&opening, members, indent)
+            }
+            AST::MapDefinition { entries } => {
+                let entries: Vec<String> = entries.iter().map(|(key, value)| format!("{} -> {}",
+                    self.expression(key, indent + 1, 1, false),
+                    self.expression(value, indent + 1, ANY, false))).collect();
+                let flat = entries.join(", ");
+                if entries.iter().any(|entry| entry.contains('\n')) || INDENT.len() * indent + flat.len() + 5 > MAX_WIDTH {
+                    let separator = format!(",\n{}", INDENT.repeat(indent + 1));
+                    format!("map(\n{}{}\n{})", INDENT.repeat(indent + 1), entries.join(&separator), INDENT.repeat(indent))
+                } else {
+                    format!("map({})", flat)
+                }
+            }
+
+            AST::VariableAccess { name } => name.to_string(),
+            AST::FieldAccess { object, field } =>
+                format!("{}.{}", self.expression(object, indent, FIELD, false), field.to_str()),
+            AST::ArrayAccess { array, index } =>
+                format!("{}[{}]", self.expression(array, indent, FIELD, false), self.expression(index, indent, ANY, false)),
+
+            AST::VariableMutation { name, value } =>
+                format!("{} <- {}", name.to_str(), self.expression(value, indent, ANY, closed)),
+            AST::FieldMutation { object, field, value } =>
+                format!("{}.{} <- {}", self.expression(object, indent, FIELD, false), field.to_str(),
+                        self.expression(value, indent, ANY, closed)),
+            AST::ArrayMutation { array, index, value } =>
+                format!("{}[{}] <- {}", self.expression(array, indent, FIELD, false),
+                        self.expression(index, indent, ANY, false), self.expression(value, indent, ANY, closed)),
+
+            AST::FunctionDefinition { function, parameters, body } =>
+                format!("function {}({}) -> {}", function.to_str(), names(parameters),
+                        self.expression(body, indent, ANY, closed)),
+            AST::OperatorDefinition { operator, parameters, body } =>
+                format!("function {}({}) -> {}", operator.to_str(), names(parameters),
+                        self.expression(body, indent, ANY, closed)),
+
+            AST::FunctionCall { function, arguments } =>
+                self.boxed_arguments(function.to_str(), arguments, indent),
+            AST::MethodCall { object, method, arguments } => {
+                let prefix = format!("{}.{}", self.expression(object, indent, FIELD, false), method.to_str());
+                self.boxed_arguments(&prefix, arguments, indent)
+            }
+            AST::SuperMethodCall { method, arguments } =>
+                self.boxed_arguments(&format!("super.{}", method.to_str()), arguments, indent),
+            AST::OperatorCall { object, operator, arguments } => {
+                let prefix = format!("{}.{}", self.expression(object, indent, FIELD, false), operator.to_str());
+                self.boxed_arguments(&prefix, arguments, indent)
+            }
+            AST::Instantiation { class, arguments } =>
+                self.boxed_arguments(&format!("new {}", class.to_str()), arguments, indent),
+            AST::Print { format, arguments } => {
+                let format = AST::String(format.clone());
+                let arguments: Vec<&AST> = std::iter::once(&format)
+                    .chain(arguments.iter().map(|argument| argument.as_ref())).collect();
+                self.arguments("print", &arguments, indent)
+            }
+
+            AST::Top(statements) | AST::Block(statements) => {
+                let statements: Vec<&AST> = statements.iter().map(|statement| statement.as_ref()).collect();
+                self.block("begin", &statements, indent)
+            }
+            AST::Operation { operator, left, right } => {
+                let level = operator_precedence(operator);
+                format!("{} {} {}", self.expression(left, indent, level, false), operator.to_str(),
+                        self.expression(right, indent, level + 1, false))
+            }
+            AST::Loop { condition, body } =>
+                format!("while {} do {}", self.expression(condition, indent, ANY, false),
+                        self.expression(body, indent, ANY, closed)),
+            AST::Iteration { variable, iterable, body } => {
+                let iterable = match iterable.as_ref() {
+                    AST::Range { start, end } => format!("{}..{}", self.expression(start, indent, 1, false),
+                                                         self.expression(end, indent, 1, false)),
+                    other => self.expression(other, indent, ANY, false),
+                };
+                format!("for {} in {} do {}", variable.to_str(), iterable, self.expression(body, indent, ANY, closed))
+            }
+            AST::Range { start, end } =>
+                format!("{}..{}", self.expression(start, indent, 1, false), self.expression(end, indent, 1, false)),
+            AST::Break => "break".to_string(),
+            AST::Continue => "continue".to_string(),
+            AST::Conditional { condition, consequent, alternative } => {
+                let condition = self.expression(condition, indent, ANY, false);
+                match alternative.as_ref() {
+                    AST::Unit if !closed =>
+                        format!("if {} then {}", condition, self.expression(consequent, indent, ANY, false)),
+                    _ => format!("if {} then {} else {}", condition, self.expression(consequent, indent, ANY, true),
+                                 self.expression(alternative, indent, ANY, closed)),
+                }
+            }
+            AST::Match { value, arms } => {
+                let value = self.expression(value, indent, ANY, false);
+                let arms: Vec<String> = arms.iter().map(|(pattern, body)| format!("{}{} -> {}",
+                    INDENT.repeat(indent + 1), self.pattern(pattern), self.expression(body, indent + 1, ANY, false))).collect();
+                format!("match {} with\n{}\n{}end", value, arms.join(";\n"), INDENT.repeat(indent))
+            }
+
+            AST::Import { path, name } => {
+                let stem = std::path::Path::new(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or(path);
+                if stem == name.to_str() {
+                    format!("import \"{}\"", path)
+                } else {
+                    format!("import \"{}\" as {}", path, name.to_str())
+                }
+            }
+        }
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> String {
+        match pattern {
+            Pattern::Number(n) => n.to_string(),
+            Pattern::Boolean(b) => b.to_string(),
+            Pattern::String(s) => format!("\"{}\"", s),
+            Pattern::Unit => "null".to_string(),
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Binding(name) => name.to_string(),
+            Pattern::Array(elements) =>
+                format!("[{}]", elements.iter().map(|element| self.pattern(element)).collect::<Vec<_>>().join(", ")),
+            Pattern::Object(fields) => format!("object({})", fields.iter().map(|(field, pattern)| match pattern {
+                Pattern::Binding(name) if name == field => field.to_string(),
+                _ => format!("{} = {}", field.to_str(), self.pattern(pattern)),
+            }).collect::<Vec<_>>().join(", ")),
+        }
+    }
+}
+
+fn names(identifiers: &[Identifier]) -> String {
+    identifiers.iter().map(|identifier| identifier.to_str()).collect::<Vec<_>>().join(", ")
+}
+
+// Float literals always have digits on both sides of the point.
+fn float(value: f64) -> String {
+    let text = format!("{:?}", value);
+    match text.find(['e', 'E']) {
+        Some(exponent) if !text[..exponent].contains('.') =>
+            format!("{}.0{}", &text[..exponent], &text[exponent..]),
+        _ => text,
+    }
+}
+
+/**
+ * The lines that differ between the original and the formatted source, for `--check`.
+ */
+pub fn diff(original: &str, formatted: &str) -> String {
+    let old: Vec<&str> = original.lines().collect();
+    let new: Vec<&str> = formatted.lines().collect();
+
+    // Lengths of the longest common subsequences of the suffixes of both.
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] { common[i + 1][j + 1] + 1 }
+                           else { common[i + 1][j].max(common[i][j + 1]) };
+        }
+    }
+
+    let mut output = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1; j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            output.push(format!("{:>4} -{}", i + 1, old[i]));
+            i += 1;
+        } else {
+            output.push(format!("{:>4} +{}", j + 1, new[j]));
+            j += 1;
+        }
+    }
+    output.join("\n")
+}
@@ -0,0 +1,651 @@
+use fml_ast::{AST, Identifier, Operator, Pattern};
+
+use crate::formatter;
+
+/**
+ * Random FML programs, for fuzzing the parser and the interpreters.
+ *
+ * A program is generated from a seed, so the same seed always gives the same program. Programs are
+ * well-formed: they only use variables where they are defined, call functions and methods with as
+ * many arguments as they take, never divide by zero or index outside of an array or a map, and
+ * always end, since loops count up to a small bound and functions cannot call functions. Output is
+ * printed only by statements outside of functions, and expressions have no side effects, so the
+ * program prints the same whatever order an interpreter evaluates operands in, and whether it
+ * short-circuits.
+ *
+ * Besides integers and booleans, programs use floats, strings, arrays, maps, objects that extend
+ * other objects, classes that extend other classes, and `match` expressions. Only integers,
+ * booleans, floats, and strings are printed, since the interpreters print other values
+ * differently.
+ */
+pub fn program(seed: u64) -> AST {
+    Generator::new(seed).program()
+}
+
+/**
+ * The source of the program generated from a seed.
+ */
+pub fn source(seed: u64) -> String {
+    formatter::print(&program(seed))
+}
+
+const MAX_DEPTH: usize = 3;
+const MAX_LOOPS: usize = 2;
+const MAX_ITERATIONS: i64 = 4;
+const MAX_PARAMETERS: usize = 3;
+const STRINGS: &[&str] = &["a", "b", "fml", "feeny"];
+
+#[derive(Clone)]
+enum Type {
+    Integer,
+    Float,
+    Boolean,
+    String,
+    Array(i64),
+    Map(Vec<i64>),
+    Object { fields: Vec<String>, methods: Vec<(String, usize)> },
+}
+
+#[derive(Clone)]
+struct Variable {
+    name: String,
+    kind: Type,
+    // Whether the statements being generated may change the variable or, for arrays, maps, and
+    // objects, its contents.
+    mutable: bool,
+}
+
+// The members of an object or class, along with the names of all its fields and methods.
+type Members = (Vec<Box<AST>>, Vec<String>, Vec<(String, usize)>);
+
+#[derive(Clone)]
+struct Class {
+    name: String,
+    arity: usize,
+    fields: Vec<String>,
+    methods: Vec<(String, usize)>,
+}
+
+// A xorshift generator, which is all the randomness needed here.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // A number from `0` to `bound - 1`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn range(&mut self, low: i64, high: i64) -> i64 {
+        low + self.below((high - low + 1) as usize) as i64
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
+
+pub struct Generator {
+    random: Random,
+    names: usize,
+    scopes: Vec<Vec<Variable>>,
+    functions: Vec<(String, usize)>,
+    classes: Vec<Class>,
+    // Fields of `this`, inside a method.
+    this: Option<Vec<String>>,
+    // Inside a function or method, which must have no side effects and cannot call functions.
+    function: bool,
+    loops: usize,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift never leaves zero, and similar seeds start out similar, so the seed is mixed.
+        let mut random = Random(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1);
+        (0..8).for_each(|_| { random.next(); });
+        Generator {
+            random, names: 0, scopes: vec!(vec!()), functions: vec!(), classes: vec!(), this: None,
+            function: false, loops: 0,
+        }
+    }
+
+    pub fn program(&mut self) -> AST {
+        let mut statements = Vec::new();
+        let count = self.random.range(4, 10);
+        for _ in 0..count {
+            match self.random.below(12) {
+                0 | 1 => statements.push(self.function_definition()),
+                2 => statements.push(self.object_definition()),
+                3 => statements.push(self.class_definition()),
+                4 if !self.classes.is_empty() => statements.push(self.instantiation()),
+                _ => statements.extend(self.statement(0)),
+            }
+        }
+        // Every program prints something.
+        statements.push(self.print());
+        AST::Top(statements.into_iter().map(Box::new).collect())
+    }
+
+    fn name(&mut self, prefix: &str) -> String {
+        self.names += 1;
+        format!("{}{}", prefix, self.names)
+    }
+
+    fn define(&mut self, name: &str, kind: Type, mutable: bool) {
+        self.scopes.last_mut().unwrap().push(Variable { name: name.to_string(), kind, mutable });
+    }
+
+    fn variables(&self, accept: impl Fn(&Variable) -> bool) -> Vec<Variable> {
+        self.scopes.iter().flatten().filter(|variable| accept(variable)).cloned().collect()
+    }
+
+    fn pick<T: Clone>(&mut self, choices: &[T]) -> T {
+        choices[self.random.below(choices.len())].clone()
+    }
+
+    fn access(name: &str) -> Box<AST> {
+        Box::new(AST::VariableAccess { name: Identifier(name.to_string()) })
+    }
+
+    // Generates something in the scope of a function, where the variables defined so far can be
+    // read but not changed.
+    fn within_function<T>(&mut self, parameters: &[Identifier], this: Option<Vec<String>>,
+                          generate: impl FnOnce(&mut Self) -> T) -> T {
+        let visible: Vec<Variable> = self.scopes.iter().flatten()
+            .map(|variable| Variable { mutable: false, ..variable.clone() }).collect();
+        let mut scope: Vec<Variable> = parameters.iter()
+            .map(|parameter| Variable { name: parameter.to_string(), kind: Type::Integer, mutable: true }).collect();
+        scope.splice(0..0, visible);
+        let scopes = std::mem::replace(&mut self.scopes, vec!(scope));
+        let this = std::mem::replace(&mut self.this, this);
+        self.function = true;
+
+        let result = generate(self);
+
+        self.function = false;
+        self.this = this;
+        self.scopes = scopes;
+        result
+    }
+
+    fn function_body(&mut self, parameters: &[Identifier], this: Option<Vec<String>>) -> AST {
+        self.within_function(parameters, this, |generator| {
+            if generator.random.chance(50) {
+                generator.integer(MAX_DEPTH)
+            } else {
+                generator.block(0, true)
+            }
+        })
+    }
+
+    fn parameters(&mut self) -> Vec<Identifier> {
+        let count = self.random.below(MAX_PARAMETERS + 1);
+        (0..count).map(|_| Identifier(self.name("p"))).collect()
+    }
+
+    fn function_definition(&mut self) -> AST {
+        let name = self.name("f");
+        let parameters = self.parameters();
+        let body = self.function_body(&parameters, None);
+        self.functions.push((name.clone(), parameters.len()));
+        AST::FunctionDefinition { function: Identifier(name), parameters, body: Box::new(body) }
+    }
+
+    // Fields with integer values, and methods that can read all the fields, including those
+    // inherited from the parent. The values of the fields of a class can also read the parameters
+    // of its constructor, which its methods cannot.
+    fn members(&mut self, parameters: Option<&[Identifier]>, mut fields: Vec<String>, mut methods: Vec<(String, usize)>)
+               -> Members {
+        let mut members = Vec::new();
+        for _ in 0..self.random.range(1, 3) {
+            let field = self.name("x");
+            let value = match parameters {
+                Some(parameters) => self.within_function(parameters, None, |generator| generator.integer(1)),
+                None => self.integer(1),
+            };
+            members.push(Box::new(AST::VariableDefinition { name: Identifier(field.clone()), value: Box::new(value) }));
+            fields.push(field);
+        }
+        for _ in 0..self.random.below(3) {
+            let method = self.name("m");
+            let parameters = self.parameters();
+            let body = self.function_body(&parameters, Some(fields.clone()));
+            methods.push((method.clone(), parameters.len()));
+            members.push(Box::new(AST::FunctionDefinition { function: Identifier(method), parameters, body: Box::new(body) }));
+        }
+        (members, fields, methods)
+    }
+
+    fn object_definition(&mut self) -> AST {
+        let name = self.name("o");
+        let parents = self.variables(|variable| matches!(variable.kind, Type::Object { .. }));
+        let (extends, inherited_fields, inherited_methods) = match parents.is_empty() || self.random.chance(50) {
+            true => (None, vec!(), vec!()),
+            false => match self.pick(&parents) {
+                Variable { name, kind: Type::Object { fields, methods }, .. } => (Some(Self::access(&name)), fields, methods),
+                _ => unreachable!(),
+            },
+        };
+        let (members, fields, methods) = self.members(None, inherited_fields, inherited_methods);
+        let object = AST::ObjectDefinition { extends, members };
+        self.define(&name, Type::Object { fields, methods }, true);
+        AST::VariableDefinition { name: Identifier(name), value: Box::new(object) }
+    }
+
+    // A class whose fields are set from the arguments of its constructor.
+    fn class_definition(&mut self) -> AST {
+        let name = self.name("C");
+        let parameters = self.parameters();
+        let parent = if self.classes.is_empty() || self.random.chance(50) {
+            None
+        } else {
+            let classes = self.classes.clone();
+            Some(self.pick(&classes))
+        };
+
+        let (extends, inherited_fields, inherited_methods) = match parent {
+            Some(parent) => {
+                let arguments = self.within_function(&parameters, None, |generator| {
+                    generator.arguments(parent.arity, 2)
+                });
+                let extends = AST::Instantiation { class: Identifier(parent.name), arguments: arguments.into_iter().map(Box::new).collect() };
+                (Some(Box::new(extends)), parent.fields, parent.methods)
+            }
+            None => (None, vec!(), vec!()),
+        };
+
+        let (members, fields, methods) = self.members(Some(&parameters), inherited_fields, inherited_methods);
+
+        self.classes.push(Class { name: name.clone(), arity: parameters.len(), fields, methods });
+        AST::ClassDefinition { name: Identifier(name), parameters, extends, members }
+    }
+
+    fn instantiation(&mut self) -> AST {
+        let classes = self.classes.clone();
+        let class = self.pick(&classes);
+        let name = self.name("o");
+        let arguments = self.arguments(class.arity, MAX_DEPTH);
+        self.define(&name, Type::Object { fields: class.fields, methods: class.methods }, true);
+        let instance = AST::Instantiation { class: Identifier(class.name), arguments: arguments.into_iter().map(Box::new).collect() };
+        AST::VariableDefinition { name: Identifier(name), value: Box::new(instance) }
+    }
+
+    // A block of statements, ending with an integer if it is the value of something.
+    fn block(&mut self, depth: usize, value: bool) -> AST {
+        self.scopes.push(vec!());
+        let mut statements = Vec::new();
+        for _ in 0..self.random.range(1, 3) {
+            statements.extend(self.statement(depth + 1));
+        }
+        if value {
+            statements.push(self.integer(MAX_DEPTH.saturating_sub(depth)));
+        }
+        self.scopes.pop();
+        AST::Block(statements.into_iter().map(Box::new).collect())
+    }
+
+    // One statement, or two for a `while` loop and its counter.
+    fn statement(&mut self, depth: usize) -> Vec<AST> {
+        let nested = depth < MAX_DEPTH;
+        let loops = nested && self.loops < MAX_LOOPS;
+        loop {
+            let statement = match self.random.below(9) {
+                0 | 1 => self.definition(),
+                2 => self.mutation(),
+                3 | 4 if !self.function => Some(self.print()),
+                5 if nested => Some(self.conditional(depth)),
+                6 if loops => return self.while_loop(depth),
+                7 if loops => Some(self.for_loop(depth)),
+                _ => None,
+            };
+            if let Some(statement) = statement {
+                return vec!(statement)
+            }
+        }
+    }
+
+    fn definition(&mut self) -> Option<AST> {
+        let name = self.name("v");
+        let (kind, value) = match self.random.below(8) {
+            0 => (Type::Boolean, self.boolean(MAX_DEPTH)),
+            1 => {
+                let size = self.random.range(1, 4);
+                let value = self.integer(1);
+                (Type::Array(size), AST::ArrayDefinition { size: Box::new(AST::Number(size)), value: Box::new(value) })
+            }
+            2 => (Type::Float, self.float(MAX_DEPTH)),
+            3 => (Type::String, self.string()),
+            4 => {
+                let mut keys: Vec<i64> = Vec::new();
+                let mut entries = Vec::new();
+                for _ in 0..self.random.range(1, 3) {
+                    let key = self.random.range(0, 5);
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                        entries.push((Box::new(AST::Number(key)), Box::new(self.integer(1))));
+                    }
+                }
+                (Type::Map(keys), AST::MapDefinition { entries })
+            }
+            _ => (Type::Integer, self.integer(MAX_DEPTH)),
+        };
+        self.define(&name, kind, true);
+        Some(AST::VariableDefinition { name: Identifier(name), value: Box::new(value) })
+    }
+
+    fn mutation(&mut self) -> Option<AST> {
+        let variables = self.variables(|variable| variable.mutable && !matches!(variable.kind, Type::Boolean));
+        if variables.is_empty() {
+            return None
+        }
+        let variable = self.pick(&variables);
+        let target = Self::access(&variable.name);
+        match variable.kind {
+            Type::Integer => Some(AST::VariableMutation { name: Identifier(variable.name), value: Box::new(self.integer(MAX_DEPTH)) }),
+            Type::Float => Some(AST::VariableMutation { name: Identifier(variable.name), value: Box::new(self.float(MAX_DEPTH)) }),
+            Type::String => Some(AST::VariableMutation { name: Identifier(variable.name), value: Box::new(self.string()) }),
+            Type::Array(size) => {
+                let index = Box::new(AST::Number(self.random.range(0, size - 1)));
+                Some(AST::ArrayMutation { array: target, index, value: Box::new(self.integer(MAX_DEPTH)) })
+            }
+            Type::Map(keys) if !keys.is_empty() => {
+                let index = Box::new(AST::Number(self.pick(&keys)));
+                Some(AST::ArrayMutation { array: target, index, value: Box::new(self.integer(MAX_DEPTH)) })
+            }
+            Type::Object { fields, .. } => {
+                let field = Identifier(self.pick(&fields));
+                Some(AST::FieldMutation { object: target, field, value: Box::new(self.integer(MAX_DEPTH)) })
+            }
+            _ => None,
+        }
+    }
+
+    fn print(&mut self) -> AST {
+        let count = self.random.range(1, 3);
+        // Only scalars: the interpreters do not print arrays, maps, and objects alike.
+        let arguments: Vec<Box<AST>> = (0..count).map(|_| {
+            let argument = match self.random.below(20) {
+                0..=3 => self.boolean(MAX_DEPTH),
+                4 | 5 => self.float(MAX_DEPTH),
+                6 | 7 => self.string(),
+                _ => self.integer(MAX_DEPTH),
+            };
+            Box::new(argument)
+        }).collect();
+        let placeholders = vec!("~"; arguments.len()).join(" ");
+        AST::Print { format: format!("{}\\n", placeholders), arguments }
+    }
+
+    fn conditional(&mut self, depth: usize) -> AST {
+        let condition = self.boolean(MAX_DEPTH);
+        let consequent = self.block(depth, false);
+        let alternative = if self.random.chance(50) { self.block(depth, false) } else { AST::Unit };
+        AST::Conditional { condition: Box::new(condition), consequent: Box::new(consequent), alternative: Box::new(alternative) }
+    }
+
+    // `let i = 0; while i < n do begin ...; i <- i + 1 end`, where the body cannot change `i`.
+    fn while_loop(&mut self, depth: usize) -> Vec<AST> {
+        let counter = self.name("i");
+        let bound = self.random.range(1, MAX_ITERATIONS);
+        self.define(&counter, Type::Integer, false);
+
+        self.loops += 1;
+        let body = match self.block(depth, false) {
+            AST::Block(mut statements) => {
+                let step = AST::Operation { operator: Operator::Addition, left: Self::access(&counter), right: Box::new(AST::Number(1)) };
+                statements.push(Box::new(AST::VariableMutation { name: Identifier(counter.clone()), value: Box::new(step) }));
+                AST::Block(statements)
+            }
+            _ => unreachable!(),
+        };
+        self.loops -= 1;
+
+        let condition = AST::Operation { operator: Operator::Less, left: Self::access(&counter), right: Box::new(AST::Number(bound)) };
+        vec!(
+            AST::VariableDefinition { name: Identifier(counter.clone()), value: Box::new(AST::Number(0)) },
+            AST::Loop { condition: Box::new(condition), body: Box::new(body) },
+        )
+    }
+
+    fn for_loop(&mut self, depth: usize) -> AST {
+        let variable = self.name("i");
+        let start = self.random.range(0, 2);
+        let end = start + self.random.range(0, MAX_ITERATIONS);
+        let iterable = AST::Range { start: Box::new(AST::Number(start)), end: Box::new(AST::Number(end)) };
+
+        self.loops += 1;
+        self.scopes.push(vec!());
+        self.define(&variable, Type::Integer, false);
+        let body = self.block(depth, false);
+        self.scopes.pop();
+        self.loops -= 1;
+
+        AST::Iteration { variable: Identifier(variable), iterable: Box::new(iterable), body: Box::new(body) }
+    }
+
+    fn integer(&mut self, depth: usize) -> AST {
+        loop {
+            let choice = if depth == 0 { self.random.below(5) } else { self.random.below(15) };
+            let expression = match choice {
+                0 => Some(AST::Number(self.random.range(-20, 100))),
+                1 => self.variable(|kind| matches!(kind, Type::Integer)),
+                2 => self.array_element(),
+                3 => self.field(),
+                4 => self.map_element(),
+                5..=7 => {
+                    let operator = self.pick(&[Operator::Addition, Operator::Subtraction, Operator::Multiplication,
+                                               Operator::Division, Operator::Module]);
+                    let left = self.integer(depth - 1);
+                    let right = match operator {
+                        Operator::Division | Operator::Module => AST::Number(self.random.range(1, 9)),
+                        _ => self.integer(depth - 1),
+                    };
+                    Some(AST::Operation { operator, left: Box::new(left), right: Box::new(right) })
+                }
+                8 => Some(AST::Conditional {
+                    condition: Box::new(self.boolean(depth - 1)),
+                    consequent: Box::new(self.integer(depth - 1)),
+                    alternative: Box::new(self.integer(depth - 1)),
+                }),
+                9 => self.call(depth),
+                10 => self.method_call(depth),
+                11 => {
+                    self.scopes.push(vec!());
+                    let name = self.name("v");
+                    let value = self.integer(depth - 1);
+                    self.define(&name, Type::Integer, true);
+                    let result = self.integer(depth - 1);
+                    self.scopes.pop();
+                    Some(AST::Block(vec!(
+                        Box::new(AST::VariableDefinition { name: Identifier(name), value: Box::new(value) }),
+                        Box::new(result),
+                    )))
+                }
+                12 => Some(self.integer_match(depth)),
+                13 => self.map_size(),
+                _ => Some(AST::Number(self.random.range(0, 9))),
+            };
+            if let Some(expression) = expression {
+                return expression
+            }
+        }
+    }
+
+    // `match n with 0 -> ...; 1 -> ...; x -> ... end`, which always ends with an arm that matches
+    // anything.
+    fn integer_match(&mut self, depth: usize) -> AST {
+        let value = self.integer(depth - 1);
+        let mut arms = Vec::new();
+        let mut literals: Vec<i64> = Vec::new();
+        for _ in 0..self.random.range(0, 2) {
+            let literal = self.random.range(0, 5);
+            if !literals.contains(&literal) {
+                literals.push(literal);
+                arms.push((Pattern::Number(literal), Box::new(self.integer(depth - 1))));
+            }
+        }
+        if self.random.chance(50) {
+            arms.push((Pattern::Wildcard, Box::new(self.integer(depth - 1))));
+        } else {
+            let name = self.name("v");
+            self.scopes.push(vec!());
+            self.define(&name, Type::Integer, false);
+            let body = self.integer(depth - 1);
+            self.scopes.pop();
+            arms.push((Pattern::Binding(Identifier(name)), Box::new(body)));
+        }
+        AST::Match { value: Box::new(value), arms }
+    }
+
+    fn float(&mut self, depth: usize) -> AST {
+        // Quarters, so that sums and products are exact and print the same everywhere.
+        let literal = |generator: &mut Self| AST::Float(generator.random.range(-12, 40) as f64 / 4.0);
+        match if depth == 0 { self.random.below(2) } else { self.random.below(5) } {
+            0 => literal(self),
+            1 => self.variable(|kind| matches!(kind, Type::Float)).unwrap_or_else(|| literal(self)),
+            2 | 3 => {
+                let operator = self.pick(&[Operator::Addition, Operator::Subtraction, Operator::Multiplication]);
+                AST::Operation { operator, left: Box::new(self.float(depth - 1)), right: Box::new(self.float(depth - 1)) }
+            }
+            _ => AST::Conditional {
+                condition: Box::new(self.boolean(depth - 1)),
+                consequent: Box::new(self.float(depth - 1)),
+                alternative: Box::new(self.float(depth - 1)),
+            },
+        }
+    }
+
+    fn string(&mut self) -> AST {
+        match self.variable(|kind| matches!(kind, Type::String)) {
+            Some(variable) if self.random.chance(50) => variable,
+            _ => AST::String(self.pick(STRINGS).to_string()),
+        }
+    }
+
+    fn boolean(&mut self, depth: usize) -> AST {
+        let depth = depth.max(1);
+        match self.random.below(9) {
+            0 => AST::Boolean(self.random.chance(50)),
+            1 => self.variable(|kind| matches!(kind, Type::Boolean))
+                .unwrap_or_else(|| AST::Boolean(self.random.chance(50))),
+            2 if depth > 1 => {
+                let operator = self.pick(&[Operator::Conjunction, Operator::Disjunction]);
+                AST::Operation { operator, left: Box::new(self.boolean(depth - 1)), right: Box::new(self.boolean(depth - 1)) }
+            }
+            3 => {
+                let operator = self.pick(&[Operator::Less, Operator::Greater, Operator::Equality, Operator::Inequality]);
+                AST::Operation { operator, left: Box::new(self.float(depth - 1)), right: Box::new(self.float(depth - 1)) }
+            }
+            4 => {
+                let operator = self.pick(&[Operator::Equality, Operator::Inequality]);
+                AST::Operation { operator, left: Box::new(self.string()), right: Box::new(self.string()) }
+            }
+            5 => {
+                let maps = self.variables(|variable| matches!(variable.kind, Type::Map(_)));
+                if maps.is_empty() {
+                    return AST::Boolean(self.random.chance(50))
+                }
+                let map = self.pick(&maps);
+                let key = AST::Number(self.random.range(0, 5));
+                AST::MethodCall { object: Self::access(&map.name), method: Identifier::from("has"), arguments: vec!(Box::new(key)) }
+            }
+            _ => {
+                let operator = self.pick(&[Operator::Less, Operator::LessEqual, Operator::Greater,
+                                           Operator::GreaterEqual, Operator::Equality, Operator::Inequality]);
+                AST::Operation { operator, left: Box::new(self.integer(depth - 1)), right: Box::new(self.integer(depth - 1)) }
+            }
+        }
+    }
+
+    fn variable(&mut self, accept: impl Fn(&Type) -> bool) -> Option<AST> {
+        let variables = self.variables(|variable| accept(&variable.kind));
+        if variables.is_empty() {
+            return None
+        }
+        Some(*Self::access(&self.pick(&variables).name))
+    }
+
+    fn array_element(&mut self) -> Option<AST> {
+        let arrays = self.variables(|variable| matches!(variable.kind, Type::Array(_)));
+        if arrays.is_empty() {
+            return None
+        }
+        let array = self.pick(&arrays);
+        let size = match array.kind { Type::Array(size) => size, _ => unreachable!() };
+        Some(AST::ArrayAccess {
+            array: Self::access(&array.name),
+            index: Box::new(AST::Number(self.random.range(0, size - 1))),
+        })
+    }
+
+    fn map_element(&mut self) -> Option<AST> {
+        let maps = self.variables(|variable| matches!(&variable.kind, Type::Map(keys) if !keys.is_empty()));
+        if maps.is_empty() {
+            return None
+        }
+        let map = self.pick(&maps);
+        let keys = match map.kind { Type::Map(keys) => keys, _ => unreachable!() };
+        Some(AST::ArrayAccess { array: Self::access(&map.name), index: Box::new(AST::Number(self.pick(&keys))) })
+    }
+
+    fn map_size(&mut self) -> Option<AST> {
+        let maps = self.variables(|variable| matches!(variable.kind, Type::Map(_)));
+        if maps.is_empty() {
+            return None
+        }
+        let map = self.pick(&maps);
+        Some(AST::MethodCall { object: Self::access(&map.name), method: Identifier::from("size"), arguments: vec!() })
+    }
+
+    fn field(&mut self) -> Option<AST> {
+        let mut choices: Vec<(String, String)> = Vec::new();
+        for variable in self.variables(|_| true) {
+            if let Type::Object { fields, .. } = &variable.kind {
+                choices.extend(fields.iter().map(|field| (variable.name.clone(), field.clone())));
+            }
+        }
+        if let Some(fields) = &self.this {
+            choices.extend(fields.iter().map(|field| ("this".to_string(), field.clone())));
+        }
+        if choices.is_empty() {
+            return None
+        }
+        let (object, field) = self.pick(&choices);
+        Some(AST::FieldAccess { object: Self::access(&object), field: Identifier(field) })
+    }
+
+    fn arguments(&mut self, count: usize, depth: usize) -> Vec<AST> {
+        (0..count).map(|_| self.integer(depth - 1)).collect()
+    }
+
+    fn call(&mut self, depth: usize) -> Option<AST> {
+        if self.function || self.functions.is_empty() {
+            return None
+        }
+        let (function, arity) = self.pick(&self.functions.clone());
+        Some(AST::FunctionCall { function: Identifier(function), arguments: self.arguments(arity, depth).into_iter().map(Box::new).collect() })
+    }
+
+    fn method_call(&mut self, depth: usize) -> Option<AST> {
+        if self.function {
+            return None
+        }
+        let mut choices: Vec<(String, String, usize)> = Vec::new();
+        for variable in self.variables(|_| true) {
+            if let Type::Object { methods, .. } = &variable.kind {
+                choices.extend(methods.iter().map(|(method, arity)| (variable.name.clone(), method.clone(), *arity)));
+            }
+        }
+        if choices.is_empty() {
+            return None
+        }
+        let (object, method, arity) = self.pick(&choices);
+        Some(AST::MethodCall { object: Self::access(&object), method: Identifier(method), arguments: self.arguments(arity, depth).into_iter().map(Box::new).collect() })
+    }
+}
@@ -0,0 +1,204 @@
+/**
+ * The tokens of FML, as the parser sees them.
+ *
+ * The parser's own lexer is generated by LALRPOP from the `match` block of `fml.lalrpop` and is not
+ * reachable from Rust, so this one follows the same rules by hand: at every position the longest
+ * token wins, and a keyword or symbol wins over an identifier or number of the same length. This
+ * means, for instance, that `4-5` is the two numbers `4` and `-5`, and that `1..2` is a range
+ * rather than a float. Any rule changed in the grammar has to be changed here too.
+ *
+ * Whitespace and comments are dropped, as the parser drops them, unless the lexer is created with
+ * `Lexer::with_trivia`. Characters that start no token are returned one at a time as `Error`
+ * tokens, where the parser would stop.
+ */
+#[derive(PartialEq,Eq,Debug,Copy,Clone)]
+pub enum TokenKind {
+    Keyword,
+    Identifier,
+    Number,
+    Float,
+    String,
+    Operator,
+    Punctuation,
+    Comment,
+    Whitespace,
+    Error,
+}
+
+#[derive(PartialEq,Eq,Debug,Copy,Clone)]
+pub struct Token<'input> {
+    pub kind: TokenKind,
+    pub text: &'input str,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl<'input> Token<'input> {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Comment | TokenKind::Whitespace)
+    }
+}
+
+pub const KEYWORDS: &[&str] = &[
+    "begin", "end", "if", "then", "else", "let", "const", "null", "print", "object", "extends",
+    "class", "new", "super", "is", "while", "do", "for", "in", "break", "continue", "match", "with",
+    "function", "array", "map", "true", "false", "this", "import", "as",
+];
+
+/**
+ * Whether the text can be used as a name in FML source: a letter or underscore followed by letters,
+ * digits, and underscores, and not a keyword.
+ */
+pub fn is_identifier(text: &str) -> bool {
+    let mut characters = text.chars();
+    let first = match characters.next() {
+        Some(character) => character,
+        None => return false,
+    };
+    (first == '_' || first.is_ascii_alphabetic())
+        && characters.all(|character| character == '_' || character.is_ascii_alphanumeric())
+        && !KEYWORDS.contains(&text)
+}
+
+pub const OPERATORS: &[&str] = &[
+    "|", "&", "==", "!=", ">", "<", ">=", "<=", "+", "-", "/", "*", "%", "=", "<-", "->",
+];
+
+pub const PUNCTUATION: &[&str] = &[";", "(", ")", ".", "..", "[", "]", ","];
+
+pub struct Lexer<'input> {
+    input: &'input str,
+    position: usize,
+    trivia: bool,
+}
+
+impl<'input> Lexer<'input> {
+    pub fn new(input: &'input str) -> Self {
+        Lexer { input, position: 0, trivia: false }
+    }
+
+    pub fn with_trivia(input: &'input str) -> Self {
+        Lexer { input, position: 0, trivia: true }
+    }
+
+    // The kind and length of the token at the start of the text.
+    fn token(text: &str) -> (TokenKind, usize) {
+        let first = text.chars().next().unwrap();
+        if first.is_whitespace() {
+            return (TokenKind::Whitespace, text.find(|c: char| !c.is_whitespace()).unwrap_or(text.len()))
+        }
+        if text.starts_with("//") {
+            return (TokenKind::Comment, text.find('\n').unwrap_or(text.len()))
+        }
+        if let Some(end) = text.strip_prefix("/*").and_then(|comment| comment.find("*/")) {
+            return (TokenKind::Comment, end + 4)
+        }
+        if first == '"' {
+            if let Some(length) = string_length(text) {
+                return (TokenKind::String, length)
+            }
+        }
+
+        // Fixed tokens win over identifiers and numbers of the same length.
+        let mut longest = (TokenKind::Error, 0);
+        let identifier = text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(text.len());
+        if identifier > 0 && !first.is_ascii_digit() {
+            longest = (TokenKind::Identifier, identifier);
+        }
+        let number = number_length(text);
+        if number > longest.1 {
+            longest = (TokenKind::Number, number);
+        }
+        let float = float_length(text);
+        if float > longest.1 {
+            longest = (TokenKind::Float, float);
+        }
+        let fixed = [(KEYWORDS, TokenKind::Keyword), (OPERATORS, TokenKind::Operator), (PUNCTUATION, TokenKind::Punctuation)];
+        for (tokens, kind) in fixed.iter() {
+            for token in tokens.iter() {
+                if text.starts_with(token) && token.len() >= longest.1 {
+                    longest = (*kind, token.len());
+                }
+            }
+        }
+
+        if longest.1 == 0 {
+            (TokenKind::Error, first.len_utf8())
+        } else {
+            longest
+        }
+    }
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Token<'input>;
+
+    fn next(&mut self) -> Option<Token<'input>> {
+        while self.position < self.input.len() {
+            let start = self.position;
+            let (kind, length) = Lexer::token(&self.input[start..]);
+            self.position += length;
+            let token = Token { kind, text: &self.input[start..self.position], start, end: self.position };
+            if self.trivia || !token.is_trivia() {
+                return Some(token)
+            }
+        }
+        None
+    }
+}
+
+pub fn tokenize(input: &str) -> Vec<Token<'_>> {
+    Lexer::new(input).collect()
+}
+
+// `"` followed by anything but `\` and `"`, or the escapes `\~`, `\n`, `\t`, `\r`, and `\\`, and `"`.
+fn string_length(text: &str) -> Option<usize> {
+    let mut characters = text.char_indices().skip(1);
+    while let Some((index, c)) = characters.next() {
+        match c {
+            '"' => return Some(index + 1),
+            '\\' => match characters.next() {
+                Some((_, '~')) | Some((_, 'n')) | Some((_, 't')) | Some((_, 'r')) | Some((_, '\\')) => (),
+                _ => return None,
+            },
+            _ => (),
+        }
+    }
+    None
+}
+
+fn digits(text: &str) -> usize {
+    text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len())
+}
+
+// `-?[0-9]+`
+fn number_length(text: &str) -> usize {
+    let sign = if text.starts_with('-') { 1 } else { 0 };
+    match digits(&text[sign..]) {
+        0 => 0,
+        length => sign + length,
+    }
+}
+
+// `-?[0-9]+\.[0-9]+([eE][-+]?[0-9]+)?`
+fn float_length(text: &str) -> usize {
+    let whole = number_length(text);
+    if whole == 0 || !text[whole..].starts_with('.') {
+        return 0
+    }
+    let fraction = digits(&text[whole + 1..]);
+    if fraction == 0 {
+        return 0
+    }
+    let length = whole + 1 + fraction;
+    let rest = &text[length..];
+    if rest.starts_with(['e', 'E']) {
+        let sign = if rest[1..].starts_with(['+', '-']) { 1 } else { 0 };
+        match digits(&rest[1 + sign..]) {
+            0 => length,
+            exponent => length + 1 + sign + exponent,
+        }
+    } else {
+        length
+    }
+}
@@ -6,17 +6,90 @@ extern crate fml_ast;
 
 lalrpop_mod!(pub fml); // synthesized by LALRPOP
 
+pub mod modules;
+pub mod diagnostics;
+pub mod lexer;
+pub mod feeny;
+pub mod serialization;
+pub mod diagram;
+pub mod trivia;
+pub mod formatter;
+pub mod checker;
+pub mod linter;
+pub mod generator;
+
+use diagnostics::Diagnostic;
+use trivia::Trivia;
+
+/**
+ * The surface syntaxes understood by the parser. Both produce the same AST.
+ */
+#[derive(PartialEq,Debug,Copy,Clone)]
+pub enum Syntax {
+    FML,
+    Feeny,
+}
+
+impl Syntax {
+    // Feeny sources are recognized by the `.feeny` extension, everything else is FML.
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension == "feeny" => Syntax::Feeny,
+            _ => Syntax::FML,
+        }
+    }
+
+    pub fn parse(&self, input: &str) -> Result<fml_ast::AST, String> {
+        match self {
+            Syntax::FML => parse(input),
+            Syntax::Feeny => feeny::parse(input),
+        }
+    }
+
+    pub fn parse_with_diagnostics(&self, input: &str) -> Result<fml_ast::AST, Vec<Diagnostic>> {
+        match self {
+            Syntax::FML => parse_with_diagnostics(input),
+            Syntax::Feeny => feeny::parse_with_diagnostics(input),
+        }
+    }
+}
+
 pub fn parse(input: &str) -> Result<fml_ast::AST, String> {
-    match fml::TopLevelParser::new().parse(input) {
-        Err(e) => Err(format!("{:?}", e)),
-        Ok(ast) => Ok(ast),
+    parse_with_diagnostics(input).map_err(|diagnostics| {
+        diagnostics.iter().map(|diagnostic| diagnostic.render(input)).collect::<Vec<String>>().join("\n")
+    })
+}
+
+/**
+ * Parses the input, recovering from syntax errors to report all of them, in the order in which
+ * they occur in the source.
+ */
+pub fn parse_with_diagnostics(input: &str) -> Result<fml_ast::AST, Vec<Diagnostic>> {
+    parse_with_trivia(input).map(|(ast, _)| ast)
+}
+
+/**
+ * Parses the input like `parse_with_diagnostics`, also returning the comments, which the AST does
+ * not retain, along with the locations of the statements they can be attached to.
+ */
+pub fn parse_with_trivia(input: &str) -> Result<(fml_ast::AST, Trivia), Vec<Diagnostic>> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut statements: Vec<(usize, usize)> = Vec::new();
+    let result = fml::TopLevelParser::new().parse(&mut diagnostics, &mut statements, input);
+    match result {
+        Ok(ast) if diagnostics.is_empty() => Ok((ast, Trivia::new(input, statements))),
+        Ok(_) => Err(diagnostics),
+        Err(error) => {
+            diagnostics.push(Diagnostic::from(error));
+            Err(diagnostics)
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::fml::TopLevelParser;
-    use fml_ast::{AST, Operator, Identifier};
+    use fml_ast::{AST, Operator, Identifier, Pattern};
 
     #[allow(dead_code)]
     fn parse_ok(input: &str, correct: AST) {
@@ -29,13 +102,17 @@ mod tests {
             }
         }
         println!();
-        assert_eq!(TopLevelParser::new().parse(input), Ok(AST::Top(vec!(Box::new(correct)))));
+        let mut errors = Vec::new();
+        assert_eq!(TopLevelParser::new().parse(&mut errors, &mut Vec::new(), input), Ok(AST::Top(vec!(Box::new(correct)))));
+        assert_eq!(errors, vec!());
     }
 
     #[allow(dead_code)]
     fn parse_err(input: &str) {
         println!("{}", input);
-        assert!(TopLevelParser::new().parse(input).is_err());
+        let mut errors = Vec::new();
+        let result = TopLevelParser::new().parse(&mut errors, &mut Vec::new(), input);
+        assert!(result.is_err() || !errors.is_empty());
     }
 
     #[test] fn test_unit()         { parse_ok("null", AST::Unit);        }
@@ -50,6 +127,19 @@ mod tests {
     #[test] fn test_00()           { parse_ok("00",   AST::Number(0));   }
     #[test] fn test_negative_042() { parse_ok("-042", AST::Number(-42)); }
     #[test] fn test_negative_00()  { parse_ok("-00",  AST::Number(0));   }
+    #[test] fn test_i64_max()      { parse_ok("9223372036854775807",  AST::Number(i64::MAX)); }
+    #[test] fn test_i64_min()      { parse_ok("-9223372036854775808", AST::Number(i64::MIN)); }
+    #[test] fn test_i64_overflow() { parse_err("9223372036854775808");                         }
+
+    #[test] fn test_float()                { parse_ok("2.5",     AST::Float(2.5));     }
+    #[test] fn test_negative_float()       { parse_ok("-0.5",    AST::Float(-0.5));    }
+    #[test] fn test_float_exponent()       { parse_ok("1.5e3",   AST::Float(1500.0));  }
+    #[test] fn test_float_negative_exponent() { parse_ok("25.0E-2", AST::Float(0.25)); }
+    #[test] fn test_float_without_fraction()  { parse_err("1.");                       }
+    #[test] fn test_float_without_integer()   { parse_err(".5");                       }
+
+    #[test] fn test_string()               { parse_ok("\"hello\"", AST::String("hello".to_string())); }
+    #[test] fn test_empty_string()         { parse_ok("\"\"",      AST::String(String::new()));        }
 
     #[test] fn test_underscore()             { parse_ok("_",     AST::VariableAccess { name: Identifier::from("_")});     }
     #[test] fn test_underscore_identifier()  { parse_ok("_x",    AST::VariableAccess { name: Identifier::from("_x")});    }
@@ -72,6 +162,13 @@ mod tests {
                      value: Box::new(AST::Number(1))});
     }
 
+    #[test] fn test_constant_definition() {
+        parse_ok("const x = 1",
+                 AST::ConstantDefinition {
+                     name: Identifier::from("x"),
+                     value: Box::new(AST::Number(1))});
+    }
+
     #[test] fn test_mutation()   {
         parse_ok("x <- 1", AST::VariableMutation {
             name: Identifier::from("x"),
@@ -215,6 +312,90 @@ mod tests {
                      body: Box::new(AST::Unit)})
     }
 
+    #[test] fn test_iteration_over_array() {
+        parse_ok("for x in a do x",
+                 AST::Iteration {
+                     variable: Identifier::from("x"),
+                     iterable: Box::new(AST::VariableAccess { name: Identifier::from("a") }),
+                     body: Box::new(AST::VariableAccess { name: Identifier::from("x") })})
+    }
+
+    #[test] fn test_iteration_over_range() {
+        parse_ok("for i in 0..n + 1 do null",
+                 AST::Iteration {
+                     variable: Identifier::from("i"),
+                     iterable: Box::new(AST::Range {
+                         start: Box::new(AST::Number(0)),
+                         end: Box::new(AST::Operation {
+                             operator: Operator::Addition,
+                             left: Box::new(AST::VariableAccess { name: Identifier::from("n") }),
+                             right: Box::new(AST::Number(1))})}),
+                     body: Box::new(AST::Unit)})
+    }
+
+    #[test] fn test_range_outside_of_iteration() {
+        parse_err("0..10");
+    }
+
+    #[test] fn test_break() {
+        parse_ok("while true do break",
+                 AST::Loop {
+                     condition: Box::new(AST::Boolean(true)),
+                     body: Box::new(AST::Break)})
+    }
+
+    #[test] fn test_continue() {
+        parse_ok("for x in a do if x then continue",
+                 AST::Iteration {
+                     variable: Identifier::from("x"),
+                     iterable: Box::new(AST::VariableAccess { name: Identifier::from("a") }),
+                     body: Box::new(AST::Conditional {
+                         condition: Box::new(AST::VariableAccess { name: Identifier::from("x") }),
+                         consequent: Box::new(AST::Continue),
+                         alternative: Box::new(AST::Unit)})})
+    }
+
+    #[test] fn test_match_literals() {
+        parse_ok("match x with 1 -> true; \"one\" -> true; null -> false; _ -> false end",
+                 AST::Match {
+                     value: Box::new(AST::VariableAccess { name: Identifier::from("x") }),
+                     arms: vec![
+                         (Pattern::Number(1), Box::new(AST::Boolean(true))),
+                         (Pattern::String("one".to_string()), Box::new(AST::Boolean(true))),
+                         (Pattern::Unit, Box::new(AST::Boolean(false))),
+                         (Pattern::Wildcard, Box::new(AST::Boolean(false))),
+                     ]})
+    }
+
+    #[test] fn test_match_array() {
+        parse_ok("match a with [x, _] -> x end",
+                 AST::Match {
+                     value: Box::new(AST::VariableAccess { name: Identifier::from("a") }),
+                     arms: vec![
+                         (Pattern::Array(vec![Pattern::Binding(Identifier::from("x")), Pattern::Wildcard]),
+                          Box::new(AST::VariableAccess { name: Identifier::from("x") })),
+                     ]})
+    }
+
+    #[test] fn test_match_object() {
+        parse_ok("match o with object(x, y = 0) -> x; end",
+                 AST::Match {
+                     value: Box::new(AST::VariableAccess { name: Identifier::from("o") }),
+                     arms: vec![
+                         (Pattern::Object(vec![
+                             (Identifier::from("x"), Pattern::Binding(Identifier::from("x"))),
+                             (Identifier::from("y"), Pattern::Number(0))]),
+                          Box::new(AST::VariableAccess { name: Identifier::from("x") })),
+                     ]})
+    }
+
+    #[test] fn test_match_no_arms() {
+        parse_ok("match x with end",
+                 AST::Match {
+                     value: Box::new(AST::VariableAccess { name: Identifier::from("x") }),
+                     arms: vec![]})
+    }
+
     #[test] fn test_conditional() {
         parse_ok("if true then false else true",
                  AST::Conditional{
@@ -266,6 +447,37 @@ mod tests {
                      value: Box::new(AST::Number(0))})
     }
 
+    #[test]
+    fn test_empty_map_definition() {
+        parse_ok("map()", AST::MapDefinition { entries: vec!() })
+    }
+
+    #[test]
+    fn test_map_definition() {
+        parse_ok("map(1 -> true, \"two\" -> null, x + 1 -> 3.0,)",
+                 AST::MapDefinition { entries: vec!(
+                     (Box::new(AST::Number(1)), Box::new(AST::Boolean(true))),
+                     (Box::new(AST::String("two".to_string())), Box::new(AST::Unit)),
+                     (Box::new(AST::Operation {
+                         operator: Operator::Addition,
+                         left: Box::new(AST::VariableAccess { name: Identifier::from("x") }),
+                         right: Box::new(AST::Number(1))}),
+                      Box::new(AST::Float(3.0))))})
+    }
+
+    #[test]
+    fn test_map_definition_without_arrow() {
+        parse_err("map(1, 2)");
+    }
+
+    #[test]
+    fn test_map_access() {
+        parse_ok("m[\"key\"]",
+                 AST::ArrayAccess {
+                     array: Box::new(AST::VariableAccess { name: Identifier::from("m") }),
+                     index: Box::new(AST::String("key".to_string()))})
+    }
+
     #[test]
     fn test_empty_object() {
         parse_ok("object begin end",
@@ -314,6 +526,20 @@ mod tests {
                              value: Box::new(AST::VariableAccess { name: Identifier::from("x")}) }))})
     }
 
+    #[test]
+    fn test_object_with_a_constant_field() {
+        parse_ok("object begin const y = x; let z = 1 end",
+                 AST::ObjectDefinition {
+                     extends: None,
+                     members: vec!(
+                         Box::new(AST::ConstantDefinition {
+                             name: Identifier::from("y"),
+                             value: Box::new(AST::VariableAccess { name: Identifier::from("x")}) }),
+                         Box::new(AST::VariableDefinition {
+                             name: Identifier::from("z"),
+                             value: Box::new(AST::Number(1)) }))})
+    }
+
     #[test]
     fn test_object_with_one_method() {
         parse_ok("object begin function m (x, y, z) -> y; end",
@@ -374,6 +600,74 @@ mod tests {
                              body: Box::new(AST::VariableAccess { name: Identifier::from("this")})}))})
     }
 
+    #[test] fn test_empty_class() {
+        parse_ok("class Point() begin end",
+                 AST::ClassDefinition {
+                     name: Identifier::from("Point"),
+                     parameters: vec!(),
+                     extends: None,
+                     members: vec!() });
+    }
+
+    #[test] fn test_class_with_members() {
+        parse_ok("class Point(x, y) begin let x = x; function y() -> y end",
+                 AST::ClassDefinition {
+                     name: Identifier::from("Point"),
+                     parameters: vec!(Identifier::from("x"), Identifier::from("y")),
+                     extends: None,
+                     members: vec!(
+                         Box::new(AST::VariableDefinition {
+                             name: Identifier::from("x"),
+                             value: Box::new(AST::VariableAccess { name: Identifier::from("x")})}),
+                         Box::new(AST::FunctionDefinition {
+                             function: Identifier::from("y"),
+                             parameters: vec!(),
+                             body: Box::new(AST::VariableAccess { name: Identifier::from("y")})})) });
+    }
+
+    #[test] fn test_class_extending_class() {
+        parse_ok("class Square(side) extends Rectangle(side, side) begin end",
+                 AST::ClassDefinition {
+                     name: Identifier::from("Square"),
+                     parameters: vec!(Identifier::from("side")),
+                     extends: Some(Box::new(AST::Instantiation {
+                         class: Identifier::from("Rectangle"),
+                         arguments: vec!(Box::new(AST::VariableAccess { name: Identifier::from("side")}),
+                                         Box::new(AST::VariableAccess { name: Identifier::from("side")}))})),
+                     members: vec!() });
+    }
+
+    #[test] fn test_class_extending_class_without_arguments() {
+        parse_ok("class B() extends A begin end",
+                 AST::ClassDefinition {
+                     name: Identifier::from("B"),
+                     parameters: vec!(),
+                     extends: Some(Box::new(AST::Instantiation {
+                         class: Identifier::from("A"),
+                         arguments: vec!() })),
+                     members: vec!() });
+    }
+
+    #[test] fn test_class_extending_expression() {
+        parse_err("class B() extends 1 + 2 begin end");
+    }
+
+    #[test] fn test_instantiation() {
+        parse_ok("new Point(1, 2).x",
+                 AST::FieldAccess {
+                     object: Box::new(AST::Instantiation {
+                         class: Identifier::from("Point"),
+                         arguments: vec!(Box::new(AST::Number(1)), Box::new(AST::Number(2)))}),
+                     field: Identifier::from("x") });
+    }
+
+    #[test] fn test_instantiation_from_module() {
+        parse_ok("new shapes.Square(1)",
+                 AST::Instantiation {
+                     class: Identifier::from("shapes.Square"),
+                     arguments: vec!(Box::new(AST::Number(1)))});
+    }
+
     #[test] fn test_field_access_from_identifier () {
         parse_ok("a.b",
                  AST::FieldAccess {
@@ -442,6 +736,28 @@ mod tests {
                      arguments: vec!(Box::new(AST::Number(1)))});
     }
 
+    #[test] fn test_super_method_call () {
+        parse_ok("super.f(1, x)",
+                 AST::SuperMethodCall {
+                     method: Identifier::from("f"),
+                     arguments: vec!(Box::new(AST::Number(1)),
+                                     Box::new(AST::VariableAccess { name: Identifier::from("x") }))});
+    }
+
+    #[test] fn test_super_operator_call () {
+        parse_ok("super.+(1).g()",
+                 AST::MethodCall {
+                     object: Box::new(AST::SuperMethodCall {
+                         method: Identifier::from("+"),
+                         arguments: vec!(Box::new(AST::Number(1)))}),
+                     method: Identifier::from("g"),
+                     arguments: vec!()});
+    }
+
+    #[test] fn test_super_without_call () {
+        parse_err("super.x");
+    }
+
     #[test] fn test_array_access () {
         parse_ok("a[1]",
                  AST::ArrayAccess {
@@ -523,6 +839,16 @@ mod tests {
                          Box::new(AST::VariableAccess { name: Identifier::from("x") }) )});
     }
 
+    #[test] fn test_print_call_with_named_placeholders() {
+        parse_ok("print(\"~{x:>3} ~ ~{y} ~{x}\", 1)",
+                 AST::Print {
+                     format: "~{1: >3} ~ ~{2} ~{1}".to_string(),
+                     arguments: vec!(
+                         Box::new(AST::Number(1)),
+                         Box::new(AST::VariableAccess { name: Identifier::from("x") }),
+                         Box::new(AST::VariableAccess { name: Identifier::from("y") }) )});
+    }
+
     #[test] fn test_print_call_without_arguments() {
         parse_ok("print(\"~ ~ ~\")",
                  AST::Print {
@@ -645,6 +971,21 @@ mod tests {
                      right: Box::new(AST::Boolean(false))});
     }
 
+    #[test] fn test_simple_identity() {
+        parse_ok("a is b & c",
+                 AST::Operation {
+                     operator: Operator::Conjunction,
+                     left: Box::new(AST::Operation {
+                         operator: Operator::Identity,
+                         left: Box::new(AST::VariableAccess { name: Identifier::from("a") }),
+                         right: Box::new(AST::VariableAccess { name: Identifier::from("b") })}),
+                     right: Box::new(AST::VariableAccess { name: Identifier::from("c") })});
+    }
+
+    #[test] fn test_identity_cannot_be_defined() {
+        parse_err("object begin function is(x) -> true end");
+    }
+
     #[test] fn test_disjunction_and_conjunction() {
         //or (true, (true & false & false)))
         parse_ok("true | true & false",
@@ -835,4 +1176,833 @@ mod tests {
     #[test] fn test_multiline_comment() {
         parse_ok("/* \n\n\n */", AST::Unit);
     }
+
+    #[test] fn test_import() {
+        parse_ok("import \"lib/util.fml\"",
+                 AST::Import { path: "lib/util.fml".to_string(), name: Identifier::from("util") });
+    }
+
+    #[test] fn test_import_as() {
+        parse_ok("import \"lib/util.fml\" as u",
+                 AST::Import { path: "lib/util.fml".to_string(), name: Identifier::from("u") });
+    }
+
+    #[test] fn test_import_without_path() {
+        parse_err("import util");
+    }
+
+    #[test] fn test_import_stem_not_identifier() {
+        parse_err("import \"lib/my-util.fml\"");
+        parse_err("import \"lib/end.fml\"");
+        parse_ok("import \"lib/my-util.fml\" as util",
+                 AST::Import { path: "lib/my-util.fml".to_string(), name: Identifier::from("util") });
+    }
+}
+
+#[cfg(test)]
+mod module_tests {
+    use crate::modules::{ModuleLoader, ModuleDirectory, Loaded};
+    use crate::parse;
+    use fml_ast::{AST, Identifier};
+
+    fn body(loaded: Loaded) -> Vec<Box<AST>> {
+        match loaded {
+            Loaded::Module { prefix: _, body } => body,
+            loaded => panic!("Expected a module body, but found {:?}", loaded),
+        }
+    }
+
+    #[test] fn test_module_members_are_prefixed() {
+        let directory = ModuleDirectory::new("prefixed", vec!(
+            ("util.fml", "let limit = 10; function max(a, b) -> if a > b then a else b; function cap(x) -> max(x, limit)")));
+        let mut loader = ModuleLoader::new(vec!(directory.path()));
+
+        let body = body(loader.load("util.fml").unwrap());
+        loader.finish();
+
+        assert_eq!(body, vec!(
+            Box::new(AST::VariableDefinition { name: Identifier::from("util::limit"), value: Box::new(AST::Number(10)) }),
+            Box::new(AST::FunctionDefinition {
+                function: Identifier::from("util::max"),
+                parameters: vec!(Identifier::from("a"), Identifier::from("b")),
+                body: Box::new(AST::Conditional {
+                    condition: Box::new(AST::Operation {
+                        operator: fml_ast::Operator::Greater,
+                        left: Box::new(AST::VariableAccess { name: Identifier::from("a") }),
+                        right: Box::new(AST::VariableAccess { name: Identifier::from("b") }) }),
+                    consequent: Box::new(AST::VariableAccess { name: Identifier::from("a") }),
+                    alternative: Box::new(AST::VariableAccess { name: Identifier::from("b") }) }) }),
+            Box::new(AST::FunctionDefinition {
+                function: Identifier::from("util::cap"),
+                parameters: vec!(Identifier::from("x")),
+                body: Box::new(AST::FunctionCall {
+                    function: Identifier::from("util::max"),
+                    arguments: vec!(Box::new(AST::VariableAccess { name: Identifier::from("x") }),
+                                    Box::new(AST::VariableAccess { name: Identifier::from("util::limit") }))}) }),
+        ));
+    }
+
+    #[test] fn test_module_parameters_shadow_globals() {
+        let directory = ModuleDirectory::new("shadow", vec!(("shadow.fml", "let x = 1; function f(x) -> x")));
+        let mut loader = ModuleLoader::new(vec!(directory.path()));
+
+        let body = body(loader.load("shadow.fml").unwrap());
+        loader.finish();
+
+        assert_eq!(body[1], Box::new(AST::FunctionDefinition {
+            function: Identifier::from("shadow::f"),
+            parameters: vec!(Identifier::from("x")),
+            body: Box::new(AST::VariableAccess { name: Identifier::from("x") }) }));
+    }
+
+    #[test] fn test_module_classes_are_prefixed() {
+        let directory = ModuleDirectory::new("classes", vec!(
+            ("shapes.fml", "let unit = 1; class Square(side) begin let side = side * unit end; function one() -> new Square(unit)")));
+        let mut loader = ModuleLoader::new(vec!(directory.path()));
+
+        let body = body(loader.load("shapes.fml").unwrap());
+        loader.finish();
+
+        assert_eq!(body[1], Box::new(AST::ClassDefinition {
+            name: Identifier::from("shapes::Square"),
+            parameters: vec!(Identifier::from("side")),
+            extends: None,
+            members: vec!(Box::new(AST::VariableDefinition {
+                name: Identifier::from("side"),
+                value: Box::new(AST::Operation {
+                    operator: fml_ast::Operator::Multiplication,
+                    left: Box::new(AST::VariableAccess { name: Identifier::from("side") }),
+                    right: Box::new(AST::VariableAccess { name: Identifier::from("shapes::unit") }) }) })) }));
+        assert_eq!(body[2], Box::new(AST::FunctionDefinition {
+            function: Identifier::from("shapes::one"),
+            parameters: vec!(),
+            body: Box::new(AST::Instantiation {
+                class: Identifier::from("shapes::Square"),
+                arguments: vec!(Box::new(AST::VariableAccess { name: Identifier::from("shapes::unit") })) }) }));
+    }
+
+    #[test] fn test_module_constants_are_prefixed() {
+        let directory = ModuleDirectory::new("constants", vec!(("limits.fml", "const max = 10; function top() -> max")));
+        let mut loader = ModuleLoader::new(vec!(directory.path()));
+
+        let body = body(loader.load("limits.fml").unwrap());
+        loader.finish();
+
+        assert_eq!(body, vec!(
+            Box::new(AST::ConstantDefinition { name: Identifier::from("limits::max"), value: Box::new(AST::Number(10)) }),
+            Box::new(AST::FunctionDefinition {
+                function: Identifier::from("limits::top"),
+                parameters: vec!(),
+                body: Box::new(AST::VariableAccess { name: Identifier::from("limits::max") }) }),
+        ));
+    }
+
+    #[test] fn test_module_loaded_once() {
+        let directory = ModuleDirectory::new("once", vec!(("once.fml", "print(\"loaded\")")));
+        let mut loader = ModuleLoader::new(vec!(directory.path()));
+
+        assert!(matches!(loader.load("once.fml"), Ok(Loaded::Module { .. })));
+        loader.finish();
+        assert_eq!(loader.load("once.fml"), Ok(Loaded::AlreadyLoaded { prefix: "once".to_string() }));
+    }
+
+    #[test] fn test_module_cycle() {
+        let directory = ModuleDirectory::new("cycle", vec!(("a.fml", "import \"b.fml\""), ("b.fml", "import \"a.fml\"")));
+        let mut loader = ModuleLoader::new(vec!(directory.path()));
+
+        assert!(matches!(loader.load("a.fml"), Ok(Loaded::Module { .. })));
+        assert!(matches!(loader.load("b.fml"), Ok(Loaded::Module { .. })));
+        let error = loader.load("a.fml").unwrap_err();
+        assert!(error.starts_with("Import cycle"), "{}", error);
+    }
+
+    #[test] fn test_module_not_found() {
+        let mut loader = ModuleLoader::new(vec!());
+        assert!(loader.load("there/is/no/such/module.fml").is_err());
+    }
+
+    #[test] fn test_link_aliases() {
+        let directory = ModuleDirectory::new("link", vec!(("util.fml", "let x = 1")));
+        let mut loader = ModuleLoader::new(vec!(directory.path()));
+
+        let program = parse("import \"util.fml\" as u; u.f(u.x); u.x <- 2").unwrap();
+        let linked = loader.link(&program).unwrap();
+
+        assert_eq!(linked, AST::Top(vec!(
+            Box::new(AST::Import { path: "util.fml".to_string(), name: Identifier::from("u") }),
+            Box::new(AST::FunctionCall {
+                function: Identifier::from("util::f"),
+                arguments: vec!(Box::new(AST::VariableAccess { name: Identifier::from("util::x") }))}),
+            Box::new(AST::VariableMutation {
+                name: Identifier::from("util::x"),
+                value: Box::new(AST::Number(2)) }),
+        )));
+    }
+
+    #[test] fn test_link_module_classes() {
+        let directory = ModuleDirectory::new("link_classes", vec!(("shapes.fml", "class Square(side) begin end")));
+        let mut loader = ModuleLoader::new(vec!(directory.path()));
+
+        let program = parse("import \"shapes.fml\" as s; class Cube(side) extends s.Square(side) begin end; new s.Square(1)").unwrap();
+        let linked = loader.link(&program).unwrap();
+
+        assert_eq!(linked, AST::Top(vec!(
+            Box::new(AST::Import { path: "shapes.fml".to_string(), name: Identifier::from("s") }),
+            Box::new(AST::ClassDefinition {
+                name: Identifier::from("Cube"),
+                parameters: vec!(Identifier::from("side")),
+                extends: Some(Box::new(AST::Instantiation {
+                    class: Identifier::from("shapes::Square"),
+                    arguments: vec!(Box::new(AST::VariableAccess { name: Identifier::from("side") }))})),
+                members: vec!() }),
+            Box::new(AST::Instantiation {
+                class: Identifier::from("shapes::Square"),
+                arguments: vec!(Box::new(AST::Number(1))) }),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod diagnostic_tests {
+    use crate::parse_with_diagnostics;
+
+    fn locations(input: &str) -> Vec<(usize, usize)> {
+        parse_with_diagnostics(input).expect_err("Expected syntax errors")
+            .iter().map(|diagnostic| diagnostic.location(input)).collect()
+    }
+
+    #[test] fn test_no_diagnostics() {
+        assert!(parse_with_diagnostics("let x = 1; x + 1").is_ok());
+    }
+
+    #[test] fn test_all_errors_are_reported() {
+        assert_eq!(locations("let x = ;\nlet y = 1;\nobject begin let = 2 end;\nx +"),
+                   vec!((1, 9), (3, 18), (4, 4)));
+    }
+
+    #[test] fn test_recovery_inside_block() {
+        assert_eq!(locations("begin let x = ; let y = ) end; let z = 1"), vec!((1, 15), (1, 25)));
+    }
+
+    #[test] fn test_integer_overflow_is_located() {
+        assert_eq!(locations("let x = 1;\nlet y = 9223372036854775808"), vec!((2, 9)));
+    }
+
+    #[test] fn test_import_alias_is_located() {
+        let input = "let x = 1;\nimport \"my-util.fml\"";
+        let diagnostics = parse_with_diagnostics(input).unwrap_err();
+        assert_eq!(diagnostics[0].location(input), (2, 8));
+        assert_eq!(diagnostics[0].message,
+                   "module name `my-util` is not an identifier, import it with `as` and an alias");
+    }
+
+    #[test] fn test_expected_tokens() {
+        let diagnostics = parse_with_diagnostics("object begin let = 2 end").unwrap_err();
+        assert_eq!(diagnostics[0].expected, vec!("an identifier".to_string(), "`this`".to_string()));
+    }
+
+    #[test] fn test_expected_expression() {
+        let diagnostics = parse_with_diagnostics("let x = ;").unwrap_err();
+        assert_eq!(diagnostics[0].expected, vec!("an expression".to_string()));
+    }
+
+    #[test] fn test_render() {
+        let input = "let x = 1;\nif x then ) else 2";
+        let diagnostics = parse_with_diagnostics(input).unwrap_err();
+        assert_eq!(diagnostics[0].render(input),
+                   "2:11: unexpected `)`, expected an expression\n    if x then ) else 2\n              ^");
+    }
+}
+
+#[cfg(test)]
+mod feeny_tests {
+    use crate::feeny::{parse, parse_with_diagnostics};
+    use crate::feeny::lexer::{Lexer, Tok};
+    use crate::Syntax;
+    use fml_ast::{AST, Operator, Identifier};
+
+    fn tokens(input: &str) -> Vec<Tok<'_>> {
+        Lexer::new(input).map(|token| token.unwrap().1).collect()
+    }
+
+    fn parse_ok(input: &str, correct: Vec<AST>) {
+        assert_eq!(parse(input), Ok(AST::Top(correct.into_iter().map(Box::new).collect())));
+    }
+
+    fn variable(name: &str) -> Box<AST> {
+        Box::new(AST::VariableAccess { name: Identifier::from(name) })
+    }
+
+    #[test] fn test_layout_tokens() {
+        assert_eq!(tokens("defn f () :\n   x\n\n   ; comment\n   y\nz"), vec!(
+            Tok::Defn, Tok::Identifier("f"), Tok::LParen, Tok::RParen, Tok::Colon, Tok::Newline,
+            Tok::Indent, Tok::Identifier("x"), Tok::Newline, Tok::Identifier("y"), Tok::Newline,
+            Tok::Dedent, Tok::Identifier("z"), Tok::Newline));
+    }
+
+    #[test] fn test_line_breaks_inside_parentheses() {
+        assert_eq!(tokens("f(1,\n     2)"), vec!(
+            Tok::Identifier("f"), Tok::LParen, Tok::Number("1"), Tok::Comma, Tok::Number("2"),
+            Tok::RParen, Tok::Newline));
+    }
+
+    #[test] fn test_negative_numbers_and_subtraction() {
+        assert_eq!(tokens("f(-4) - 1"), vec!(
+            Tok::Identifier("f"), Tok::LParen, Tok::Number("-4"), Tok::RParen, Tok::Minus,
+            Tok::Number("1"), Tok::Newline));
+    }
+
+    #[test] fn test_function_definition() {
+        parse_ok("defn add-one (x) :\n   x + 1\nadd-one(2)", vec!(
+            AST::FunctionDefinition {
+                function: Identifier::from("add-one"),
+                parameters: vec!(Identifier::from("x")),
+                body: Box::new(AST::Block(vec!(Box::new(AST::Operation {
+                    operator: Operator::Addition, left: variable("x"), right: Box::new(AST::Number(1)) })))),
+            },
+            AST::FunctionCall { function: Identifier::from("add-one"), arguments: vec!(Box::new(AST::Number(2))) },
+        ));
+    }
+
+    #[test] fn test_conditional_chain() {
+        parse_ok("if x < 0 : 1\nelse if x == 0 : 2\nelse : 3", vec!(
+            AST::Conditional {
+                condition: Box::new(AST::Operation { operator: Operator::Less, left: variable("x"),
+                                                     right: Box::new(AST::Number(0)) }),
+                consequent: Box::new(AST::Number(1)),
+                alternative: Box::new(AST::Conditional {
+                    condition: Box::new(AST::Operation { operator: Operator::Equality, left: variable("x"),
+                                                         right: Box::new(AST::Number(0)) }),
+                    consequent: Box::new(AST::Number(2)),
+                    alternative: Box::new(AST::Number(3)),
+                }),
+            },
+        ));
+    }
+
+    #[test] fn test_object_with_operator_method() {
+        parse_ok("var o = object(p) :\n   var x = 1\n   method add (y) : y\no.add(1)", vec!(
+            AST::VariableDefinition {
+                name: Identifier::from("o"),
+                value: Box::new(AST::ObjectDefinition {
+                    extends: Some(variable("p")),
+                    members: vec!(
+                        Box::new(AST::VariableDefinition { name: Identifier::from("x"), value: Box::new(AST::Number(1)) }),
+                        Box::new(AST::OperatorDefinition { operator: Operator::Addition,
+                                                           parameters: vec!(Identifier::from("y")),
+                                                           body: variable("y") }),
+                    ),
+                }),
+            },
+            AST::OperatorCall { object: variable("o"), operator: Operator::Addition,
+                                arguments: vec!(Box::new(AST::Number(1))) },
+        ));
+    }
+
+    #[test] fn test_assignments() {
+        parse_ok("x = 1\no.f = 2\na[0] = 3\nm[0, 1] = 4", vec!(
+            AST::VariableMutation { name: Identifier::from("x"), value: Box::new(AST::Number(1)) },
+            AST::FieldMutation { object: variable("o"), field: Identifier::from("f"), value: Box::new(AST::Number(2)) },
+            AST::ArrayMutation { array: variable("a"), index: Box::new(AST::Number(0)), value: Box::new(AST::Number(3)) },
+            AST::MethodCall { object: variable("m"), method: Identifier::from("set"),
+                              arguments: vec!(Box::new(AST::Number(0)), Box::new(AST::Number(1)), Box::new(AST::Number(4))) },
+        ));
+    }
+
+    #[test] fn test_array_and_printf() {
+        parse_ok("var a = array(3, null)\nprintf(\"~\\n\", a[0])", vec!(
+            AST::VariableDefinition { name: Identifier::from("a"), value: Box::new(AST::ArrayDefinition {
+                size: Box::new(AST::Number(3)), value: Box::new(AST::Unit) }) },
+            AST::Print { format: "~\\n".to_string(), arguments: vec!(Box::new(AST::ArrayAccess {
+                array: variable("a"), index: Box::new(AST::Number(0)) })) },
+        ));
+    }
+
+    #[test] fn test_invalid_assignment_target() {
+        let diagnostics = parse_with_diagnostics("f() = 1").unwrap_err();
+        assert_eq!(diagnostics[0].message, "only variables, fields, and array elements can be assigned to");
+    }
+
+    #[test] fn test_inconsistent_indentation() {
+        let input = "defn f () :\n      x\n   y";
+        let diagnostics = parse_with_diagnostics(input).unwrap_err();
+        assert_eq!(diagnostics[0].location(input), (3, 1));
+        assert_eq!(diagnostics[0].message, "indentation does not match any enclosing block");
+    }
+
+    #[test] fn test_syntax_from_path() {
+        assert_eq!(Syntax::from_path(std::path::Path::new("tests/hanoi.feeny")), Syntax::Feeny);
+        assert_eq!(Syntax::from_path(std::path::Path::new("tests/hanoi.fml")), Syntax::FML);
+    }
+
+    #[test] fn test_course_programs_parse() {
+        let directory = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../feeny/tests");
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some(std::ffi::OsStr::new("feeny")) {
+                continue
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            if let Err(error) = parse(&source) {
+                panic!("{}:{}", path.display(), error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod serialization_tests {
+    use crate::parse;
+    use crate::serialization::ASTFormat;
+
+    const PROGRAM: &str = "\
+        function f(x) -> x * 2.5; \
+        let o = object extends null begin let a = array(2, 1); function +(y) -> this.a[0] + y end; \
+        match o with object(a) -> print(\"~\\n\", a); _ -> null end; \
+        for i in 0..3 do if i == 1 then break else continue; \
+        let m = map(\"k\" -> true)";
+
+    fn round_trip(format: ASTFormat) {
+        let ast = parse(PROGRAM).unwrap();
+        let serialized = format.serialize(&ast).unwrap();
+        assert_eq!(format.deserialize(&serialized), Ok(ast));
+    }
+
+    #[test] fn test_json_round_trip() { round_trip(ASTFormat::JSON) }
+    #[test] fn test_yaml_round_trip() { round_trip(ASTFormat::YAML) }
+    #[test] fn test_lisp_round_trip() { round_trip(ASTFormat::LISP) }
+
+    #[test] fn test_format_names() {
+        assert_eq!(ASTFormat::from_name("json"), Some(ASTFormat::JSON));
+        assert_eq!(ASTFormat::from_name("yaml"), Some(ASTFormat::YAML));
+        assert_eq!(ASTFormat::from_name("sexpr"), Some(ASTFormat::LISP));
+        assert_eq!(ASTFormat::from_name("xml"), None);
+    }
+
+    #[test] fn test_malformed_input() {
+        let error = ASTFormat::JSON.deserialize("{\"Number\": ").unwrap_err();
+        assert!(error.starts_with("Cannot read JSON AST"), "{}", error);
+    }
+
+    #[test] fn test_dot() {
+        let dot = ASTFormat::DOT.serialize(&parse("if x then f(1) else \"a\"").unwrap()).unwrap();
+        assert_eq!(dot, "digraph AST {\n  node [shape=box, fontname=monospace];\n  n0 [label=\"Top\"];\n  \
+                         n1 [label=\"Conditional\"];\n  n2 [label=\"VariableAccess\\nx\"];\n  \
+                         n1 -> n2 [label=\"condition\"];\n  n3 [label=\"FunctionCall\\nf\"];\n  \
+                         n4 [label=\"Number\\n1\"];\n  n3 -> n4 [label=\"arguments[0]\"];\n  \
+                         n1 -> n3 [label=\"consequent\"];\n  n5 [label=\"String\\n\\\"a\\\"\"];\n  \
+                         n1 -> n5 [label=\"alternative\"];\n  n0 -> n1 [label=\"statements[0]\"];\n}\n");
+    }
+
+    #[test] fn test_html() {
+        let html = ASTFormat::HTML.serialize(&parse(PROGRAM).unwrap()).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>") && html.ends_with("</html>\n"));
+        assert!(html.contains("<li><details open><summary><span class=\"field\">condition:</span> \
+                               <span class=\"variant\">Operation</span> <span class=\"detail\">==</span></summary>"));
+        assert!(html.contains("<li><span class=\"field\">keys[0]:</span> <span class=\"variant\">String</span> \
+                               <span class=\"detail\">&quot;k&quot;</span></li>"));
+        assert_eq!(html.matches("<details").count(), html.matches("</details>").count());
+    }
+
+    #[test] fn test_diagrams_are_not_read() {
+        let error = ASTFormat::DOT.deserialize("digraph AST {}").unwrap_err();
+        assert!(error.starts_with("Cannot read DOT AST"), "{}", error);
+    }
+}
+
+#[cfg(test)]
+mod formatter_tests {
+    use crate::parse;
+    use crate::formatter::{format, diff};
+
+    // Formatting keeps the program the same, and formatted programs stay as they are.
+    fn check(source: &str) -> String {
+        let formatted = format(source).unwrap();
+        assert_eq!(parse(&formatted), parse(source), "{}", formatted);
+        assert_eq!(format(&formatted).unwrap(), formatted);
+        formatted
+    }
+
+    #[test] fn test_spacing_and_parentheses() {
+        assert_eq!(check("let x=(1+2)*3-(4 - 5);x<-x.f+(a|b)&c"),
+                   "let x = (1 + 2) * 3 - (4 - 5);\nx <- x.f + (a | b) & c\n");
+    }
+
+    #[test] fn test_blocks_and_objects() {
+        assert_eq!(check("function f(a,b)->begin let o=object extends a begin let q=1; function +(w)->q+w end; o+b end"),
+                   "function f(a, b) -> begin\n  let o = object extends a begin\n    let q = 1;\n    function +(w) -> q + w\n  end;\n  o + b\nend\n");
+    }
+
+    #[test] fn test_if_chains() {
+        assert_eq!(check("if a then 1 else if b then 2 else 3"), "if a then 1 else if b then 2 else 3\n");
+        assert_eq!(check("if a then (if b then 1) else 2"), "if a then if b then 1 else null else 2\n");
+    }
+
+    #[test] fn test_comments_are_kept() {
+        let source = "// head\nlet x = 1; // one\n\n/* two */\nbegin\n  x\n  // last\nend\n// tail\n";
+        assert_eq!(check(source), source);
+    }
+
+    #[test] fn test_comments_inside_expressions_are_not_moved() {
+        let source = "if a then begin print(\"x\"); end else begin // only comment\nend\n";
+        assert_eq!(check(source), source);
+        let source = "f(1, // first\n  2 /* second */, 3)\n";
+        assert_eq!(check(source), source);
+        let source = "while x do /* inline */ x <- x - 1\n";
+        assert_eq!(check(source), source);
+        assert_eq!(check("let y=1;\nwhile x do /* inline */ x <- x - 1;\nlet z=2"),
+                   "let y = 1;\nwhile x do /* inline */ x <- x - 1;\nlet z = 2\n");
+    }
+
+    #[test] fn test_comments_stay_in_their_block() {
+        let source = "if a then begin\n  x // first\nend else begin\n  // second\n  y;\n  z\n  // third\nend\n";
+        assert_eq!(check(source), source);
+        let source = "if a then begin\n  x\n  // first\nend else begin\n  y\nend\n";
+        assert_eq!(check(source), source);
+    }
+
+    #[test] fn test_long_arguments_are_broken() {
+        let formatted = check("print(\"~ ~ ~ ~\", first_long_argument_name_here, second_long_argument_name_here, third_long_argument_name_here)");
+        assert_eq!(formatted, "print(\n  \"~ ~ ~ ~\",\n  first_long_argument_name_here,\n  second_long_argument_name_here,\n  third_long_argument_name_here\n)\n");
+        assert_eq!(check("f(a, b)"), "f(a, b)\n");
+    }
+
+    #[test] fn test_conformance_programs() {
+        let directory = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../conformance/equality");
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() == Some(std::ffi::OsStr::new("fml")) {
+                check(&std::fs::read_to_string(&path).unwrap());
+            }
+        }
+    }
+
+    #[test] fn test_diff() {
+        assert_eq!(diff("a\nb\nc\n", "a\nB\nc\n"), "   2 -b\n   2 +B");
+        assert_eq!(diff("a\n", "a\n"), "");
+    }
+}
+
+#[cfg(test)]
+mod lexer_tests {
+    use crate::lexer::{Lexer, TokenKind, tokenize};
+    use lalrpop_util::lexer::MatcherBuilder;
+
+    fn kinds(input: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(input).into_iter().map(|token| (token.kind, token.text)).collect()
+    }
+
+    #[test] fn test_tokens() {
+        assert_eq!(kinds("let x = y.f <- 1.5;"), vec!(
+            (TokenKind::Keyword, "let"), (TokenKind::Identifier, "x"), (TokenKind::Operator, "="),
+            (TokenKind::Identifier, "y"), (TokenKind::Punctuation, "."), (TokenKind::Identifier, "f"),
+            (TokenKind::Operator, "<-"), (TokenKind::Float, "1.5"), (TokenKind::Punctuation, ";")));
+    }
+
+    #[test] fn test_longest_match() {
+        assert_eq!(kinds("4-5 1..2 endx >= -x"), vec!(
+            (TokenKind::Number, "4"), (TokenKind::Number, "-5"), (TokenKind::Number, "1"),
+            (TokenKind::Punctuation, ".."), (TokenKind::Number, "2"), (TokenKind::Identifier, "endx"),
+            (TokenKind::Operator, ">="), (TokenKind::Operator, "-"), (TokenKind::Identifier, "x")));
+    }
+
+    #[test] fn test_strings() {
+        assert_eq!(kinds(r#""a\n~" "b\"c""#), vec!(
+            (TokenKind::String, r#""a\n~""#), (TokenKind::Error, "\""), (TokenKind::Identifier, "b"),
+            (TokenKind::Error, "\\"), (TokenKind::String, r#""c""#)));
+    }
+
+    #[test] fn test_trivia() {
+        let tokens: Vec<_> = Lexer::with_trivia("x // one\n/* two */y").collect();
+        let kinds: Vec<_> = tokens.iter().map(|token| token.kind).collect();
+        assert_eq!(kinds, vec!(TokenKind::Identifier, TokenKind::Whitespace, TokenKind::Comment,
+                               TokenKind::Whitespace, TokenKind::Comment, TokenKind::Identifier));
+        assert_eq!((tokens[2].start, tokens[2].end), (2, 8));
+        assert_eq!(tokens.iter().map(|token| token.text).collect::<String>(), "x // one\n/* two */y");
+    }
+
+    #[test] fn test_errors() {
+        assert_eq!(kinds("a @ b"), vec!((TokenKind::Identifier, "a"), (TokenKind::Error, "@"), (TokenKind::Identifier, "b")));
+    }
+
+    // The rules of the grammar's `match` block, run through the matcher LALRPOP generates its
+    // lexer with: regular expressions first, so that fixed tokens win ties as they do there.
+    fn grammar_matcher() -> MatcherBuilder {
+        let grammar = include_str!("fml.lalrpop");
+        let block = &grammar[grammar.find("\nmatch {").unwrap()..];
+        let block = &block[..block.find("\n}").unwrap()];
+        let mut regexes = Vec::new();
+        let mut literals = Vec::new();
+        for line in block.lines().map(|line| line.trim()) {
+            if let Some(rule) = line.strip_prefix("r#\"") {
+                regexes.push(format!("^({})", &rule[..rule.find("\"#").unwrap()]));
+            } else if let Some(rule) = line.strip_prefix("r\"") {
+                regexes.push(format!("^({})", &rule[..rule.find("\" =>").unwrap()]));
+            } else if let Some(rule) = line.strip_prefix('"') {
+                literals.push(format!("^({})", regex::escape(&rule[..rule.find('"').unwrap()])));
+            }
+        }
+        literals.sort();
+        MatcherBuilder::new(regexes.into_iter().chain(literals).map(|rule| (rule, false))).unwrap()
+    }
+
+    #[test] fn test_agrees_with_grammar() {
+        let builder = grammar_matcher();
+        let directory = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../conformance/equality");
+        let mut sources = vec!(
+            "let x=4-5;x<-1..2;y>=-3.5e-2|a&b!=c%d/e*f;function +(a)->a.b[c] // c\n".to_string(),
+            "/* a ** / b */ \"s\\~\\\\\" endif thisx this..this 1.2.3 0.5e 7e3".to_string(),
+        );
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() == Some(std::ffi::OsStr::new("fml")) {
+                sources.push(std::fs::read_to_string(&path).unwrap());
+            }
+        }
+        for source in sources.iter() {
+            let expected: Vec<(usize, &str)> = builder.matcher::<&str>(source)
+                .map(|token| token.unwrap())
+                .filter(|(start, _, end)| start != end)
+                .map(|(start, token, _)| (start, token.1)).collect();
+            let actual: Vec<(usize, &str)> = Lexer::with_trivia(source)
+                .map(|token| (token.start, token.text)).collect();
+            assert_eq!(actual, expected, "{}", source);
+        }
+    }
+}
+
+#[cfg(test)]
+mod checker_tests {
+    use crate::Syntax;
+    use crate::checker::{check, check_source};
+    use crate::diagnostics::Severity;
+
+    // Messages, with the text they point at and whether they are errors.
+    fn problems(source: &str) -> Vec<(String, &str, bool)> {
+        let (_, diagnostics) = check_source(Syntax::FML, source).unwrap();
+        diagnostics.iter()
+            .map(|diagnostic| (diagnostic.message.clone(), &source[diagnostic.start..diagnostic.end], diagnostic.is_error()))
+            .collect()
+    }
+
+    fn problem(message: &str, at: &'static str, error: bool) -> (String, &'static str, bool) {
+        (message.to_string(), at, error)
+    }
+
+    #[test] fn test_correct_program() {
+        let source = "function f(x) -> g(x, 1);\nfunction g(a, b) -> a + b;\nlet o = object begin let v = 1; function m() -> this.v + f(2) end;\nclass C(x) begin let x = x end;\nlet c = new C(length(array(1, 0)));\nprint(\"~{o}\")";
+        assert_eq!(problems(source), vec!());
+    }
+
+    #[test] fn test_undefined_function() {
+        assert_eq!(problems("let x = 1;\nfoo(x)"), vec!(problem("function `foo` is not defined", "foo", true)));
+        assert_eq!(problems("new C()"), vec!(problem("class `C` is not defined", "C", true)));
+    }
+
+    #[test] fn test_argument_counts() {
+        assert_eq!(problems("function f(a, b) -> a;\nf(1);\npush(1)"), vec!(
+            problem("function `f` takes 2 arguments, but 1 was given", "f", true),
+            problem("function `push` takes 2 arguments, but 1 was given", "push", true)));
+        assert_eq!(problems("class P(x, y) begin end;\nnew P(1, 2, 3)"), vec!(
+            problem("class `P` takes 2 arguments, but 3 were given", "P", true)));
+    }
+
+    #[test] fn test_repeated_parameters() {
+        assert_eq!(problems("function f(a, b, a) -> a"), vec!(problem("parameter `a` is repeated", "a", true)));
+    }
+
+    #[test] fn test_this_outside_of_methods() {
+        assert_eq!(problems("function f() -> this;\nlet o = object begin let x = this end"), vec!(
+            problem("`this` can only be used inside a method", "this", true),
+            problem("`this` can only be used inside a method", "this", true)));
+        assert_eq!(problems("super.m()"), vec!(problem("`super.m` can only be called inside a method", "m", true)));
+    }
+
+    #[test] fn test_redefined_locals() {
+        let source = "function f() -> begin let x = 1; begin let x = 2 end; let x = 3 end";
+        let found = problems(source);
+        assert_eq!(found, vec!(problem("`x` is already defined in this scope", "x", true)));
+        let (_, diagnostics) = check_source(Syntax::FML, source).unwrap();
+        assert_eq!(diagnostics[0].start, source.rfind('x').unwrap());
+        assert_eq!(problems("let x = 1; let x = 2"), vec!());
+        assert_eq!(problems("const x = 1; let x = 2"), vec!(problem("constant `x` cannot be redefined", "x", true)));
+    }
+
+    #[test] fn test_undefined_variables_are_warnings() {
+        assert_eq!(problems("function f(a) -> a + b;\nlet y = 1"), vec!(problem("`b` is not defined", "b", false)));
+        let diagnostics = check(&crate::parse("z").unwrap());
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!((diagnostics[0].start, diagnostics[0].end), (0, 0));
+    }
+
+    // Each use of a name, with the offset of the definition it resolves to.
+    fn resolved(source: &str) -> Vec<(&str, usize, usize)> {
+        let (_, _, references) = crate::checker::resolve_source(Syntax::FML, source).unwrap();
+        references.iter()
+            .map(|reference| (&source[reference.name.0..reference.name.1], reference.name.0, reference.definition.0))
+            .collect()
+    }
+
+    #[test] fn test_resolution_follows_scopes() {
+        let source = "function f(x) -> x;\nfunction g() -> x;\nlet x = 1;\nlet x = x + 1;\nf(x)";
+        let parameter = source.find("x)").unwrap();
+        let first = source.find("let x").unwrap() + 4;
+        let second = source.rfind("let x").unwrap() + 4;
+        assert_eq!(resolved(source), vec!(
+            ("x", source.find("x;").unwrap(), parameter),
+            ("x", source.find("x;\nlet").unwrap(), first),
+            ("x", second + 4, first),
+            ("f", source.rfind('f').unwrap(), source.find('f').unwrap() + 9),
+            ("x", source.rfind('x').unwrap(), second)));
+
+        let source = "let v = 1;\nlet o = object begin let v = 2 end;\no.v";
+        assert_eq!(resolved(source), vec!(("o", source.rfind('o').unwrap(), source.find('o').unwrap())));
+    }
+
+    #[test] fn test_example_programs_are_correct() {
+        let directories = [("../conformance/equality", "fml", Syntax::FML), ("../../feeny/tests", "feeny", Syntax::Feeny)];
+        for (directory, extension, syntax) in directories.iter() {
+            let directory = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(directory);
+            for entry in std::fs::read_dir(directory).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension() != Some(std::ffi::OsStr::new(extension)) {
+                    continue
+                }
+                let source = std::fs::read_to_string(&path).unwrap();
+                let (_, diagnostics) = check_source(*syntax, &source).unwrap();
+                let errors: Vec<String> = diagnostics.iter().filter(|diagnostic| diagnostic.is_error())
+                    .map(|diagnostic| diagnostic.render(&source)).collect();
+                assert!(errors.is_empty(), "{}: {}", path.display(), errors.join("\n"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod linter_tests {
+    use crate::Syntax;
+    use crate::linter::{lint, to_json, Config};
+
+    // Rules, with the text they point at.
+    fn lints(source: &str) -> Vec<(&'static str, &str)> {
+        lints_with(source, &Config::default())
+    }
+
+    fn lints_with<'a>(source: &'a str, config: &Config) -> Vec<(&'static str, &'a str)> {
+        lint(Syntax::FML, source, config).unwrap().iter()
+            .map(|lint| (lint.rule, &source[lint.diagnostic.start..lint.diagnostic.end]))
+            .collect()
+    }
+
+    #[test] fn test_clean_program() {
+        let source = "let g = 1;\nfunction f(a, _b) -> begin let x = a; for i in 0..x do print(\"~\", i); x end;\nlet o = object begin let v = 1; function m(y) -> this.v + y end;\nif o is null then f(g, 1) else null";
+        assert_eq!(lints(source), vec!());
+    }
+
+    #[test] fn test_unused_variables_and_parameters() {
+        assert_eq!(lints("function f(a, b) -> begin let x = 1; let y = 2; y <- b; x end"), vec!(
+            ("unused-parameter", "a"), ("unused-variable", "y")));
+        assert_eq!(lints("function f(xs) -> for x in xs do null"), vec!(("unused-variable", "x")));
+        assert_eq!(lints("function f(v) -> match v with [a, b] -> a end"), vec!(("unused-variable", "b")));
+        assert_eq!(lints("let x = 1; let y = 2"), vec!());
+    }
+
+    #[test] fn test_shadowed_bindings() {
+        assert_eq!(lints("let x = 1;\nfunction f(x) -> x"), vec!(("shadowed-binding", "x")));
+        assert_eq!(lints("function f(y) -> begin let z = begin let y = 1; y end; y + z end"), vec!(("shadowed-binding", "y")));
+        assert_eq!(lints("function f(y) -> y;\nfunction g(y) -> y"), vec!());
+    }
+
+    #[test] fn test_infinite_loops() {
+        assert_eq!(lints("while true do print(\"x\")"), vec!(("infinite-loop", "while")));
+        assert_eq!(lints("while true do while true do break"), vec!(("infinite-loop", "while")));
+        assert_eq!(lints("while true do if read_int() == 0 then break else null"), vec!());
+        assert_eq!(lints("while true do exit(0)"), vec!());
+    }
+
+    #[test] fn test_empty_blocks() {
+        assert_eq!(lints("if true then begin end else begin // nothing\nend"), vec!(
+            ("empty-block", "begin end"), ("empty-block", "begin // nothing\nend")));
+        assert_eq!(lints("let o = object begin end;\nclass C() begin end"), vec!());
+    }
+
+    #[test] fn test_null_comparisons() {
+        assert_eq!(lints("let o = object begin end;\nif o == null then 1 else 2"), vec!(("null-comparison", "==")));
+        assert_eq!(lints("let o = 1;\nnull != o"), vec!(("null-comparison", "!=")));
+        assert_eq!(lints("1 == null"), vec!());
+    }
+
+    #[test] fn test_configuration() {
+        let source = "function f(a) -> begin end";
+        let config = Config::from_json("{\"rules\": {\"empty-block\": false}}").unwrap();
+        assert!(!config.is_enabled("empty-block"));
+        assert_eq!(lints_with(source, &config), vec!(("unused-parameter", "a")));
+        assert_eq!(Config::from_json("{\"rules\": {\"no-such-rule\": false}}"), Err("Unknown lint rule: no-such-rule".to_string()));
+    }
+
+    #[test] fn test_suppression_comments() {
+        assert_eq!(lints("// lint: allow unused-parameter\nfunction f(a) -> null;\nfunction g(b) -> null"), vec!(
+            ("unused-parameter", "b")));
+        assert_eq!(lints("function f(a) -> null; // lint: allow unused-parameter, empty-block"), vec!());
+        assert_eq!(lints("/* lint: allow-file unused-parameter */\nfunction f(a) -> null;\nfunction g(b) -> null"), vec!());
+    }
+
+    #[test] fn test_json_output() {
+        let source = "function f(a) -> null";
+        let lints = lint(Syntax::FML, source, &Config::default()).unwrap();
+        assert_eq!(to_json("f.fml", source, &lints), serde_json::json!([{
+            "file": "f.fml", "rule": "unused-parameter", "severity": "warning",
+            "message": "parameter `a` is never used", "line": 1, "column": 12, "start": 11, "end": 12,
+        }]));
+    }
+
+    #[test] fn test_syntax_errors() {
+        assert!(lint(Syntax::FML, "let = 1", &Config::default()).is_err());
+    }
+}
+
+#[cfg(test)]
+mod generator_tests {
+    use crate::{parse, Syntax};
+    use crate::checker::check_source;
+    use crate::formatter::format;
+    use crate::generator::{program, source};
+
+    // Printed programs parse back into the ASTs they were printed from.
+    #[test] fn test_print_parse_round_trip() {
+        for seed in 0..100 {
+            let source = source(seed);
+            let ast = parse(&source).unwrap_or_else(|diagnostics| panic!("{}: {:?}\n{}", seed, diagnostics, source));
+            assert!(ast == program(seed), "{}\n{}", seed, source);
+            assert_eq!(format(&source).unwrap(), source);
+        }
+    }
+
+    // Generated programs only use what they define, with the right number of arguments.
+    #[test] fn test_programs_are_well_formed() {
+        for seed in 0..100 {
+            let source = source(seed);
+            let (_, diagnostics) = check_source(Syntax::FML, &source).unwrap();
+            assert!(diagnostics.is_empty(), "{}\n{}\n{:?}", seed, source, diagnostics);
+        }
+    }
+
+    #[test] fn test_same_seed_same_program() {
+        assert!(program(7) == program(7));
+        assert_ne!(source(7), source(8));
+    }
+
+    // The generated conformance programs, run by both interpreters, are still what the generator
+    // makes from their seeds.
+    #[test] fn test_conformance_programs() {
+        let directory = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../conformance/generated");
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() == Some(std::ffi::OsStr::new("fml")) {
+                let seed: u64 = path.file_stem().unwrap().to_str().unwrap()
+                    .trim_start_matches("seed").parse().unwrap();
+                assert_eq!(std::fs::read_to_string(&path).unwrap(), source(seed), "{}", path.display());
+            }
+        }
+    }
 }
@@ -0,0 +1,432 @@
+use std::collections::HashSet;
+
+use fml_ast::{AST, Identifier, Operator, Pattern};
+use serde_json::{json, Value};
+
+use crate::Syntax;
+use crate::checker::{Locator, children, parse_and_locate};
+use crate::diagnostics::Diagnostic;
+use crate::lexer::{Lexer, TokenKind};
+use crate::trivia::comments;
+
+/**
+ * The rules of the linter, by name, with what they look for.
+ */
+pub const RULES: &[(&str, &str)] = &[
+    ("unused-variable", "a local variable that is never read"),
+    ("unused-parameter", "a parameter that is never read"),
+    ("shadowed-binding", "a local that hides a variable of an enclosing scope or a global"),
+    ("infinite-loop", "a `while true` loop with no `break` and no call to `exit`"),
+    ("empty-block", "a `begin end` block with nothing in it"),
+    ("null-comparison", "`==` or `!=` with `null`, which calls the operator of an object"),
+];
+
+/**
+ * Style problems found by a rule of the linter. The diagnostic is always a warning.
+ */
+#[derive(PartialEq,Debug,Clone)]
+pub struct Lint {
+    pub rule: &'static str,
+    pub diagnostic: Diagnostic,
+}
+
+/**
+ * Which rules are enabled. All of them are by default.
+ *
+ * A configuration file is a JSON object whose `rules` map rule names to `true` or `false`, eg.
+ * `{"rules": {"shadowed-binding": false}}`. Rules it does not mention keep their default.
+ */
+#[derive(PartialEq,Debug,Clone,Default)]
+pub struct Config {
+    disabled: HashSet<&'static str>,
+}
+
+impl Config {
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(text).map_err(|error| format!("Cannot read lint configuration: {}", error))?;
+        let mut config = Config::default();
+        let rules = match value.get("rules") {
+            Some(Value::Object(rules)) => rules,
+            Some(_) => return Err("Cannot read lint configuration: `rules` must be an object".to_string()),
+            None => return Ok(config),
+        };
+        for (name, enabled) in rules {
+            let rule = rule(name).ok_or_else(|| format!("Unknown lint rule: {}", name))?;
+            match enabled {
+                Value::Bool(true) => { config.disabled.remove(rule); }
+                Value::Bool(false) => { config.disabled.insert(rule); }
+                _ => return Err(format!("Cannot read lint configuration: rule {} must be true or false", name)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn is_enabled(&self, rule: &str) -> bool {
+        !self.disabled.contains(rule)
+    }
+}
+
+fn rule(name: &str) -> Option<&'static str> {
+    RULES.iter().map(|(rule, _)| *rule).find(|rule| *rule == name)
+}
+
+/**
+ * Lints a program, failing with its syntax errors if it does not parse.
+ *
+ * A rule can be turned off for a line with a comment on that line or on the line before it:
+ * `// lint: allow unused-variable, shadowed-binding`, and for the whole file with
+ * `// lint: allow-file infinite-loop`.
+ */
+pub fn lint(syntax: Syntax, input: &str, config: &Config) -> Result<Vec<Lint>, Vec<Diagnostic>> {
+    let (ast, locator) = parse_and_locate(syntax, input)?;
+    let mut linter = Linter::new(&ast, locator);
+    linter.check(&ast);
+    linter.leave_frame();
+    if syntax == Syntax::FML {
+        linter.empty_blocks(input);
+    }
+
+    let suppressions = suppressions(syntax, input);
+    let mut lints: Vec<Lint> = linter.lints.into_iter()
+        .filter(|lint| config.is_enabled(lint.rule))
+        .filter(|lint| {
+            let line = line_of(input, lint.diagnostic.start);
+            !suppressions.iter().any(|(rule, lines)| rule == lint.rule && lines.is_none_or(|(first, last)| first <= line && line <= last))
+        })
+        .collect();
+    lints.sort_by_key(|lint| lint.diagnostic.start);
+    Ok(lints)
+}
+
+/**
+ * Lints as a JSON array, with the location of each as a line and column counted from 1.
+ */
+pub fn to_json(file: &str, input: &str, lints: &[Lint]) -> Value {
+    Value::Array(lints.iter().map(|lint| {
+        let (line, column) = lint.diagnostic.location(input);
+        json!({
+            "file": file, "rule": lint.rule, "severity": "warning", "message": lint.diagnostic.message,
+            "line": line, "column": column, "start": lint.diagnostic.start, "end": lint.diagnostic.end,
+        })
+    }).collect())
+}
+
+fn line_of(input: &str, offset: usize) -> usize {
+    input[..offset.min(input.len())].matches('\n').count() + 1
+}
+
+// Rules turned off by comments, with the lines they are off for, or `None` for the whole file.
+fn suppressions(syntax: Syntax, input: &str) -> Vec<(String, Option<(usize, usize)>)> {
+    if syntax != Syntax::FML {
+        return vec!()
+    }
+    let mut suppressions = Vec::new();
+    for comment in comments(input) {
+        let text = comment.text.trim_start_matches('/').trim_start_matches('*').trim_end_matches("*/").trim();
+        let (lines, rules) = if let Some(rules) = text.strip_prefix("lint: allow-file ") {
+            (None, rules)
+        } else if let Some(rules) = text.strip_prefix("lint: allow ") {
+            let line = line_of(input, comment.start);
+            (Some((line, line_of(input, comment.end) + 1)), rules)
+        } else {
+            continue
+        };
+        for rule in rules.split(',') {
+            suppressions.push((rule.trim().to_string(), lines));
+        }
+    }
+    suppressions
+}
+
+struct Binding {
+    name: String,
+    location: (usize, usize),
+    parameter: bool,
+    used: bool,
+}
+
+struct Linter<'a> {
+    globals: HashSet<String>,
+    // Every function body has its own scopes, each holding its bindings.
+    frames: Vec<Vec<Vec<Binding>>>,
+    locator: Locator<'a>,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(ast: &AST, locator: Locator<'a>) -> Self {
+        let mut globals = HashSet::new();
+        if let AST::Top(statements) = ast {
+            for statement in statements {
+                if let AST::VariableDefinition { name, .. } | AST::ConstantDefinition { name, .. } = statement.as_ref() {
+                    globals.insert(name.to_string());
+                }
+            }
+        }
+        Linter { globals, frames: vec!(vec!(vec!())), locator, lints: vec!() }
+    }
+
+    fn report(&mut self, rule: &'static str, (start, end): (usize, usize), message: String) {
+        self.lints.push(Lint { rule, diagnostic: Diagnostic::warning(start, end, &message) });
+    }
+
+    fn in_global_scope(&self) -> bool {
+        self.frames.len() == 1 && self.frames[0].len() == 1
+    }
+
+    fn enter_scope(&mut self) {
+        self.frames.last_mut().unwrap().push(vec!());
+    }
+
+    fn leave_scope(&mut self) {
+        let bindings = self.frames.last_mut().unwrap().pop().unwrap();
+        for binding in bindings.into_iter().filter(|binding| !binding.used && !binding.name.starts_with('_')) {
+            if binding.parameter {
+                self.report("unused-parameter", binding.location, format!("parameter `{}` is never used", binding.name));
+            } else {
+                self.report("unused-variable", binding.location, format!("`{}` is never used", binding.name));
+            }
+        }
+    }
+
+    fn enter_frame(&mut self) {
+        self.frames.push(vec!(vec!()));
+    }
+
+    fn leave_frame(&mut self) {
+        while !self.frames.last().unwrap().is_empty() {
+            self.leave_scope();
+        }
+        self.frames.pop();
+    }
+
+    fn bind(&mut self, name: &Identifier, location: (usize, usize), parameter: bool) {
+        if self.in_global_scope() {
+            return
+        }
+        let name = name.to_string();
+        let scopes = self.frames.last().unwrap();
+        let shadows = self.globals.contains(&name)
+            || scopes[..scopes.len() - 1].iter().flatten().any(|binding| binding.name == name);
+        if shadows {
+            self.report("shadowed-binding", location, format!("`{}` shadows another variable of the same name", name));
+        }
+        self.frames.last_mut().unwrap().last_mut().unwrap().push(Binding { name, location, parameter, used: false });
+    }
+
+    fn use_variable(&mut self, name: &str) {
+        let binding = self.frames.last_mut().unwrap().iter_mut().rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|binding| binding.name == name));
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+    }
+
+    fn check_function(&mut self, parameters: &[Identifier], body: &AST) {
+        self.enter_frame();
+        self.bind_parameters(parameters);
+        self.check(body);
+        self.leave_frame();
+    }
+
+    fn bind_parameters(&mut self, parameters: &[Identifier]) {
+        for parameter in parameters {
+            let location = self.locator.locate(parameter.to_str());
+            self.bind(parameter, location, true);
+        }
+    }
+
+    fn check_sequence(&mut self, statements: &[Box<AST>]) {
+        for statement in statements {
+            self.locator.enter_statement();
+            self.check(statement);
+            self.locator.leave_statement();
+        }
+    }
+
+    fn check_members(&mut self, members: &[Box<AST>]) {
+        for member in members {
+            self.locator.enter_statement();
+            match member.as_ref() {
+                AST::FunctionDefinition { function, parameters, body } => {
+                    self.locator.locate(function.to_str());
+                    self.check_function(parameters, body);
+                }
+                AST::OperatorDefinition { parameters, body, .. } => self.check_function(parameters, body),
+                AST::VariableDefinition { name, value } | AST::ConstantDefinition { name, value } => {
+                    self.locator.locate(name.to_str());
+                    self.check(value);
+                }
+                other => self.check(other),
+            }
+            self.locator.leave_statement();
+        }
+    }
+
+    fn check_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Binding(name) => {
+                let location = self.locator.locate(name.to_str());
+                self.bind(name, location, false);
+            }
+            Pattern::Array(elements) => elements.iter().for_each(|element| self.check_pattern(element)),
+            Pattern::Object(fields) => for (field, pattern) in fields {
+                match pattern {
+                    Pattern::Binding(name) if name == field => (),
+                    _ => { self.locator.locate(field.to_str()); }
+                }
+                self.check_pattern(pattern);
+            },
+            _ => (),
+        }
+    }
+
+    fn check(&mut self, ast: &AST) {
+        match ast {
+            AST::Top(statements) => self.check_sequence(statements),
+            AST::Block(statements) => {
+                self.enter_scope();
+                self.check_sequence(statements);
+                self.leave_scope();
+            }
+
+            AST::VariableDefinition { name, value } | AST::ConstantDefinition { name, value } => {
+                // The value is evaluated before the variable exists, so it cannot use it.
+                let location = self.locator.locate(name.to_str());
+                self.check(value);
+                self.bind(name, location, false);
+            }
+            AST::FunctionDefinition { function, parameters, body } => {
+                self.locator.locate(function.to_str());
+                self.check_function(parameters, body);
+            }
+            AST::OperatorDefinition { parameters, body, .. } => self.check_function(parameters, body),
+            AST::ClassDefinition { name, parameters, extends, members } => {
+                self.locator.locate(name.to_str());
+                self.enter_frame();
+                self.bind_parameters(parameters);
+                if let Some(parent) = extends { self.check(parent) }
+                self.check_members(members);
+                self.leave_frame();
+            }
+            AST::ObjectDefinition { extends, members } => {
+                if let Some(parent) = extends { self.check(parent) }
+                self.check_members(members);
+            }
+
+            AST::VariableAccess { name } => {
+                self.locator.locate(name.to_str());
+                self.use_variable(name.to_str());
+            }
+            AST::VariableMutation { name, value } => {
+                self.locator.locate(name.to_str());
+                self.check(value);
+            }
+            AST::FieldAccess { object, field } => {
+                self.check(object);
+                self.locator.locate(field.to_str());
+            }
+            AST::FieldMutation { object, field, value } => {
+                self.check(object);
+                self.locator.locate(field.to_str());
+                self.check(value);
+            }
+            AST::FunctionCall { function: name, arguments } | AST::Instantiation { class: name, arguments }
+            | AST::SuperMethodCall { method: name, arguments } => {
+                self.locator.locate(name.to_str());
+                arguments.iter().for_each(|argument| self.check(argument));
+            }
+            AST::MethodCall { object, method, arguments } => {
+                self.check(object);
+                self.locator.locate(method.to_str());
+                arguments.iter().for_each(|argument| self.check(argument));
+            }
+
+            AST::Operation { operator, left, right } => {
+                self.check(left);
+                if let Operator::Equality | Operator::Inequality = operator {
+                    let location = self.locator.locate(operator.to_str());
+                    if is_null_comparison(left, right) {
+                        self.report("null-comparison", location, format!(
+                            "`{}` with `null` calls the operator of an object; use `is` to compare with `null`",
+                            operator.to_str()));
+                    }
+                }
+                self.check(right);
+            }
+
+            AST::Loop { condition, body } => {
+                let location = self.locator.locate("while");
+                if **condition == AST::Boolean(true) && !exits(body) {
+                    self.report("infinite-loop", location, "`while true` loop never ends".to_string());
+                }
+                self.check(condition);
+                self.check(body);
+            }
+            AST::Iteration { variable, iterable, body } => {
+                let location = self.locator.locate(variable.to_str());
+                self.check(iterable);
+                self.enter_scope();
+                self.bind(variable, location, false);
+                self.check(body);
+                self.leave_scope();
+            }
+            AST::Match { value, arms } => {
+                self.check(value);
+                for (pattern, body) in arms {
+                    self.enter_scope();
+                    self.check_pattern(pattern);
+                    self.check(body);
+                    self.leave_scope();
+                }
+            }
+
+            other => children(other).into_iter().for_each(|child| self.check(child)),
+        }
+    }
+
+    // Blocks are found among the tokens, since `begin end` is `null` in the AST. The first `begin`
+    // after `object` or `class` starts their members rather than a block.
+    fn empty_blocks(&mut self, input: &str) {
+        let tokens: Vec<_> = Lexer::new(input).filter(|token| token.kind == TokenKind::Keyword).collect();
+        let mut member_blocks = 0;
+        for (index, token) in tokens.iter().enumerate() {
+            match token.text {
+                "object" | "class" => member_blocks += 1,
+                "begin" if member_blocks > 0 => member_blocks -= 1,
+                "begin" if tokens.get(index + 1).is_some_and(|next| next.text == "end" && only_trivia(input, token.end, next.start)) =>
+                    self.report("empty-block", (token.start, tokens[index + 1].end), "empty block".to_string()),
+                _ => (),
+            }
+        }
+    }
+}
+
+fn only_trivia(input: &str, start: usize, end: usize) -> bool {
+    Lexer::new(&input[start..end]).next().is_none()
+}
+
+fn is_null_comparison(left: &AST, right: &AST) -> bool {
+    let primitive = |ast: &AST| matches!(ast, AST::Number(_) | AST::Float(_) | AST::Boolean(_) | AST::String(_) | AST::Unit);
+    (*left == AST::Unit && !primitive(right)) || (*right == AST::Unit && !primitive(left))
+}
+
+// Whether a loop body can leave the loop: a `break` that is not inside a nested loop, or a call to
+// `exit` anywhere.
+fn exits(body: &AST) -> bool {
+    match body {
+        AST::Break => true,
+        AST::FunctionCall { function, .. } if function.to_str() == "exit" => true,
+        AST::Loop { condition, body } => exits(condition) || calls_exit(body),
+        AST::Iteration { iterable, body, .. } => exits(iterable) || calls_exit(body),
+        AST::FunctionDefinition { .. } | AST::OperatorDefinition { .. } => false,
+        other => children(other).into_iter().any(exits),
+    }
+}
+
+fn calls_exit(ast: &AST) -> bool {
+    match ast {
+        AST::FunctionCall { function, .. } if function.to_str() == "exit" => true,
+        other => children(other).into_iter().any(calls_exit),
+    }
+}
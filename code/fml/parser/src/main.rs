@@ -1,5 +1,3 @@
-#[macro_use] extern crate lalrpop_util;
-#[macro_use] extern crate fml_ast;
 #[macro_use] extern crate anyhow;
 
 extern crate serde;
@@ -7,9 +5,8 @@ extern crate serde_lexpr;
 extern crate serde_json;
 extern crate serde_yaml;
 
-lalrpop_mod!(pub fml); // synthesized by LALRPOP
-
-use crate::fml::TopLevelParser;
+use fml_parser::Syntax;
+use fml_parser::serialization::ASTFormat;
 use fml_ast::{AST};
 use std::io::{Read, Stdin, BufReader, BufRead, Write, BufWriter};
 use std::fs::{File, create_dir_all};
@@ -41,16 +38,56 @@ struct CommandLineOptions {
     #[clap(long = "as-sexpr", alias = "as-lisp")]
     pub lisp: bool,
 
+    #[clap(long = "as-dot", alias = "as-graphviz")]
+    pub dot: bool,
+
+    #[clap(long = "as-html")]
+    pub html: bool,
+
+    // Parse the inputs as Feeny rather than FML; files ending in `.feeny` always are.
+    #[clap(long = "feeny")]
+    pub feeny: bool,
+
+    // Reformat the FML inputs in place (or from stdin to stdout) instead of printing their ASTs.
+    #[clap(long = "fmt")]
+    pub format: bool,
+
+    // Report the FML inputs that are not formatted, with a diff, and change nothing.
+    #[clap(long = "check")]
+    pub check: bool,
+
+    // Report the problems the checker finds in the inputs instead of printing their ASTs.
+    #[clap(long = "analyze")]
+    pub analyze: bool,
+
+    // Report the style problems the linter finds in the inputs instead of printing their ASTs.
+    #[clap(long = "lint")]
+    pub lint: bool,
+
+    // Like --lint, but print the problems to stdout as JSON.
+    #[clap(long = "lint-json")]
+    pub lint_json: bool,
+
+    // A JSON file that enables or disables lint rules, eg. `{"rules": {"empty-block": false}}`.
+    #[clap(long = "lint-config", parse(from_os_str))]
+    pub lint_config: Option<PathBuf>,
+
+    // Print the random FML program generated from this seed instead of reading any inputs.
+    #[clap(long = "generate")]
+    pub generate: Option<u64>,
+
     //#[structopt(short = "f", long = "force")]
     //pub force: bool,
 }
 impl CommandLineOptions {
-    pub fn selected_ast_serializers(&self) -> Vec<ASTSerializer> {
+    pub fn selected_ast_serializers(&self) -> Vec<ASTFormat> {
         let mut serializers = Vec::new();
-        if self.json { serializers.push(ASTSerializer::JSON) }
-        if self.yaml { serializers.push(ASTSerializer::YAML) }
-        if self.lisp { serializers.push(ASTSerializer::LISP) }
-        if serializers.is_empty() { serializers.push(ASTSerializer::LISP) }
+        if self.json { serializers.push(ASTFormat::JSON) }
+        if self.yaml { serializers.push(ASTFormat::YAML) }
+        if self.lisp { serializers.push(ASTFormat::LISP) }
+        if self.dot { serializers.push(ASTFormat::DOT) }
+        if self.html { serializers.push(ASTFormat::HTML) }
+        if serializers.is_empty() { serializers.push(ASTFormat::LISP) }
         serializers
     }
 
@@ -138,21 +175,6 @@ impl CommandLineOptions {
     }
 }
 
-#[derive(Debug)]
-enum ASTSerializer {
-    LISP, JSON, YAML,
-}
-impl ASTSerializer {
-    pub fn serialize(&self, ast: &AST) -> Result<String> {
-        let string = match self {
-            ASTSerializer::LISP => serde_lexpr::to_string(&ast)?,
-            ASTSerializer::JSON => serde_json::to_string(&ast)?,
-            ASTSerializer::YAML => serde_yaml::to_string(&ast)?,
-        };
-        Ok(string)
-    }
-}
-
 #[derive(Clone,Hash,Debug,Eq,PartialEq,PartialOrd,Ord)]
 enum Stream {
     File(String),
@@ -286,10 +308,143 @@ fn read_source_from_files_or_stdin(files: Vec<String>) -> Result<String> {
 
 
 
+#[cfg(not(test))]
+fn format_inputs(options: &CommandLineOptions) {
+    let mut unformatted = false;
+    for input in options.selected_inputs() {
+        let source: NamedSource = input.expect("Error creating input");
+        let source_name = source.name.clone();
+        let name = match &source_name {
+            Stream::File(file) => file.clone(),
+            Stream::Console => "stdin".to_owned(),
+        };
+
+        let input = source.into_string().expect("Error reading input");
+        let formatted = match fml_parser::formatter::format(&input) {
+            Ok(formatted) => formatted,
+            Err(diagnostics) => {
+                for diagnostic in diagnostics.iter() {
+                    eprintln!("{}:{}", name, diagnostic.render(&input));
+                }
+                std::process::exit(1)
+            }
+        };
+
+        if options.check {
+            if formatted != input {
+                println!("{} is not formatted:\n{}", name, fml_parser::formatter::diff(&input, &formatted));
+                unformatted = true;
+            }
+        } else {
+            match &source_name {
+                Stream::File(file) if formatted != input =>
+                    std::fs::write(file, &formatted)
+                        .unwrap_or_else(|error| panic!("Error writing \"{}\": {}", file, error)),
+                Stream::File(_) => (),
+                Stream::Console => print!("{}", formatted),
+            }
+        }
+    }
+    if unformatted {
+        std::process::exit(1)
+    }
+}
+
+#[cfg(not(test))]
+fn analyze_inputs(options: &CommandLineOptions) {
+    let mut errors = false;
+    for input in options.selected_inputs() {
+        let source: NamedSource = input.expect("Error creating input");
+        let (name, syntax) = match &source.name {
+            Stream::File(file) if options.feeny => (file.clone(), Syntax::Feeny),
+            Stream::File(file) => (file.clone(), Syntax::from_path(std::path::Path::new(file))),
+            Stream::Console if options.feeny => ("stdin".to_owned(), Syntax::Feeny),
+            Stream::Console => ("stdin".to_owned(), Syntax::FML),
+        };
+
+        let input = source.into_string().expect("Error reading input");
+        let diagnostics = match fml_parser::checker::check_source(syntax, &input) {
+            Ok((_, diagnostics)) => diagnostics,
+            Err(diagnostics) => diagnostics,
+        };
+        for diagnostic in diagnostics.iter() {
+            eprintln!("{}:{}", name, diagnostic.render(&input));
+        }
+        errors |= diagnostics.iter().any(|diagnostic| diagnostic.is_error());
+    }
+    if errors {
+        std::process::exit(1)
+    }
+}
+
+#[cfg(not(test))]
+fn lint_inputs(options: &CommandLineOptions) {
+    let config = match &options.lint_config {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .unwrap_or_else(|error| panic!("Error reading \"{}\": {}", path.to_string_lossy(), error));
+            fml_parser::linter::Config::from_json(&text).unwrap_or_else(|error| panic!("{}", error))
+        }
+        None => fml_parser::linter::Config::default(),
+    };
+
+    let mut problems = false;
+    let mut json = Vec::new();
+    for input in options.selected_inputs() {
+        let source: NamedSource = input.expect("Error creating input");
+        let (name, syntax) = match &source.name {
+            Stream::File(file) if options.feeny => (file.clone(), Syntax::Feeny),
+            Stream::File(file) => (file.clone(), Syntax::from_path(std::path::Path::new(file))),
+            Stream::Console if options.feeny => ("stdin".to_owned(), Syntax::Feeny),
+            Stream::Console => ("stdin".to_owned(), Syntax::FML),
+        };
+
+        let input = source.into_string().expect("Error reading input");
+        let lints = match fml_parser::linter::lint(syntax, &input, &config) {
+            Ok(lints) => lints,
+            Err(diagnostics) => {
+                for diagnostic in diagnostics.iter() {
+                    eprintln!("{}:{}", name, diagnostic.render(&input));
+                }
+                std::process::exit(1)
+            }
+        };
+        problems |= !lints.is_empty();
+        if options.lint_json {
+            if let serde_json::Value::Array(lints) = fml_parser::linter::to_json(&name, &input, &lints) {
+                json.extend(lints);
+            }
+        } else {
+            for lint in lints.iter() {
+                eprintln!("{}:{} [{}]", name, lint.diagnostic.render(&input), lint.rule);
+            }
+        }
+    }
+    if options.lint_json {
+        println!("{}", serde_json::to_string_pretty(&json).expect("Error serializing lints"));
+    }
+    if problems {
+        std::process::exit(1)
+    }
+}
+
 #[cfg(not(test))]
 fn main() {
     let options = CommandLineOptions::parse(); // Populate config from commandline arguments.
 
+    if let Some(seed) = options.generate {
+        return print!("{}", fml_parser::generator::source(seed))
+    }
+    if options.format || options.check {
+        return format_inputs(&options)
+    }
+    if options.analyze {
+        return analyze_inputs(&options)
+    }
+    if options.lint || options.lint_json {
+        return lint_inputs(&options)
+    }
+
     let mut outputs: HashMap<Stream, NamedSink> = options.selected_outputs()
         .expect("Error creating outputs")
         .into_iter()
@@ -302,15 +457,32 @@ fn main() {
 
         let source_name = source.name.clone();
 
-        let ast: AST = TopLevelParser::new()
-            .parse(&source.into_string().expect("Error reading input"))
-            .expect("Parse error");
+        let syntax = match &source_name {
+            _ if options.feeny => Syntax::Feeny,
+            Stream::File(file) => Syntax::from_path(std::path::Path::new(file)),
+            Stream::Console => Syntax::FML,
+        };
+
+        let input = source.into_string().expect("Error reading input");
+        let ast: AST = match syntax.parse_with_diagnostics(&input) {
+            Ok(ast) => ast,
+            Err(diagnostics) => {
+                let name = match &source_name {
+                    Stream::File(file) => file.as_str(),
+                    Stream::Console => "stdin",
+                };
+                for diagnostic in diagnostics.iter() {
+                    eprintln!("{}:{}", name, diagnostic.render(&input));
+                }
+                std::process::exit(1)
+            }
+        };
 
         let sink = outputs.get_mut(&source_name)
             .expect(&format!("No output found for input source {:?}", &source_name));
 
         // FIXME the extensions need to be set for different serializers too D:
-        let serializers: Vec<ASTSerializer> = options.selected_ast_serializers();
+        let serializers: Vec<ASTFormat> = options.selected_ast_serializers();
         for serializer in serializers {
             let ast_string = serializer.serialize(&ast)
                 .expect("Could not serialize ASt to string");
@@ -0,0 +1,560 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::fs::{read_to_string, create_dir_all, write, remove_dir_all};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use fml_ast::{AST, Identifier};
+
+/**
+ * Separates the prefix of a module from the names of its members, eg. `util::max`. The separator
+ * cannot occur in identifiers written in FML source, so mangled names never clash with user names.
+ */
+pub const MODULE_SEPARATOR: &str = "::";
+
+/**
+ * Environment variable holding additional directories to search for imported modules. Uses the
+ * platform's path list syntax (eg. `lib:/usr/share/fml` on Unix).
+ */
+pub const SEARCH_PATH_VARIABLE: &str = "FML_PATH";
+
+/**
+ * The result of importing a module.
+ *
+ * The first time a module is imported its body is handed to the interpreter or compiler to be
+ * evaluated at the point of the import. Afterwards the module is only referred to by its prefix.
+ */
+#[derive(PartialEq,Debug,Clone)]
+pub enum Loaded {
+    Module { prefix: String, body: Vec<Box<AST>> },
+    AlreadyLoaded { prefix: String },
+}
+
+/**
+ * Finds, parses, and keeps track of imported FML modules.
+ *
+ * Top-level functions and variables of a module are given mangled names (`prefix::name`) and every
+ * use of a module through its alias (`alias.function(...)`, `alias.variable`) is rewritten into a
+ * use of the mangled name. This way neither backend needs to know anything about modules except
+ * that an `Import` evaluates the module body once, in the global scope.
+ *
+ * Modules are looked up relative to the importing file first, and then in each directory on the
+ * search path. Importing a module that is still being evaluated is an import cycle and an error.
+ */
+#[derive(PartialEq,Debug,Clone)]
+pub struct ModuleLoader {
+    search_path: Vec<PathBuf>,
+    main_directory: Option<PathBuf>,
+    prefixes: HashMap<PathBuf, String>,
+    loaded: HashSet<PathBuf>,
+    in_progress: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        ModuleLoader {
+            search_path,
+            main_directory: None,
+            prefixes: HashMap::new(),
+            loaded: HashSet::new(),
+            in_progress: Vec::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let search_path = match std::env::var_os(SEARCH_PATH_VARIABLE) {
+            Some(paths) => std::env::split_paths(&paths).collect(),
+            None => Vec::new(),
+        };
+        ModuleLoader::new(search_path)
+    }
+
+    /**
+     * Imports in the main program are resolved relative to the directory containing it.
+     */
+    pub fn set_main_file(&mut self, path: &Path) {
+        self.main_directory = path.parent().map(|directory| directory.to_path_buf());
+    }
+
+    pub fn search_path(&self) -> &[PathBuf] {
+        &self.search_path
+    }
+
+    /**
+     * Rewrites all uses of module aliases in the main program into uses of mangled names. The
+     * main program's own definitions keep their names.
+     */
+    pub fn link(&mut self, ast: &AST) -> Result<AST, String> {
+        let members = match ast {
+            AST::Top(children) => Members::without_prefix(self.aliases(children)?),
+            _ => Members::without_prefix(HashMap::new()),
+        };
+        Ok(members.rename(ast))
+    }
+
+    /**
+     * Imports the module at the given path. If this is the first time the module is imported,
+     * returns its body and marks it as being evaluated until `finish` is called.
+     */
+    pub fn load(&mut self, path: &str) -> Result<Loaded, String> {
+        let resolved = self.resolve(path)?;
+
+        if let Some(position) = self.in_progress.iter().position(|p| *p == resolved) {
+            let cycle: Vec<String> = self.in_progress[position..].iter()
+                .chain(std::iter::once(&resolved))
+                .map(|p| p.to_string_lossy().to_string())
+                .collect();
+            return Err(format!("Import cycle: {}", cycle.join(" -> ")))
+        }
+
+        let prefix = self.prefix_for(&resolved);
+        if self.loaded.contains(&resolved) {
+            return Ok(Loaded::AlreadyLoaded { prefix })
+        }
+
+        let source = read_to_string(&resolved)
+            .map_err(|e| format!("Cannot read module {:?}: {}", resolved, e))?;
+        let ast = crate::parse(&source)
+            .map_err(|e| format!("Cannot parse module {:?}: {}", resolved, e))?;
+
+        let children = match ast {
+            AST::Top(children) => children,
+            ast => vec!(Box::new(ast)),
+        };
+
+        self.in_progress.push(resolved.clone());
+        let aliases = match self.aliases(&children) {
+            Ok(aliases) => aliases,
+            Err(e) => { self.in_progress.pop(); return Err(e) }
+        };
+
+        let members = Members::with_prefix(prefix.clone(), &children, aliases);
+        let body = children.iter().map(|child| Box::new(members.rename(child))).collect();
+
+        self.loaded.insert(resolved);
+        Ok(Loaded::Module { prefix, body })
+    }
+
+    /**
+     * Marks the module most recently returned by `load` as fully evaluated.
+     */
+    pub fn finish(&mut self) {
+        self.in_progress.pop()
+            .expect("Module loader: cannot finish a module, no module is being loaded");
+    }
+
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let requested = Path::new(path);
+        let candidates: Vec<PathBuf> = if requested.is_absolute() {
+            vec!(requested.to_path_buf())
+        } else {
+            let base = match self.in_progress.last() {
+                Some(importer) => importer.parent().map(|p| p.to_path_buf()),
+                None => self.main_directory.clone(),
+            }.unwrap_or_else(|| PathBuf::from("."));
+
+            std::iter::once(base).chain(self.search_path.iter().cloned())
+                .map(|directory| directory.join(requested))
+                .collect()
+        };
+
+        candidates.iter()
+            .find(|candidate| candidate.is_file())
+            .map(|candidate| candidate.canonicalize()
+                .map_err(|e| format!("Cannot resolve module {}: {}", path, e)))
+            .unwrap_or_else(|| Err(format!("Cannot find module {} (tried: {:?})", path, candidates)))
+    }
+
+    fn prefix_for(&mut self, resolved: &PathBuf) -> String {
+        if let Some(prefix) = self.prefixes.get(resolved) {
+            return prefix.clone()
+        }
+
+        let stem = resolved.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "module".to_string());
+
+        let taken: HashSet<&String> = self.prefixes.values().collect();
+        let mut prefix = stem.clone();
+        let mut counter = 1;
+        while taken.contains(&prefix) {
+            prefix = format!("{}_{}", stem, counter);
+            counter += 1;
+        }
+
+        self.prefixes.insert(resolved.clone(), prefix.clone());
+        prefix
+    }
+
+    fn aliases(&mut self, children: &[Box<AST>]) -> Result<HashMap<String, String>, String> {
+        let mut aliases = HashMap::new();
+        for child in children {
+            if let AST::Import { path, name } = &**child {
+                let resolved = self.resolve(path)?;
+                let prefix = self.prefix_for(&resolved);
+                aliases.insert(name.to_string(), prefix);
+            }
+        }
+        Ok(aliases)
+    }
+}
+
+pub fn mangle(prefix: &str, name: &str) -> String {
+    format!("{}{}{}", prefix, MODULE_SEPARATOR, name)
+}
+
+/**
+ * The names defined at the top level of a module, and the aliases of the modules it imports.
+ */
+struct Members {
+    prefix: Option<String>,
+    functions: HashSet<String>,
+    globals: HashSet<String>,
+    aliases: HashMap<String, String>,
+}
+
+/**
+ * Variables bound by parameters and local `let`s, which shadow module members and aliases.
+ */
+struct Scopes(Vec<HashSet<String>>);
+
+impl Scopes {
+    fn at_top_level(&self) -> bool {
+        self.0.is_empty()
+    }
+    fn shadows(&self, name: &str) -> bool {
+        self.0.iter().any(|scope| scope.contains(name))
+    }
+    fn enter(&mut self, names: Vec<String>) {
+        self.0.push(names.into_iter().collect())
+    }
+    fn leave(&mut self) {
+        self.0.pop().expect("Module loader: cannot leave scope: the scope stack is empty");
+    }
+    fn define(&mut self, name: &str) {
+        if let Some(scope) = self.0.last_mut() {
+            scope.insert(name.to_string());
+        }
+    }
+}
+
+impl Members {
+    fn without_prefix(aliases: HashMap<String, String>) -> Self {
+        Members { prefix: None, functions: HashSet::new(), globals: HashSet::new(), aliases }
+    }
+
+    fn with_prefix(prefix: String, children: &[Box<AST>], aliases: HashMap<String, String>) -> Self {
+        let mut functions = HashSet::new();
+        let mut globals = HashSet::new();
+        for child in children {
+            match &**child {
+                AST::FunctionDefinition { function, parameters: _, body: _ } => {
+                    functions.insert(function.to_string());
+                }
+                AST::ClassDefinition { name, parameters: _, extends: _, members: _ } => {
+                    functions.insert(name.to_string());
+                }
+                AST::VariableDefinition { name, value: _ }
+                | AST::ConstantDefinition { name, value: _ } => {
+                    globals.insert(name.to_string());
+                }
+                _ => (),
+            }
+        }
+        Members { prefix: Some(prefix), functions, globals, aliases }
+    }
+
+    fn rename(&self, ast: &AST) -> AST {
+        self.rename_in(ast, &mut Scopes(Vec::new()))
+    }
+
+    fn function_name(&self, name: &Identifier) -> Identifier {
+        match &self.prefix {
+            Some(prefix) if self.functions.contains(name.to_str()) =>
+                Identifier(mangle(prefix, name.to_str())),
+            _ => name.clone(),
+        }
+    }
+
+    fn global_name(&self, name: &Identifier, scopes: &Scopes) -> Identifier {
+        match &self.prefix {
+            Some(prefix) if self.globals.contains(name.to_str()) && !scopes.shadows(name.to_str()) =>
+                Identifier(mangle(prefix, name.to_str())),
+            _ => name.clone(),
+        }
+    }
+
+    fn class_name(&self, class: &Identifier, scopes: &Scopes) -> Identifier {
+        match class.to_str().split_once('.') {
+            Some((alias, name)) => {
+                match self.alias(&AST::VariableAccess { name: Identifier::from(alias) }, scopes) {
+                    Some(prefix) => Identifier(mangle(prefix, name)),
+                    None => class.clone(),
+                }
+            }
+            None => self.function_name(class),
+        }
+    }
+
+    fn alias(&self, object: &AST, scopes: &Scopes) -> Option<&String> {
+        match object {
+            AST::VariableAccess { name } if !scopes.shadows(name.to_str())
+                                         && !self.globals.contains(name.to_str()) =>
+                self.aliases.get(name.to_str()),
+            _ => None,
+        }
+    }
+
+    fn rename_all(&self, asts: &[Box<AST>], scopes: &mut Scopes) -> Vec<Box<AST>> {
+        asts.iter().map(|ast| Box::new(self.rename_in(ast, scopes))).collect()
+    }
+
+    fn rename_box(&self, ast: &AST, scopes: &mut Scopes) -> Box<AST> {
+        Box::new(self.rename_in(ast, scopes))
+    }
+
+    fn rename_function(&self, parameters: &[Identifier], body: &AST, receiver: bool,
+                       scopes: &mut Scopes) -> Box<AST> {
+        let mut names: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+        if receiver {
+            names.push("this".to_string());
+        }
+        scopes.enter(names);
+        let body = self.rename_box(body, scopes);
+        scopes.leave();
+        body
+    }
+
+    fn rename_in(&self, ast: &AST, scopes: &mut Scopes) -> AST {
+        match ast {
+            AST::VariableDefinition { name, value } => {
+                let value = self.rename_box(value, scopes);
+                let name = if scopes.at_top_level() {
+                    self.global_name(name, scopes)
+                } else {
+                    scopes.define(name.to_str());
+                    name.clone()
+                };
+                AST::VariableDefinition { name, value }
+            }
+
+            AST::ConstantDefinition { name, value } => {
+                let value = self.rename_box(value, scopes);
+                let name = if scopes.at_top_level() {
+                    self.global_name(name, scopes)
+                } else {
+                    scopes.define(name.to_str());
+                    name.clone()
+                };
+                AST::ConstantDefinition { name, value }
+            }
+
+            AST::VariableAccess { name } =>
+                AST::VariableAccess { name: self.global_name(name, scopes) },
+
+            AST::VariableMutation { name, value } =>
+                AST::VariableMutation { name: self.global_name(name, scopes),
+                                        value: self.rename_box(value, scopes) },
+
+            AST::FunctionDefinition { function, parameters, body } => {
+                let function = if scopes.at_top_level() {
+                    self.function_name(function)
+                } else {
+                    function.clone()
+                };
+                let body = self.rename_function(parameters, body, false, scopes);
+                AST::FunctionDefinition { function, parameters: parameters.clone(), body }
+            }
+
+            AST::ClassDefinition { name, parameters, extends, members } => {
+                let name = if scopes.at_top_level() {
+                    self.function_name(name)
+                } else {
+                    name.clone()
+                };
+                // The parent and the members are renamed like an object built inside the constructor.
+                scopes.enter(parameters.iter().map(|p| p.to_string()).collect());
+                let object = AST::ObjectDefinition { extends: extends.clone(), members: members.clone() };
+                let object = self.rename_in(&object, scopes);
+                scopes.leave();
+                match object {
+                    AST::ObjectDefinition { extends, members } =>
+                        AST::ClassDefinition { name, parameters: parameters.clone(), extends, members },
+                    _ => unreachable!(),
+                }
+            }
+
+            AST::SuperMethodCall { method, arguments } =>
+                AST::SuperMethodCall { method: method.clone(),
+                                       arguments: self.rename_all(arguments, scopes) },
+
+            AST::Instantiation { class, arguments } =>
+                AST::Instantiation { class: self.class_name(class, scopes),
+                                     arguments: self.rename_all(arguments, scopes) },
+
+            AST::OperatorDefinition { operator, parameters, body } =>
+                AST::OperatorDefinition { operator: *operator,
+                                          parameters: parameters.clone(),
+                                          body: self.rename_function(parameters, body, true, scopes) },
+
+            AST::FunctionCall { function, arguments } =>
+                AST::FunctionCall { function: self.function_name(function),
+                                    arguments: self.rename_all(arguments, scopes) },
+
+            AST::MethodCall { object, method, arguments } => match self.alias(object, scopes) {
+                Some(prefix) =>
+                    AST::FunctionCall { function: Identifier(mangle(prefix, method.to_str())),
+                                        arguments: self.rename_all(arguments, scopes) },
+                None =>
+                    AST::MethodCall { object: self.rename_box(object, scopes),
+                                      method: method.clone(),
+                                      arguments: self.rename_all(arguments, scopes) },
+            },
+
+            AST::FieldAccess { object, field } => match self.alias(object, scopes) {
+                Some(prefix) =>
+                    AST::VariableAccess { name: Identifier(mangle(prefix, field.to_str())) },
+                None =>
+                    AST::FieldAccess { object: self.rename_box(object, scopes), field: field.clone() },
+            },
+
+            AST::FieldMutation { object, field, value } => match self.alias(object, scopes) {
+                Some(prefix) =>
+                    AST::VariableMutation { name: Identifier(mangle(prefix, field.to_str())),
+                                            value: self.rename_box(value, scopes) },
+                None =>
+                    AST::FieldMutation { object: self.rename_box(object, scopes),
+                                         field: field.clone(),
+                                         value: self.rename_box(value, scopes) },
+            },
+
+            AST::ObjectDefinition { extends, members } => {
+                let extends = extends.as_ref().map(|e| self.rename_box(e, scopes));
+                let members = members.iter().map(|member| Box::new(match &**member {
+                    AST::VariableDefinition { name, value } =>
+                        AST::VariableDefinition { name: name.clone(),
+                                                  value: self.rename_box(value, scopes) },
+                    AST::ConstantDefinition { name, value } =>
+                        AST::ConstantDefinition { name: name.clone(),
+                                                  value: self.rename_box(value, scopes) },
+                    AST::FunctionDefinition { function, parameters, body } =>
+                        AST::FunctionDefinition { function: function.clone(),
+                                                  parameters: parameters.clone(),
+                                                  body: self.rename_function(parameters, body, true, scopes) },
+                    member => self.rename_in(member, scopes),
+                })).collect();
+                AST::ObjectDefinition { extends, members }
+            }
+
+            AST::Block(children) => {
+                scopes.enter(Vec::new());
+                let children = self.rename_all(children, scopes);
+                scopes.leave();
+                AST::Block(children)
+            }
+
+            AST::Top(children) =>
+                AST::Top(self.rename_all(children, scopes)),
+
+            AST::ArrayDefinition { size, value } =>
+                AST::ArrayDefinition { size: self.rename_box(size, scopes),
+                                       value: self.rename_box(value, scopes) },
+
+            AST::MapDefinition { entries } =>
+                AST::MapDefinition { entries: entries.iter()
+                    .map(|(key, value)| (self.rename_box(key, scopes), self.rename_box(value, scopes)))
+                    .collect() },
+
+            AST::ArrayAccess { array, index } =>
+                AST::ArrayAccess { array: self.rename_box(array, scopes),
+                                   index: self.rename_box(index, scopes) },
+
+            AST::ArrayMutation { array, index, value } =>
+                AST::ArrayMutation { array: self.rename_box(array, scopes),
+                                     index: self.rename_box(index, scopes),
+                                     value: self.rename_box(value, scopes) },
+
+            AST::OperatorCall { object, operator, arguments } =>
+                AST::OperatorCall { object: self.rename_box(object, scopes),
+                                    operator: *operator,
+                                    arguments: self.rename_all(arguments, scopes) },
+
+            AST::Print { format, arguments } =>
+                AST::Print { format: format.clone(), arguments: self.rename_all(arguments, scopes) },
+
+            AST::Operation { operator, left, right } =>
+                AST::Operation { operator: *operator,
+                                 left: self.rename_box(left, scopes),
+                                 right: self.rename_box(right, scopes) },
+
+            AST::Loop { condition, body } =>
+                AST::Loop { condition: self.rename_box(condition, scopes),
+                            body: self.rename_box(body, scopes) },
+
+            AST::Iteration { variable, iterable, body } => {
+                let iterable = self.rename_box(iterable, scopes);
+                scopes.enter(vec!(variable.to_string()));
+                let body = self.rename_box(body, scopes);
+                scopes.leave();
+                AST::Iteration { variable: variable.clone(), iterable, body }
+            }
+
+            AST::Range { start, end } =>
+                AST::Range { start: self.rename_box(start, scopes),
+                             end: self.rename_box(end, scopes) },
+
+            AST::Conditional { condition, consequent, alternative } =>
+                AST::Conditional { condition: self.rename_box(condition, scopes),
+                                   consequent: self.rename_box(consequent, scopes),
+                                   alternative: self.rename_box(alternative, scopes) },
+
+            AST::Match { value, arms } => {
+                let value = self.rename_box(value, scopes);
+                let arms = arms.iter().map(|(pattern, body)| {
+                    scopes.enter(pattern.bindings().iter().map(|name| name.to_string()).collect());
+                    let body = self.rename_box(body, scopes);
+                    scopes.leave();
+                    (pattern.clone(), body)
+                }).collect();
+                AST::Match { value, arms }
+            }
+
+            AST::Number(_) | AST::Float(_) | AST::Boolean(_) | AST::String(_) | AST::Unit |
+            AST::Break | AST::Continue | AST::Import { path: _, name: _ } =>
+                ast.clone(),
+        }
+    }
+}
+
+/**
+ * A temporary directory of module files, removed together with its contents when dropped. Used by
+ * the tests of the module loader and of both backends.
+ */
+pub struct ModuleDirectory {
+    path: PathBuf,
+}
+
+impl ModuleDirectory {
+    pub fn new(name: &str, files: Vec<(&str, &str)>) -> Self {
+        static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+        let unique = SEQUENCE.fetch_add(1, AtomicOrdering::SeqCst);
+        let path = std::env::temp_dir()
+            .join(format!("fml_modules_{}_{}_{}", name, std::process::id(), unique));
+        create_dir_all(&path)
+            .unwrap_or_else(|e| panic!("Cannot create module directory {:?}: {}", path, e));
+        for (file, source) in files {
+            write(path.join(file), source)
+                .unwrap_or_else(|e| panic!("Cannot write module {}: {}", file, e));
+        }
+        ModuleDirectory { path }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+}
+
+impl Drop for ModuleDirectory {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.path);
+    }
+}
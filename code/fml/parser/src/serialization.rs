@@ -0,0 +1,48 @@
+use fml_ast::AST;
+
+use crate::diagram;
+
+/**
+ * The formats in which ASTs are written out by the parser and read back in by the interpreters,
+ * so that tools other than the parser can produce programs for them. Graphviz and HTML are only
+ * written out, for people to look at.
+ */
+#[derive(PartialEq,Debug,Copy,Clone)]
+pub enum ASTFormat {
+    JSON,
+    YAML,
+    LISP,
+    DOT,
+    HTML,
+}
+
+impl ASTFormat {
+    // Names as they are given on the command line.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(ASTFormat::JSON),
+            "yaml" | "yml" => Some(ASTFormat::YAML),
+            "sexpr" | "lisp" => Some(ASTFormat::LISP),
+            _ => None,
+        }
+    }
+
+    pub fn serialize(&self, ast: &AST) -> Result<String, String> {
+        match self {
+            ASTFormat::JSON => serde_json::to_string(ast).map_err(|e| e.to_string()),
+            ASTFormat::YAML => serde_yaml::to_string(ast).map_err(|e| e.to_string()),
+            ASTFormat::LISP => serde_lexpr::to_string(ast).map_err(|e| e.to_string()),
+            ASTFormat::DOT => Ok(diagram::dot(ast)),
+            ASTFormat::HTML => Ok(diagram::html(ast)),
+        }
+    }
+
+    pub fn deserialize(&self, input: &str) -> Result<AST, String> {
+        match self {
+            ASTFormat::JSON => serde_json::from_str(input).map_err(|e| e.to_string()),
+            ASTFormat::YAML => serde_yaml::from_str(input).map_err(|e| e.to_string()),
+            ASTFormat::LISP => serde_lexpr::from_str(input).map_err(|e| e.to_string()),
+            ASTFormat::DOT | ASTFormat::HTML => Err("the format can only be written".to_string()),
+        }.map_err(|e| format!("Cannot read {:?} AST: {}", self, e))
+    }
+}
@@ -0,0 +1,41 @@
+use crate::lexer::{Lexer, TokenKind};
+
+/**
+ * The parts of the source that the AST does not retain: comments, and where the statements they
+ * belong to are.
+ *
+ * Statements are those of the program, of blocks, and the members of objects and classes. Their
+ * locations are sorted by where they start, which is also the order in which a walk of the AST
+ * that visits a statement before the statements nested in it (and earlier ones before later ones)
+ * meets them, so each statement of the AST can be matched with its location by counting.
+ * A comment belongs to the first statement that starts after it, or, if it is on the same line as
+ * the end of a statement, it trails that statement.
+ */
+#[derive(PartialEq,Debug,Clone)]
+pub struct Trivia {
+    pub comments: Vec<Comment>,
+    pub statements: Vec<(usize, usize)>,
+}
+
+#[derive(PartialEq,Debug,Clone)]
+pub struct Comment {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl Trivia {
+    pub fn new(source: &str, statements: Vec<(usize, usize)>) -> Self {
+        let mut statements = statements;
+        statements.sort();
+        Trivia { comments: comments(source), statements }
+    }
+}
+
+// Comments are `// ...` to the end of the line, and `/* ... */`, as the parser's lexer finds them.
+pub fn comments(source: &str) -> Vec<Comment> {
+    Lexer::with_trivia(source)
+        .filter(|token| token.kind == TokenKind::Comment)
+        .map(|token| Comment { start: token.start, end: token.end, text: token.text.trim_end().to_string() })
+        .collect()
+}